@@ -0,0 +1,460 @@
+#!/usr/bin/env python3
+"""Builds assets/PrismEmoji.ttf, the bundled fallback for emoji.
+
+The glyphs are outline drawings of the emoji documents use most (faces,
+hearts, hands, marks and a few objects), made from circles, arcs and
+polygons so the font stays small and needs no outside sources. It has
+no color and no shaping: each emoji is one glyph in the cmap.
+
+    python3 assets/emoji/build_font.py
+
+Only the standard library is needed.
+"""
+
+import math
+import os
+import struct
+import time
+
+UPM = 1000
+ASCENT, DESCENT = 880, -120
+ADVANCE = 1000
+# Centre and radius of the face circle most glyphs are drawn around
+CX, CY, R = 500, 380, 440
+
+
+# --- Contours -----------------------------------------------------------------
+# A contour is a list of (x, y) on-curve points. Filled contours run clockwise
+# and holes counter-clockwise, so overlapping filled parts stay filled.
+
+def area(points):
+    return sum(x0 * y1 - x1 * y0 for (x0, y0), (x1, y1) in zip(points, points[1:] + points[:1])) / 2
+
+
+def filled(points):
+    return points[::-1] if area(points) > 0 else points
+
+
+def hole(points):
+    return points if area(points) > 0 else points[::-1]
+
+
+def ellipse(cx, cy, rx, ry, steps=48):
+    return [(cx + rx * math.cos(2 * math.pi * i / steps), cy + ry * math.sin(2 * math.pi * i / steps)) for i in range(steps)]
+
+
+def circle(cx, cy, r, steps=48):
+    return ellipse(cx, cy, r, r, steps)
+
+
+def arc(cx, cy, r, start, end, steps=24):
+    """Points along a circle from `start` to `end` degrees"""
+    return [(cx + r * math.cos(math.radians(start + (end - start) * i / steps)),
+             cy + r * math.sin(math.radians(start + (end - start) * i / steps))) for i in range(steps + 1)]
+
+
+def stroke_arc(cx, cy, r, start, end, width):
+    """A band `width` thick along an arc, with round ends"""
+    outer = arc(cx, cy, r + width / 2, start, end)
+    inner = arc(cx, cy, r - width / 2, end, start)
+    cap = lambda angle, a0: arc(cx + r * math.cos(math.radians(angle)), cy + r * math.sin(math.radians(angle)), width / 2, a0, a0 + 180, 8)
+    return outer + cap(end, end)[1:-1] + inner + cap(start, start + 180)[1:-1]
+
+
+def line(x0, y0, x1, y1, width):
+    """A band `width` thick between two points, with round ends"""
+    angle = math.degrees(math.atan2(y1 - y0, x1 - x0))
+    return arc(x1, y1, width / 2, angle - 90, angle + 90, 8) + arc(x0, y0, width / 2, angle + 90, angle + 270, 8)
+
+
+def rect(x0, y0, x1, y1, radius=0):
+    if radius <= 0:
+        return [(x0, y0), (x1, y0), (x1, y1), (x0, y1)]
+    corners = [(x1 - radius, y0 + radius, -90), (x1 - radius, y1 - radius, 0), (x0 + radius, y1 - radius, 90), (x0 + radius, y0 + radius, 180)]
+    return [p for cx, cy, a in corners for p in arc(cx, cy, radius, a, a + 90, 6)]
+
+
+def heart(cx, cy, size):
+    points = []
+    for i in range(64):
+        t = 2 * math.pi * i / 64
+        x = 16 * math.sin(t) ** 3
+        y = 13 * math.cos(t) - 5 * math.cos(2 * t) - 2 * math.cos(3 * t) - math.cos(4 * t)
+        points.append((cx + x * size / 34, cy + (y + 2) * size / 34))
+    return points
+
+
+def star(cx, cy, outer, inner, points=5, turn=90):
+    return [(cx + (outer if i % 2 == 0 else inner) * math.cos(math.radians(turn + 180 * i / points)),
+             cy + (outer if i % 2 == 0 else inner) * math.sin(math.radians(turn + 180 * i / points))) for i in range(points * 2)]
+
+
+def drop(cx, cy, size):
+    """A teardrop pointing up, its round end centred on (cx, cy)"""
+    return arc(cx, cy, size, -30, 210, 24) + [(cx, cy + size * 2)]
+
+
+def move(points, dx, dy):
+    return [(x + dx, y + dy) for x, y in points]
+
+
+def rotate(points, degrees, ox=CX, oy=CY):
+    a = math.radians(degrees)
+    return [(ox + (x - ox) * math.cos(a) - (y - oy) * math.sin(a), oy + (x - ox) * math.sin(a) + (y - oy) * math.cos(a)) for x, y in points]
+
+
+def mirror_y(points, oy=CY):
+    return [(x, 2 * oy - y) for x, y in points]
+
+
+# --- Glyphs -------------------------------------------------------------------
+
+def face(*features):
+    return [filled(circle(CX, CY, R)), hole(circle(CX, CY, R - 60))] + [filled(f) for f in features]
+
+
+EYES = [ellipse(CX - 140, CY + 90, 45, 70), ellipse(CX + 140, CY + 90, 45, 70)]
+BIG_EYES = [ellipse(CX - 140, CY + 100, 50, 90), ellipse(CX + 140, CY + 100, 50, 90)]
+HAPPY_EYES = [stroke_arc(CX - 140, CY + 60, 60, 20, 160, 44), stroke_arc(CX + 140, CY + 60, 60, 20, 160, 44)]
+CLOSED_EYES = [stroke_arc(CX - 140, CY + 130, 60, 200, 340, 44), stroke_arc(CX + 140, CY + 130, 60, 200, 340, 44)]
+SMILE = stroke_arc(CX, CY + 20, 210, 205, 335, 50)
+FROWN = stroke_arc(CX, CY - 260, 190, 35, 145, 50)
+GRIN = arc(CX, CY - 40, 220, 180, 360, 24)
+
+
+def grin_with_teeth():
+    return [filled(GRIN), hole(rect(CX - 180, CY - 110, CX + 180, CY - 80))]
+
+
+def glyphs():
+    g = {}
+    g[0x1F642] = face(*EYES, SMILE)                               # slightly smiling
+    g[0x263A] = g[0x1F60A] = face(*HAPPY_EYES, SMILE)             # smiling
+    g[0x1F600] = face(*EYES, GRIN)                                # grinning
+    g[0x1F603] = face(*BIG_EYES, GRIN)                            # grinning, big eyes
+    g[0x1F604] = face(*HAPPY_EYES, GRIN)                          # grinning, smiling eyes
+    g[0x1F601] = face(*HAPPY_EYES) + grin_with_teeth()            # beaming
+    g[0x1F606] = face(line(CX - 200, CY + 150, CX - 90, CY + 90, 40), line(CX - 90, CY + 90, CX - 200, CY + 30, 40),
+                      line(CX + 200, CY + 150, CX + 90, CY + 90, 40), line(CX + 90, CY + 90, CX + 200, CY + 30, 40), GRIN)
+    g[0x1F609] = face(EYES[0], HAPPY_EYES[1], SMILE)              # winking
+    g[0x1F60D] = face(heart(CX - 140, CY + 90, 150), heart(CX + 140, CY + 90, 150), GRIN)
+    g[0x1F618] = face(EYES[0], HAPPY_EYES[1], ellipse(CX, CY - 160, 40, 30), heart(CX + 250, CY - 170, 110))
+    g[0x1F602] = face(*HAPPY_EYES, GRIN, drop(CX - 250, CY - 10, 40), drop(CX + 250, CY - 10, 40))
+    g[0x1F605] = face(*HAPPY_EYES, GRIN, drop(CX + 250, CY + 150, 40))
+    g[0x1F607] = face(*HAPPY_EYES, SMILE)[:2] + [filled(f) for f in HAPPY_EYES + [SMILE]]
+    g[0x1F610] = face(*EYES, rect(CX - 150, CY - 170, CX + 150, CY - 120, 25))
+    g[0x1F611] = face(rect(CX - 200, CY + 70, CX - 80, CY + 110, 20), rect(CX + 80, CY + 70, CX + 200, CY + 110, 20),
+                      rect(CX - 150, CY - 170, CX + 150, CY - 120, 25))
+    g[0x1F641] = g[0x2639] = face(*EYES, FROWN)                   # frowning
+    g[0x1F61E] = face(*CLOSED_EYES, FROWN)                        # disappointed
+    g[0x1F622] = face(*EYES, FROWN, drop(CX + 160, CY - 60, 36))  # crying
+    g[0x1F62D] = face(*CLOSED_EYES, ellipse(CX, CY - 150, 90, 70),
+                      rect(CX - 175, CY - 330, CX - 115, CY + 80, 30), rect(CX + 115, CY - 330, CX + 175, CY + 80, 30))
+    g[0x1F62E] = face(*EYES, ellipse(CX, CY - 150, 70, 95))       # open mouth
+    g[0x1F632] = face(*BIG_EYES, ellipse(CX, CY - 160, 80, 110))  # astonished
+    g[0x1F60E] = face(rect(CX - 290, CY + 20, CX - 30, CY + 160, 50), rect(CX + 30, CY + 20, CX + 290, CY + 160, 50),
+                      rect(CX - 60, CY + 110, CX + 60, CY + 140), SMILE)
+    g[0x1F621] = g[0x1F620] = face(line(CX - 230, CY + 200, CX - 70, CY + 130, 44), line(CX + 230, CY + 200, CX + 70, CY + 130, 44),
+                                   ellipse(CX - 140, CY + 50, 40, 50), ellipse(CX + 140, CY + 50, 40, 50), FROWN)
+    g[0x1F914] = face(*EYES, line(CX - 40, CY - 150, CX + 150, CY - 110, 44), line(CX - 220, CY + 200, CX - 70, CY + 220, 36))
+    g[0x1F634] = face(*CLOSED_EYES, ellipse(CX, CY - 160, 50, 40))
+
+    # Hearts: every color looks the same without color
+    for code in (0x2764, 0x2665, 0x1F499, 0x1F49A, 0x1F49B, 0x1F49C, 0x1F5A4, 0x1F9E1, 0x1F90E, 0x1F496, 0x1F497):
+        g[code] = [filled(heart(CX, CY, 900))]
+    g[0x1F90D] = g[0x2661] = [filled(heart(CX, CY, 900)), hole(heart(CX, CY + 12, 720))]
+    g[0x1F495] = [filled(heart(CX - 150, CY + 120, 520)), filled(heart(CX + 200, CY - 160, 420))]
+    crack = [(CX + 10, CY + 330), (CX - 70, CY + 150), (CX + 50, CY + 20), (CX - 60, CY - 140), (CX + 10, CY - 320),
+             (CX - 20, CY - 320), (CX - 110, CY - 140), (CX + 10, CY + 20), (CX - 110, CY + 150), (CX - 20, CY + 330)]
+    g[0x1F494] = [filled(heart(CX, CY, 900)), hole(crack)]
+
+    # Hands
+    thumb = [filled(rect(CX - 300, CY - 380, CX + 130, CY + 80, 70)), filled(rect(CX - 120, CY, CX + 20, CY + 420, 70)),
+             hole(rect(CX - 230, CY - 300, CX + 60, CY - 240, 25)), hole(rect(CX - 230, CY - 180, CX + 60, CY - 120, 25)),
+             hole(rect(CX - 230, CY - 60, CX + 60, CY, 25)), filled(rect(CX + 170, CY - 380, CX + 320, CY + 40, 30))]
+    g[0x1F44D] = thumb
+    g[0x1F44E] = [filled(mirror_y(c)) if area(c) < 0 else hole(mirror_y(c)) for c in thumb]
+    g[0x270B] = g[0x1F91A] = [filled(rect(CX - 250, CY - 420, CX + 200, CY + 60, 120))] + [
+        filled(rect(x, CY - 100, x + 90, CY + top, 45)) for x, top in ((CX - 250, 300), (CX - 130, 400), (CX - 10, 420), (CX + 110, 350))
+    ] + [filled(line(CX + 160, CY - 80, CX + 360, CY + 100, 90))]
+    g[0x1F44B] = [filled(rotate(c, -20)) for c in g[0x270B]]
+
+    # Marks
+    check = [(CX - 330, CY + 20), (CX - 220, CY + 130), (CX - 90, CY), (CX + 240, CY + 330), (CX + 350, CY + 220), (CX - 90, CY - 220)]
+    g[0x2714] = g[0x2713] = [filled(check)]
+    g[0x2705] = [filled(rect(CX - R, CY - R, CX + R, CY + R, 120)), hole([(x * 0.8 + CX * 0.2, y * 0.8 + CY * 0.2) for x, y in check])]
+    cross = rotate(rect(CX - 380, CY - 55, CX + 380, CY + 55, 30), 45)
+    g[0x274C] = g[0x2716] = [filled(cross), filled(rotate(cross, 90))]
+    g[0x274E] = [filled(rect(CX - R, CY - R, CX + R, CY + R, 120)), hole(rotate(rect(CX - 280, CY - 50, CX + 280, CY + 50), 45))]
+    g[0x2795] = [filled(rect(CX - 380, CY - 55, CX + 380, CY + 55, 30)), filled(rect(CX - 55, CY - 380, CX + 55, CY + 380, 30))]
+    g[0x2796] = [filled(rect(CX - 380, CY - 55, CX + 380, CY + 55, 30))]
+    g[0x2757] = [filled(rect(CX - 70, CY - 100, CX + 70, CY + 420, 60)), filled(circle(CX, CY - 320, 80))]
+    g[0x2753] = [filled(stroke_arc(CX, CY + 230, 170, -90, 180, 110)), filled(rect(CX - 55, CY - 150, CX + 55, CY + 70, 30)),
+                 filled(circle(CX, CY - 320, 80))]
+    triangle = [(CX, CY + 440), (CX + 460, CY - 380), (CX - 460, CY - 380)]
+    inner = [(CX, CY + 290), (CX + 330, CY - 310), (CX - 330, CY - 310)]
+    g[0x26A0] = [filled(triangle), hole(inner), filled(rect(CX - 45, CY - 120, CX + 45, CY + 170, 30)), filled(circle(CX, CY - 220, 50))]
+    g[0x2B50] = g[0x2605] = [filled(star(CX, CY - 20, 470, 190))]
+    g[0x1F31F] = [filled(star(CX, CY - 20, 350, 140))] + [filled(line(CX + 400 * math.cos(a), CY - 20 + 400 * math.sin(a), CX + 470 * math.cos(a), CY - 20 + 470 * math.sin(a), 50))
+                                                          for a in (math.radians(d) for d in (54, 126, 198, 270, 342))]
+    g[0x2728] = [filled(star(CX - 100, CY - 60, 360, 70, 4)), filled(star(CX + 270, CY + 270, 160, 35, 4)), filled(star(CX + 280, CY - 300, 120, 28, 4))]
+    g[0x1F4AF] = [filled(rect(CX - 440, CY - 60, CX - 360, CY + 340, 20)),
+                  filled(ellipse(CX - 120, CY + 140, 150, 210)), hole(ellipse(CX - 120, CY + 140, 80, 140)),
+                  filled(ellipse(CX + 280, CY + 140, 150, 210)), hole(ellipse(CX + 280, CY + 140, 80, 140)),
+                  filled(rect(CX - 440, CY - 200, CX + 440, CY - 140, 30)), filled(rect(CX - 400, CY - 320, CX + 400, CY - 260, 30))]
+    for code in (0x1F534, 0x1F535, 0x1F7E0, 0x1F7E1, 0x1F7E2, 0x1F7E3, 0x1F7E4, 0x26AB):
+        g[code] = [filled(circle(CX, CY, 400))]
+    g[0x26AA] = [filled(circle(CX, CY, 400)), hole(circle(CX, CY, 340))]
+    for code in (0x1F7E5, 0x1F7E6, 0x1F7E7, 0x1F7E8, 0x1F7E9, 0x1F7EA, 0x1F7EB, 0x2B1B):
+        g[code] = [filled(rect(CX - 400, CY - 400, CX + 400, CY + 400))]
+    g[0x2B1C] = [filled(rect(CX - 400, CY - 400, CX + 400, CY + 400)), hole(rect(CX - 340, CY - 340, CX + 340, CY + 340))]
+    arrow = [(CX + 420, CY), (CX + 20, CY + 380), (CX + 20, CY + 140), (CX - 420, CY + 140), (CX - 420, CY - 140), (CX + 20, CY - 140), (CX + 20, CY - 380)]
+    g[0x27A1] = [filled(arrow)]
+    g[0x2B06] = [filled(rotate(arrow, 90))]
+    g[0x2B05] = [filled(rotate(arrow, 180))]
+    g[0x2B07] = [filled(rotate(arrow, 270))]
+
+    # Weather and nature
+    g[0x2600] = g[0x1F31E] = [filled(circle(CX, CY, 230))] + [filled(rotate(rect(CX - 35, CY + 300, CX + 35, CY + 450, 30), d)) for d in range(0, 360, 45)]
+    cloud = [filled(circle(CX - 200, CY - 60, 180)), filled(circle(CX + 40, CY + 90, 250)), filled(circle(CX + 260, CY - 60, 170)),
+             filled(rect(CX - 200, CY - 240, CX + 260, CY - 20))]
+    g[0x2601] = cloud
+    g[0x26C5] = [filled(circle(CX + 170, CY + 200, 200))] + [filled(move(c, -40, -110)) for c in cloud]
+    g[0x26A1] = [filled([(CX + 120, CY + 460), (CX - 260, CY - 20), (CX - 10, CY - 20), (CX - 140, CY - 460), (CX + 270, CY + 90), (CX + 20, CY + 90)])]
+    g[0x1F525] = [filled([(CX, CY + 460), (CX + 180, CY + 200), (CX + 330, CY - 40)] + arc(CX, CY - 130, 330, 15, -195, 24)[1:-1] + [(CX - 330, CY - 40), (CX - 170, CY + 150), (CX - 60, CY + 60)]),
+                  hole([(CX + 10, CY + 130), (CX + 160, CY - 100)] + arc(CX, CY - 210, 170, 30, -210, 16)[1:-1] + [(CX - 160, CY - 100)])]
+    dx = 260
+    y = math.sqrt(R * R - dx * dx / 4)
+    top = math.degrees(math.atan2(y, dx / 2))
+    outer = arc(CX - 80, CY, R, top, 360 - top, 32)
+    inner_top = math.degrees(math.atan2(y, -dx / 2))
+    inner = arc(CX - 80 + dx, CY, R, 360 - inner_top, inner_top, 32)
+    g[0x1F319] = [filled(outer + inner[1:-1])]
+    g[0x1F308] = [filled(stroke_arc(CX, CY - 300, r, 0, 180, 80)) for r in (420, 320, 220)]
+    g[0x1F331] = [filled(rect(CX - 30, CY - 440, CX + 30, CY + 60)), filled(rotate(ellipse(CX - 150, CY + 150, 190, 90), 30, CX - 150, CY + 150)),
+                  filled(rotate(ellipse(CX + 150, CY + 200, 190, 90), -30, CX + 150, CY + 200))]
+    g[0x1F338] = [filled(circle(CX + 260 * math.cos(math.radians(a)), CY + 260 * math.sin(math.radians(a)), 180)) for a in range(90, 450, 72)] + [hole(circle(CX, CY, 110))]
+
+    # Objects
+    g[0x1F389] = [filled([(CX - 440, CY - 440), (CX - 260, CY + 140), (CX + 140, CY - 260)])] + [
+        filled(circle(CX + x, CY + y, 45)) for x, y in ((-40, 200), (200, 60), (330, 330), (80, 420), (400, -60))
+    ] + [filled(rotate(rect(CX + 100, CY + 220, CX + 130, CY + 340, 15), 30, CX + 115, CY + 280)),
+         filled(rotate(rect(CX + 260, CY + 150, CX + 290, CY + 270, 15), -30, CX + 275, CY + 210))]
+    body = [filled(ellipse(CX, CY + 40, 150, 380)), hole(circle(CX, CY + 140, 70)),
+            filled([(CX - 140, CY - 120), (CX - 300, CY - 320), (CX - 110, CY - 260)]),
+            filled([(CX + 140, CY - 120), (CX + 300, CY - 320), (CX + 110, CY - 260)]),
+            filled([(CX - 80, CY - 360), (CX + 80, CY - 360), (CX, CY - 460)])]
+    g[0x1F680] = [filled(rotate(c, -45)) if area(c) < 0 else hole(rotate(c, -45)) for c in body]
+    g[0x1F440] = [filled(ellipse(CX - 200, CY, 180, 300)), hole(ellipse(CX - 200, CY, 130, 250)), filled(circle(CX - 150, CY - 60, 80)),
+                  filled(ellipse(CX + 200, CY, 180, 300)), hole(ellipse(CX + 200, CY, 130, 250)), filled(circle(CX + 250, CY - 60, 80))]
+    g[0x1F4A1] = [filled(circle(CX, CY + 120, 320)), hole(circle(CX, CY + 120, 260)),
+                  filled(rect(CX - 150, CY - 280, CX + 150, CY - 140, 30)), filled(rect(CX - 120, CY - 420, CX + 120, CY - 310, 40)),
+                  filled(rect(CX - 30, CY - 140, CX + 30, CY + 40))]
+    g[0x1F4CC] = [filled(rotate(c, -30)) for c in ([(CX - 260, CY + 80), (CX + 260, CY + 80), (CX + 160, CY + 200), (CX + 160, CY + 380), (CX - 160, CY + 380), (CX - 160, CY + 200)],
+                                                   rect(CX - 25, CY - 440, CX + 25, CY + 90))]
+    shackle = stroke_arc(CX, CY + 100, 210, 0, 180, 90)
+    g[0x1F512] = [filled(rect(CX - 330, CY - 420, CX + 330, CY + 100, 60)), hole(circle(CX, CY - 120, 70)), hole(rect(CX - 30, CY - 300, CX + 30, CY - 120)),
+                  filled(shackle), filled(rect(CX - 255, CY + 60, CX - 165, CY + 100)), filled(rect(CX + 165, CY + 60, CX + 255, CY + 100))]
+    g[0x1F513] = [filled(rect(CX - 330, CY - 420, CX + 330, CY + 100, 60)), hole(circle(CX, CY - 120, 70)), hole(rect(CX - 30, CY - 300, CX + 30, CY - 120)),
+                  filled(stroke_arc(CX + 240, CY + 220, 210, 0, 180, 90)), filled(rect(CX + 405, CY + 120, CX + 495, CY + 220))]
+    g[0x1F514] = [filled([(CX - 380, CY - 260), (CX + 380, CY - 260), (CX + 280, CY - 140), (CX + 280, CY + 160)] + arc(CX, CY + 160, 280, 0, 180, 24)[1:-1] + [(CX - 280, CY + 160), (CX - 280, CY - 140)]),
+                  filled(circle(CX, CY - 340, 90)), filled(circle(CX, CY + 460, 50))]
+    g[0x1F3B5] = g[0x266A] = [filled(rotate(ellipse(CX - 150, CY - 300, 150, 110), 20, CX - 150, CY - 300)), filled(rect(CX - 20, CY - 300, CX + 50, CY + 440)),
+                              filled([(CX + 50, CY + 440), (CX + 330, CY + 220), (CX + 330, CY + 100), (CX + 50, CY + 300)])]
+    g[0x1F3E0] = [filled([(CX, CY + 450), (CX + 460, CY + 40), (CX + 340, CY + 40), (CX + 340, CY - 420), (CX - 340, CY - 420), (CX - 340, CY + 40), (CX - 460, CY + 40)]),
+                  hole(rect(CX - 90, CY - 420, CX + 90, CY - 120))]
+    envelope = [filled(rect(CX - 450, CY - 300, CX + 450, CY + 300, 40)), hole(rect(CX - 390, CY - 240, CX + 390, CY + 240, 10)),
+                filled(line(CX - 390, CY + 240, CX, CY - 40, 60)), filled(line(CX + 390, CY + 240, CX, CY - 40, 60))]
+    g[0x2709] = g[0x1F4E7] = g[0x1F4E9] = envelope
+    g[0x1F50D] = g[0x1F50E] = [filled(circle(CX - 90, CY + 90, 310)), hole(circle(CX - 90, CY + 90, 230)), filled(line(CX + 150, CY - 150, CX + 400, CY - 400, 120))]
+    g[0x1F4C5] = g[0x1F4C6] = [filled(rect(CX - 400, CY - 420, CX + 400, CY + 340, 50)), hole(rect(CX - 340, CY - 360, CX + 340, CY + 120)),
+                               filled(rect(CX - 230, CY + 280, CX - 150, CY + 440, 30)), filled(rect(CX + 150, CY + 280, CX + 230, CY + 440, 30))] + [
+        filled(rect(CX + x - 50, CY + y - 40, CX + x + 50, CY + y + 40, 10)) for x in (-220, 0, 220) for y in (0, -200)]
+    g[0x1F4F7] = g[0x1F4F8] = [filled(rect(CX - 460, CY - 320, CX + 460, CY + 260, 70)), filled(rect(CX - 180, CY + 200, CX + 120, CY + 350, 30)),
+                               hole(circle(CX, CY - 30, 220)), filled(circle(CX, CY - 30, 140))]
+    g[0x1F4AC] = [filled(rect(CX - 460, CY - 200, CX + 460, CY + 420, 120)), hole(rect(CX - 390, CY - 130, CX + 390, CY + 350, 70)),
+                  filled([(CX - 260, CY - 180), (CX - 330, CY - 440), (CX - 60, CY - 180)])]
+    link = [filled(rotate(rect(CX - 420, CY - 130, CX + 60, CY + 130, 130), 45)), hole(rotate(rect(CX - 350, CY - 60, CX - 10, CY + 60, 60), 45)),
+            filled(rotate(rect(CX - 60, CY - 130, CX + 420, CY + 130, 130), 45)), hole(rotate(rect(CX + 10, CY - 60, CX + 350, CY + 60, 60), 45))]
+    g[0x1F517] = link
+    g[0x1F4C1] = g[0x1F4C2] = [filled([(CX - 460, CY - 360), (CX + 460, CY - 360), (CX + 460, CY + 220), (CX - 40, CY + 220), (CX - 120, CY + 320), (CX - 460, CY + 320)])]
+    g[0x1F4C4] = g[0x1F4C3] = g[0x1F4DD] = [filled([(CX - 340, CY - 440), (CX + 340, CY - 440), (CX + 340, CY + 260), (CX + 160, CY + 440), (CX - 340, CY + 440)]),
+                                            hole([(CX - 280, CY - 380), (CX - 280, CY + 380), (CX + 100, CY + 380), (CX + 100, CY + 200), (CX + 280, CY + 200), (CX + 280, CY - 380)])] + [
+        filled(rect(CX - 200, CY + y, CX + 200, CY + y + 50, 20)) for y in (60, -80, -220)]
+    gear = []
+    for i in range(96):
+        a = 2 * math.pi * i / 96
+        tooth = (i // 6) % 2 == 0
+        r = 440 if tooth else 340
+        gear.append((CX + r * math.cos(a), CY + r * math.sin(a)))
+    g[0x2699] = [filled(gear), hole(circle(CX, CY, 150))]
+    pencil = [filled(rect(CX - 90, CY - 200, CX + 90, CY + 440, 20)), filled([(CX - 90, CY - 240), (CX + 90, CY - 240), (CX, CY - 460)])]
+    g[0x270F] = g[0x1F58A] = [filled(rotate(c, -45)) for c in pencil]
+    g[0x1F464] = [filled(circle(CX, CY + 200, 200)), filled(arc(CX, CY - 440, 400, 0, 180, 24))]
+    g[0x1F465] = [filled(move(c, -170, 0)) for c in g[0x1F464]] + [filled(circle(CX + 260, CY + 140, 160)), filled(arc(CX + 260, CY - 440, 280, 0, 180, 24))]
+    g[0x2615] = [filled([(CX - 360, CY + 200), (CX + 240, CY + 200), (CX + 200, CY - 300), (CX + 100, CY - 380), (CX - 220, CY - 380), (CX - 320, CY - 300)]),
+                 filled(stroke_arc(CX + 260, CY - 40, 140, -90, 90, 60)), filled(stroke_arc(CX - 120, CY + 330, 60, 160, 380, 40)),
+                 filled(stroke_arc(CX + 60, CY + 330, 60, 160, 380, 40))]
+    g[0x1F3C6] = [filled([(CX - 280, CY + 420), (CX + 280, CY + 420), (CX + 240, CY + 60)] + arc(CX, CY + 60, 240, 0, -180, 16)[1:-1] + [(CX - 240, CY + 60)]),
+                  filled(stroke_arc(CX - 300, CY + 250, 110, 90, 270, 50)), filled(stroke_arc(CX + 300, CY + 250, 110, -90, 90, 50)),
+                  filled(rect(CX - 40, CY - 290, CX + 40, CY - 180)), filled(rect(CX - 200, CY - 420, CX + 200, CY - 290, 20))]
+    g[0x1F381] = [filled(rect(CX - 400, CY - 420, CX + 400, CY + 120, 20)), filled(rect(CX - 450, CY + 140, CX + 450, CY + 300, 20)),
+                  hole(rect(CX - 50, CY - 420, CX + 50, CY + 120)), filled(stroke_arc(CX - 130, CY + 380, 110, 0, 300, 50)),
+                  filled(stroke_arc(CX + 130, CY + 380, 110, -120, 180, 50))]
+    g[0x1F5D1] = [filled(rect(CX - 300, CY - 440, CX + 300, CY + 220, 50)), filled(rect(CX - 400, CY + 260, CX + 400, CY + 340, 20)),
+                  filled(rect(CX - 110, CY + 340, CX + 110, CY + 420, 20))] + [hole(rect(CX + x - 30, CY - 340, CX + x + 30, CY + 120, 30)) for x in (-150, 0, 150)]
+    g[0x23F0] = [filled(circle(CX, CY - 40, 380)), hole(circle(CX, CY - 40, 310)), filled(rect(CX - 30, CY - 60, CX + 30, CY + 200)),
+                 filled(rect(CX - 20, CY - 70, CX + 180, CY - 10)), filled(stroke_arc(CX - 300, CY + 330, 110, 10, 170, 70)),
+                 filled(stroke_arc(CX + 300, CY + 330, 110, 10, 170, 70))]
+    g[0x231B] = g[0x23F3] = [filled(rect(CX - 340, CY + 360, CX + 340, CY + 440)), filled(rect(CX - 340, CY - 440, CX + 340, CY - 360)),
+                             filled([(CX - 280, CY + 360), (CX + 280, CY + 360), (CX + 40, CY), (CX + 280, CY - 360), (CX - 280, CY - 360), (CX - 40, CY)])]
+    g[0x1F30D] = g[0x1F30E] = g[0x1F30F] = g[0x1F310] = [filled(circle(CX, CY, 430)), hole(circle(CX, CY, 370)),
+                                                        filled(ellipse(CX, CY, 150, 370)), hole(ellipse(CX, CY, 100, 320)),
+                                                        filled(rect(CX - 370, CY - 25, CX + 370, CY + 25)), filled(stroke_arc(CX, CY - 480, 420, 55, 125, 40)),
+                                                        filled(stroke_arc(CX, CY + 480, 420, 235, 305, 40))]
+    return g
+
+
+# --- TrueType -----------------------------------------------------------------
+
+def glyph_data(contours):
+    contours = [[(round(x), round(y)) for x, y in c] for c in contours]
+    # Drop points repeated back to back, which have no segment between them
+    contours = [[p for i, p in enumerate(c) if p != c[i - 1]] for c in contours]
+    contours = [c for c in contours if len(c) >= 3]
+    if not contours:
+        return b"", (0, 0, 0, 0)
+    points = [p for c in contours for p in c]
+    xs, ys = [p[0] for p in points], [p[1] for p in points]
+    bounds = (min(xs), min(ys), max(xs), max(ys))
+    out = struct.pack(">hhhhh", len(contours), *bounds)
+    end = -1
+    for c in contours:
+        end += len(c)
+        out += struct.pack(">H", end)
+    out += struct.pack(">H", 0)
+    out += bytes([0x01] * len(points))
+    last = 0
+    for x in xs:
+        out += struct.pack(">h", x - last)
+        last = x
+    last = 0
+    for y in ys:
+        out += struct.pack(">h", y - last)
+        last = y
+    if len(out) % 2:
+        out += b"\0"
+    return out, bounds
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def name_table(strings):
+    records, blob = b"", b""
+    for name_id, text in strings:
+        encoded = text.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(blob))
+        blob += encoded
+    return struct.pack(">HHH", 0, len(strings), 6 + len(records)) + records + blob
+
+
+def cmap_table(mapping):
+    codes = sorted(mapping)
+    # Format 12 groups of consecutive codes mapping to consecutive glyphs
+    groups = []
+    for code in codes:
+        if groups and code == groups[-1][1] + 1 and mapping[code] == groups[-1][2] + (code - groups[-1][0]):
+            groups[-1][1] = code
+        else:
+            groups.append([code, code, mapping[code]])
+    f12 = struct.pack(">HHIII", 12, 0, 16 + 12 * len(groups), 0, len(groups))
+    for start, end, glyph in groups:
+        f12 += struct.pack(">III", start, end, glyph)
+    # Format 4 for the BMP codes, one segment per code
+    bmp = [c for c in codes if c < 0xFFFF]
+    segments = [(c, c, mapping[c]) for c in bmp] + [(0xFFFF, 0xFFFF, 0)]
+    count = len(segments)
+    search = 2 ** int(math.log2(count))
+    f4 = b"".join(struct.pack(">H", end) for _, end, _ in segments) + struct.pack(">H", 0)
+    f4 += b"".join(struct.pack(">H", start) for start, _, _ in segments)
+    f4 += b"".join(struct.pack(">h", ((glyph - start + 0x8000) % 0x10000) - 0x8000 if start != 0xFFFF else 1) for start, _, glyph in segments)
+    f4 += struct.pack(">%dH" % count, *([0] * count))
+    f4 = struct.pack(">HHHHHHH", 4, 14 + len(f4), 0, count * 2, search * 2, int(math.log2(search)), (count - search) * 2) + f4
+    header = struct.pack(">HH", 0, 2)
+    header += struct.pack(">HHI", 3, 1, 4 + 16)
+    header += struct.pack(">HHI", 3, 10, 4 + 16 + len(f4))
+    return header + f4 + f12
+
+
+def build(path):
+    drawn = glyphs()
+    # One glyph per distinct drawing; codes sharing a drawing share the glyph
+    shapes, mapping = [[]], {}
+    for code in sorted(drawn):
+        contours = drawn[code]
+        for index, existing in enumerate(shapes):
+            if existing is contours:
+                mapping[code] = index
+                break
+        else:
+            mapping[code] = len(shapes)
+            shapes.append(contours)
+
+    glyf, loca, bounds_all = b"", [0], []
+    for contours in shapes:
+        data, bounds = glyph_data(contours)
+        glyf += data
+        loca.append(len(glyf))
+        if data:
+            bounds_all.append(bounds)
+    x_min = min(b[0] for b in bounds_all)
+    y_min = min(b[1] for b in bounds_all)
+    x_max = max(b[2] for b in bounds_all)
+    y_max = max(b[3] for b in bounds_all)
+    max_points = max(sum(len(c) for c in s) for s in shapes)
+    max_contours = max(len(s) for s in shapes)
+    count = len(shapes)
+    created = int(time.mktime((2026, 10, 1, 0, 0, 0, 0, 0, 0))) + 2082844800
+
+    tables = {
+        b"head": struct.pack(">IIIIHHQQhhhhHHhhh", 0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, UPM, created, created,
+                             x_min, y_min, x_max, y_max, 0, 8, 2, 1, 0),
+        b"hhea": struct.pack(">IhhhHhhhhhhhhhhhH", 0x00010000, ASCENT, DESCENT, 0, ADVANCE, 0, 0, x_max, 1, 0, 0, 0, 0, 0, 0, 0, count),
+        b"maxp": struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, count, max_points, max_contours, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0),
+        b"OS/2": struct.pack(">HhHHHhhhhhhhhhhh10sIIII4sHHHhhhHHIIhhHHH", 4, ADVANCE, 400, 5, 0, 650, 600, 0, 75, 650, 600, 0, 350, 50, 250, 0,
+                             bytes(10), 0, 0, 0, 0, b"PRSM", 0x40, min(mapping), min(max(mapping), 0xFFFF), ASCENT, -DESCENT, 0, ASCENT, -DESCENT,
+                             1, 0, 500, 700, 0, 0x20, 0),
+        b"hmtx": b"".join(struct.pack(">Hh", ADVANCE, 0) for _ in shapes),
+        b"loca": b"".join(struct.pack(">I", offset) for offset in loca),
+        b"glyf": glyf,
+        b"cmap": cmap_table(mapping),
+        b"name": name_table([
+            (0, "Drawn for the Prism browser; same license as the browser"),
+            (1, "Prism Emoji"), (2, "Regular"), (3, "Prism Emoji Regular 1.0"), (4, "Prism Emoji Regular"),
+            (5, "Version 1.0"), (6, "PrismEmoji-Regular"),
+        ]),
+        b"post": struct.pack(">IIhhIIIII", 0x00030000, 0, -75, 50, 0, 0, 0, 0, 0),
+    }
+    tags = sorted(tables)
+    search = 2 ** int(math.log2(len(tags)))
+    font = struct.pack(">IHHHH", 0x00010000, len(tags), search * 16, int(math.log2(search)), len(tags) * 16 - search * 16)
+    offset = 12 + 16 * len(tags)
+    directory, body = b"", b""
+    for tag in tags:
+        data = tables[tag]
+        directory += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    font += directory + body
+    adjustment = (0xB1B0AFBA - checksum(font)) & 0xFFFFFFFF
+    head = font.index(tables[b"head"], 12 + 16 * len(tags))
+    font = font[:head + 8] + struct.pack(">I", adjustment) + font[head + 12:]
+    with open(path, "wb") as f:
+        f.write(font)
+    print("%s: %d emoji in %d glyphs, %d bytes" % (path, len(mapping), count - 1, len(font)))
+
+
+if __name__ == "__main__":
+    build(os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "PrismEmoji.ttf"))
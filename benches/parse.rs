@@ -0,0 +1,73 @@
+//! Parser throughput on large generated documents (`cargo bench --bench parse`)
+//!
+//! Runs without the unstable bench harness: each case is parsed a few times
+//! and the best time is reported. The last case also checks that an error at
+//! the end of a large file still reports the right line and column.
+
+use prism::parser;
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+/// A page with `rows` cards, each with text, an input and a button, plus as
+/// many state fields and actions
+fn document(rows: usize, text: &str) -> String {
+    let mut out = String::from("@app \"Bench\"\n@version 1\n\n-- State --\nstate {\n");
+    for i in 0..rows {
+        let _ = writeln!(out, "  field_{i}: {{ name: \"{text} {i}\", count: {i}, tags: [\"a\", \"b\"] }}");
+    }
+    out.push_str("}\n\nview {\n  column {\n    padding: 16\n");
+    for i in 0..rows {
+        let _ = writeln!(
+            out,
+            "    card {{\n      text \"{text} {{field_{i}.name}}: {{field_{i}.count * 2 + 1}}\" {{ size: 14 }}\n      \
+             input {{ bind: field_{i}  placeholder: \"{text}\" }}\n      \
+             button \"Bump\" {{ on_click: bump_{i} }}\n    }}"
+        );
+    }
+    out.push_str("  }\n}\n\nactions {\n");
+    for i in 0..rows {
+        let _ = writeln!(out, "  bump_{i} {{\n    if field_{i}.count < 10 {{\n      field_{i}.count: field_{i}.count + 1\n    }}\n  }}");
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn best_of(source: &str) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            black_box(parser::parse(black_box(source)).expect("benchmark document parses"));
+            started.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let cases = [
+        ("ascii, 1k rows", document(1_000, "Item")),
+        ("ascii, 10k rows", document(10_000, "Item")),
+        ("non-ascii, 10k rows", document(10_000, "Ítem ✓ 項目")),
+    ];
+    for (name, source) in &cases {
+        let elapsed = best_of(source);
+        let mib = source.len() as f64 / (1024.0 * 1024.0);
+        println!(
+            "{:<22} {:>8.2} MiB {:>10.2?} {:>8.1} MiB/s",
+            name,
+            mib,
+            elapsed,
+            mib / elapsed.as_secs_f64()
+        );
+    }
+
+    // Positions stay exact after megabytes of multi-byte text
+    let (_, source) = &cases[2];
+    let broken = format!("{}view {{ ? }}\n", source);
+    let error = parser::parse(&broken).expect_err("trailing garbage fails to parse");
+    let line = broken.lines().count();
+    assert_eq!((error.line, error.col), (line, 8), "error position drifted: {}", error);
+}
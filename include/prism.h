@@ -0,0 +1,73 @@
+/* Prism engine C API - see src/ffi.rs for the full contract.
+ *
+ * Build with `cargo build --release`; link against libprism.so / prism.dll /
+ * libprism.dylib from target/release. Engines are single-threaded. */
+
+#ifndef PRISM_H
+#define PRISM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PRISM_OK 0
+#define PRISM_ERROR (-1)
+
+#define PRISM_KEY_BACKSPACE 1
+#define PRISM_KEY_TAB 2
+#define PRISM_KEY_ENTER 3
+#define PRISM_KEY_ESCAPE 4
+#define PRISM_KEY_UP 5
+#define PRISM_KEY_DOWN 6
+#define PRISM_KEY_HOME 7
+#define PRISM_KEY_END 8
+
+typedef struct PrismEngine PrismEngine;
+
+PrismEngine *prism_engine_new(uint32_t width, uint32_t height);
+void prism_engine_free(PrismEngine *engine);
+
+/* Load a .prism source; `location` may be NULL. Queues a "load" event. */
+int prism_engine_load_source(PrismEngine *engine, const char *source, const char *location);
+/* Message of the last failed call, owned by the engine, or NULL. */
+const char *prism_engine_last_error(const PrismEngine *engine);
+
+void prism_engine_resize(PrismEngine *engine, uint32_t width, uint32_t height);
+int prism_engine_scroll(PrismEngine *engine, int dy);
+/* Host preferences pages read as env.dark_mode and env.locale ("pt-BR"). */
+void prism_engine_set_dark_mode(PrismEngine *engine, int dark);
+void prism_engine_set_locale(PrismEngine *engine, const char *locale);
+/* Also delivers broadcasts from same-origin engines (those loaded with a
+ * location) and queues "notification" events; call once per frame. */
+int prism_engine_needs_render(PrismEngine *engine);
+uint32_t prism_engine_content_height(PrismEngine *engine);
+/* Writes width * height 0x00RRGGBB pixels, row-major; `len` is in pixels. */
+int prism_engine_render(PrismEngine *engine, uint32_t *buffer, size_t len);
+
+int prism_engine_pointer_down(PrismEngine *engine, int x, int y);
+int prism_engine_pointer_move(PrismEngine *engine, int x, int y);
+int prism_engine_pointer_leave(PrismEngine *engine);
+void prism_engine_pointer_up(PrismEngine *engine, int x, int y);
+int prism_engine_key_char(PrismEngine *engine, uint32_t codepoint);
+/* IME composition shown at the caret (`cursor` is a byte offset, -1 for the
+ * end; "" ends it), and the text it finally commits. */
+int prism_engine_ime_preedit(PrismEngine *engine, const char *text, int cursor);
+int prism_engine_ime_commit(PrismEngine *engine, const char *text);
+int prism_engine_key(PrismEngine *engine, int key, int shift);
+
+/* Nodes of the last render matching a selector such as `button[content="Save"]`,
+ * as a JSON array; NULL on an invalid selector. Release with prism_string_free. */
+char *prism_engine_query(PrismEngine *engine, const char *selector);
+
+/* Next event as JSON, or NULL when none; release with prism_string_free. */
+char *prism_engine_poll_event(PrismEngine *engine);
+void prism_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PRISM_H */
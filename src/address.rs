@@ -0,0 +1,98 @@
+//! Web addresses typed into the address bar or followed from links
+//!
+//! Addresses are requested (and kept in history) in their encoded ASCII form:
+//! spaces and other characters outside the URL grammar are percent-encoded
+//! and unicode hostnames are converted to punycode. The address bar shows the
+//! readable form instead, except for hostnames that mix Latin with lookalike
+//! Cyrillic or Greek letters, which stay in punycode so `аpple.com` can't pass
+//! for `apple.com`.
+
+use url::{Position, Url};
+
+/// Cyrillic and Greek letters that look like Latin lowercase letters
+const LOOKALIKES: &str = "аеорсухіјѕԁһԛԝӏүѵοικνρυ";
+
+/// The encoded form of an `http(s)://` address, for requesting it
+pub fn normalize(address: &str) -> Result<String, String> {
+    let url = Url::parse(address.trim()).map_err(|e| format!("Invalid address {}: {}", address.trim(), e))?;
+    Ok(url.to_string())
+}
+
+/// How an address is shown in the address bar; anything that isn't an
+/// `http(s)://` URL is shown as is
+pub fn display(address: &str) -> String {
+    let url = match Url::parse(address) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+        _ => return address.to_string(),
+    };
+    let host = url.host_str().map(readable_host).unwrap_or_default();
+    format!("{}{}{}", &url[..Position::BeforeHost], host, decode(&url[Position::AfterHost..]))
+}
+
+/// The unicode form of a punycode hostname, unless it could be mistaken for another
+fn readable_host(host: &str) -> String {
+    match idna::domain_to_unicode(host) {
+        (unicode, Ok(())) if !unicode.split('.').any(is_spoofable) => unicode,
+        _ => host.to_string(),
+    }
+}
+
+/// Whether a hostname label mixes Latin with Cyrillic or Greek, or is made
+/// only of Cyrillic or Greek letters that look Latin
+fn is_spoofable(label: &str) -> bool {
+    let letters: Vec<char> = label.chars().filter(|c| c.is_alphabetic()).collect();
+    let latin = letters.iter().any(|c| c.is_ascii_alphabetic() || ('\u{C0}'..='\u{24F}').contains(c));
+    let cyrillic = letters.iter().any(|c| ('\u{400}'..='\u{52F}').contains(c));
+    let greek = letters.iter().any(|c| ('\u{370}'..='\u{3FF}').contains(c));
+    let scripts = [latin, cyrillic, greek].iter().filter(|s| **s).count();
+    scripts > 1 || (!latin && !letters.is_empty() && letters.iter().all(|c| LOOKALIKES.contains(*c)))
+}
+
+/// Decode percent-escapes that spell out visible non-ASCII characters; ASCII
+/// escapes such as `%20` or `%2F` keep their meaning and stay encoded
+fn decode(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // The run of consecutive escapes starting here
+        let mut bytes = vec![];
+        while let Some(byte) = rest.get(1..3).filter(|_| rest.starts_with('%')).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            bytes.push(byte);
+            rest = &rest[3..];
+        }
+        if bytes.is_empty() {
+            out.push('%');
+            rest = &rest[1..];
+            continue;
+        }
+        match String::from_utf8(bytes) {
+            Ok(decoded) => {
+                for c in decoded.chars() {
+                    if c.is_ascii() || c.is_whitespace() || c.is_control() || is_invisible(c) {
+                        let mut buf = [0; 4];
+                        for byte in c.encode_utf8(&mut buf).bytes() {
+                            out.push_str(&format!("%{:02X}", byte));
+                        }
+                    } else {
+                        out.push(c);
+                    }
+                }
+            }
+            Err(e) => {
+                for byte in e.into_bytes() {
+                    out.push_str(&format!("%{:02X}", byte));
+                }
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Zero-width and bidirectional formatting characters, which could disguise
+/// what an address says
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{AD}' | '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2069}' | '\u{FEFF}')
+}
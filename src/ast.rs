@@ -4,7 +4,8 @@
 //! The AST represents the parsed structure of a .prism file.
 //! Extended for production use with lists, objects, components, and more.
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 // ============================================================================
@@ -12,17 +13,31 @@ use std::fmt;
 // ============================================================================
 
 /// Root of a Prism application
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrismApp {
     pub name: String,
     pub version: u32,
     pub imports: Vec<Import>,
+    pub capabilities: Vec<String>,
+    /// `@strict`: assignments that don't match a field's declared type are refused
+    pub strict: bool,
+    /// Fonts from `@font` directives, in declaration order
+    pub fonts: Vec<FontFace>,
     pub state: StateBlock,
+    #[serde(serialize_with = "ordered_map")]
     pub computed: HashMap<String, Expression>,
+    #[serde(serialize_with = "ordered_map")]
     pub components: HashMap<String, ComponentDef>,
+    /// Named prop sets from the `styles` block, for nodes' `style` prop
+    #[serde(serialize_with = "ordered_map")]
+    pub styles: HashMap<String, BTreeMap<String, PropValue>>,
     pub view: ViewNode,
+    #[serde(serialize_with = "ordered_map")]
     pub actions: HashMap<String, ActionBlock>,
+    #[serde(serialize_with = "ordered_map")]
     pub routes: HashMap<String, ViewNode>,
+    /// Translations from the `strings` block, in declaration order
+    pub strings: Vec<StringTable>,
 }
 
 impl Default for PrismApp {
@@ -31,9 +46,13 @@ impl Default for PrismApp {
             name: "Untitled".to_string(),
             version: 1,
             imports: vec![],
+            capabilities: vec![],
+            strict: false,
+            fonts: vec![],
             state: StateBlock::default(),
             computed: HashMap::new(),
             components: HashMap::new(),
+            styles: HashMap::new(),
             view: ViewNode {
                 kind: NodeKind::Column,
                 props: HashMap::new(),
@@ -41,29 +60,166 @@ impl Default for PrismApp {
             },
             actions: HashMap::new(),
             routes: HashMap::new(),
+            strings: vec![],
         }
     }
 }
 
+impl PrismApp {
+    /// The view drawn for a route: its entry in the `routes` block, or the `view` block
+    pub fn view_for(&self, route: &str) -> &ViewNode {
+        let path = route.split(['?', '#']).next().unwrap_or(route);
+        self.match_route(path).map_or(&self.view, |(view, _)| view)
+    }
+
+    /// The `routes` pattern a view is drawn for, or "" for the `view` block
+    pub fn route_pattern(&self, view: &ViewNode) -> &str {
+        self.routes.iter().find(|(_, route)| std::ptr::eq(*route, view)).map_or("", |(pattern, _)| pattern.as_str())
+    }
+
+    /// The `routes` entry for a path, with the values of its `:name`
+    /// parameters; an exact entry wins over a parameterized one
+    pub fn match_route(&self, path: &str) -> Option<(&ViewNode, HashMap<String, String>)> {
+        if let Some(view) = self.routes.get(path) {
+            return Some((view, HashMap::new()));
+        }
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let mut patterns: Vec<&String> = self.routes.keys().filter(|p| p.contains("/:")).collect();
+        patterns.sort();
+        patterns.into_iter().find_map(|pattern| {
+            let parts: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+            if parts.len() != segments.len() {
+                return None;
+            }
+            let mut params = HashMap::new();
+            for (part, segment) in parts.iter().zip(&segments) {
+                match part.strip_prefix(':') {
+                    Some(name) if !segment.is_empty() => {
+                        params.insert(name.to_string(), segment.to_string());
+                    }
+                    _ if part == segment => {}
+                    _ => return None,
+                }
+            }
+            Some((&self.routes[pattern], params))
+        })
+    }
+}
+
+/// One locale of the `strings` block: keys to text, or to plural forms
+/// (`{ one: "...", other: "..." }`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StringTable {
+    pub locale: String,
+    #[serde(serialize_with = "ordered_map")]
+    pub entries: HashMap<String, Value>,
+}
+
 /// Import statement for modules
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Import {
     pub path: String,
     pub alias: Option<String>,
 }
 
+/// `@font "Inter-Bold" "fonts/inter-bold.ttf"`: a font file the `font` prop can name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FontFace {
+    pub name: String,
+    /// Path of a TrueType/OpenType file among the document's assets
+    pub src: String,
+}
+
 // ============================================================================
 // STATE MANAGEMENT
 // ============================================================================
 
 /// State declaration block
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StateBlock {
+    #[serde(serialize_with = "ordered_map")]
     pub fields: HashMap<String, Value>,
+    /// Declared types of the fields that have one (`count: int = 0`)
+    #[serde(serialize_with = "ordered_map")]
+    pub types: HashMap<String, ValueType>,
+}
+
+/// Type annotation on a state field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ValueType {
+    Any,
+    Bool,
+    Int,
+    /// Also holds ints, which widen
+    Float,
+    String,
+    /// `list<T>`; a bare `list` holds `Any`
+    List(Box<ValueType>),
+    Object,
+}
+
+impl ValueType {
+    /// The type named by a keyword in a state block, without element type
+    pub fn from_keyword(name: &str) -> Option<ValueType> {
+        Some(match name {
+            "any" => ValueType::Any,
+            "bool" => ValueType::Bool,
+            "int" => ValueType::Int,
+            "float" => ValueType::Float,
+            "string" => ValueType::String,
+            "list" => ValueType::List(Box::new(ValueType::Any)),
+            "object" => ValueType::Object,
+            _ => return None,
+        })
+    }
+
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (ValueType::Any, _) => true,
+            (ValueType::Bool, Value::Bool(_)) => true,
+            (ValueType::Int, Value::Int(_)) => true,
+            (ValueType::Float, Value::Float(_) | Value::Int(_)) => true,
+            (ValueType::String, Value::String(_)) => true,
+            (ValueType::List(item), Value::List(items)) => items.iter().all(|v| item.accepts(v)),
+            (ValueType::Object, Value::Object(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// The value of a field declared with a type but no value
+    pub fn default_value(&self) -> Value {
+        match self {
+            ValueType::Any => Value::Null,
+            ValueType::Bool => Value::Bool(false),
+            ValueType::Int => Value::Int(0),
+            ValueType::Float => Value::Float(0.0),
+            ValueType::String => Value::String(String::new()),
+            ValueType::List(_) => Value::List(vec![]),
+            ValueType::Object => Value::Object(HashMap::new()),
+        }
+    }
+
+    /// The type of a list's items, for checks on push and insert
+    pub fn item_type(&self) -> Option<&ValueType> {
+        match self {
+            ValueType::List(item) => Some(item),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::List(item) if **item == ValueType::Any => write!(f, "list"),
+            ValueType::List(item) => write!(f, "list<{}>", item),
+            other => write!(f, "{}", format!("{:?}", other).to_lowercase()),
+        }
+    }
 }
 
 /// A value in the Prism type system - now with Lists and Objects
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Value {
     Null,
     Bool(bool),
@@ -71,7 +227,7 @@ pub enum Value {
     Float(f64),
     String(String),
     List(Vec<Value>),
-    Object(HashMap<String, Value>),
+    Object(#[serde(serialize_with = "ordered_map")] HashMap<String, Value>),
 }
 
 impl PartialEq for Value {
@@ -90,6 +246,17 @@ impl PartialEq for Value {
 }
 
 impl Value {
+    /// Approximate bytes the value occupies, including what it owns
+    pub fn estimated_size(&self) -> usize {
+        let inline = std::mem::size_of::<Value>();
+        match self {
+            Value::String(s) => inline + s.capacity(),
+            Value::List(items) => inline + items.iter().map(Value::estimated_size).sum::<usize>(),
+            Value::Object(map) => inline + map.iter().map(|(k, v)| k.capacity() + v.estimated_size()).sum::<usize>(),
+            _ => inline,
+        }
+    }
+
     pub fn type_name(&self) -> &'static str {
         match self {
             Value::Null => "null",
@@ -129,11 +296,36 @@ impl Value {
         }
     }
 
+    /// Plain JSON form: numbers, strings, arrays and objects as themselves
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => (*b).into(),
+            Value::Int(i) => (*i).into(),
+            Value::Float(f) => serde_json::Number::from_f64(*f).map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::String(s) => s.clone().into(),
+            Value::List(items) => items.iter().map(Value::to_json).collect(),
+            Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v.to_json())).collect(),
+        }
+    }
+
+    pub fn from_json(json: &serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(*b),
+            serde_json::Value::Number(n) => n.as_i64().map_or_else(|| Value::Float(n.as_f64().unwrap_or(0.0)), Value::Int),
+            serde_json::Value::String(s) => Value::String(s.clone()),
+            serde_json::Value::Array(items) => Value::List(items.iter().map(Value::from_json).collect()),
+            serde_json::Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), Value::from_json(v))).collect()),
+        }
+    }
+
     pub fn as_int(&self) -> i64 {
         match self {
             Value::Int(i) => *i,
             Value::Float(f) => *f as i64,
-            Value::String(s) => s.parse().unwrap_or(0),
+            Value::String(s) => s.trim().parse()
+                .unwrap_or_else(|_| crate::locale::parse_number(s, '.').filter(|n| n.is_finite()).unwrap_or(0.0) as i64),
             Value::Bool(b) => if *b { 1 } else { 0 },
             Value::List(l) => l.len() as i64,
             Value::Null | Value::Object(_) => 0,
@@ -144,7 +336,7 @@ impl Value {
         match self {
             Value::Int(i) => *i as f64,
             Value::Float(f) => *f,
-            Value::String(s) => s.parse().unwrap_or(0.0),
+            Value::String(s) => crate::locale::parse_number(s, '.').unwrap_or(0.0),
             Value::Bool(b) => if *b { 1.0 } else { 0.0 },
             Value::List(l) => l.len() as f64,
             Value::Null | Value::Object(_) => 0.0,
@@ -224,17 +416,20 @@ impl fmt::Display for Value {
 // ============================================================================
 
 /// Component definition - reusable UI pieces
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentDef {
     pub name: String,
     pub props: Vec<PropDef>,
     pub state: StateBlock,
+    /// Names read from the nearest enclosing `provide`, in declaration order
+    pub inject: Vec<String>,
     pub view: ViewNode,
+    #[serde(serialize_with = "ordered_map")]
     pub actions: HashMap<String, ActionBlock>,
 }
 
 /// Property definition for components
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PropDef {
     pub name: String,
     pub default: Option<Value>,
@@ -246,15 +441,27 @@ pub struct PropDef {
 // ============================================================================
 
 /// A node in the view tree
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ViewNode {
     pub kind: NodeKind,
+    #[serde(serialize_with = "ordered_map")]
     pub props: HashMap<String, PropValue>,
     pub children: Vec<ViewNode>,
 }
 
+impl ViewNode {
+    /// Names in the `style` prop: one bare name or several in a string
+    pub fn style_names(&self) -> Vec<&str> {
+        match self.props.get("style") {
+            Some(PropValue::Handler(name)) => vec![name.as_str()],
+            Some(PropValue::Static(Value::String(names))) => names.split_whitespace().collect(),
+            _ => vec![],
+        }
+    }
+}
+
 /// Types of view nodes - extended for real applications
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum NodeKind {
     // Layout
     Column,
@@ -273,6 +480,7 @@ pub enum NodeKind {
     Text,
     Link,
     Markdown,
+    Code,
     
     // Interactive
     Button,
@@ -283,6 +491,11 @@ pub enum NodeKind {
     Select,
     Slider,
     Toggle,
+    FilePicker,
+    Combobox,
+    Tags,
+    Rating,
+    Stepper,
     
     // Media
     Image,
@@ -303,6 +516,7 @@ pub enum NodeKind {
     Toast,
     Tooltip,
     Popover,
+    Skeleton,
     
     // Control Flow
     Each,       // List iteration
@@ -310,13 +524,25 @@ pub enum NodeKind {
     Show,       // Visibility toggle (keeps in DOM)
     Switch,     // Multi-branch conditional
     Slot,       // Component slot
+    Empty,      // Shown by list/table/each when the collection is empty
+    Error,      // Shown by list/table/each when the collection failed to load
     
     // Custom
     Component(String),  // User-defined component
 }
 
+impl NodeKind {
+    /// The keyword that declares this node in source (`textarea`, or the component's name)
+    pub fn name(&self) -> String {
+        match self {
+            NodeKind::Component(name) => name.clone(),
+            kind => format!("{:?}", kind).to_lowercase(),
+        }
+    }
+}
+
 /// Property values can be static, dynamic, or handlers
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PropValue {
     Static(Value),
     Expression(Expression),
@@ -326,7 +552,7 @@ pub enum PropValue {
 }
 
 /// Event handler with optional parameters
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventHandler {
     pub action: String,
     pub args: Vec<Expression>,
@@ -337,7 +563,7 @@ pub struct EventHandler {
 // ============================================================================
 
 /// Colors in Prism
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -416,15 +642,10 @@ impl Color {
         ((self.a as u32) << 24) | ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
+    /// `other` drawn over this color with its alpha, in linear light
     pub fn blend(&self, other: &Color) -> Color {
-        let alpha = other.a as f32 / 255.0;
-        let inv_alpha = 1.0 - alpha;
-        Color {
-            r: (self.r as f32 * inv_alpha + other.r as f32 * alpha) as u8,
-            g: (self.g as f32 * inv_alpha + other.g as f32 * alpha) as u8,
-            b: (self.b as f32 * inv_alpha + other.b as f32 * alpha) as u8,
-            a: 255,
-        }
+        let (r, g, b) = crate::color::unpack(crate::color::blend(self.to_u32(), other.to_u32(), other.a));
+        Color { r, g, b, a: 255 }
     }
 }
 
@@ -439,7 +660,7 @@ impl Default for Color {
 // ============================================================================
 
 /// Expressions for dynamic values - significantly expanded
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expression {
     // Literals
     Literal(Value),
@@ -530,13 +751,13 @@ pub enum Expression {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum InterpolationPart {
     Literal(String),
     Expression(Box<Expression>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOp {
     // Arithmetic
     Add,
@@ -566,7 +787,7 @@ pub enum BinaryOp {
     NotIn,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum UnaryOp {
     Not,
     Neg,
@@ -579,14 +800,14 @@ pub enum UnaryOp {
 // ============================================================================
 
 /// Action block - state mutations with control flow
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionBlock {
     pub params: Vec<String>,
     pub statements: Vec<Statement>,
 }
 
 /// Statements within actions
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Statement {
     // Variable assignment
     Assign {
@@ -642,6 +863,19 @@ pub enum Statement {
     // Navigate to route
     Navigate(Expression),
     
+    // Put text on the system clipboard
+    Copy(Expression),
+    
+    // Move keyboard focus to, or scroll to, the node with this `id`
+    Focus(Expression),
+    ScrollTo(Expression),
+    
+    // Tell the user something happened: `notify(title, body)`
+    Notify {
+        title: Expression,
+        body: Option<Expression>,
+    },
+    
     // HTTP fetch (sandboxed)
     Fetch {
         url: Expression,
@@ -681,7 +915,7 @@ pub enum Statement {
 }
 
 /// Assignment target (can be nested)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AssignTarget {
     Variable(String),
     Index {
@@ -695,7 +929,7 @@ pub enum AssignTarget {
 }
 
 /// HTTP methods for fetch
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum HttpMethod {
     Get,
     Post,
@@ -709,7 +943,7 @@ pub enum HttpMethod {
 // ============================================================================
 
 /// Legacy mutation format (for backwards compatibility)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Mutation {
     pub target: String,
     pub value: Expression,
@@ -723,3 +957,8 @@ impl From<Mutation> for Statement {
         }
     }
 }
+
+/// Serialize a map with sorted keys so dumps of the same document are identical
+fn ordered_map<V: Serialize, S: Serializer>(map: &HashMap<String, V>, serializer: S) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
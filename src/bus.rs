@@ -0,0 +1,87 @@
+//! Process-wide message bus between documents of the same origin
+//!
+//! Every runtime that knows its location joins the bus under that location's
+//! origin. `broadcast(channel, data)` posts to every *other* member with the
+//! same origin; members of other origins never see the message. Messages wait
+//! in the receiver's inbox until its host pumps it (see
+//! `Runtime::pump_broadcasts`).
+
+use crate::ast::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+/// Messages an inbox holds before the oldest are dropped
+const INBOX_LIMIT: usize = 256;
+
+struct Member {
+    origin: String,
+    inbox: VecDeque<(String, Value)>,
+}
+
+struct Bus {
+    next_id: u64,
+    members: BTreeMap<u64, Member>,
+}
+
+static BUS: Mutex<Bus> = Mutex::new(Bus { next_id: 1, members: BTreeMap::new() });
+
+fn with_bus<R>(f: impl FnOnce(&mut Bus) -> R) -> R {
+    let mut bus = BUS.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut bus)
+}
+
+/// Origin of a document location: `scheme://host[:port]` for URLs, the
+/// containing directory for local files
+pub fn origin_of(location: &str) -> String {
+    if let Some((scheme, rest)) = location.split_once("://") {
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+        return format!("{}://{}", scheme.to_lowercase(), authority.to_lowercase());
+    }
+    let path = std::path::Path::new(location);
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    format!("file://{}", dir.display())
+}
+
+/// Join the bus, returning the member id
+pub fn join(origin: &str) -> u64 {
+    with_bus(|bus| {
+        let id = bus.next_id;
+        bus.next_id += 1;
+        bus.members.insert(id, Member { origin: origin.to_string(), inbox: VecDeque::new() });
+        id
+    })
+}
+
+pub fn leave(id: u64) {
+    with_bus(|bus| {
+        bus.members.remove(&id);
+    });
+}
+
+/// Deliver a message to every other member sharing the sender's origin,
+/// returning how many received it
+pub fn post(from: u64, channel: &str, data: &Value) -> usize {
+    with_bus(|bus| {
+        let Some(origin) = bus.members.get(&from).map(|m| m.origin.clone()) else {
+            return 0;
+        };
+        let mut delivered = 0;
+        for (id, member) in bus.members.iter_mut() {
+            if *id == from || member.origin != origin {
+                continue;
+            }
+            if member.inbox.len() >= INBOX_LIMIT {
+                member.inbox.pop_front();
+            }
+            member.inbox.push_back((channel.to_string(), data.clone()));
+            delivered += 1;
+        }
+        delivered
+    })
+}
+
+/// Take every message waiting for `id`
+pub fn drain(id: u64) -> Vec<(String, Value)> {
+    with_bus(|bus| bus.members.get_mut(&id).map(|m| m.inbox.drain(..).collect()).unwrap_or_default())
+}
@@ -0,0 +1,784 @@
+//! Browser chrome: the toolbar's layout, drawing and hit-testing
+//!
+//! Everything positioned in the toolbar comes from a [`ChromeLayout`] built
+//! from the window size and the [`ChromeStyle`], so compact mode and moving the
+//! toolbar to the bottom need no coordinates elsewhere. The page is drawn in
+//! `layout.content`. User-configured [`ToolbarButton`]s sit between the
+//! address bar and the devtools buttons.
+
+use crate::settings::ToolbarButton;
+use crate::tls::Connection;
+use crate::{baseline_for_box, draw_refresh_arc, draw_text_fb, fit_text, line_metrics, measure_text_width, spinner_angle, Browser};
+use prism::image::Image;
+use prism::qr::{QrCode, QUIET_ZONE};
+use prism::renderer::{FrameBuffer, Misspelling};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Where the toolbar sits in the window
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ToolbarPosition {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// User-selectable chrome appearance
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChromeStyle {
+    /// Shorter toolbar with smaller buttons and text
+    pub compact: bool,
+    pub position: ToolbarPosition,
+    /// Page laid out in a narrow column centred in the window
+    pub reader: bool,
+    /// Private window: a dark toolbar with a "Private" badge
+    pub private: bool,
+    /// Battery saver: flat fills instead of gradients, and no shadows
+    pub battery_saver: bool,
+}
+
+impl ChromeStyle {
+    /// Top and bottom colors of a gradient, both the top one in battery saver
+    pub fn shades(&self, top: u32, bottom: u32) -> (u32, u32) {
+        if self.battery_saver { (top, top) } else { (top, bottom) }
+    }
+}
+
+/// Sizes of one chrome density
+struct Metrics {
+    bar_height: i32,
+    button: i32,
+    address_height: i32,
+    /// Space between the window edge and the outer buttons
+    edge: i32,
+    /// Space between neighbouring buttons
+    gap: i32,
+    /// Space between the navigation buttons and the address bar
+    address_gap: i32,
+    text_size: f32,
+    icon_size: i32,
+}
+
+const REGULAR: Metrics = Metrics {
+    bar_height: 50,
+    button: 28,
+    address_height: 32,
+    edge: 10,
+    gap: 7,
+    address_gap: 12,
+    text_size: 14.0,
+    icon_size: 20,
+};
+
+const COMPACT: Metrics = Metrics {
+    bar_height: 34,
+    button: 22,
+    address_height: 24,
+    edge: 6,
+    gap: 4,
+    address_gap: 8,
+    text_size: 12.0,
+    icon_size: 16,
+};
+
+/// Narrowest the address bar gets before it overlaps the devtools buttons
+const MIN_ADDRESS_WIDTH: i32 = 200;
+/// Widest the page is laid out in reader mode
+const READER_WIDTH: i32 = 680;
+const PRIVATE_LABEL: &str = "Private";
+const SECURITY_PANEL_WIDTH: i32 = 360;
+const SECURITY_LINE_HEIGHT: i32 = 22;
+/// Highlight behind selected address bar text
+const ADDRESS_SELECTION: u32 = 0xB4D5FE;
+const LEAVE_DIALOG_WIDTH: i32 = 360;
+const LEAVE_DIALOG_HEIGHT: i32 = 128;
+const LEAVE_BUTTON_WIDTH: i32 = 88;
+const LEAVE_TITLE: &str = "Discard changes?";
+const LEAVE_MESSAGE: &str = "This page has changes that may not be saved.";
+const SHARE_DIALOG_WIDTH: i32 = 320;
+/// Side of the QR code with its quiet zone, at most
+const SHARE_CODE_SIZE: i32 = 232;
+const SHARE_TITLE: &str = "Share this page";
+const REGION_HINT: &str = "Drag to copy a region · Shift saves it · Esc cancels";
+const REGION_OUTLINE: u32 = 0x1A73E8;
+const MENU_MIN_WIDTH: i32 = 160;
+const MENU_ITEM_HEIGHT: i32 = 26;
+const NO_SUGGESTIONS: &str = "No suggestions";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    fn new(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    fn right(&self) -> i32 {
+        self.x + self.width
+    }
+}
+
+/// A clickable part of the toolbar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChromeTarget {
+    Back,
+    Forward,
+    Address,
+    /// Lock (or warning) before an `https://` address, opening the certificate panel
+    Security,
+    Refresh,
+    SaveState,
+    LoadState,
+    /// A configured toolbar button, by index
+    Extension(usize),
+}
+
+/// Positions of the toolbar, its controls and the page area for one window size
+#[derive(Debug, Clone, Default)]
+pub struct ChromeLayout {
+    pub bar: Rect,
+    pub content: Rect,
+    pub back: Rect,
+    pub forward: Rect,
+    pub address: Rect,
+    pub security: Rect,
+    pub refresh: Rect,
+    pub save_state: Rect,
+    pub load_state: Rect,
+    pub extensions: Vec<Rect>,
+    /// "Private" badge before the address bar in private windows
+    pub private_badge: Option<Rect>,
+    pub text_size: f32,
+    icon_size: i32,
+}
+
+impl ChromeLayout {
+    pub fn new(width: usize, height: usize, style: ChromeStyle, extensions: usize) -> ChromeLayout {
+        let m = if style.compact { &COMPACT } else { &REGULAR };
+        let (width, height) = (width as i32, height as i32);
+        let bar_y = match style.position {
+            ToolbarPosition::Top => 0,
+            ToolbarPosition::Bottom => (height - m.bar_height).max(0),
+        };
+        let bar = Rect::new(0, bar_y, width, m.bar_height);
+        let mut content = match style.position {
+            ToolbarPosition::Top => Rect::new(0, bar.bottom(), width, (height - bar.height).max(1)),
+            ToolbarPosition::Bottom => Rect::new(0, 0, width, bar_y.max(1)),
+        };
+        if style.reader && width > READER_WIDTH {
+            content.x = (width - READER_WIDTH) / 2;
+            content.width = READER_WIDTH;
+        }
+
+        // Controls are centred in the bar, rounding away from the border line
+        let centered = |size: i32| bar.y + (bar.height - size + 1) / 2;
+        let button_y = centered(m.button);
+        let back = Rect::new(m.edge, button_y, m.button, m.button);
+        let forward = Rect::new(back.right() + m.gap, button_y, m.button, m.button);
+
+        let load_state = Rect::new(width - m.edge - m.button, button_y, m.button, m.button);
+        let save_state = Rect::new(load_state.x - m.gap - m.button, button_y, m.button, m.button);
+
+        let badge_width = (measure_text_width(PRIVATE_LABEL, m.text_size - 2.0) as i32) + m.edge * 2;
+        let private_badge = style.private.then(|| Rect::new(forward.right() + m.gap + m.address_gap, button_y, badge_width, m.button));
+        let address_x = match private_badge {
+            Some(badge) => badge.right() + m.gap,
+            None => forward.right() + m.gap + m.address_gap,
+        };
+
+        // Configured buttons fill leftward from the devtools buttons, in order;
+        // the last ones are left out when a narrow window has no room for them
+        let room = save_state.x - m.address_gap * 2 + m.gap - address_x - MIN_ADDRESS_WIDTH;
+        let extension_count = (extensions as i32).min(room.max(0) / (m.button + m.gap));
+        let first_extension = save_state.x - m.address_gap - extension_count * (m.button + m.gap) + m.gap;
+        let extensions: Vec<Rect> = (0..extension_count)
+            .map(|i| Rect::new(first_extension + i * (m.button + m.gap), button_y, m.button, m.button))
+            .collect();
+        let address_end = if extensions.is_empty() { save_state.x } else { first_extension };
+        let address_width = (address_end - m.address_gap - address_x).max(MIN_ADDRESS_WIDTH);
+        let address = Rect::new(address_x, centered(m.address_height), address_width, m.address_height);
+        let icon_inset = (m.address_height - m.icon_size) / 2;
+        let refresh = Rect::new(address.right() - m.icon_size - icon_inset * 2, address.y + icon_inset, m.icon_size, m.icon_size);
+        let security = Rect::new(address.x + icon_inset, address.y + icon_inset, m.icon_size, m.icon_size);
+
+        ChromeLayout {
+            bar,
+            content,
+            back,
+            forward,
+            address,
+            security,
+            refresh,
+            save_state,
+            load_state,
+            extensions,
+            private_badge,
+            text_size: m.text_size,
+            icon_size: m.icon_size,
+        }
+    }
+
+    /// The control under a point, before checking whether it is enabled
+    pub fn target_at(&self, x: i32, y: i32) -> Option<ChromeTarget> {
+        [
+            (self.refresh, ChromeTarget::Refresh),
+            (self.security, ChromeTarget::Security),
+            (self.save_state, ChromeTarget::SaveState),
+            (self.load_state, ChromeTarget::LoadState),
+            (self.back, ChromeTarget::Back),
+            (self.forward, ChromeTarget::Forward),
+            (self.address, ChromeTarget::Address),
+        ]
+        .into_iter()
+        .chain(self.extensions.iter().enumerate().map(|(i, rect)| (*rect, ChromeTarget::Extension(i))))
+        .find(|(rect, _)| rect.contains(x, y))
+        .map(|(_, target)| target)
+    }
+
+    /// Left edge of the address text, after the security indicator when there is one
+    fn address_text_x(&self, secure: bool) -> i32 {
+        if secure {
+            self.security.right() + 4
+        } else {
+            self.address.x + 10
+        }
+    }
+}
+
+/// The enabled control under a point
+pub fn enabled_target(browser: &Browser, x: i32, y: i32) -> Option<ChromeTarget> {
+    let target = browser.layout.target_at(x, y)?;
+    let enabled = match target {
+        ChromeTarget::Back => browser.can_go_back(),
+        ChromeTarget::Forward => browser.can_go_forward(),
+        ChromeTarget::Refresh => browser.can_refresh(),
+        ChromeTarget::SaveState | ChromeTarget::LoadState => browser.runtime.is_some(),
+        ChromeTarget::Security => browser.tls.is_some(),
+        ChromeTarget::Address => true,
+        ChromeTarget::Extension(i) => extension_enabled(browser, i),
+    };
+    // The refresh and security icons sit inside the address bar, which takes the click when they're off
+    match (enabled, target) {
+        (true, _) => Some(target),
+        (false, ChromeTarget::Refresh | ChromeTarget::Security) => Some(ChromeTarget::Address),
+        (false, _) => None,
+    }
+}
+
+fn extension_enabled(browser: &Browser, i: usize) -> bool {
+    match browser.settings.toolbar.get(i) {
+        Some(ToolbarButton::Home) => true,
+        Some(ToolbarButton::Reader | ToolbarButton::Screenshot) => browser.runtime.is_some(),
+        Some(ToolbarButton::Share) => browser.can_share(),
+        Some(ToolbarButton::Action { action, .. }) => browser.runtime.as_ref().is_some_and(|rt| rt.app.actions.contains_key(action)),
+        None => false,
+    }
+}
+
+/// Whether the pointer is over a button, for the hand cursor
+pub fn over_button(browser: &Browser, x: i32, y: i32) -> bool {
+    !matches!(enabled_target(browser, x, y), None | Some(ChromeTarget::Address))
+}
+
+/// A click on the toolbar; a `double` click in the address bar selects a word
+pub fn click(browser: &mut Browser, x: i32, y: i32, double: bool) {
+    match enabled_target(browser, x, y) {
+        Some(ChromeTarget::Back) => browser.go_back(),
+        Some(ChromeTarget::Forward) => browser.go_forward(),
+        Some(ChromeTarget::Refresh) => browser.start_refresh(),
+        Some(ChromeTarget::Security) => browser.security_open = !browser.security_open,
+        Some(ChromeTarget::SaveState) => browser.save_state_snapshot(),
+        Some(ChromeTarget::LoadState) => browser.load_state_snapshot(),
+        Some(ChromeTarget::Extension(i)) => browser.run_toolbar_button(i),
+        Some(ChromeTarget::Address) => {
+            browser.address_focused = true;
+            let at = address_cursor_at(browser, x);
+            if double {
+                browser.select_address_word(at);
+            } else {
+                browser.move_address_cursor(at, false);
+                browser.address_dragging = true;
+            }
+        }
+        None => {}
+    }
+}
+
+/// Extend the address bar selection to the character nearest `x`
+pub fn drag_address(browser: &mut Browser, x: i32) {
+    let at = address_cursor_at(browser, x);
+    browser.move_address_cursor(at, true);
+}
+
+fn address_text_x(browser: &Browser) -> i32 {
+    browser.layout.address_text_x(browser.tls.is_some())
+}
+
+/// Character index in the address text nearest to `x`
+fn address_cursor_at(browser: &Browser, x: i32) -> usize {
+    let layout = &browser.layout;
+    let rel_x = (x - address_text_x(browser)).max(0) as u32;
+    let mut accumulated = String::new();
+    let mut prev_width = 0u32;
+    for (i, ch) in browser.address_text.chars().enumerate() {
+        accumulated.push(ch);
+        let w = measure_text_width(&accumulated, layout.text_size);
+        if rel_x < (prev_width + w) / 2 {
+            return i;
+        }
+        prev_width = w;
+    }
+    browser.address_text.chars().count()
+}
+
+fn draw_button(fb: &mut FrameBuffer, style: ChromeStyle, rect: Rect, label: &str, size: f32, color: u32) {
+    let (top, bottom) = style.shades(0xEDEFF4, 0xD8DDE6);
+    fb.fill_rounded_rect_vertical_gradient(rect.x, rect.y, rect.width as u32, rect.height as u32, 6, top, bottom);
+    let base = baseline_for_box(rect.y, rect.height, size);
+    let w = measure_text_width(label, size) as i32;
+    draw_text_fb(fb, label, rect.x + (rect.width - w) / 2, base, size, color);
+}
+
+/// Name and SVG source of a button's icon
+fn icon(button: &ToolbarButton) -> (&'static str, &'static [u8]) {
+    match button {
+        ToolbarButton::Home => ("home", include_bytes!("../assets/icons/home.svg")),
+        ToolbarButton::Reader => ("reader", include_bytes!("../assets/icons/reader.svg")),
+        ToolbarButton::Screenshot => ("screenshot", include_bytes!("../assets/icons/screenshot.svg")),
+        ToolbarButton::Share => ("share", include_bytes!("../assets/icons/share.svg")),
+        ToolbarButton::Action { .. } => ("action", include_bytes!("../assets/icons/action.svg")),
+    }
+}
+
+thread_local! {
+    /// Decoded toolbar icons by name
+    static ICONS: RefCell<HashMap<&'static str, Image>> = RefCell::new(HashMap::new());
+}
+
+/// A toolbar icon centred in `rect`, tinted with `color`
+fn draw_icon(fb: &mut FrameBuffer, rect: Rect, size: i32, button: &ToolbarButton, color: u32) {
+    let (name, source) = icon(button);
+    ICONS.with(|icons| {
+        let mut icons = icons.borrow_mut();
+        if !icons.contains_key(name) {
+            match Image::decode(source) {
+                Ok(image) => {
+                    icons.insert(name, image);
+                }
+                Err(e) => {
+                    eprintln!("[PRISM] Failed to decode the {} toolbar icon: {}", name, e);
+                    return;
+                }
+            }
+        }
+        let Some(image) = icons.get_mut(name) else { return };
+        let (src_w, src_h, pixels) = image.pixels_for_size(size as u32, size as u32);
+        let tinted: Vec<u32> = pixels.iter().map(|p| (p & 0xFF000000) | color).collect();
+        let (x, y) = (rect.x + (rect.width - size) / 2, rect.y + (rect.height - size) / 2);
+        let clip = (rect.x, rect.y, rect.right(), rect.bottom());
+        fb.blend_scaled(x, y, size as u32, size as u32, src_w, src_h, &tinted, clip);
+    });
+}
+
+pub fn draw(fb: &mut FrameBuffer, browser: &Browser) {
+    let layout = &browser.layout;
+    let bar = layout.bar;
+    let (bar_top, bar_bottom) = if browser.chrome.private { (0x4A3B6B, 0x3E3160) } else { (0xFBFCFE, 0xF3F5F8) };
+    let (bar_top, bar_bottom) = browser.chrome.shades(bar_top, bar_bottom);
+    fb.fill_rounded_rect_vertical_gradient(bar.x, bar.y, bar.width as u32, bar.height as u32, 0, bar_top, bar_bottom);
+    // Border line on the side facing the page, which becomes a progress bar
+    // while slow work runs or a document streams in
+    let border_y = if bar.y == 0 { bar.bottom() - 1 } else { bar.y };
+    fb.fill_rect(bar.x, border_y, bar.width as u32, 1, if browser.chrome.private { 0x2F2548 } else { 0xDDDDDD });
+    let streamed = browser.stream.as_ref().map(|stream| stream.document.progress());
+    if browser.busy.is_some() || streamed.is_some() {
+        let progress_y = if bar.y == 0 { bar.bottom() - 2 } else { bar.y };
+        let done = streamed.flatten().map(|share| (bar.width as f32 * share) as u32).unwrap_or(bar.width as u32 / 3);
+        fb.fill_rect(bar.x, progress_y, bar.width as u32, 2, 0xCFE0FC);
+        fb.fill_rect(bar.x, progress_y, done, 2, 0x4285F4);
+    }
+
+    let arrow_size = layout.text_size + 2.0;
+    let enabled_color = |enabled: bool| if enabled { 0x333333 } else { 0x999999 };
+    draw_button(fb, browser.chrome, layout.back, "‹", arrow_size, enabled_color(browser.can_go_back()));
+    draw_button(fb, browser.chrome, layout.forward, "›", arrow_size, enabled_color(browser.can_go_forward()));
+
+    if let Some(badge) = layout.private_badge {
+        let size = layout.text_size - 2.0;
+        let (top, bottom) = browser.chrome.shades(0x6C58A0, 0x604C94);
+        fb.fill_rounded_rect_vertical_gradient(badge.x, badge.y, badge.width as u32, badge.height as u32, badge.height as u32 / 2, top, bottom);
+        let w = measure_text_width(PRIVATE_LABEL, size) as i32;
+        draw_text_fb(fb, PRIVATE_LABEL, badge.x + (badge.width - w) / 2, baseline_for_box(badge.y, badge.height, size), size, 0xFFFFFF);
+    }
+
+    let address = layout.address;
+    let border_color = if browser.address_focused { 0x4285F4 } else { 0xCCCCCC };
+    let (top, bottom) = browser.chrome.shades(0xFFFFFF, 0xF4F6F8);
+    fb.fill_rounded_rect_vertical_gradient(address.x, address.y, address.width as u32, address.height as u32, 6, top, bottom);
+    fb.draw_rect_outline(address.x, address.y, address.width as u32, address.height as u32, border_color, 1);
+
+    let text_size = layout.text_size;
+    let text_y = baseline_for_box(address.y, address.height, text_size);
+    let text_x = address_text_x(browser);
+    if let Some(tls) = &browser.tls {
+        draw_security_icon(fb, layout.security, tls.error.is_none());
+    }
+    if let Some((start, end)) = browser.address_selection().filter(|_| browser.address_focused) {
+        let before: String = browser.address_text.chars().take(start).collect();
+        let selected: String = browser.address_text.chars().skip(start).take(end - start).collect();
+        let (ascent, descent, _) = line_metrics(text_size);
+        let x = text_x + measure_text_width(&before, text_size) as i32;
+        fb.fill_rect(x, text_y - ascent, measure_text_width(&selected, text_size), (ascent + descent) as u32, ADDRESS_SELECTION);
+    }
+    if browser.address_text.is_empty() && !browser.address_focused {
+        draw_text_fb(fb, "Enter path (examples/home.prism)", text_x, text_y, text_size, 0x999999);
+    } else if browser.address_focused && !browser.address_preedit.is_empty() {
+        // The IME composition sits at the caret, underlined, until committed
+        let before: String = browser.address_text.chars().take(browser.address_cursor).collect();
+        let after: String = browser.address_text.chars().skip(browser.address_cursor).collect();
+        let preedit_x = text_x + measure_text_width(&before, text_size) as i32;
+        let preedit_width = measure_text_width(&browser.address_preedit, text_size);
+        draw_text_fb(fb, &before, text_x, text_y, text_size, 0x333333);
+        draw_text_fb(fb, &browser.address_preedit, preedit_x, text_y, text_size, 0x333333);
+        fb.fill_rect(preedit_x, text_y + 2, preedit_width, 1, 0x333333);
+        draw_text_fb(fb, &after, preedit_x + preedit_width as i32, text_y, text_size, 0x333333);
+    } else {
+        draw_text_fb(fb, &browser.address_text, text_x, text_y, text_size, 0x333333);
+    }
+
+    if browser.can_refresh() {
+        let icon = layout.refresh;
+        let (cx, cy) = (icon.x as f32 + icon.width as f32 / 2.0, icon.y as f32 + icon.height as f32 / 2.0);
+        let angle = browser.refresh_started.map(spinner_angle).unwrap_or(0.0);
+        let radius = icon.width as f32 * 0.325;
+        draw_refresh_arc(fb, cx, cy, radius, angle, 1.0, 0x555555, (bar.x, bar.y, bar.right(), bar.bottom()));
+    }
+
+    if browser.runtime.is_some() {
+        draw_button(fb, browser.chrome, layout.save_state, "↓", text_size, 0x555555);
+        draw_button(fb, browser.chrome, layout.load_state, "↑", text_size, 0x555555);
+    }
+
+    let icon_size = layout.icon_size * 3 / 4;
+    for (i, (button, rect)) in browser.settings.toolbar.iter().zip(&layout.extensions).enumerate() {
+        draw_button(fb, browser.chrome, *rect, "", text_size, 0);
+        // The reader button stays highlighted while reader mode is on
+        let color = match button {
+            ToolbarButton::Reader if browser.chrome.reader => 0x4285F4,
+            _ if extension_enabled(browser, i) => 0x555555,
+            _ => 0x999999,
+        };
+        draw_icon(fb, *rect, icon_size, button, color);
+    }
+
+    if browser.address_focused && browser.cursor_visible {
+        let (cursor_x, top, height) = address_caret(browser);
+        fb.fill_rect(cursor_x, top, 2, height, 0x333333);
+    }
+}
+
+/// The address bar caret (x, top, height), after any IME composition's own caret
+pub fn address_caret(browser: &Browser) -> (i32, i32, u32) {
+    let layout = &browser.layout;
+    let text_size = layout.text_size;
+    let text_y = baseline_for_box(layout.address.y, layout.address.height, text_size);
+    let mut before: String = browser.address_text.chars().take(browser.address_cursor).collect();
+    let preedit = &browser.address_preedit;
+    before.push_str(browser.address_preedit_cursor.and_then(|i| preedit.get(..i)).unwrap_or(preedit));
+    let (ascent, descent, _) = line_metrics(text_size);
+    (address_text_x(browser) + measure_text_width(&before, text_size) as i32, text_y - ascent, (ascent + descent) as u32)
+}
+
+/// A padlock, or a red warning sign when the certificate didn't verify
+fn draw_security_icon(fb: &mut FrameBuffer, rect: Rect, secure: bool) {
+    let s = rect.width;
+    if secure {
+        let body = Rect::new(rect.x + s * 3 / 16, rect.y + s * 7 / 16, s * 10 / 16, s * 7 / 16);
+        fb.draw_rect_outline(rect.x + s * 5 / 16, rect.y + s * 2 / 16, (s * 6 / 16) as u32, (s * 7 / 16) as u32, 0x555555, 2);
+        fb.fill_rounded_rect_vertical_gradient(body.x, body.y, body.width as u32, body.height as u32, 2, 0x555555, 0x555555);
+    } else {
+        let size = s as f32 * 0.8;
+        fb.fill_rounded_rect_vertical_gradient(rect.x, rect.y, s as u32, s as u32, s as u32 / 2, 0xD93025, 0xD93025);
+        let w = measure_text_width("!", size) as i32;
+        draw_text_fb(fb, "!", rect.x + (s - w) / 2, baseline_for_box(rect.y, rect.height, size), size, 0xFFFFFF);
+    }
+}
+
+/// Lines of the certificate panel, with their colours
+fn security_lines(tls: &Connection) -> Vec<(String, u32)> {
+    let mut lines = vec![match &tls.error {
+        None => (format!("Connection to {} is secure", tls.host), 0x188038),
+        Some(e) => (format!("Certificate not valid: {}", e), 0xD93025),
+    }];
+    if let Some(cert) = &tls.certificate {
+        lines.push((format!("Issued to: {}", cert.subject), 0x333333));
+        lines.push((format!("Issued by: {}", cert.issuer), 0x333333));
+        lines.push((format!("Expires: {}", cert.expires), 0x333333));
+    }
+    if !tls.protocol.is_empty() {
+        lines.push((format!("Protocol: {}", tls.protocol), 0x333333));
+    }
+    lines
+}
+
+/// The open certificate panel, hanging from the address bar toward the page
+pub fn security_panel(browser: &Browser) -> Option<Rect> {
+    let tls = browser.tls.as_ref().filter(|_| browser.security_open)?;
+    let layout = &browser.layout;
+    let height = security_lines(tls).len() as i32 * SECURITY_LINE_HEIGHT + 16;
+    let y = if layout.bar.y == 0 { layout.bar.bottom() + 4 } else { layout.bar.y - height - 4 };
+    Some(Rect::new(layout.address.x, y, SECURITY_PANEL_WIDTH, height))
+}
+
+pub fn draw_security_panel(fb: &mut FrameBuffer, browser: &Browser) {
+    let (Some(panel), Some(tls)) = (security_panel(browser), &browser.tls) else {
+        return;
+    };
+    if !browser.chrome.battery_saver {
+        fb.fill_rounded_rect_vertical_gradient(panel.x, panel.y + 1, panel.width as u32, panel.height as u32, 8, 0xD0D0D0, 0xD0D0D0);
+    }
+    fb.fill_rounded_rect_vertical_gradient(panel.x, panel.y, panel.width as u32, panel.height as u32, 8, 0xFFFFFF, 0xFFFFFF);
+    let size = browser.layout.text_size - 1.0;
+    for (i, (line, color)) in security_lines(tls).into_iter().enumerate() {
+        let y = panel.y + 8 + i as i32 * SECURITY_LINE_HEIGHT;
+        let line = fit_text(&line, size, (panel.width - 24) as u32);
+        draw_text_fb(fb, &line, panel.x + 12, baseline_for_box(y, SECURITY_LINE_HEIGHT, size), size, color);
+    }
+}
+
+/// The "Discard changes?" dialog, centred in the window
+pub struct LeaveDialog {
+    pub panel: Rect,
+    pub stay: Rect,
+    pub discard: Rect,
+}
+
+impl LeaveDialog {
+    /// `Some(true)` over Discard, `Some(false)` over Stay
+    pub fn button_at(&self, x: i32, y: i32) -> Option<bool> {
+        if self.discard.contains(x, y) {
+            Some(true)
+        } else if self.stay.contains(x, y) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// The dialog while the page's `on_before_leave` is keeping the user
+pub fn leave_dialog(browser: &Browser) -> Option<LeaveDialog> {
+    browser.leave_prompt.as_ref()?;
+    let layout = &browser.layout;
+    let (window_width, window_height) = (layout.bar.width, layout.bar.height + layout.content.height);
+    let panel = Rect::new((window_width - LEAVE_DIALOG_WIDTH) / 2, (window_height - LEAVE_DIALOG_HEIGHT) / 2, LEAVE_DIALOG_WIDTH, LEAVE_DIALOG_HEIGHT);
+    let button_height = layout.address.height;
+    let button_y = panel.bottom() - 16 - button_height;
+    let discard = Rect::new(panel.right() - 16 - LEAVE_BUTTON_WIDTH, button_y, LEAVE_BUTTON_WIDTH, button_height);
+    let stay = Rect::new(discard.x - 8 - LEAVE_BUTTON_WIDTH, button_y, LEAVE_BUTTON_WIDTH, button_height);
+    Some(LeaveDialog { panel, stay, discard })
+}
+
+/// The dialog over the dimmed window
+pub fn draw_leave_dialog(fb: &mut FrameBuffer, browser: &Browser) {
+    let Some(dialog) = leave_dialog(browser) else {
+        return;
+    };
+    for y in 0..fb.height {
+        for x in 0..fb.width {
+            fb.blend_pixel(x, y, 0x000000, 96);
+        }
+    }
+    let panel = dialog.panel;
+    fb.fill_rounded_rect_vertical_gradient(panel.x, panel.y, panel.width as u32, panel.height as u32, 8, 0xFFFFFF, 0xFFFFFF);
+    let size = browser.layout.text_size;
+    draw_text_fb(fb, LEAVE_TITLE, panel.x + 16, baseline_for_box(panel.y + 14, 24, size + 2.0), size + 2.0, 0x222222);
+    let message = fit_text(LEAVE_MESSAGE, size - 1.0, (panel.width - 32) as u32);
+    draw_text_fb(fb, &message, panel.x + 16, baseline_for_box(panel.y + 42, 20, size - 1.0), size - 1.0, 0x555555);
+    draw_button(fb, browser.chrome, dialog.stay, "Stay", size, 0x333333);
+    let discard = dialog.discard;
+    let (top, bottom) = browser.chrome.shades(0xE0483D, 0xD93025);
+    fb.fill_rounded_rect_vertical_gradient(discard.x, discard.y, discard.width as u32, discard.height as u32, 6, top, bottom);
+    let w = measure_text_width("Discard", size) as i32;
+    draw_text_fb(fb, "Discard", discard.x + (discard.width - w) / 2, baseline_for_box(discard.y, discard.height, size), size, 0xFFFFFF);
+}
+
+/// The Share button's address, copied to the clipboard when it was pressed
+pub struct Share {
+    pub address: String,
+    /// `None` when the address is too long for a QR code
+    pub code: Option<QrCode>,
+    pub copied: bool,
+}
+
+/// The dialog showing what the Share button copied, centred in the window
+pub struct ShareDialog {
+    pub panel: Rect,
+    pub code: Rect,
+    pub done: Rect,
+}
+
+pub fn share_dialog(browser: &Browser) -> Option<ShareDialog> {
+    browser.share.as_ref()?;
+    let layout = &browser.layout;
+    let (window_width, window_height) = (layout.bar.width, layout.bar.height + layout.content.height);
+    let button_height = layout.address.height;
+    let height = 92 + SHARE_CODE_SIZE + 16 + button_height + 16;
+    let panel = Rect::new((window_width - SHARE_DIALOG_WIDTH) / 2, (window_height - height) / 2, SHARE_DIALOG_WIDTH, height);
+    let code = Rect::new(panel.x + (panel.width - SHARE_CODE_SIZE) / 2, panel.y + 92, SHARE_CODE_SIZE, SHARE_CODE_SIZE);
+    let done = Rect::new(panel.right() - 16 - LEAVE_BUTTON_WIDTH, panel.bottom() - 16 - button_height, LEAVE_BUTTON_WIDTH, button_height);
+    Some(ShareDialog { panel, code, done })
+}
+
+/// The address, whether it was copied, and its QR code, over the dimmed window
+pub fn draw_share_dialog(fb: &mut FrameBuffer, browser: &Browser) {
+    let (Some(share), Some(dialog)) = (&browser.share, share_dialog(browser)) else {
+        return;
+    };
+    for y in 0..fb.height {
+        for x in 0..fb.width {
+            fb.blend_pixel(x, y, 0x000000, 96);
+        }
+    }
+    let panel = dialog.panel;
+    fb.fill_rounded_rect_vertical_gradient(panel.x, panel.y, panel.width as u32, panel.height as u32, 8, 0xFFFFFF, 0xFFFFFF);
+    let size = browser.layout.text_size;
+    draw_text_fb(fb, SHARE_TITLE, panel.x + 16, baseline_for_box(panel.y + 14, 24, size + 2.0), size + 2.0, 0x222222);
+    let address = fit_text(&share.address, size - 1.0, (panel.width - 32) as u32);
+    draw_text_fb(fb, &address, panel.x + 16, baseline_for_box(panel.y + 42, 20, size - 1.0), size - 1.0, 0x555555);
+    let (status, color) = if share.copied {
+        ("Copied to the clipboard", 0x188038)
+    } else {
+        ("Couldn't copy it to the clipboard", 0xD93025)
+    };
+    draw_text_fb(fb, status, panel.x + 16, baseline_for_box(panel.y + 62, 20, size - 1.0), size - 1.0, color);
+
+    let area = dialog.code;
+    match &share.code {
+        Some(code) => {
+            let modules = (code.size() + QUIET_ZONE * 2) as i32;
+            let scale = area.width / modules;
+            let side = modules * scale;
+            let (left, top) = (area.x + (area.width - side) / 2, area.y + (area.height - side) / 2);
+            fb.fill_rect(left, top, side as u32, side as u32, 0xFFFFFF);
+            fb.draw_rect_outline(left, top, side as u32, side as u32, 0xDDDDDD, 1);
+            let origin = QUIET_ZONE as i32 * scale;
+            for y in 0..code.size() {
+                for x in (0..code.size()).filter(|&x| code.is_dark(x, y)) {
+                    fb.fill_rect(left + origin + x as i32 * scale, top + origin + y as i32 * scale, scale as u32, scale as u32, 0x000000);
+                }
+            }
+        }
+        None => {
+            fb.fill_rounded_rect_vertical_gradient(area.x, area.y, area.width as u32, area.height as u32, 6, 0xF3F5F8, 0xF3F5F8);
+            let message = "Too long for a QR code";
+            let w = measure_text_width(message, size - 1.0) as i32;
+            draw_text_fb(fb, message, area.x + (area.width - w) / 2, baseline_for_box(area.y, area.height, size - 1.0), size - 1.0, 0x999999);
+        }
+    }
+    draw_button(fb, browser.chrome, dialog.done, "Done", size, 0x333333);
+}
+
+/// Ctrl+Shift+C: a rectangle the user is dragging over the page
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegionSelect {
+    /// Where the drag started, once the button is down
+    pub anchor: Option<(i32, i32)>,
+    pub cursor: (i32, i32),
+}
+
+impl RegionSelect {
+    /// The dragged rectangle clipped to `content`, if it covers any pixels
+    pub fn rect(&self, content: Rect) -> Option<Rect> {
+        let (ax, ay) = self.anchor?;
+        let (cx, cy) = self.cursor;
+        let x0 = ax.min(cx).max(content.x);
+        let y0 = ay.min(cy).max(content.y);
+        let x1 = ax.max(cx).min(content.right());
+        let y1 = ay.max(cy).min(content.bottom());
+        (x1 > x0 && y1 > y0).then(|| Rect::new(x0, y0, x1 - x0, y1 - y0))
+    }
+}
+
+/// Dim the page outside the region being selected, with a hint on top
+pub fn draw_region_select(fb: &mut FrameBuffer, browser: &Browser) {
+    let Some(region) = browser.region else {
+        return;
+    };
+    let content = browser.layout.content;
+    let selected = region.rect(content);
+    for y in content.y.max(0)..content.bottom().min(fb.height as i32) {
+        for x in content.x.max(0)..content.right().min(fb.width as i32) {
+            if !selected.is_some_and(|rect| rect.contains(x, y)) {
+                fb.blend_pixel(x as usize, y as usize, 0x000000, 96);
+            }
+        }
+    }
+    if let Some(rect) = selected {
+        fb.draw_rect_outline(rect.x - 1, rect.y - 1, rect.width as u32 + 2, rect.height as u32 + 2, REGION_OUTLINE, 1);
+    }
+    let size = browser.layout.text_size - 1.0;
+    let width = measure_text_width(REGION_HINT, size) as i32 + 24;
+    let hint = Rect::new(content.x + (content.width - width) / 2, content.y + 12, width, 28);
+    fb.fill_rounded_rect_vertical_gradient(hint.x, hint.y, hint.width as u32, hint.height as u32, 6, 0x333333, 0x333333);
+    draw_text_fb(fb, REGION_HINT, hint.x + 12, baseline_for_box(hint.y, hint.height, size), size, 0xFFFFFF);
+}
+
+/// Right-click on a misspelled word: its suggested corrections
+pub struct SpellingMenu {
+    pub misspelling: Misspelling,
+    pub suggestions: Vec<String>,
+    /// Where the menu was opened, in window coordinates
+    pub x: i32,
+    pub y: i32,
+    /// Suggestion under the pointer
+    pub hovered: Option<usize>,
+}
+
+/// The spelling menu's panel and one row per suggestion, kept inside the page area
+pub fn spelling_menu_rows(browser: &Browser) -> Option<(Rect, Vec<Rect>)> {
+    let menu = browser.spelling_menu.as_ref()?;
+    let size = browser.layout.text_size;
+    let labels = menu.suggestions.iter().map(String::as_str);
+    let widest = labels.chain([NO_SUGGESTIONS]).map(|label| measure_text_width(label, size) as i32).max().unwrap_or(0);
+    let width = (widest + 24).max(MENU_MIN_WIDTH);
+    let height = menu.suggestions.len().max(1) as i32 * MENU_ITEM_HEIGHT + 8;
+    let content = browser.layout.content;
+    let x = menu.x.min(content.right() - width).max(content.x);
+    let y = menu.y.min(content.bottom() - height).max(content.y);
+    let panel = Rect::new(x, y, width, height);
+    let rows = (0..menu.suggestions.len()).map(|i| Rect::new(x, y + 4 + i as i32 * MENU_ITEM_HEIGHT, width, MENU_ITEM_HEIGHT)).collect();
+    Some((panel, rows))
+}
+
+pub fn draw_spelling_menu(fb: &mut FrameBuffer, browser: &Browser) {
+    let (Some(menu), Some((panel, rows))) = (&browser.spelling_menu, spelling_menu_rows(browser)) else {
+        return;
+    };
+    fb.fill_rounded_rect_vertical_gradient(panel.x - 1, panel.y - 1, panel.width as u32 + 2, panel.height as u32 + 2, 6, 0xC8C8C8, 0xC8C8C8);
+    fb.fill_rounded_rect_vertical_gradient(panel.x, panel.y, panel.width as u32, panel.height as u32, 6, 0xFFFFFF, 0xFFFFFF);
+    let size = browser.layout.text_size;
+    if rows.is_empty() {
+        let baseline = baseline_for_box(panel.y + 4, MENU_ITEM_HEIGHT, size);
+        draw_text_fb(fb, NO_SUGGESTIONS, panel.x + 12, baseline, size, 0x999999);
+    }
+    for (i, (row, suggestion)) in rows.iter().zip(&menu.suggestions).enumerate() {
+        if menu.hovered == Some(i) {
+            fb.fill_rect(row.x, row.y, row.width as u32, row.height as u32, 0xE8F0FE);
+        }
+        draw_text_fb(fb, suggestion, row.x + 12, baseline_for_box(row.y, row.height, size), size, 0x222222);
+    }
+}
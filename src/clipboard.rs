@@ -0,0 +1,76 @@
+//! Writing to the system clipboard
+//!
+//! There is no clipboard crate in the build, so data is handed to the
+//! platform's own tool: `wl-copy` under Wayland, `xclip` under X11, `pbcopy`
+//! (text) or `osascript` (images) on macOS and `clip` (text) on Windows. An
+//! error means no tool took it; callers fall back to something else.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Put `data` of type `mime` (`text/plain` or `image/png`) on the clipboard
+pub fn copy(mime: &str, data: &[u8]) -> Result<(), String> {
+    let mut failures = vec![];
+    for mut command in commands(mime, data)? {
+        let program = command.get_program().to_string_lossy().into_owned();
+        match pipe(&mut command, data) {
+            Ok(()) => return Ok(()),
+            Err(e) => failures.push(format!("{}: {}", program, e)),
+        }
+    }
+    Err(if failures.is_empty() {
+        format!("no clipboard tool for {} on this system", mime)
+    } else {
+        failures.join("; ")
+    })
+}
+
+/// Commands to try in order, each reading the data on stdin
+fn commands(mime: &str, data: &[u8]) -> Result<Vec<Command>, String> {
+    let mut commands = vec![];
+    if cfg!(target_os = "macos") {
+        if mime.starts_with("text/") {
+            commands.push(Command::new("pbcopy"));
+        } else {
+            // AppleScript reads the image from a file rather than stdin
+            let path = std::env::temp_dir().join(format!("prism-clipboard-{}.png", std::process::id()));
+            std::fs::write(&path, data).map_err(|e| e.to_string())?;
+            let mut command = Command::new("osascript");
+            command.args(["-e", &format!("set the clipboard to (read (POSIX file \"{}\") as «class PNGf»)", path.display())]);
+            commands.push(command);
+        }
+    } else if cfg!(windows) {
+        if mime.starts_with("text/") {
+            commands.push(Command::new("clip"));
+        }
+    } else {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            let mut command = Command::new("wl-copy");
+            command.args(["--type", mime]);
+            commands.push(command);
+        }
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-t", mime, "-i"]);
+        commands.push(command);
+    }
+    Ok(commands)
+}
+
+fn pipe(command: &mut Command, data: &[u8]) -> Result<(), String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    // The macOS image command reads its file, not stdin
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(data).map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(status.to_string())
+    }
+}
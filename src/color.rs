@@ -0,0 +1,120 @@
+//! Color math shared by the renderer, the browser chrome and image decoding
+//!
+//! Pixels are packed sRGB, 0x00RRGGBB (or 0xAARRGGBB with straight alpha for
+//! images). Blending, gradients and fades convert to linear light first and
+//! back to sRGB at the end, so a 50% blend of black and white looks halfway
+//! between them and anti-aliased edges don't darken.
+//!
+//! [`PALETTE`] lists the named colors of [`Color`], which the parser accepts
+//! in color props.
+
+use crate::ast::Color;
+use std::sync::OnceLock;
+
+/// Precision of the linear-to-sRGB table
+const SRGB_STEPS: usize = 4096;
+
+/// The built-in named colors
+pub const PALETTE: &[(&str, Color)] = &[
+    ("transparent", Color::TRANSPARENT),
+    ("white", Color::WHITE),
+    ("black", Color::BLACK),
+    ("gray", Color::GRAY),
+    ("light_gray", Color::LIGHT_GRAY),
+    ("dark_gray", Color::DARK_GRAY),
+    ("red", Color::RED),
+    ("green", Color::GREEN),
+    ("blue", Color::BLUE),
+    ("yellow", Color::YELLOW),
+    ("orange", Color::ORANGE),
+    ("purple", Color::PURPLE),
+    ("cyan", Color::CYAN),
+    ("pink", Color::PINK),
+];
+
+/// Look up a named color of the palette
+pub fn named(name: &str) -> Option<Color> {
+    PALETTE.iter().find(|(n, _)| *n == name).map(|(_, color)| *color)
+}
+
+/// Linear light (0.0-1.0) of an sRGB channel
+pub fn to_linear(channel: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0.0; 256];
+        for (i, value) in table.iter_mut().enumerate() {
+            let c = i as f32 / 255.0;
+            *value = if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+        }
+        table
+    })[channel as usize]
+}
+
+/// sRGB channel of a linear light value, clamped to 0.0-1.0
+pub fn to_srgb(linear: f32) -> u8 {
+    static TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        (0..SRGB_STEPS)
+            .map(|i| {
+                let l = i as f32 / (SRGB_STEPS - 1) as f32;
+                let c = if l <= 0.0031308 { l * 12.92 } else { 1.055 * l.powf(1.0 / 2.4) - 0.055 };
+                (c * 255.0).round() as u8
+            })
+            .collect()
+    });
+    table[(linear.clamp(0.0, 1.0) * (SRGB_STEPS - 1) as f32).round() as usize]
+}
+
+/// Channels of a packed pixel as (r, g, b)
+pub fn unpack(pixel: u32) -> (u8, u8, u8) {
+    ((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8)
+}
+
+pub fn pack(r: u8, g: u8, b: u8) -> u32 {
+    ((r as u32) << 16) | ((g as u32) << 8) | b as u32
+}
+
+/// `from` moved `t` (0.0-1.0) of the way to `to`
+pub fn mix(from: u32, to: u32, t: f32) -> u32 {
+    let t = t.clamp(0.0, 1.0);
+    let (r1, g1, b1) = unpack(from);
+    let (r2, g2, b2) = unpack(to);
+    let channel = |a: u8, b: u8| {
+        let (a, b) = (to_linear(a), to_linear(b));
+        to_srgb(a + (b - a) * t)
+    };
+    pack(channel(r1, r2), channel(g1, g2), channel(b1, b2))
+}
+
+/// `src` drawn over `dst` with coverage `alpha`
+pub fn blend(dst: u32, src: u32, alpha: u8) -> u32 {
+    match alpha {
+        0 => dst & 0xFF_FFFF,
+        255 => src & 0xFF_FFFF,
+        alpha => mix(dst, src, alpha as f32 / 255.0),
+    }
+}
+
+/// Relative luminance (0.0-1.0) of a pixel
+pub fn luminance(pixel: u32) -> f32 {
+    let (r, g, b) = unpack(pixel);
+    0.2126 * to_linear(r) + 0.7152 * to_linear(g) + 0.0722 * to_linear(b)
+}
+
+/// A pixel turned gray, keeping its luminance
+pub fn grayscale(pixel: u32) -> u32 {
+    let v = to_srgb(luminance(pixel));
+    pack(v, v, v)
+}
+
+/// Source-over of a straight-alpha color onto a straight-alpha ARGB pixel
+pub fn composite_over(dst: u32, r: u8, g: u8, b: u8, a: u8) -> u32 {
+    let (sa, da) = (a as f32 / 255.0, (dst >> 24) as f32 / 255.0);
+    let out_a = sa + da * (1.0 - sa);
+    if out_a <= 0.0 {
+        return 0;
+    }
+    let (dr, dg, db) = unpack(dst);
+    let channel = |s: u8, d: u8| to_srgb((to_linear(s) * sa + to_linear(d) * da * (1.0 - sa)) / out_a);
+    (((out_a * 255.0).round() as u32) << 24) | pack(channel(r, dr), channel(g, dg), channel(b, db))
+}
@@ -0,0 +1,79 @@
+//! Pre-compiled documents (.prismc)
+//!
+//! A .prismc file holds the parsed AST of a document encoded with postcard,
+//! behind a magic number and a format version so files written by another
+//! build are rejected instead of misread.
+
+use crate::ast::PrismApp;
+use crate::vfs::Vfs;
+use std::path::Path;
+
+const MAGIC: &[u8; 6] = b"PRISMC";
+
+/// Bumped whenever the AST changes shape
+pub const FORMAT_VERSION: u16 = 3;
+
+/// Whether a path or URL names a pre-compiled document
+pub fn is_compiled(path: &str) -> bool {
+    path.ends_with(".prismc")
+}
+
+pub fn encode(app: &PrismApp) -> Result<Vec<u8>, String> {
+    let payload = postcard::to_stdvec(app).map_err(|e| format!("Cannot encode document: {}", e))?;
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+pub fn decode(bytes: &[u8]) -> Result<PrismApp, String> {
+    let header = MAGIC.len() + 2;
+    if bytes.len() < header || &bytes[..MAGIC.len()] != MAGIC {
+        return Err("Not a compiled Prism document".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "Compiled with format version {}, this browser reads version {}; recompile the .prism source",
+            version, FORMAT_VERSION
+        ));
+    }
+    postcard::from_bytes(&bytes[header..]).map_err(|e| format!("Corrupt compiled document: {}", e))
+}
+
+/// `prism compile <input.prism> [-o output.prismc]`, returning the exit code
+pub fn run(input: &str, output: Option<&str>) -> i32 {
+    let source = match std::fs::read_to_string(input) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("[PRISM] Cannot read {}: {}", input, e);
+            return 1;
+        }
+    };
+    let app = match crate::parser::parse_with_assets(&source, &Vfs::for_location(input)) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("[PRISM] {}: {}", input, e);
+            return 1;
+        }
+    };
+    let output = match output {
+        Some(path) => path.to_string(),
+        None => Path::new(input).with_extension("prismc").to_string_lossy().into_owned(),
+    };
+    let written = encode(&app).and_then(|bytes| {
+        std::fs::write(&output, &bytes).map_err(|e| format!("Cannot write {}: {}", output, e))?;
+        Ok(bytes.len())
+    });
+    match written {
+        Ok(size) => {
+            println!("Compiled {} -> {} ({} bytes)", input, output, size);
+            0
+        }
+        Err(e) => {
+            eprintln!("[PRISM] {}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Data loading for Prism applications
+//!
+//! Backs the `load_csv` builtin. Sources are resolved relative to the
+//! current document and gated by the sandbox capabilities. Remote sources are
+//! fetched on a background thread: `load_csv` returns null until the data
+//! arrives, and the host polls the loader to re-render then.
+//! - Local files require `@capability files`; files bundled in a signed
+//!   package don't
+//! - Remote URLs require `@capability network`, the document's origin and
+//!   the user's content policy to allow fetching; packaged apps run offline
+//!   and never fetch
+
+use crate::ast::Value;
+use crate::bus::origin_of;
+use crate::sandbox::{Capabilities, OriginPolicy, Permission, MAX_FILE_SIZE_BYTES};
+use crate::vfs::Vfs;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::sync::mpsc;
+
+/// Result of a background fetch, with the delimiter to parse it with
+type PendingFetch = (char, mpsc::Receiver<Result<String, String>>);
+
+/// Where a source's text comes from
+enum Source {
+    Text(String),
+    /// An address cleared for fetching
+    Remote(String),
+}
+
+/// Loads and caches delimited data files for a single document
+pub struct DataLoader {
+    /// URL of a remote document; relative sources are fetched next to it
+    remote_base: Option<String>,
+    /// Files of a local or packaged document
+    assets: Vfs,
+    capabilities: Capabilities,
+    policy: OriginPolicy,
+    cache: RefCell<HashMap<String, Value>>,
+    /// Remote sources still being fetched, by cache key
+    pending: RefCell<HashMap<String, PendingFetch>>,
+    /// Cache keys of sources that couldn't be read or fetched
+    failed: RefCell<HashSet<String>>,
+    /// Loads that returned a failure so far, so callers can tell whether an
+    /// evaluation hit one (see `failures`)
+    failures: Cell<u64>,
+}
+
+impl DataLoader {
+    pub fn new(capabilities: Capabilities) -> Self {
+        Self {
+            remote_base: None,
+            assets: Vfs::Empty,
+            capabilities,
+            policy: OriginPolicy::default(),
+            cache: RefCell::new(HashMap::new()),
+            pending: RefCell::new(HashMap::new()),
+            failed: RefCell::new(HashSet::new()),
+            failures: Cell::new(0),
+        }
+    }
+
+    /// Set the location of the current document (a file path or URL)
+    pub fn set_base(&mut self, location: &str) {
+        let is_remote = location.starts_with("http://") || location.starts_with("https://");
+        self.remote_base = is_remote.then(|| location.to_string());
+        self.cache.borrow_mut().clear();
+        self.pending.borrow_mut().clear();
+        self.failed.borrow_mut().clear();
+    }
+
+    /// Set the files that relative sources of local documents resolve against
+    pub fn set_assets(&mut self, assets: Vfs) {
+        self.assets = assets;
+        self.cache.borrow_mut().clear();
+        self.pending.borrow_mut().clear();
+        self.failed.borrow_mut().clear();
+    }
+
+    pub fn set_policy(&mut self, policy: OriginPolicy) {
+        self.policy = policy;
+        self.cache.borrow_mut().clear();
+        self.pending.borrow_mut().clear();
+        self.failed.borrow_mut().clear();
+    }
+
+    /// Load a CSV/TSV source into a list of objects keyed by the header row
+    /// Results (including failures) are cached so repeated renders stay cheap;
+    /// remote sources are null until `poll` sees them arrive
+    pub fn load_csv(&self, source: &str, delimiter: Option<char>) -> Value {
+        let delimiter = delimiter.unwrap_or(if source.ends_with(".tsv") { '\t' } else { ',' });
+        let key = format!("{}\u{0}{}", source, delimiter);
+        if let Some(cached) = self.cache.borrow().get(&key) {
+            if self.failed.borrow().contains(&key) {
+                self.failures.set(self.failures.get() + 1);
+            }
+            return cached.clone();
+        }
+        if self.pending.borrow().contains_key(&key) {
+            return Value::Null;
+        }
+
+        let value = match self.read_source(source) {
+            Ok(Source::Text(text)) => parse_delimited(&text, delimiter),
+            Ok(Source::Remote(url)) => {
+                let (sender, receiver) = mpsc::channel();
+                std::thread::spawn(move || {
+                    let _ = sender.send(fetch(&url));
+                });
+                self.pending.borrow_mut().insert(key, (delimiter, receiver));
+                return Value::Null;
+            }
+            Err(e) => {
+                eprintln!("[PRISM DATA] load_csv(\"{}\"): {}", source, e);
+                self.failed.borrow_mut().insert(key.clone());
+                self.failures.set(self.failures.get() + 1);
+                Value::Null
+            }
+        };
+        self.cache.borrow_mut().insert(key, value.clone());
+        value
+    }
+
+    /// Cache the remote sources that finished fetching; returns true if any did
+    pub fn poll(&self) -> bool {
+        let mut arrived = Vec::new();
+        let mut failed = self.failed.borrow_mut();
+        self.pending.borrow_mut().retain(|key, (delimiter, receiver)| {
+            let result = match receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return true,
+                Err(mpsc::TryRecvError::Disconnected) => Err("the fetch stopped unexpectedly".to_string()),
+            };
+            let value = match result {
+                Ok(text) => parse_delimited(&text, *delimiter),
+                Err(e) => {
+                    eprintln!("[PRISM DATA] load_csv(\"{}\"): {}", key.split('\u{0}').next().unwrap_or(key), e);
+                    failed.insert(key.clone());
+                    Value::Null
+                }
+            };
+            arrived.push((key.clone(), value));
+            false
+        });
+        let any = !arrived.is_empty();
+        self.cache.borrow_mut().extend(arrived);
+        any
+    }
+
+    /// How many loads have returned a failure; compare before and after an
+    /// evaluation to learn whether a source it read failed
+    pub fn failures(&self) -> u64 {
+        self.failures.get()
+    }
+
+    fn read_source(&self, source: &str) -> Result<Source, String> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            if self.assets.is_package() {
+                return Err("packaged apps run offline and can't load remote data".to_string());
+            }
+            return self.check_fetch(source).map(Source::Remote);
+        }
+        if let Some(base) = &self.remote_base {
+            let url = url::Url::parse(base).and_then(|base| base.join(source)).map_err(|e| e.to_string())?;
+            return self.check_fetch(url.as_str()).map(Source::Remote);
+        }
+        if !self.capabilities.files && !self.assets.is_package() {
+            return Err("reading local data requires @capability files".to_string());
+        }
+        self.assets.read_to_string(source, MAX_FILE_SIZE_BYTES).map(Source::Text)
+    }
+
+    /// The address, if the document may fetch it
+    fn check_fetch(&self, url: &str) -> Result<String, String> {
+        if !self.capabilities.network_same_origin {
+            return Err("loading remote data requires @capability network".to_string());
+        }
+        let same_origin = self.remote_base.as_deref().is_some_and(|base| origin_of(base) == origin_of(url));
+        if !same_origin {
+            return Err("remote data must share the document's origin".to_string());
+        }
+        self.policy.check(Permission::Fetch, url).map_err(|e| e.to_string())?;
+        Ok(url.to_string())
+    }
+}
+
+/// Most redirects followed, as in reqwest's default policy
+const MAX_REDIRECTS: usize = 10;
+
+/// Download a remote source, giving up as soon as it is larger than the size
+/// limit; redirects are followed only within the source's origin
+fn fetch(url: &str) -> Result<String, String> {
+    let origin = origin_of(url);
+    let policy = reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if origin_of(attempt.url().as_str()) != origin {
+            attempt.error("redirected to another origin")
+        } else {
+            attempt.follow()
+        }
+    });
+    let client = reqwest::blocking::Client::builder().redirect(policy).build().map_err(|e| e.to_string())?;
+    let response = client.get(url).send().map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error {}", response.status()));
+    }
+    let too_large = || "response exceeds maximum size limit".to_string();
+    if response.content_length().is_some_and(|len| len > MAX_FILE_SIZE_BYTES as u64) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    response.take(MAX_FILE_SIZE_BYTES as u64 + 1).read_to_end(&mut body).map_err(|e| e.to_string())?;
+    if body.len() > MAX_FILE_SIZE_BYTES {
+        return Err(too_large());
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Parse delimited text into a list of objects keyed by the header row
+/// Numeric cells become ints/floats; quoted fields may contain delimiters,
+/// newlines and doubled quotes.
+pub fn parse_delimited(text: &str, delimiter: char) -> Value {
+    let mut records = split_records(text, delimiter).into_iter();
+    let header = match records.next() {
+        Some(h) => h,
+        None => return Value::List(vec![]),
+    };
+
+    let rows = records
+        .filter(|r| !(r.len() == 1 && r[0].is_empty()))
+        .map(|record| {
+            let mut row = HashMap::new();
+            for (i, column) in header.iter().enumerate() {
+                let cell = record.get(i).map(|c| c.as_str()).unwrap_or("");
+                row.insert(column.clone(), coerce_cell(cell));
+            }
+            Value::Object(row)
+        })
+        .collect();
+    Value::List(rows)
+}
+
+fn split_records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.is_empty() {
+            in_quotes = true;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            records.push(std::mem::take(&mut record));
+        } else {
+            field.push(c);
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn coerce_cell(cell: &str) -> Value {
+    let trimmed = cell.trim();
+    if let Ok(i) = trimmed.parse::<i64>() {
+        return Value::Int(i);
+    }
+    if let Ok(f) = trimmed.parse::<f64>() {
+        if f.is_finite() {
+            return Value::Float(f);
+        }
+    }
+    Value::String(cell.to_string())
+}
@@ -0,0 +1,280 @@
+//! `prism drive` - scripted end-to-end runs without a window
+//!
+//! A JSON script lists synthetic events and checks that are replayed against
+//! the headless renderer:
+//!
+//! ```json
+//! { "width": 800, "height": 600, "steps": [
+//!     { "click": { "text": "Add" } },
+//!     { "click": { "selector": "button[content=\"Save\"]" } },
+//!     { "click": { "x": 40, "y": 120 } },
+//!     { "type": "Buy milk" },
+//!     { "key": "enter" },
+//!     { "wait": { "until": "count == 1", "timeout_ms": 500 } },
+//!     { "idle": 60 },
+//!     { "assert": "count > 0" },
+//!     { "assert_text": "Buy milk" },
+//!     { "assert_count": { "selector": "checkbox", "count": 1 } },
+//!     { "screenshot": "after.png" }
+//! ] }
+//! ```
+
+use crate::renderer::FrameBuffer;
+use crate::runtime::{Key, Runtime};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize)]
+struct Script {
+    #[serde(default = "default_width")]
+    width: usize,
+    #[serde(default = "default_height")]
+    height: usize,
+    steps: Vec<Step>,
+}
+
+fn default_width() -> usize {
+    800
+}
+
+fn default_height() -> usize {
+    600
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Step {
+    /// Press at a point, on the first visible matching text, or on the first node a selector matches
+    Click(Target),
+    /// Move the pointer onto a target, like `click` picks it
+    Hover(Target),
+    /// Type text into the focused input
+    Type(String),
+    /// Press a named key: backspace, tab, enter, escape, up, down, home, end
+    Key(String),
+    /// Scroll the page by a number of pixels
+    Scroll(i32),
+    /// Re-render until an expression over the page state is true
+    Wait {
+        until: String,
+        #[serde(default = "default_timeout")]
+        timeout_ms: u64,
+    },
+    /// Let seconds pass without input, telling `on_idle` about each one
+    Idle(u64),
+    /// Fail unless an expression over the page state is true
+    Assert(String),
+    /// Fail unless the text is visible in the viewport
+    AssertText(String),
+    /// Fail unless the selector matches exactly `count` rendered nodes
+    AssertCount { selector: String, count: usize },
+    /// Save the viewport as a PNG, relative to the script
+    Screenshot(String),
+}
+
+fn default_timeout() -> u64 {
+    1000
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Target {
+    Point { x: i32, y: i32 },
+    Text { text: String },
+    Selector { selector: String },
+}
+
+/// Page under test: its runtime, where it was loaded from and the viewport
+struct Driver {
+    runtime: Runtime,
+    location: PathBuf,
+    fb: FrameBuffer,
+    scroll_y: i32,
+}
+
+impl Driver {
+    fn load(path: &Path, width: usize, height: usize) -> Result<Self, String> {
+        let app = crate::load_document(path)?;
+        let mut runtime = Runtime::new(app);
+        runtime.set_location(&path.to_string_lossy());
+        runtime.renderer.record_props = true;
+        let mut driver = Self { runtime, location: path.to_path_buf(), fb: FrameBuffer::new(width, height), scroll_y: 0 };
+        driver.render();
+        Ok(driver)
+    }
+
+    fn render(&mut self) {
+        self.runtime.pump_broadcasts();
+        self.runtime.pump_data();
+        let content = self.runtime.content_height(self.fb.width as u32) as i32;
+        let max_scroll = (content - self.fb.height as i32).max(0);
+        self.scroll_y = self.scroll_y.clamp(0, max_scroll);
+        self.runtime.render(&mut self.fb, self.scroll_y);
+        if let Some(y) = self.runtime.take_scroll_request() {
+            self.scroll_y = y.clamp(0, max_scroll);
+            self.runtime.render(&mut self.fb, self.scroll_y);
+        }
+    }
+
+    fn condition(&self, source: &str) -> Result<bool, String> {
+        let expr = crate::parser::parse_expression(source).map_err(|e| e.to_string())?;
+        Ok(self.runtime.state.evaluate(&expr).as_bool())
+    }
+
+    fn find_text(&self, text: &str) -> Option<(i32, i32)> {
+        let runs = &self.runtime.renderer.text_runs;
+        runs.iter()
+            .find(|r| r.text.trim() == text)
+            .or_else(|| runs.iter().find(|r| r.text.contains(text)))
+            .map(|r| (r.x + r.width as i32 / 2, r.y + r.height as i32 / 2))
+    }
+
+    /// Where a step aims: the point, the middle of the text, or the middle of the first match
+    fn target_point(&self, target: &Target) -> Result<(i32, i32), String> {
+        match target {
+            Target::Point { x, y } => Ok((*x, *y)),
+            Target::Text { text } => self.find_text(text).ok_or_else(|| format!("no visible text {:?}", text)),
+            Target::Selector { selector } => {
+                let node = self.runtime.query(selector)?.first().map(|n| (n.x + n.width as i32 / 2, n.y + n.height as i32 / 2));
+                node.ok_or_else(|| format!("nothing matches {}", selector))
+            }
+        }
+    }
+
+    fn click(&mut self, x: i32, y: i32) -> Result<(), String> {
+        if self.runtime.begin_column_resize(x, y) {
+            self.runtime.end_column_resize();
+            return Ok(());
+        }
+        let href = self.runtime.renderer.hit_test(x, y).and_then(|b| b.link_href.clone());
+        match href {
+            Some(href) if self.runtime.follow_route(&href) => Ok(()),
+            Some(href) => self.follow(&href),
+            None => {
+                self.runtime.handle_click(x, y);
+                self.runtime.renderer.set_focus(self.runtime.focused_input.clone());
+                Ok(())
+            }
+        }
+    }
+
+    /// Links load local documents next to the current one, like the browser does
+    fn follow(&mut self, href: &str) -> Result<(), String> {
+        if href.contains("://") {
+            return Err(format!("cannot follow remote link {}", href));
+        }
+        let path = self.location.parent().unwrap_or(Path::new(".")).join(href);
+        *self = Self::load(&path, self.fb.width, self.fb.height)?;
+        Ok(())
+    }
+
+    fn step(&mut self, step: &Step, script_dir: &Path) -> Result<(), String> {
+        match step {
+            Step::Click(target) => {
+                let (x, y) = self.target_point(target)?;
+                self.click(x, y)?;
+            }
+            Step::Hover(target) => {
+                let (x, y) = self.target_point(target)?;
+                self.runtime.handle_hover(Some((x, y)));
+            }
+            Step::Type(text) => {
+                if self.runtime.focused_input.is_none() {
+                    return Err("no input has focus".to_string());
+                }
+                for ch in text.chars() {
+                    self.runtime.handle_key(ch);
+                }
+            }
+            Step::Key(name) => {
+                let key = match name.to_lowercase().as_str() {
+                    "backspace" => Key::Backspace,
+                    "tab" => Key::Tab,
+                    "enter" | "return" => Key::Enter,
+                    "escape" | "esc" => Key::Escape,
+                    "up" => Key::Up,
+                    "down" => Key::Down,
+                    "home" => Key::Home,
+                    "end" => Key::End,
+                    other => return Err(format!("unknown key {:?}", other)),
+                };
+                self.runtime.handle_special_key(key, false);
+            }
+            Step::Scroll(dy) => self.scroll_y += dy,
+            Step::Wait { until, timeout_ms } => {
+                let deadline = Instant::now() + Duration::from_millis(*timeout_ms);
+                while !self.condition(until)? {
+                    if Instant::now() >= deadline {
+                        return Err(format!("timed out after {}ms", timeout_ms));
+                    }
+                    std::thread::sleep(Duration::from_millis(16));
+                    self.runtime.renderer.tick();
+                    self.render();
+                }
+            }
+            Step::Idle(seconds) => {
+                for second in 1..=*seconds {
+                    self.runtime.idle(second);
+                }
+            }
+            Step::Assert(condition) => {
+                if !self.condition(condition)? {
+                    return Err("condition is false".to_string());
+                }
+            }
+            Step::AssertText(text) => {
+                if self.find_text(text).is_none() {
+                    return Err(format!("{:?} is not visible", text));
+                }
+            }
+            Step::AssertCount { selector, count } => {
+                let found = self.runtime.query(selector)?.len();
+                if found != *count {
+                    return Err(format!("{} nodes match, expected {}", found, count));
+                }
+            }
+            Step::Screenshot(file) => {
+                let path = script_dir.join(file);
+                self.fb.save_png(&path).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+            }
+        }
+        self.render();
+        Ok(())
+    }
+}
+
+/// `prism drive <page> <script.json>`, returning the exit code
+pub fn run(page: &str, script_path: &str) -> i32 {
+    let script: Script = match std::fs::read_to_string(script_path) {
+        Ok(text) => match serde_json::from_str(&text) {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("[PRISM] Invalid script {}: {}", script_path, e);
+                return 2;
+            }
+        },
+        Err(e) => {
+            eprintln!("[PRISM] Cannot read {}: {}", script_path, e);
+            return 2;
+        }
+    };
+    let mut driver = match Driver::load(Path::new(page), script.width.max(1), script.height.max(1)) {
+        Ok(driver) => driver,
+        Err(e) => {
+            eprintln!("[PRISM] {}", e);
+            return 1;
+        }
+    };
+    let script_dir = Path::new(script_path).parent().unwrap_or(Path::new("."));
+
+    for (i, step) in script.steps.iter().enumerate() {
+        if let Err(e) = driver.step(step, script_dir) {
+            println!("step {} {:?}: FAILED: {}", i + 1, step, e);
+            return 1;
+        }
+        println!("step {} {:?}: ok", i + 1, step);
+    }
+    println!("{} steps passed", script.steps.len());
+    0
+}
@@ -0,0 +1,156 @@
+//! `prism ast` - print the parsed form of a document
+//!
+//! The tree view is meant for reading; `--json` gives the exact AST for tools
+//! and bug reports.
+
+use crate::ast::*;
+use crate::vfs::Vfs;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Parse `path` and print its AST, returning the process exit code
+pub fn run(path: &str, json: bool) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("[PRISM] Cannot read {}: {}", path, e);
+            return 1;
+        }
+    };
+    let app = match crate::parser::parse_with_assets(&source, &Vfs::for_location(path)) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("[PRISM] {}: {}", path, e);
+            return 1;
+        }
+    };
+    if json {
+        match serde_json::to_string_pretty(&app) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("[PRISM] Cannot serialize AST: {}", e);
+                return 1;
+            }
+        }
+    } else {
+        print!("{}", tree(&app));
+    }
+    0
+}
+
+/// Indented outline of the whole document
+pub fn tree(app: &PrismApp) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "app {:?} v{}", app.name, app.version);
+    if app.strict {
+        out.push_str("  strict\n");
+    }
+    for import in &app.imports {
+        match &import.alias {
+            Some(alias) => writeln!(out, "  import {} as {}", import.path, alias),
+            None => writeln!(out, "  import {}", import.path),
+        }
+        .ok();
+    }
+    for capability in &app.capabilities {
+        let _ = writeln!(out, "  capability {}", capability);
+    }
+    for font in &app.fonts {
+        let _ = writeln!(out, "  font {:?} {:?}", font.name, font.src);
+    }
+
+    if !app.state.fields.is_empty() {
+        out.push_str("  state\n");
+        for (name, value) in sorted(&app.state.fields) {
+            match app.state.types.get(name) {
+                Some(ty) => writeln!(out, "    {}: {} = {}", name, ty, value_text(value)),
+                None => writeln!(out, "    {} = {}", name, value_text(value)),
+            }
+            .ok();
+        }
+    }
+    for (name, component) in sorted(&app.components) {
+        let props: Vec<String> = component
+            .props
+            .iter()
+            .map(|p| match &p.default {
+                Some(default) => format!("{} = {}", p.name, value_text(default)),
+                None => p.name.clone(),
+            })
+            .collect();
+        let _ = writeln!(out, "  component {} ({})", name, props.join(", "));
+        if !component.inject.is_empty() {
+            let _ = writeln!(out, "    inject {}", component.inject.join(", "));
+        }
+        for (name, value) in sorted(&component.state.fields) {
+            let _ = writeln!(out, "    state {} = {}", name, value_text(value));
+        }
+        node(&mut out, &component.view, 2);
+        for (name, action) in sorted(&component.actions) {
+            let _ = writeln!(out, "    action {}", name);
+            for statement in &action.statements {
+                let _ = writeln!(out, "      {:?}", statement);
+            }
+        }
+    }
+
+    out.push_str("  view\n");
+    node(&mut out, &app.view, 2);
+    for (path, view) in sorted(&app.routes) {
+        let _ = writeln!(out, "  route {:?}", path);
+        node(&mut out, view, 2);
+    }
+
+    if !app.actions.is_empty() {
+        out.push_str("  actions\n");
+        for (name, action) in sorted(&app.actions) {
+            let _ = writeln!(out, "    {}", name);
+            for statement in &action.statements {
+                let _ = writeln!(out, "      {:?}", statement);
+            }
+        }
+    }
+    out
+}
+
+fn node(out: &mut String, node: &ViewNode, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let kind = match &node.kind {
+        NodeKind::Component(name) => format!("component {}", name),
+        kind => format!("{:?}", kind).to_lowercase(),
+    };
+    let _ = writeln!(out, "{}{}", indent, kind);
+    for (name, prop) in sorted(&node.props) {
+        let _ = writeln!(out, "{}  .{}: {}", indent, name, prop_text(prop));
+    }
+    for child in &node.children {
+        self::node(out, child, depth + 1);
+    }
+}
+
+fn prop_text(prop: &PropValue) -> String {
+    match prop {
+        PropValue::Static(value) => value_text(value),
+        PropValue::Color(c) => format!("#{:02X}{:02X}{:02X}", c.r, c.g, c.b),
+        PropValue::Handler(name) => name.clone(),
+        PropValue::Expression(expr) => format!("{:?}", expr),
+        PropValue::EventHandler(handler) => format!("{}({:?})", handler.action, handler.args),
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::String(s) => format!("{:?}", s),
+        Value::List(items) => format!("[{}]", items.iter().map(value_text).collect::<Vec<_>>().join(", ")),
+        Value::Object(map) => {
+            let fields: Vec<String> = sorted(map).into_iter().map(|(k, v)| format!("{}: {}", k, value_text(v))).collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        other => other.as_string(),
+    }
+}
+
+fn sorted<V>(map: &std::collections::HashMap<String, V>) -> BTreeMap<&String, &V> {
+    map.iter().collect()
+}
@@ -0,0 +1,168 @@
+//! Emoji and other symbols the UI font lacks
+//!
+//! The embedded Inter font has no emoji, so text drawn with it alone showed
+//! blanks for them. Runs of text are split by font instead: what the page's
+//! font can draw stays with it, and the rest is taken from the first fallback
+//! font that has it. Fallbacks are outline (monochrome) emoji and symbol fonts
+//! found on the system, loaded the first time a page needs one;
+//! `PRISM_EMOJI_FONT` names a font file to try first. Last comes the bundled
+//! Prism Emoji font (built by `assets/emoji/build_font.py`), so the common
+//! faces, hearts, hands and marks show even where no emoji font is installed.
+//! Color bitmap fonts (Noto Color Emoji, Apple Color Emoji) have no outlines
+//! to rasterize and aren't used.
+//!
+//! Text is split into [`clusters`] first, so an emoji with a skin tone,
+//! variation selector or zero-width joiner stays in one font, and backspace
+//! removes it whole. Sequences aren't shaped into a single glyph: a ZWJ family
+//! shows its members side by side, and the invisible parts a font can't draw
+//! are left out rather than shown as boxes.
+
+use fontdue::layout::{Layout, TextStyle};
+use fontdue::{Font, FontSettings};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+/// Font files tried as fallbacks, in order; the first few found are used
+const FALLBACK_PATHS: &[&str] = &[
+    "/usr/share/fonts/truetype/noto/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/noto/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/google-noto-emoji/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/truetype/ancient-scripts/Symbola_hint.ttf",
+    "/usr/share/fonts/TTF/Symbola.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\seguiemj.ttf",
+    "C:\\Windows\\Fonts\\seguisym.ttf",
+    "/System/Library/Fonts/Apple Symbols.ttf",
+];
+
+/// Most system fallback fonts loaded; each costs its parsed outlines in memory
+const MAX_FALLBACK_FONTS: usize = 3;
+
+/// The emoji font drawn for Prism, tried after every system font
+const BUNDLED_FONT: &[u8] = include_bytes!("../assets/PrismEmoji.ttf");
+
+const ZWJ: char = '\u{200D}';
+
+/// The fallback fonts, loaded on first use
+pub fn fallback_fonts() -> &'static [Font] {
+    static FONTS: OnceLock<Vec<Font>> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let custom = std::env::var("PRISM_EMOJI_FONT").ok().filter(|path| !path.is_empty());
+        let mut fonts: Vec<Font> = custom
+            .iter()
+            .map(String::as_str)
+            .chain(FALLBACK_PATHS.iter().copied())
+            .filter_map(|path| {
+                let bytes = std::fs::read(path).ok()?;
+                Font::from_bytes(bytes, FontSettings { scale: 40.0, ..FontSettings::default() })
+                    .map_err(|e| eprintln!("[PRISM] Skipping fallback font {}: {}", path, e))
+                    .ok()
+            })
+            .take(MAX_FALLBACK_FONTS)
+            .collect();
+        match Font::from_bytes(BUNDLED_FONT, FontSettings { scale: 40.0, ..FontSettings::default() }) {
+            Ok(font) => fonts.push(font),
+            Err(e) => eprintln!("[PRISM] Skipping bundled emoji font: {}", e),
+        }
+        fonts
+    })
+}
+
+/// The font of a laid-out glyph: `primary` for index 0, else a fallback
+pub fn font_for(primary: &Font, index: usize) -> &Font {
+    match index {
+        0 => primary,
+        i => fallback_fonts().get(i - 1).unwrap_or(primary),
+    }
+}
+
+/// Whether `c` only modifies what comes before it
+fn is_extend(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}'
+        | ZWJ)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+/// `text` split into user-perceived characters: a char with the marks,
+/// modifiers and selectors after it, chars joined by ZWJ, and flag pairs
+pub fn clusters(text: &str) -> impl Iterator<Item = &str> + '_ {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices().peekable();
+        let (_, first) = chars.next()?;
+        let mut end = first.len_utf8();
+        let mut joined = false;
+        let mut flag = is_regional_indicator(first);
+        while let Some(&(i, c)) = chars.peek() {
+            let takes = joined || is_extend(c) || flag && is_regional_indicator(c);
+            if !takes {
+                break;
+            }
+            flag = false;
+            joined = c == ZWJ;
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        let (cluster, tail) = rest.split_at(end);
+        rest = tail;
+        Some(cluster)
+    })
+}
+
+/// `text` split into runs by the font that draws them, as (text, font index
+/// for [`font_for`]); modifiers the chosen font can't draw are dropped
+pub fn font_runs<'a>(primary: &Font, text: &'a str) -> Vec<(Cow<'a, str>, usize)> {
+    if text.chars().all(|c| primary.has_glyph(c) || c.is_whitespace() || c.is_control()) {
+        return vec![(Cow::Borrowed(text), 0)];
+    }
+    let mut runs: Vec<(Cow<str>, usize)> = vec![];
+    for cluster in clusters(text) {
+        let base = cluster.chars().next().unwrap_or(' ');
+        let index = if primary.has_glyph(base) || base.is_whitespace() || base.is_control() {
+            0
+        } else {
+            fallback_fonts().iter().position(|font| font.has_glyph(base)).map_or(0, |i| i + 1)
+        };
+        let font = font_for(primary, index);
+        // Skin tones, selectors and joiners a font lacks would draw as boxes
+        let drawn: String = cluster
+            .chars()
+            .enumerate()
+            .filter(|&(i, c)| i == 0 || font.has_glyph(c) || !is_extend(c))
+            .map(|(_, c)| c)
+            .collect();
+        match runs.last_mut() {
+            Some((run, last)) if *last == index => run.to_mut().push_str(&drawn),
+            _ => runs.push((Cow::Owned(drawn), index)),
+        }
+    }
+    runs
+}
+
+/// Lay out `text` after what `layout` holds, in `font`, with the fallbacks for
+/// what it can't draw; rasterize each glyph with [`font_for`]
+pub fn append_text(layout: &mut Layout, font: &Font, text: &str, size: f32) {
+    let runs = font_runs(font, text);
+    if let [(run, 0)] = runs.as_slice() {
+        layout.append(&[font], &TextStyle::new(run, size, 0));
+        return;
+    }
+    let fonts: Vec<&Font> = std::iter::once(font).chain(fallback_fonts()).collect();
+    for (run, index) in &runs {
+        layout.append(&fonts, &TextStyle::new(run, size, *index));
+    }
+}
@@ -0,0 +1,471 @@
+//! `prism export-html` - static HTML snapshot of a document
+//!
+//! The view is evaluated against the document's initial state and written as
+//! semantic HTML with a small stylesheet. Nothing is interactive: controls
+//! show their current values, and actions are dropped.
+
+use crate::ast::*;
+use crate::runtime::Runtime;
+use crate::state::StateStore;
+use crate::vfs::Vfs;
+use std::fmt::Write;
+
+/// Convert the document at `path` and print it, or write it to `output`
+pub fn run(path: &str, output: Option<&str>) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("[PRISM] Cannot read {}: {}", path, e);
+            return 1;
+        }
+    };
+    let app = match crate::parser::parse_with_assets(&source, &Vfs::for_location(path)) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("[PRISM] {}: {}", path, e);
+            return 1;
+        }
+    };
+    let mut runtime = Runtime::new(app);
+    runtime.set_location(path);
+    let html = to_html(&runtime.app, &runtime.state);
+
+    match output {
+        Some(output) => match std::fs::write(output, html) {
+            Ok(()) => {
+                println!("Exported {} -> {}", path, output);
+                0
+            }
+            Err(e) => {
+                eprintln!("[PRISM] Cannot write {}: {}", output, e);
+                1
+            }
+        },
+        None => {
+            print!("{}", html);
+            0
+        }
+    }
+}
+
+/// Full HTML document for the app's view in its current state
+pub fn to_html(app: &PrismApp, state: &StateStore) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(out, "<title>{}</title>\n<style>{}</style>\n</head>\n<body>", escape(&app.name), STYLE);
+    let view = app.view_for("/");
+    state.index_view(view, app.route_pattern(view));
+    node(&mut out, view, state, 0);
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+const STYLE: &str = "
+body { margin: 0; font-family: Inter, system-ui, sans-serif; font-size: 16px; color: #000; }
+.column { display: flex; flex-direction: column; }
+.row { display: flex; flex-direction: row; align-items: center; justify-content: center; }
+.center { display: flex; align-items: center; justify-content: center; }
+.spacer { flex: 1; }
+p, h1, h2, h3 { margin: 0; }
+a { color: #1976D2; }
+table { border-collapse: collapse; }
+th, td { border-bottom: 1px solid #E0E0E0; padding: 6px 10px; text-align: left; }
+.badge { display: inline-block; padding: 2px 8px; border-radius: 10px; background: #E0E0E0; font-size: 12px; }
+.tags { display: flex; flex-wrap: wrap; gap: 6px; list-style: none; padding: 0; margin: 0; }
+.tags li { padding: 2px 10px; border-radius: 12px; background: #E8F0FE; }
+.rating { color: #F5A623; font-size: 20px; }
+.avatar { display: inline-flex; width: 40px; height: 40px; border-radius: 50%; background: #4285F4;
+  color: #FFF; align-items: center; justify-content: center; }
+.card { border: 1px solid #E0E0E0; border-radius: 8px; padding: 12px; }
+.code { margin: 0; padding: 12px; border-radius: 6px; background: #F6F8FA; overflow-x: auto;
+  font-family: \"DejaVu Sans Mono\", monospace; font-size: 13px; }
+";
+
+fn node(out: &mut String, node: &ViewNode, state: &StateStore, depth: usize) {
+    if !bool_prop(node, "visible", state, true) {
+        return;
+    }
+    let indent = "  ".repeat(depth);
+    let style = box_style(node, state);
+    let content = || escape(&string_prop(node, "content", state, ""));
+
+    match &node.kind {
+        NodeKind::Column | NodeKind::Box | NodeKind::Stack | NodeKind::Scroll => {
+            container(out, node, state, depth, &format!("<div class=\"column\"{}>", style), "</div>");
+        }
+        NodeKind::Row => container(out, node, state, depth, &format!("<div class=\"row\"{}>", style), "</div>"),
+        NodeKind::Center => container(out, node, state, depth, &format!("<div class=\"center\"{}>", style), "</div>"),
+        NodeKind::Grid => {
+            let columns = int_prop(node, "columns", state, 2).max(1);
+            let open = format!(
+                "<div style=\"display: grid; grid-template-columns: repeat({}, 1fr);{}\">",
+                columns,
+                inline_style(node, state)
+            );
+            container(out, node, state, depth, &open, "</div>");
+        }
+        NodeKind::Card => container(out, node, state, depth, &format!("<article class=\"card\"{}>", style), "</article>"),
+        NodeKind::Spacer => {
+            let _ = writeln!(out, "{}<div class=\"spacer\"></div>", indent);
+        }
+        NodeKind::Divider => {
+            let _ = writeln!(out, "{}<hr>", indent);
+        }
+        NodeKind::Text | NodeKind::Markdown => {
+            let size = int_prop(node, "size", state, 16);
+            let tag = match size {
+                28.. => "h1",
+                22..=27 => "h2",
+                18..=21 => "h3",
+                _ => "p",
+            };
+            let mut text_style = format!("font-size: {}px;", size);
+            if let Some(color) = color_prop(node, "color") {
+                let _ = write!(text_style, " color: {};", color);
+            }
+            let _ = writeln!(out, "{}<{} style=\"{}\">{}</{}>", indent, tag, text_style, content(), tag);
+        }
+        NodeKind::Code => {
+            // Inside <pre> the code can't be indented
+            let source = string_prop(node, "content", state, "");
+            let language = string_prop(node, "language", state, "");
+            let mut code = String::new();
+            let mut last = 0;
+            for token in crate::highlight::tokenize_code(&language, &source) {
+                let Some(rgb) = token.class.rgb() else { continue };
+                code.push_str(&escape(&source[last..token.start]));
+                let _ = write!(code, "<span style=\"color: #{:06X}\">{}</span>", rgb, escape(&source[token.start..token.end]));
+                last = token.end;
+            }
+            code.push_str(&escape(&source[last..]));
+            let _ = writeln!(out, "{}<pre class=\"code\"{}>{}</pre>", indent, style, code);
+        }
+        NodeKind::Link => {
+            let href = url_prop(node, "href", state).map(|href| format!(" href=\"{}\"", escape(&href))).unwrap_or_default();
+            let _ = writeln!(out, "{}<a{}>{}</a>", indent, href, content());
+        }
+        NodeKind::Button => {
+            let _ = writeln!(out, "{}<button type=\"button\"{}>{}</button>", indent, style, content());
+        }
+        NodeKind::FilePicker => {
+            let _ = writeln!(out, "{}<input type=\"file\">", indent);
+        }
+        NodeKind::Input | NodeKind::Combobox => {
+            let kind = string_prop(node, "type", state, "text");
+            let _ = writeln!(
+                out,
+                "{}<input type=\"{}\" value=\"{}\" placeholder=\"{}\">",
+                indent,
+                escape(&kind),
+                escape(&bound(node, state).as_string()),
+                escape(&string_prop(node, "placeholder", state, ""))
+            );
+        }
+        NodeKind::TextArea => {
+            let _ = writeln!(out, "{}<textarea>{}</textarea>", indent, escape(&bound(node, state).as_string()));
+        }
+        NodeKind::Checkbox | NodeKind::Toggle | NodeKind::Radio => {
+            let kind = if node.kind == NodeKind::Radio { "radio" } else { "checkbox" };
+            let checked = bound(node, state).as_bool() || bool_prop(node, "checked", state, false) || bool_prop(node, "selected", state, false);
+            let _ = writeln!(
+                out,
+                "{}<label><input type=\"{}\"{}> {}</label>",
+                indent,
+                kind,
+                if checked { " checked" } else { "" },
+                escape(&string_prop(node, "label", state, ""))
+            );
+        }
+        NodeKind::Select => {
+            let current = bound(node, state).as_string();
+            let _ = writeln!(out, "{}<select>", indent);
+            for option in list_prop(node, "options", state) {
+                let option = option.as_string();
+                let selected = if option == current { " selected" } else { "" };
+                let _ = writeln!(out, "{}  <option{}>{}</option>", indent, selected, escape(&option));
+            }
+            let _ = writeln!(out, "{}</select>", indent);
+        }
+        NodeKind::Slider => {
+            let _ = writeln!(
+                out,
+                "{}<input type=\"range\" min=\"{}\" max=\"{}\" value=\"{}\">",
+                indent,
+                int_prop(node, "min", state, 0),
+                int_prop(node, "max", state, 100),
+                bound(node, state).as_float()
+            );
+        }
+        NodeKind::Tags => {
+            let _ = writeln!(out, "{}<ul class=\"tags\">", indent);
+            for tag in bound(node, state).as_list() {
+                let _ = writeln!(out, "{}  <li>{}</li>", indent, escape(&tag.as_string()));
+            }
+            let _ = writeln!(out, "{}</ul>", indent);
+        }
+        NodeKind::Rating => {
+            let max = int_prop(node, "max", state, 5).clamp(1, 20);
+            let value = bound(node, state).as_int().clamp(0, max);
+            let stars = "★".repeat(value as usize) + &"☆".repeat((max - value) as usize);
+            let _ = writeln!(out, "{}<span class=\"rating\" aria-label=\"{} of {}\">{}</span>", indent, value, max, stars);
+        }
+        NodeKind::Stepper | NodeKind::Progress => {
+            let tag = if node.kind == NodeKind::Progress { "progress" } else { "output" };
+            let value = match node.kind {
+                NodeKind::Progress => value_prop(node, "value", state).unwrap_or(Value::Int(0)),
+                _ => bound(node, state),
+            };
+            let max = int_prop(node, "max", state, 100);
+            let _ = writeln!(out, "{}<{} value=\"{}\" max=\"{}\">{}</{}>", indent, tag, value.as_string(), max, value.as_string(), tag);
+        }
+        NodeKind::Image | NodeKind::Icon => {
+            let Some(src) = url_prop(node, "src", state).filter(|src| !src.is_empty()) else {
+                return;
+            };
+            let _ = writeln!(out, "{}<img src=\"{}\" alt=\"{}\"{}>", indent, escape(&src), escape(&string_prop(node, "alt", state, "")), style);
+        }
+        NodeKind::Video | NodeKind::Audio => {
+            let tag = if node.kind == NodeKind::Video { "video" } else { "audio" };
+            let src = url_prop(node, "src", state).unwrap_or_default();
+            let _ = writeln!(out, "{}<{} src=\"{}\" controls></{}>", indent, tag, escape(&src), tag);
+        }
+        NodeKind::Badge => {
+            let _ = writeln!(out, "{}<span class=\"badge\">{}</span>", indent, content());
+        }
+        NodeKind::Avatar => {
+            let name = string_prop(node, "name", state, "");
+            let initials: String = name.split_whitespace().filter_map(|w| w.chars().next()).take(2).collect();
+            let _ = writeln!(out, "{}<span class=\"avatar\" title=\"{}\">{}</span>", indent, escape(&name), escape(&initials));
+        }
+        NodeKind::Table => table(out, node, state, depth),
+        NodeKind::List | NodeKind::Each => items(out, node, state, depth),
+        NodeKind::Modal => {
+            if bool_prop(node, "open", state, false) {
+                container(out, node, state, depth, "<dialog open>", "</dialog>");
+            }
+        }
+        NodeKind::Switch => {
+            if let Some(child) = node.children.iter().find(|c| bool_prop(c, "visible", state, true)) {
+                self::node(out, child, state, depth);
+            }
+        }
+        NodeKind::If | NodeKind::Show | NodeKind::Slot => {
+            for child in &node.children {
+                self::node(out, child, state, depth);
+            }
+        }
+        NodeKind::Component(_) => {
+            state.with_scope(crate::renderer::component_bindings(node, state), || {
+                for child in &node.children {
+                    self::node(out, child, state, depth);
+                }
+            });
+        }
+        // Placeholders only make sense while the page runs
+        NodeKind::Skeleton | NodeKind::Toast | NodeKind::Tooltip | NodeKind::Popover | NodeKind::Empty | NodeKind::Error => {}
+    }
+}
+
+fn container(out: &mut String, node: &ViewNode, state: &StateStore, depth: usize, open: &str, close: &str) {
+    let indent = "  ".repeat(depth);
+    let _ = writeln!(out, "{}{}", indent, open);
+    for child in &node.children {
+        self::node(out, child, state, depth + 1);
+    }
+    let _ = writeln!(out, "{}{}", indent, close);
+}
+
+/// `list`/`each`: children once per item, or the `empty`/`error` child
+fn items(out: &mut String, node: &ViewNode, state: &StateStore, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let items = match value_prop(node, "items", state) {
+        Some(Value::List(items)) => items,
+        other => {
+            let wanted = if other.is_some() { NodeKind::Error } else { NodeKind::Empty };
+            if let Some(slot) = node.children.iter().find(|c| c.kind == wanted) {
+                container(out, slot, state, depth, "<div class=\"column\">", "</div>");
+            }
+            return;
+        }
+    };
+    if items.is_empty() {
+        if let Some(slot) = node.children.iter().find(|c| c.kind == NodeKind::Empty) {
+            container(out, slot, state, depth, "<div class=\"column\">", "</div>");
+        }
+        return;
+    }
+
+    let name = match node.props.get("as") {
+        Some(PropValue::Handler(name)) => name.clone(),
+        _ => "item".to_string(),
+    };
+    let list = node.kind == NodeKind::List;
+    let _ = writeln!(out, "{}{}", indent, if list { "<ul>" } else { "<div class=\"column\">" });
+    for (i, item) in items.into_iter().enumerate() {
+        let bindings = vec![(name.clone(), item), ("index".to_string(), Value::Int(i as i64))];
+        state.with_scope(bindings, || {
+            if list {
+                let _ = writeln!(out, "{}  <li>", indent);
+            }
+            for child in node.children.iter().filter(|c| !matches!(c.kind, NodeKind::Empty | NodeKind::Error)) {
+                self::node(out, child, state, depth + 2);
+            }
+            if list {
+                let _ = writeln!(out, "{}  </li>", indent);
+            }
+        });
+    }
+    let _ = writeln!(out, "{}{}", indent, if list { "</ul>" } else { "</div>" });
+}
+
+fn table(out: &mut String, node: &ViewNode, state: &StateStore, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let rows = list_prop(node, "data", state);
+    let declared = string_prop(node, "columns", state, "");
+    let columns: Vec<String> = if declared.is_empty() {
+        match rows.first() {
+            Some(Value::Object(first)) => {
+                let mut keys: Vec<String> = first.keys().cloned().collect();
+                keys.sort();
+                keys
+            }
+            _ => vec![],
+        }
+    } else {
+        declared.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect()
+    };
+
+    let _ = writeln!(out, "{}<table>", indent);
+    let header: String = columns.iter().map(|c| format!("<th>{}</th>", escape(c))).collect();
+    let _ = writeln!(out, "{}  <thead><tr>{}</tr></thead>", indent, header);
+    let _ = writeln!(out, "{}  <tbody>", indent);
+    for row in &rows {
+        let cells: String = columns
+            .iter()
+            .map(|c| {
+                let cell = match row {
+                    Value::Object(fields) => fields.get(c).map(|v| v.as_string()).unwrap_or_default(),
+                    other => other.as_string(),
+                };
+                format!("<td>{}</td>", escape(&cell))
+            })
+            .collect();
+        let _ = writeln!(out, "{}    <tr>{}</tr>", indent, cells);
+    }
+    let _ = writeln!(out, "{}  </tbody>", indent);
+    let _ = writeln!(out, "{}</table>", indent);
+}
+
+/// ` style="..."` for padding, gap, background, borders, fixed sizes and alignment, or nothing
+fn box_style(node: &ViewNode, state: &StateStore) -> String {
+    let style = inline_style(node, state);
+    if style.is_empty() {
+        String::new()
+    } else {
+        format!(" style=\"{}\"", style.trim())
+    }
+}
+
+fn inline_style(node: &ViewNode, state: &StateStore) -> String {
+    let mut style = String::new();
+    for (prop, css) in [("padding", "padding"), ("gap", "gap"), ("width", "width"), ("height", "height")] {
+        if let Some(value) = value_prop(node, prop, state) {
+            let _ = write!(style, " {}: {}px;", css, value.as_int());
+        }
+    }
+    if let Some(color) = color_prop(node, "background") {
+        let _ = write!(style, " background: {};", color);
+    }
+    if let Some(border) = value_prop(node, "border", state).filter(|v| v.as_int() > 0) {
+        let color = color_prop(node, "border_color").unwrap_or_else(|| "#CCCCCC".to_string());
+        let _ = write!(style, " border: {}px solid {};", border.as_int(), color);
+    }
+    let radius = value_prop(node, "radius", state).or_else(|| value_prop(node, "border_radius", state));
+    if let Some(radius) = radius.filter(|v| v.as_int() > 0) {
+        let _ = write!(style, " border-radius: {}px;", radius.as_int());
+    }
+    if value_prop(node, "wrap", state).is_some_and(|v| v.as_bool()) {
+        style.push_str(" flex-wrap: wrap;");
+    }
+    if let Some(flex) = value_prop(node, "flex", state).filter(|v| v.as_int() > 0) {
+        let _ = write!(style, " flex: {};", flex.as_int());
+    }
+    for (prop, css) in [("justify", "justify-content"), ("align", "align-items")] {
+        let value = match value_prop(node, prop, state).map(|v| v.as_string()).as_deref() {
+            Some("start") => "flex-start",
+            Some("center") => "center",
+            Some("end") => "flex-end",
+            Some("stretch") => "stretch",
+            Some("space_between") => "space-between",
+            _ => continue,
+        };
+        let _ = write!(style, " {}: {};", css, value);
+    }
+    style
+}
+
+/// The bound state variable's value, or Null
+fn bound(node: &ViewNode, state: &StateStore) -> Value {
+    match node.props.get("bind") {
+        Some(PropValue::Handler(var)) => state.get(var).unwrap_or(Value::Null),
+        _ => value_prop(node, "value", state).unwrap_or(Value::Null),
+    }
+}
+
+fn value_prop(node: &ViewNode, name: &str, state: &StateStore) -> Option<Value> {
+    match node.props.get(name)? {
+        PropValue::Static(v) => Some(v.clone()),
+        PropValue::Expression(expr) => Some(state.evaluate(expr)),
+        PropValue::Handler(var) => Some(state.get(var).unwrap_or_else(|| Value::String(var.clone()))),
+        _ => None,
+    }
+}
+
+fn string_prop(node: &ViewNode, name: &str, state: &StateStore, default: &str) -> String {
+    value_prop(node, name, state).map(|v| v.as_string()).unwrap_or_else(|| default.to_string())
+}
+
+/// A link or media address, unless it has a scheme other than http, https or
+/// mailto (such as `javascript:`), which the exported page mustn't run
+fn url_prop(node: &ViewNode, name: &str, state: &StateStore) -> Option<String> {
+    let url = string_prop(node, name, state, "");
+    // Browsers ignore tabs and newlines anywhere in a URL and spaces around it
+    let cleaned: String = url.trim_matches(|c: char| c <= ' ').chars().filter(|c| !matches!(c, '\t' | '\n' | '\r')).collect();
+    let scheme = cleaned.split_once(':').map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']))
+        .map(str::to_ascii_lowercase);
+    match scheme.as_deref() {
+        None | Some("http" | "https" | "mailto") => Some(url),
+        Some(_) => None,
+    }
+}
+
+fn int_prop(node: &ViewNode, name: &str, state: &StateStore, default: i64) -> i64 {
+    value_prop(node, name, state).map(|v| v.as_int()).unwrap_or(default)
+}
+
+fn bool_prop(node: &ViewNode, name: &str, state: &StateStore, default: bool) -> bool {
+    value_prop(node, name, state).map(|v| v.as_bool()).unwrap_or(default)
+}
+
+fn list_prop(node: &ViewNode, name: &str, state: &StateStore) -> Vec<Value> {
+    value_prop(node, name, state).map(|v| v.as_list()).unwrap_or_default()
+}
+
+/// CSS color for a color prop
+fn color_prop(node: &ViewNode, name: &str) -> Option<String> {
+    let color = match node.props.get(name)? {
+        PropValue::Color(c) => *c,
+        PropValue::Static(Value::String(s)) => Color::from_hex(s)?,
+        _ => return None,
+    };
+    Some(if color.a == 255 {
+        format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+    } else {
+        format!("rgba({}, {}, {}, {:.3})", color.r, color.g, color.b, color.a as f32 / 255.0)
+    })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,489 @@
+//! C ABI for embedding the Prism runtime
+//!
+//! A host creates an engine, loads a document, forwards pointer and keyboard
+//! input, renders into its own pixel buffer and polls the events the page
+//! emits (document loads, link navigation, actions). Everything is single-threaded:
+//! an engine must only be used from the thread that created it.
+//!
+//! Strings passed in are NUL-terminated UTF-8. Strings returned by
+//! `prism_engine_poll_event` are owned by the caller and must be released
+//! with `prism_string_free`; `prism_engine_last_error` returns a pointer
+//! owned by the engine, valid until the next call on it.
+//!
+//! A panic inside the engine never unwinds into the host: the call fails as
+//! if it had returned an error (`PRISM_ERROR`, 0 or null) and
+//! `prism_engine_last_error` describes it.
+
+use crate::renderer::FrameBuffer;
+use crate::runtime::{Key, Runtime};
+use crate::vfs::Vfs;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+pub const PRISM_OK: c_int = 0;
+pub const PRISM_ERROR: c_int = -1;
+
+/// Keys for `prism_engine_key`; printable text goes through `prism_engine_key_char`
+pub const PRISM_KEY_BACKSPACE: c_int = 1;
+pub const PRISM_KEY_TAB: c_int = 2;
+pub const PRISM_KEY_ENTER: c_int = 3;
+pub const PRISM_KEY_ESCAPE: c_int = 4;
+pub const PRISM_KEY_UP: c_int = 5;
+pub const PRISM_KEY_DOWN: c_int = 6;
+pub const PRISM_KEY_HOME: c_int = 7;
+pub const PRISM_KEY_END: c_int = 8;
+
+/// An embedded runtime with its viewport, scroll position and event queue
+pub struct PrismEngine {
+    runtime: Option<Runtime>,
+    width: usize,
+    height: usize,
+    scroll_y: i32,
+    events: VecDeque<serde_json::Value>,
+    last_error: Option<CString>,
+    /// Host preferences for `env`, kept across loads
+    dark_mode: bool,
+    locale: Option<String>,
+}
+
+impl PrismEngine {
+    fn set_error(&mut self, message: String) -> c_int {
+        self.last_error = CString::new(message.replace('\0', " ")).ok();
+        PRISM_ERROR
+    }
+
+    fn max_scroll(&mut self) -> i32 {
+        let width = self.width as u32;
+        let content = self.runtime.as_mut().map_or(0, |rt| rt.content_height(width)) as i32;
+        (content - self.height as i32).max(0)
+    }
+}
+
+/// Run an entry point's body on an engine handle. A null handle gives
+/// `failed`, and so does a panic, which is reported through `last_error`
+/// instead of unwinding into the host.
+unsafe fn with_engine<T>(engine: *mut PrismEngine, failed: T, body: impl FnOnce(&mut PrismEngine) -> T) -> T {
+    let Some(handle) = engine.as_mut() else {
+        return failed;
+    };
+    match catch_unwind(AssertUnwindSafe(|| body(handle))) {
+        Ok(value) => value,
+        Err(panic) => {
+            if let Some(handle) = engine.as_mut() {
+                handle.set_error(format!("Internal error: {}", panic_message(&*panic)));
+            }
+            failed
+        }
+    }
+}
+
+/// Run an entry point's body that has no engine, giving `failed` on a panic
+fn guarded<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(failed)
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message,
+        (_, Some(message)) => message,
+        _ => "panic",
+    }
+}
+
+unsafe fn string_arg(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+/// Create an engine with a `width` x `height` viewport (at least 1x1)
+#[no_mangle]
+pub extern "C" fn prism_engine_new(width: u32, height: u32) -> *mut PrismEngine {
+    guarded(std::ptr::null_mut(), || {
+        Box::into_raw(Box::new(PrismEngine {
+            runtime: None,
+            width: width.max(1) as usize,
+            height: height.max(1) as usize,
+            scroll_y: 0,
+            events: VecDeque::new(),
+            last_error: None,
+            dark_mode: false,
+            locale: None,
+        }))
+    })
+}
+
+/// Destroy an engine created by `prism_engine_new`
+///
+/// # Safety
+/// `engine` must be null or a pointer from `prism_engine_new` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_free(engine: *mut PrismEngine) {
+    if !engine.is_null() {
+        guarded((), || drop(Box::from_raw(engine)));
+    }
+}
+
+/// Parse and load a .prism source; `location` (may be null) is the path or URL
+/// relative resources resolve against. Emits a `load` event on success.
+///
+/// # Safety
+/// `engine` must be a live engine; `source` and `location` NUL-terminated strings or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_load_source(
+    engine: *mut PrismEngine,
+    source: *const c_char,
+    location: *const c_char,
+) -> c_int {
+    with_engine(engine, PRISM_ERROR, |engine| {
+        let Some(source) = string_arg(source) else {
+            return engine.set_error("source is null".to_string());
+        };
+        let location = string_arg(location);
+        let assets = location.as_deref().map(Vfs::for_location).unwrap_or_default();
+        let app = match crate::parser::parse_with_assets(&source, &assets) {
+            Ok(app) => app,
+            Err(e) => return engine.set_error(format!("Parse error: {}", e)),
+        };
+        let mut runtime = Runtime::new(app);
+        if let Some(location) = location {
+            runtime.set_location(&location);
+        }
+        // `prism_engine_query` matches selectors against the drawn nodes' props
+        runtime.renderer.record_props = true;
+        runtime.set_dark_mode(engine.dark_mode);
+        if let Some(locale) = &engine.locale {
+            runtime.set_locale(locale);
+        }
+        engine.events.push_back(json!({ "type": "load", "title": runtime.title() }));
+        engine.runtime = Some(runtime);
+        engine.scroll_y = 0;
+        engine.last_error = None;
+        PRISM_OK
+    })
+}
+
+/// Message of the last failed call, or null
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_last_error(engine: *const PrismEngine) -> *const c_char {
+    guarded(std::ptr::null(), || match engine.as_ref().and_then(|e| e.last_error.as_ref()) {
+        Some(message) => message.as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+/// Change the viewport size
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_resize(engine: *mut PrismEngine, width: u32, height: u32) {
+    with_engine(engine, (), |engine| {
+        engine.width = width.max(1) as usize;
+        engine.height = height.max(1) as usize;
+        if let Some(rt) = engine.runtime.as_mut() {
+            rt.invalidate();
+        }
+    })
+}
+
+/// Tell pages whether the host uses a dark theme (`env.dark_mode`)
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_set_dark_mode(engine: *mut PrismEngine, dark: c_int) {
+    with_engine(engine, (), |engine| {
+        engine.dark_mode = dark != 0;
+        if let Some(rt) = engine.runtime.as_mut() {
+            rt.set_dark_mode(engine.dark_mode);
+        }
+    })
+}
+
+/// Override the system locale pages see as `env.locale`, e.g. "pt-BR"
+///
+/// # Safety
+/// `engine` must be a live engine or null; `locale` a NUL-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_set_locale(engine: *mut PrismEngine, locale: *const c_char) {
+    with_engine(engine, (), |engine| {
+        let Some(locale) = string_arg(locale) else {
+            return;
+        };
+        if let Some(rt) = engine.runtime.as_mut() {
+            rt.set_locale(&locale);
+        }
+        engine.locale = Some(locale);
+    })
+}
+
+/// Scroll the page by `dy` pixels, clamped to the content; returns the new offset
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_scroll(engine: *mut PrismEngine, dy: c_int) -> c_int {
+    with_engine(engine, 0, |engine| {
+        let max = engine.max_scroll();
+        engine.scroll_y = (engine.scroll_y + dy).clamp(0, max);
+        engine.scroll_y
+    })
+}
+
+/// Whether the page changed since the last render; also delivers messages
+/// other same-origin engines broadcast and queues `notification` events, so
+/// call it once per frame
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_needs_render(engine: *mut PrismEngine) -> c_int {
+    with_engine(engine, 0, |engine| match engine.runtime.as_mut() {
+        Some(rt) => {
+            rt.pump_broadcasts();
+            rt.pump_data();
+            for notice in rt.take_notices() {
+                engine.events.push_back(json!({ "type": "notification", "title": notice.title, "body": notice.body }));
+            }
+            rt.state.is_dirty() as c_int
+        }
+        None => 0,
+    })
+}
+
+/// Render the viewport into `buffer`, row-major `width * height` pixels as
+/// 0x00RRGGBB; `len` is the buffer's length in pixels
+///
+/// # Safety
+/// `engine` must be a live engine; `buffer` must point to `len` writable `u32`s.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_render(engine: *mut PrismEngine, buffer: *mut u32, len: usize) -> c_int {
+    with_engine(engine, PRISM_ERROR, |engine| {
+        if buffer.is_null() || len < engine.width * engine.height {
+            return engine.set_error(format!("buffer must hold {} pixels", engine.width * engine.height));
+        }
+        let max = engine.max_scroll();
+        engine.scroll_y = engine.scroll_y.min(max);
+        let mut fb = FrameBuffer::new(engine.width, engine.height);
+        match engine.runtime.as_mut() {
+            Some(rt) => {
+                rt.render(&mut fb, engine.scroll_y);
+                if let Some(y) = rt.take_scroll_request() {
+                    engine.scroll_y = y.clamp(0, max);
+                    rt.render(&mut fb, engine.scroll_y);
+                }
+            }
+            None => return engine.set_error("no document loaded".to_string()),
+        }
+        std::slice::from_raw_parts_mut(buffer, fb.pixels.len()).copy_from_slice(&fb.pixels);
+        PRISM_OK
+    })
+}
+
+/// Total height of the laid-out page at the current width
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_content_height(engine: *mut PrismEngine) -> u32 {
+    with_engine(engine, 0, |engine| {
+        let width = engine.width as u32;
+        engine.runtime.as_mut().map_or(0, |rt| rt.content_height(width))
+    })
+}
+
+/// Press the primary pointer button at viewport coordinates. Links emit a
+/// `navigate` event instead of loading anything; the host decides what to do.
+/// Returns 1 when the page handled the press.
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_pointer_down(engine: *mut PrismEngine, x: c_int, y: c_int) -> c_int {
+    with_engine(engine, 0, |engine| {
+        let Some(rt) = engine.runtime.as_mut() else {
+            return 0;
+        };
+        if rt.begin_column_resize(x, y) {
+            return 1;
+        }
+        let hit = rt.renderer.hit_test(x, y).map(|b| (b.link_href.clone(), b.action.clone()));
+        if let Some((Some(href), _)) = hit {
+            if rt.follow_route(&href) {
+                return 1;
+            }
+            engine.events.push_back(json!({ "type": "navigate", "href": href }));
+            return 1;
+        }
+        let handled = rt.handle_click(x, y);
+        rt.renderer.set_focus(rt.focused_input.clone());
+        if let Some((_, Some(action))) = hit.filter(|_| handled) {
+            engine.events.push_back(json!({ "type": "action", "name": action }));
+        }
+        handled as c_int
+    })
+}
+
+/// Move the pointer; drags a table column divider while one is held
+/// Returns 1 when the page needs a render
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_pointer_move(engine: *mut PrismEngine, x: c_int, y: c_int) -> c_int {
+    with_engine(engine, 0, |engine| match engine.runtime.as_mut() {
+        Some(rt) if rt.is_resizing_column() => rt.drag_column_resize(x) as c_int,
+        Some(rt) => rt.handle_hover(Some((x, y))) as c_int,
+        None => 0,
+    })
+}
+
+/// The pointer left the page; ends `hovering` and runs `on_leave`
+/// Returns 1 when the page needs a render
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_pointer_leave(engine: *mut PrismEngine) -> c_int {
+    with_engine(engine, 0, |engine| match engine.runtime.as_mut() {
+        Some(rt) => rt.handle_hover(None) as c_int,
+        None => 0,
+    })
+}
+
+/// Release the primary pointer button
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_pointer_up(engine: *mut PrismEngine, _x: c_int, _y: c_int) {
+    with_engine(engine, (), |engine| {
+        if let Some(rt) = engine.runtime.as_mut() {
+            rt.end_column_resize();
+        }
+    })
+}
+
+/// Type a Unicode scalar into the focused input; returns 1 when consumed
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_key_char(engine: *mut PrismEngine, codepoint: u32) -> c_int {
+    with_engine(engine, 0, |engine| {
+        let Some(rt) = engine.runtime.as_mut() else {
+            return 0;
+        };
+        match char::from_u32(codepoint) {
+            Some(ch) if !ch.is_control() && rt.focused_input.is_some() => rt.handle_key(ch) as c_int,
+            _ => 0,
+        }
+    })
+}
+
+/// Show an IME composition (UTF-8) at the focused input's caret, with its
+/// caret at byte `cursor` (-1 for the end); an empty string ends it
+/// Returns 1 when the page needs a render
+///
+/// # Safety
+/// `engine` must be a live engine or null; `text` a NUL-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_ime_preedit(engine: *mut PrismEngine, text: *const c_char, cursor: c_int) -> c_int {
+    with_engine(engine, 0, |engine| {
+        let (Some(rt), Some(text)) = (engine.runtime.as_mut(), string_arg(text)) else {
+            return 0;
+        };
+        rt.set_preedit(&text, usize::try_from(cursor).ok()) as c_int
+    })
+}
+
+/// Type the text (UTF-8) an IME finished composing; returns 1 when consumed
+///
+/// # Safety
+/// `engine` must be a live engine or null; `text` a NUL-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_ime_commit(engine: *mut PrismEngine, text: *const c_char) -> c_int {
+    with_engine(engine, 0, |engine| {
+        let (Some(rt), Some(text)) = (engine.runtime.as_mut(), string_arg(text)) else {
+            return 0;
+        };
+        rt.commit_text(&text) as c_int
+    })
+}
+
+/// Press one of the `PRISM_KEY_*` keys; returns 1 when the page handled it
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_key(engine: *mut PrismEngine, key: c_int, shift: c_int) -> c_int {
+    with_engine(engine, 0, |engine| {
+        let Some(rt) = engine.runtime.as_mut() else {
+            return 0;
+        };
+        let key = match key {
+            PRISM_KEY_BACKSPACE => Key::Backspace,
+            PRISM_KEY_TAB => Key::Tab,
+            PRISM_KEY_ENTER => Key::Enter,
+            PRISM_KEY_ESCAPE => Key::Escape,
+            PRISM_KEY_UP => Key::Up,
+            PRISM_KEY_DOWN => Key::Down,
+            PRISM_KEY_HOME => Key::Home,
+            PRISM_KEY_END => Key::End,
+            _ => return 0,
+        };
+        rt.handle_special_key(key, shift != 0) as c_int
+    })
+}
+
+/// Nodes of the last render matching `selector` (see `query::Selector`), as a
+/// JSON array of `{kind, props, x, y, width, height}`; null on an invalid
+/// selector. Free it with `prism_string_free`.
+///
+/// # Safety
+/// `engine` must be a live engine; `selector` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_query(engine: *mut PrismEngine, selector: *const c_char) -> *mut c_char {
+    with_engine(engine, std::ptr::null_mut(), |engine| {
+        let (Some(rt), Some(selector)) = (engine.runtime.as_ref(), string_arg(selector)) else {
+            return std::ptr::null_mut();
+        };
+        let json = match rt.query(&selector).map(|nodes| serde_json::to_string(&nodes).unwrap_or_default()) {
+            Ok(json) => json,
+            Err(e) => {
+                engine.set_error(format!("Invalid selector: {}", e));
+                return std::ptr::null_mut();
+            }
+        };
+        CString::new(json).map_or(std::ptr::null_mut(), CString::into_raw)
+    })
+}
+
+/// Next queued event as a JSON object (`{"type": "navigate", "href": ...}`,
+/// `{"type": "action", "name": ...}`, `{"type": "load", "title": ...}`),
+/// or null when the queue is empty. Free it with `prism_string_free`.
+///
+/// # Safety
+/// `engine` must be a live engine or null.
+#[no_mangle]
+pub unsafe extern "C" fn prism_engine_poll_event(engine: *mut PrismEngine) -> *mut c_char {
+    with_engine(engine, std::ptr::null_mut(), |engine| match engine.events.pop_front() {
+        Some(event) => CString::new(event.to_string()).map_or(std::ptr::null_mut(), CString::into_raw),
+        None => std::ptr::null_mut(),
+    })
+}
+
+/// Release a string returned by this library
+///
+/// # Safety
+/// `s` must be null or a string from `prism_engine_poll_event` not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn prism_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        guarded((), || drop(CString::from_raw(s)));
+    }
+}
@@ -0,0 +1,103 @@
+//! Gamepad and remote-control input for spatial navigation
+//!
+//! Controllers are read through gilrs on a background thread, so TV and kiosk
+//! setups need no mouse or keyboard: the D-pad or left stick of any connected
+//! controller moves a highlight between clickable boxes, A (the bottom face
+//! button) clicks it and B goes back. Controllers plugged in later are picked
+//! up as they connect. Without a gamepad backend there is no gamepad input.
+
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Stick deflection that counts as a D-pad press
+const AXIS_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    Activate,
+    Back,
+}
+
+/// Presses from every connected controller, in arrival order
+pub struct Gamepads {
+    presses: Receiver<PadButton>,
+}
+
+impl Gamepads {
+    pub fn start() -> Gamepads {
+        let (tx, presses) = channel();
+        std::thread::spawn(move || read_controllers(tx));
+        Gamepads { presses }
+    }
+
+    /// Presses since the last poll
+    pub fn poll(&self) -> Vec<PadButton> {
+        self.presses.try_iter().collect()
+    }
+}
+
+/// Forward presses from every controller until the browser goes away
+fn read_controllers(tx: Sender<PadButton>) {
+    let mut gilrs = match Gilrs::new() {
+        Ok(gilrs) => gilrs,
+        Err(e) => {
+            eprintln!("[PRISM] Gamepad input unavailable: {}", e);
+            return;
+        }
+    };
+    // Last direction each controller's stick axes were pushed in, so a held stick presses once
+    let mut axes: HashMap<(GamepadId, Axis), i8> = HashMap::new();
+    loop {
+        let Some(event) = gilrs.next_event_blocking(None) else {
+            continue;
+        };
+        let press = match event.event {
+            EventType::ButtonPressed(pressed, _) => button(pressed),
+            EventType::AxisChanged(moved, value, _) => axis(axes.entry((event.id, moved)).or_default(), moved, value),
+            EventType::Disconnected => {
+                axes.retain(|(id, _), _| *id != event.id);
+                None
+            }
+            _ => None,
+        };
+        if press.is_some_and(|press| tx.send(press).is_err()) {
+            return;
+        }
+    }
+}
+
+/// Xbox-style layout: A, B and the D-pad
+fn button(pressed: Button) -> Option<PadButton> {
+    match pressed {
+        Button::South => Some(PadButton::Activate),
+        Button::East => Some(PadButton::Back),
+        Button::DPadLeft => Some(PadButton::Left),
+        Button::DPadRight => Some(PadButton::Right),
+        Button::DPadUp => Some(PadButton::Up),
+        Button::DPadDown => Some(PadButton::Down),
+        _ => None,
+    }
+}
+
+/// The left stick (up is positive)
+fn axis(last: &mut i8, moved: Axis, value: f32) -> Option<PadButton> {
+    let direction = if value > AXIS_THRESHOLD { 1 } else if value < -AXIS_THRESHOLD { -1 } else { 0 };
+    if direction == *last {
+        return None;
+    }
+    *last = direction;
+    match (moved, direction) {
+        (Axis::LeftStickX, -1) => Some(PadButton::Left),
+        (Axis::LeftStickX, 1) => Some(PadButton::Right),
+        (Axis::LeftStickY, 1) => Some(PadButton::Up),
+        (Axis::LeftStickY, -1) => Some(PadButton::Down),
+        _ => None,
+    }
+}
@@ -0,0 +1,68 @@
+//! Rasterized glyphs kept between frames
+//!
+//! Drawing text used to rasterize every glyph of every run on every render.
+//! The [`GlyphCache`] keeps coverage bitmaps by font, glyph and size, so text
+//! already on screen costs a copy, and text that scrolls into view is usually
+//! warm too: runs laid out just below the viewport are queued by the renderer
+//! and rasterized ahead of time in idle frames (see `Renderer::prerasterize`).
+//!
+//! The cache holds at most its byte budget, set by the runtime from the
+//! sandbox's memory limit; when full it starts over.
+
+use fontdue::layout::GlyphRasterConfig;
+use fontdue::{Font, Metrics};
+use std::collections::HashMap;
+
+/// Per-entry bookkeeping counted against the budget besides the bitmap
+const ENTRY_OVERHEAD_BYTES: usize = 64;
+
+/// Coverage bitmaps of rasterized glyphs
+#[derive(Default)]
+pub struct GlyphCache {
+    glyphs: HashMap<GlyphRasterConfig, (Metrics, Vec<u8>)>,
+    bytes: usize,
+    budget: usize,
+}
+
+impl GlyphCache {
+    pub fn new(budget: usize) -> Self {
+        Self { budget, ..Self::default() }
+    }
+
+    /// Change the budget, dropping everything if the cache no longer fits
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        if self.bytes > budget {
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.bytes = 0;
+    }
+
+    /// Bytes held by cached bitmaps
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn contains(&self, key: &GlyphRasterConfig) -> bool {
+        self.glyphs.contains_key(key)
+    }
+
+    /// The glyph's metrics and coverage, rasterizing it on first use
+    pub fn get(&mut self, font: &Font, key: GlyphRasterConfig) -> (&Metrics, &[u8]) {
+        if !self.glyphs.contains_key(&key) {
+            let (metrics, bitmap) = font.rasterize_config(key);
+            let size = bitmap.len() + ENTRY_OVERHEAD_BYTES;
+            if self.bytes + size > self.budget {
+                self.clear();
+            }
+            self.bytes += size;
+            self.glyphs.insert(key, (metrics, bitmap));
+        }
+        let (metrics, bitmap) = &self.glyphs[&key];
+        (metrics, bitmap)
+    }
+}
@@ -0,0 +1,464 @@
+//! Syntax classification for Prism sources
+//!
+//! `tokenize` splits a document into classified byte ranges that editors and
+//! tools can consume; `to_html` and `to_ansi` render them for `prism highlight`.
+//! `tokenize_code` does the same, more roughly, for the languages `code` nodes
+//! highlight.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenClass {
+    Comment,
+    Directive,
+    Keyword,
+    Node,
+    Component,
+    Property,
+    Identifier,
+    String,
+    Interpolation,
+    Number,
+    Color,
+    Operator,
+    Punctuation,
+}
+
+impl TokenClass {
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenClass::Comment => "comment",
+            TokenClass::Directive => "directive",
+            TokenClass::Keyword => "keyword",
+            TokenClass::Node => "node",
+            TokenClass::Component => "component",
+            TokenClass::Property => "property",
+            TokenClass::Identifier => "identifier",
+            TokenClass::String => "string",
+            TokenClass::Interpolation => "interpolation",
+            TokenClass::Number => "number",
+            TokenClass::Color => "color",
+            TokenClass::Operator => "operator",
+            TokenClass::Punctuation => "punctuation",
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            TokenClass::Comment => "\x1b[90m",
+            TokenClass::Directive => "\x1b[35m",
+            TokenClass::Keyword => "\x1b[1;35m",
+            TokenClass::Node => "\x1b[1;34m",
+            TokenClass::Component => "\x1b[34m",
+            TokenClass::Property => "\x1b[36m",
+            TokenClass::Identifier => "",
+            TokenClass::String => "\x1b[32m",
+            TokenClass::Interpolation => "\x1b[33m",
+            TokenClass::Number | TokenClass::Color => "\x1b[33m",
+            TokenClass::Operator | TokenClass::Punctuation => "",
+        }
+    }
+
+    /// Color drawn in `code` nodes, matching the HTML style; `None` keeps the
+    /// node's text color
+    pub fn rgb(self) -> Option<u32> {
+        match self {
+            TokenClass::Comment => Some(0x80868B),
+            TokenClass::Directive | TokenClass::Keyword => Some(0xA142F4),
+            TokenClass::Node | TokenClass::Component => Some(0x1A73E8),
+            TokenClass::Property => Some(0x007B83),
+            TokenClass::String => Some(0x188038),
+            TokenClass::Interpolation | TokenClass::Number | TokenClass::Color => Some(0xB06000),
+            TokenClass::Identifier | TokenClass::Operator | TokenClass::Punctuation => None,
+        }
+    }
+}
+
+/// A classified span of the source; `start`/`end` are byte offsets, `line` is 1-based
+#[derive(Debug, Clone, Serialize)]
+pub struct Token {
+    pub class: TokenClass,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+const KEYWORDS: &[&str] = &[
+    "state", "view", "actions", "component", "props", "true", "false", "null", "and", "or", "not", "in", "as",
+];
+
+/// Classify every non-whitespace part of `source`
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    let mut push = |class, start, end, line| tokens.push(Token { class, start, end, line });
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        match c {
+            b'\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                push(TokenClass::Comment, start, i, line);
+            }
+            b'@' => {
+                i += 1 + ident_len(&bytes[i + 1..]);
+                push(TokenClass::Directive, start, i, line);
+            }
+            b'"' => {
+                // Strings are split around `{...}` interpolations
+                let mut piece = i;
+                let mut piece_line = line;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    match bytes[i] {
+                        b'\\' => i += 2,
+                        b'{' => {
+                            if piece < i {
+                                push(TokenClass::String, piece, i, piece_line);
+                            }
+                            piece = i;
+                            while i < bytes.len() && bytes[i] != b'}' && bytes[i] != b'"' {
+                                i += 1;
+                            }
+                            if bytes.get(i) == Some(&b'}') {
+                                i += 1;
+                            }
+                            push(TokenClass::Interpolation, piece, i, line);
+                            piece = i;
+                            piece_line = line;
+                        }
+                        b'\n' => {
+                            line += 1;
+                            i += 1;
+                        }
+                        _ => i += 1,
+                    }
+                }
+                i = (i + 1).min(bytes.len());
+                if piece < i {
+                    push(TokenClass::String, piece, i, piece_line);
+                }
+            }
+            b'#' => {
+                i += 1;
+                while i < bytes.len() && bytes[i].is_ascii_hexdigit() {
+                    i += 1;
+                }
+                let class = if i - start > 1 { TokenClass::Color } else { TokenClass::Operator };
+                push(class, start, i, line);
+            }
+            c if c.is_ascii_digit() => {
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)) {
+                    i += 1;
+                }
+                push(TokenClass::Number, start, i, line);
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                i += ident_len(&bytes[i..]);
+                let word = &source[start..i];
+                let next = bytes[i..].iter().find(|b| **b != b' ' && **b != b'\t');
+                let class = if KEYWORDS.contains(&word) {
+                    TokenClass::Keyword
+                } else if next == Some(&b':') {
+                    TokenClass::Property
+                } else if crate::parser::is_builtin_node(word) {
+                    TokenClass::Node
+                } else if matches!(next, Some(b'{') | Some(b'"')) {
+                    TokenClass::Component
+                } else {
+                    TokenClass::Identifier
+                };
+                push(class, start, i, line);
+            }
+            b'{' | b'}' | b'(' | b')' | b'[' | b']' | b',' | b':' => {
+                i += 1;
+                push(TokenClass::Punctuation, start, i, line);
+            }
+            _ => {
+                // Operators, and any other character, one UTF-8 scalar at a time
+                i += source[i..].chars().next().map(char::len_utf8).unwrap_or(1);
+                push(TokenClass::Operator, start, i, line);
+            }
+        }
+    }
+    tokens
+}
+
+/// Lexical rules of a language `code` nodes highlight
+struct Language {
+    names: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [u8],
+    /// `'a` is a lifetime rather than the start of a char literal
+    lifetimes: bool,
+    keywords: &'static [&'static str],
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: b"\"'",
+        lifetimes: true,
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for",
+            "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static",
+            "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+    },
+    Language {
+        names: &["python", "py"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: b"\"'",
+        lifetimes: false,
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif", "else", "except", "False",
+            "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass",
+            "raise", "return", "True", "try", "while", "with", "yield",
+        ],
+    },
+    Language {
+        names: &["javascript", "js", "typescript", "ts", "jsx", "tsx"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: b"\"'`",
+        lifetimes: false,
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete", "do", "else", "export",
+            "extends", "false", "finally", "for", "from", "function", "if", "import", "in", "instanceof", "interface", "let", "new",
+            "null", "of", "return", "static", "switch", "this", "throw", "true", "try", "type", "typeof", "undefined", "var",
+            "void", "while", "yield",
+        ],
+    },
+    Language {
+        names: &["c", "h", "cpp", "c++", "cc", "hpp"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: b"\"'",
+        lifetimes: false,
+        keywords: &[
+            "auto", "bool", "break", "case", "char", "class", "const", "continue", "default", "delete", "do", "double", "else",
+            "enum", "extern", "false", "float", "for", "if", "include", "int", "long", "namespace", "new", "nullptr", "private",
+            "public", "return", "short", "signed", "sizeof", "static", "struct", "switch", "template", "this", "true", "typedef",
+            "union", "unsigned", "using", "virtual", "void", "while",
+        ],
+    },
+    Language {
+        names: &["go", "golang"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: b"\"'`",
+        lifetimes: false,
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "false", "for", "func", "go", "if",
+            "import", "interface", "map", "nil", "package", "range", "return", "select", "struct", "switch", "true", "type", "var",
+        ],
+    },
+    Language {
+        names: &["java", "kotlin", "kt"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: b"\"'",
+        lifetimes: false,
+        keywords: &[
+            "abstract", "boolean", "break", "case", "catch", "class", "else", "extends", "false", "final", "finally", "for",
+            "fun", "if", "implements", "import", "int", "interface", "new", "null", "override", "package", "private",
+            "protected", "public", "return", "static", "super", "switch", "this", "throw", "throws", "true", "try", "val",
+            "var", "void", "when", "while",
+        ],
+    },
+    Language {
+        names: &["shell", "sh", "bash", "zsh", "console"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: b"\"'",
+        lifetimes: false,
+        keywords: &[
+            "case", "do", "done", "echo", "elif", "else", "esac", "exit", "export", "fi", "for", "function", "if", "in", "local",
+            "return", "then", "while",
+        ],
+    },
+    Language {
+        names: &["json", "toml", "yaml", "yml"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: b"\"'",
+        lifetimes: false,
+        keywords: &["true", "false", "null"],
+    },
+];
+
+/// Classify `source` as code in `language` (a name or file extension, any
+/// case); `prism` gets the full document tokenizer, and unknown languages
+/// aren't classified at all
+pub fn tokenize_code(language: &str, source: &str) -> Vec<Token> {
+    let language = language.trim().to_ascii_lowercase();
+    if language == "prism" {
+        return tokenize(source);
+    }
+    let Some(rules) = LANGUAGES.iter().find(|l| l.names.contains(&language.as_str())) else {
+        return vec![];
+    };
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    let mut push = |class, start, end, line| tokens.push(Token { class, start, end, line });
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        let rest = &source[i..];
+        if c == b'\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_ascii_whitespace() {
+            i += 1;
+        } else if rules.line_comments.iter().any(|marker| rest.starts_with(marker)) {
+            i += rest.find('\n').unwrap_or(rest.len());
+            push(TokenClass::Comment, start, i, line);
+        } else if let Some((open, close)) = rules.block_comment.filter(|(open, _)| rest.starts_with(open)) {
+            i += rest[open.len()..].find(close).map_or(rest.len(), |end| open.len() + end + close.len());
+            push(TokenClass::Comment, start, i, line);
+            line += source[start..i].matches('\n').count();
+        } else if rules.lifetimes && c == b'\'' && bytes.get(i + 2) != Some(&b'\'') && bytes.get(i + 1) != Some(&b'\\') {
+            i += 1 + ident_len(&bytes[i + 1..]);
+            push(TokenClass::Identifier, start, i, line);
+        } else if rules.quotes.contains(&c) {
+            // Python's triple quotes and backtick templates may span lines
+            let triple = [c; 3];
+            let close: &[u8] = if bytes[i..].starts_with(&triple) { &triple } else { &triple[..1] };
+            i += close.len();
+            while i < bytes.len() && !bytes[i..].starts_with(close) {
+                match bytes[i] {
+                    b'\\' => i += 2,
+                    b'\n' if close.len() == 1 && c != b'`' => break,
+                    _ => i += 1,
+                }
+            }
+            i = (i + close.len()).min(bytes.len());
+            // A string ends on a char boundary unless it was cut short
+            while !source.is_char_boundary(i) {
+                i -= 1;
+            }
+            push(TokenClass::String, start, i, line);
+            line += source[start..i].matches('\n').count();
+        } else if c.is_ascii_digit() {
+            i += bytes[i..].iter().take_while(|b| b.is_ascii_alphanumeric() || **b == b'_' || **b == b'.').count();
+            push(TokenClass::Number, start, i, line);
+        } else if c.is_ascii_alphabetic() || c == b'_' || c == b'$' {
+            i += 1 + ident_len(&bytes[i + 1..]);
+            let word = &source[start..i];
+            let class = if rules.keywords.contains(&word) {
+                TokenClass::Keyword
+            } else if c.is_ascii_uppercase() {
+                // Capitalized names are usually types
+                TokenClass::Component
+            } else {
+                TokenClass::Identifier
+            };
+            push(class, start, i, line);
+        } else if b"{}()[],:;".contains(&c) {
+            i += 1;
+            push(TokenClass::Punctuation, start, i, line);
+        } else {
+            i += rest.chars().next().map(char::len_utf8).unwrap_or(1);
+            push(TokenClass::Operator, start, i, line);
+        }
+    }
+    tokens
+}
+
+fn ident_len(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|b| b.is_ascii_alphanumeric() || **b == b'_').count()
+}
+
+/// Standalone HTML: one `<span class="prism-<class>">` per token
+pub fn to_html(source: &str) -> String {
+    let mut out = String::from(HTML_STYLE);
+    out.push_str("<pre class=\"prism-source\">");
+    let mut last = 0;
+    for token in tokenize(source) {
+        out.push_str(&escape_html(&source[last..token.start]));
+        out.push_str(&format!(
+            "<span class=\"prism-{}\">{}</span>",
+            token.class.name(),
+            escape_html(&source[token.start..token.end])
+        ));
+        last = token.end;
+    }
+    out.push_str(&escape_html(&source[last..]));
+    out.push_str("</pre>\n");
+    out
+}
+
+/// Source with ANSI color escapes, for terminals
+pub fn to_ansi(source: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for token in tokenize(source) {
+        out.push_str(&source[last..token.start]);
+        let color = token.class.ansi();
+        if color.is_empty() {
+            out.push_str(&source[token.start..token.end]);
+        } else {
+            out.push_str(&format!("{}{}\x1b[0m", color, &source[token.start..token.end]));
+        }
+        last = token.end;
+    }
+    out.push_str(&source[last..]);
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+const HTML_STYLE: &str = "<style>
+.prism-source { font-family: monospace; background: #FAFAFA; padding: 12px; }
+.prism-comment { color: #80868B; font-style: italic; }
+.prism-directive, .prism-keyword { color: #A142F4; }
+.prism-node { color: #1A73E8; font-weight: bold; }
+.prism-component { color: #1A73E8; }
+.prism-property { color: #007B83; }
+.prism-string { color: #188038; }
+.prism-interpolation, .prism-number, .prism-color { color: #B06000; }
+</style>
+";
+
+/// `prism highlight <file.prism> [--html | --tokens]`, returning the exit code
+pub fn run(path: &str, format: &str) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("[PRISM] Cannot read {}: {}", path, e);
+            return 1;
+        }
+    };
+    match format {
+        "--html" => print!("{}", to_html(&source)),
+        "--tokens" => match serde_json::to_string_pretty(&tokenize(&source)) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("[PRISM] Cannot serialize tokens: {}", e);
+                return 1;
+            }
+        },
+        _ => print!("{}", to_ansi(&source)),
+    }
+    0
+}
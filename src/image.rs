@@ -0,0 +1,379 @@
+//! Image decoding for the `image` node
+//!
+//! Decodes PNG (including APNG) and GIF files into fully composited frames.
+//! Pixels are stored as ARGB with straight alpha in the top byte. Animated
+//! images advance by wall-clock time from `Renderer::tick`.
+//!
+//! SVG files are parsed with usvg and rasterized with resvg at whatever
+//! size they are drawn, so vector images stay sharp when scaled.
+
+use crate::color;
+use std::io::Cursor;
+use std::path::Path;
+use std::time::Instant;
+
+/// Largest image file that will be loaded (8MB)
+pub const MAX_IMAGE_FILE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Upper bound on decoded pixels across all frames (guards decompression bombs)
+pub const MAX_DECODED_PIXELS: usize = 32 * 1024 * 1024;
+
+/// Frames with a delay at or below this are shown for `DEFAULT_DELAY_MS`,
+/// matching how other browsers treat zero-delay GIFs
+const MIN_DELAY_MS: u32 = 10;
+const DEFAULT_DELAY_MS: u32 = 100;
+
+/// A single composited frame
+pub struct ImageFrame {
+    pub pixels: Vec<u32>,
+    pub delay_ms: u32,
+}
+
+/// A decoded image, possibly animated
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    frames: Vec<ImageFrame>,
+    /// Parsed SVG document for vector images
+    vector: Option<usvg::Tree>,
+    /// Last rasterization of `vector` as (width, height, pixels)
+    raster: Option<(usize, usize, Vec<u32>)>,
+    /// Total plays before stopping on the last frame (`None` loops forever)
+    plays: Option<u32>,
+    current: usize,
+    completed_plays: u32,
+    frame_started: Option<Instant>,
+}
+
+impl Image {
+    /// Read and decode an image file
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let meta = std::fs::metadata(path).map_err(|e| e.to_string())?;
+        if meta.len() as usize > MAX_IMAGE_FILE_BYTES {
+            return Err("Image exceeds maximum size limit".to_string());
+        }
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        Self::decode(&bytes)
+    }
+
+    /// Decode PNG/APNG, GIF or SVG data, sniffing the format from its contents
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            decode_png(bytes)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            decode_gif(bytes)
+        } else if is_svg(bytes) {
+            decode_svg(bytes)
+        } else {
+            Err("Unsupported image format".to_string())
+        }
+    }
+
+    fn from_frames(width: usize, height: usize, frames: Vec<ImageFrame>, plays: Option<u32>) -> Self {
+        Self {
+            width,
+            height,
+            frames,
+            plays,
+            vector: None,
+            raster: None,
+            current: 0,
+            completed_plays: 0,
+            frame_started: None,
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    /// Index of the frame currently on screen
+    pub fn frame_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn frame(&self) -> &ImageFrame {
+        &self.frames[self.current]
+    }
+
+    /// Pixels to draw at the given size, as (width, height, pixels)
+    /// Vector images are rasterized at exactly that size (cached until the
+    /// size changes); bitmaps return the current frame at intrinsic size.
+    pub fn pixels_for_size(&mut self, width: u32, height: u32) -> (usize, usize, &[u32]) {
+        let Some(tree) = &self.vector else {
+            return (self.width, self.height, &self.frames[self.current].pixels);
+        };
+        let (w, h) = (width.max(1) as usize, height.max(1) as usize);
+        let stale = self.raster.as_ref().map(|(rw, rh, _)| (*rw, *rh) != (w, h)).unwrap_or(true);
+        if stale && check_budget(w, h, 1).is_ok() {
+            if let Some(pixels) = rasterize_svg(tree, w, h) {
+                self.raster = Some((w, h, pixels));
+            }
+        }
+        match &self.raster {
+            Some((rw, rh, pixels)) => (*rw, *rh, pixels),
+            None => (self.width, self.height, &self.frames[0].pixels),
+        }
+    }
+
+    /// Advance by wall-clock time; returns true if the visible frame changed
+    pub fn advance(&mut self) -> bool {
+        if !self.is_animated() {
+            return false;
+        }
+        let now = Instant::now();
+        let started = *self.frame_started.get_or_insert(now);
+        let mut elapsed = now.duration_since(started).as_millis() as u64;
+        let before = self.current;
+
+        loop {
+            let delay = self.frames[self.current].delay_ms as u64;
+            if elapsed < delay {
+                break;
+            }
+            if self.current + 1 < self.frames.len() {
+                self.current += 1;
+            } else if self.plays.map(|n| self.completed_plays + 1 < n).unwrap_or(true) {
+                self.completed_plays += 1;
+                self.current = 0;
+            } else {
+                // Finished the last play; hold the final frame
+                self.frame_started = Some(now);
+                return self.current != before;
+            }
+            elapsed -= delay;
+        }
+
+        self.frame_started = Some(now - std::time::Duration::from_millis(elapsed));
+        self.current != before
+    }
+}
+
+fn normalize_delay(delay_ms: u32) -> u32 {
+    if delay_ms <= MIN_DELAY_MS { DEFAULT_DELAY_MS } else { delay_ms }
+}
+
+fn check_budget(width: usize, height: usize, frames: usize) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err("Image has no pixels".to_string());
+    }
+    if width.saturating_mul(height).saturating_mul(frames) > MAX_DECODED_PIXELS {
+        return Err("Decoded image exceeds memory limit".to_string());
+    }
+    Ok(())
+}
+
+/// Composite an RGBA sub-rectangle onto the canvas
+/// `blend` mixes with existing pixels; otherwise the region is replaced
+#[allow(clippy::too_many_arguments)]
+fn composite(canvas: &mut [u32], canvas_w: usize, canvas_h: usize, rgba: &[u8], x: usize, y: usize, w: usize, h: usize, blend: bool) {
+    for row in 0..h {
+        let cy = y + row;
+        if cy >= canvas_h {
+            break;
+        }
+        for col in 0..w {
+            let cx = x + col;
+            if cx >= canvas_w {
+                break;
+            }
+            let i = (row * w + col) * 4;
+            if i + 3 >= rgba.len() {
+                return;
+            }
+            let (r, g, b, a) = (rgba[i] as u32, rgba[i + 1] as u32, rgba[i + 2] as u32, rgba[i + 3] as u32);
+            let dst = &mut canvas[cy * canvas_w + cx];
+            if !blend || a == 255 {
+                *dst = (a << 24) | (r << 16) | (g << 8) | b;
+            } else if a > 0 {
+                *dst = color::composite_over(*dst, r as u8, g as u8, b as u8, a as u8);
+            }
+        }
+    }
+}
+
+fn clear_rect(canvas: &mut [u32], canvas_w: usize, canvas_h: usize, x: usize, y: usize, w: usize, h: usize) {
+    for cy in y..(y + h).min(canvas_h) {
+        for cx in x..(x + w).min(canvas_w) {
+            canvas[cy * canvas_w + cx] = 0;
+        }
+    }
+}
+
+/// Expand any PNG output color type to RGBA8
+fn png_to_rgba(buf: &[u8], color_type: png::ColorType) -> Vec<u8> {
+    match color_type {
+        png::ColorType::Rgba => buf.to_vec(),
+        png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        // EXPAND turns indexed images into RGB(A) before they reach us
+        png::ColorType::Indexed => buf.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+
+    let width = reader.info().width as usize;
+    let height = reader.info().height as usize;
+    let animation = reader.info().animation_control;
+    let frame_count = animation.map(|a| a.num_frames as usize).unwrap_or(1).max(1);
+    check_budget(width, height, frame_count)?;
+
+    let mut buf = vec![0u8; reader.output_buffer_size()];
+    let mut canvas = vec![0u32; width * height];
+    let mut frames = vec![];
+    let mut first = true;
+
+    loop {
+        let output = match reader.next_frame(&mut buf) {
+            Ok(output) => output,
+            Err(e) if frames.is_empty() => return Err(e.to_string()),
+            Err(_) => break,
+        };
+        let rgba = png_to_rgba(&buf[..output.buffer_size()], output.color_type);
+        let control = reader.info().frame_control;
+
+        // The default image is not part of the animation when it has no fcTL
+        if animation.is_some() && first && control.is_none() {
+            first = false;
+            continue;
+        }
+        first = false;
+
+        let Some(fc) = control.filter(|_| animation.is_some()) else {
+            composite(&mut canvas, width, height, &rgba, 0, 0, output.width as usize, output.height as usize, false);
+            frames.push(ImageFrame { pixels: canvas, delay_ms: 0 });
+            break;
+        };
+
+        let (x, y) = (fc.x_offset as usize, fc.y_offset as usize);
+        let (w, h) = (fc.width as usize, fc.height as usize);
+        let previous = (fc.dispose_op == png::DisposeOp::Previous).then(|| canvas.clone());
+        composite(&mut canvas, width, height, &rgba, x, y, w, h, fc.blend_op == png::BlendOp::Over);
+
+        let den = if fc.delay_den == 0 { 100 } else { fc.delay_den as u32 };
+        let delay_ms = normalize_delay(fc.delay_num as u32 * 1000 / den);
+        frames.push(ImageFrame { pixels: canvas.clone(), delay_ms });
+
+        match fc.dispose_op {
+            png::DisposeOp::Background => clear_rect(&mut canvas, width, height, x, y, w, h),
+            png::DisposeOp::Previous => {
+                if let Some(previous) = previous {
+                    canvas = previous;
+                }
+            }
+            png::DisposeOp::None => {}
+        }
+        if frames.len() >= frame_count {
+            break;
+        }
+    }
+
+    let plays = animation.map(|a| a.num_plays).filter(|&n| n > 0);
+    Ok(Image::from_frames(width, height, frames, plays))
+}
+
+fn decode_gif(bytes: &[u8]) -> Result<Image, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+
+    let width = decoder.width() as usize;
+    let height = decoder.height() as usize;
+    check_budget(width, height, 1)?;
+
+    let mut canvas = vec![0u32; width * height];
+    let mut frames: Vec<ImageFrame> = vec![];
+
+    loop {
+        let frame = match decoder.read_next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(e) if frames.is_empty() => return Err(e.to_string()),
+            Err(_) => break,
+        };
+        check_budget(width, height, frames.len() + 1)?;
+
+        let (x, y) = (frame.left as usize, frame.top as usize);
+        let (w, h) = (frame.width as usize, frame.height as usize);
+        let previous = (frame.dispose == gif::DisposalMethod::Previous).then(|| canvas.clone());
+        composite(&mut canvas, width, height, &frame.buffer, x, y, w, h, true);
+        frames.push(ImageFrame {
+            pixels: canvas.clone(),
+            delay_ms: normalize_delay(frame.delay as u32 * 10),
+        });
+
+        match frame.dispose {
+            gif::DisposalMethod::Background => clear_rect(&mut canvas, width, height, x, y, w, h),
+            gif::DisposalMethod::Previous => {
+                if let Some(previous) = previous {
+                    canvas = previous;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if frames.is_empty() {
+        return Err("GIF contains no frames".to_string());
+    }
+    let plays = match decoder.repeat() {
+        gif::Repeat::Infinite => None,
+        gif::Repeat::Finite(n) => Some(n as u32 + 1),
+    };
+    Ok(Image::from_frames(width, height, frames, plays))
+}
+
+fn is_svg(bytes: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with('<') && head.contains("<svg")
+}
+
+fn decode_svg(bytes: &[u8]) -> Result<Image, String> {
+    // Only inline `data:` images; never follow hrefs to the filesystem
+    let options = usvg::Options {
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: usvg::ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_data(bytes, &options).map_err(|e| e.to_string())?;
+
+    let size = tree.size();
+    let width = size.width().ceil().max(1.0) as usize;
+    let height = size.height().ceil().max(1.0) as usize;
+    check_budget(width, height, 1)?;
+    let pixels = rasterize_svg(&tree, width, height)
+        .ok_or_else(|| "Failed to rasterize SVG".to_string())?;
+
+    let mut image = Image::from_frames(width, height, vec![ImageFrame { pixels, delay_ms: 0 }], None);
+    image.vector = Some(tree);
+    Ok(image)
+}
+
+/// Render an SVG tree stretched to exactly `width` x `height`
+fn rasterize_svg(tree: &usvg::Tree, width: usize, height: usize) -> Option<Vec<u32>> {
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width as u32, height as u32)?;
+    let size = tree.size();
+    let transform = resvg::tiny_skia::Transform::from_scale(
+        width as f32 / size.width(),
+        height as f32 / size.height(),
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+    Some(
+        pixmap
+            .pixels()
+            .iter()
+            .map(|p| {
+                let c = p.demultiply();
+                ((c.alpha() as u32) << 24) | ((c.red() as u32) << 16) | ((c.green() as u32) << 8) | c.blue() as u32
+            })
+            .collect(),
+    )
+}
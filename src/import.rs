@@ -0,0 +1,634 @@
+//! Importer for plain HTML and Markdown documents
+//!
+//! Only a constrained subset is understood: headings, paragraphs, lists,
+//! links, images, tables, rules, quotes and preformatted text. Everything
+//! else is reduced to its text, so simple pages can be browsed as Prism views.
+
+use crate::ast::*;
+use std::collections::HashMap;
+
+/// Whether a path or URL names a document this module converts
+pub fn is_importable(path: &str) -> bool {
+    let path = path.split(['?', '#']).next().unwrap_or(path).to_lowercase();
+    path.ends_with(".html") || path.ends_with(".htm") || path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+/// Convert by extension: Markdown for .md/.markdown, HTML otherwise
+pub fn convert(path: &str, source: &str) -> PrismApp {
+    let lower = path.split(['?', '#']).next().unwrap_or(path).to_lowercase();
+    if lower.ends_with(".md") || lower.ends_with(".markdown") {
+        from_markdown(source)
+    } else {
+        from_html(source)
+    }
+}
+
+// ============================================================================
+// VIEW BUILDING
+// ============================================================================
+
+fn document(title: Option<String>, blocks: Vec<ViewNode>) -> PrismApp {
+    let mut props = HashMap::new();
+    props.insert("padding".to_string(), PropValue::Static(Value::Int(24)));
+    props.insert("gap".to_string(), PropValue::Static(Value::Int(12)));
+    let title = title.or_else(|| {
+        blocks.iter().find_map(|b| match b.props.get("content") {
+            Some(PropValue::Static(Value::String(s))) if b.kind == NodeKind::Text => Some(s.clone()),
+            _ => None,
+        })
+    });
+    PrismApp {
+        name: title.unwrap_or_else(|| "Untitled".to_string()),
+        view: ViewNode { kind: NodeKind::Column, props, children: blocks },
+        ..PrismApp::default()
+    }
+}
+
+fn node(kind: NodeKind, props: Vec<(&str, Value)>, children: Vec<ViewNode>) -> ViewNode {
+    let props = props.into_iter().map(|(k, v)| (k.to_string(), PropValue::Static(v))).collect();
+    ViewNode { kind, props, children }
+}
+
+fn text(content: &str, size: i64) -> ViewNode {
+    node(NodeKind::Text, vec![("content", Value::String(content.to_string())), ("size", Value::Int(size))], vec![])
+}
+
+fn heading(level: usize, content: &str) -> ViewNode {
+    let size = [32, 26, 22, 18, 16, 14][level.clamp(1, 6) - 1];
+    text(content, size)
+}
+
+fn link(label: &str, href: &str) -> ViewNode {
+    node(NodeKind::Link, vec![("content", Value::String(label.to_string())), ("href", Value::String(href.to_string()))], vec![])
+}
+
+fn image(src: &str, alt: &str) -> ViewNode {
+    node(NodeKind::Image, vec![("src", Value::String(src.to_string())), ("alt", Value::String(alt.to_string()))], vec![])
+}
+
+fn quote(children: Vec<ViewNode>) -> ViewNode {
+    let mut quote = node(NodeKind::Box, vec![("padding", Value::Int(12))], children);
+    quote.props.insert("background".to_string(), PropValue::Color(Color::from_hex("#F1F3F4").unwrap_or(Color::LIGHT_GRAY)));
+    quote
+}
+
+/// `code` block, highlighted when `language` is one it knows
+fn code(content: &str, language: &str) -> ViewNode {
+    let mut props = vec![("content", Value::String(content.to_string()))];
+    if !language.is_empty() {
+        props.push(("language", Value::String(language.to_string())));
+    }
+    node(NodeKind::Code, props, vec![])
+}
+
+/// Table from header names and rows of cells, as a `table` node with object rows
+fn table(header: Vec<String>, rows: Vec<Vec<String>>) -> ViewNode {
+    let columns = if header.is_empty() {
+        (1..=rows.iter().map(Vec::len).max().unwrap_or(0)).map(|i| format!("Column {}", i)).collect()
+    } else {
+        header
+    };
+    let data = rows
+        .into_iter()
+        .map(|cells| {
+            let fields = columns.iter().cloned().zip(cells.into_iter().chain(std::iter::repeat(String::new())));
+            Value::Object(fields.map(|(k, v)| (k, Value::String(v))).collect())
+        })
+        .collect();
+    node(NodeKind::Table, vec![("data", Value::List(data)), ("columns", Value::String(columns.join(",")))], vec![])
+}
+
+/// Inline content: runs of text and links, one node each, in a column
+fn inline(runs: Vec<Inline>, size: i64) -> Option<ViewNode> {
+    let mut nodes: Vec<ViewNode> = runs
+        .into_iter()
+        .filter_map(|run| match run {
+            Inline::Text(t) => {
+                let t = t.trim();
+                (!t.is_empty()).then(|| text(t, size))
+            }
+            Inline::Link(label, href) => Some(link(label.trim(), &href)),
+            Inline::Image(src, alt) => Some(image(&src, &alt)),
+        })
+        .collect();
+    match nodes.len() {
+        0 => None,
+        1 => nodes.pop(),
+        _ => Some(node(NodeKind::Column, vec![("gap", Value::Int(4))], nodes)),
+    }
+}
+
+enum Inline {
+    Text(String),
+    Link(String, String),
+    Image(String, String),
+}
+
+fn push_text(runs: &mut Vec<Inline>, s: &str) {
+    if let Some(Inline::Text(last)) = runs.last_mut() {
+        last.push_str(s);
+    } else {
+        runs.push(Inline::Text(s.to_string()));
+    }
+}
+
+// ============================================================================
+// HTML
+// ============================================================================
+
+enum Token {
+    Open(String, HashMap<String, String>),
+    Close(String),
+    Text(String),
+}
+
+/// Convert an HTML document
+pub fn from_html(source: &str) -> PrismApp {
+    let tokens = tokenize_html(source);
+    let mut title = None;
+    let mut blocks = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Token::Open(tag, _) = &tokens[i] {
+            if tag == "title" {
+                let (runs, next) = collect_inline(&tokens, i + 1, "title");
+                title = runs.into_iter().find_map(|r| match r {
+                    Inline::Text(t) => Some(t.trim().to_string()),
+                    _ => None,
+                });
+                i = next;
+                continue;
+            }
+        }
+        i = html_block(&tokens, i, &mut blocks);
+    }
+    document(title, blocks)
+}
+
+/// Convert the block starting at `tokens[i]`, returning the next index
+fn html_block(tokens: &[Token], i: usize, blocks: &mut Vec<ViewNode>) -> usize {
+    match &tokens[i] {
+        Token::Open(tag, attrs) => match tag.as_str() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let (runs, next) = collect_inline(tokens, i + 1, tag);
+                let level = tag[1..].parse().unwrap_or(1);
+                blocks.push(heading(level, &plain(&runs)));
+                next
+            }
+            "p" | "li" | "dt" | "dd" | "figcaption" => {
+                let (runs, next) = collect_inline(tokens, i + 1, tag);
+                blocks.extend(inline(runs, 16));
+                next
+            }
+            "a" => {
+                let (runs, next) = collect_inline(tokens, i + 1, "a");
+                blocks.push(link(&plain(&runs), attrs.get("href").map(String::as_str).unwrap_or("")));
+                next
+            }
+            "ul" | "ol" => {
+                let (items, next) = html_list(tokens, i + 1, tag);
+                blocks.push(node(NodeKind::Column, vec![("gap", Value::Int(6))], items));
+                next
+            }
+            "img" => {
+                blocks.push(image(attrs.get("src").map(String::as_str).unwrap_or(""), attrs.get("alt").map(String::as_str).unwrap_or("")));
+                i + 1
+            }
+            "hr" => {
+                blocks.push(node(NodeKind::Divider, vec![], vec![]));
+                i + 1
+            }
+            "pre" => {
+                let (runs, next) = collect_inline(tokens, i + 1, "pre");
+                blocks.push(code(plain(&runs).trim_end(), ""));
+                next
+            }
+            "blockquote" => {
+                let mut inner = vec![];
+                let mut j = i + 1;
+                while j < tokens.len() && !matches!(&tokens[j], Token::Close(t) if t == "blockquote") {
+                    j = html_block(tokens, j, &mut inner);
+                }
+                blocks.push(quote(inner));
+                j + 1
+            }
+            "table" => {
+                let (node, next) = html_table(tokens, i + 1);
+                blocks.push(node);
+                next
+            }
+            _ => i + 1,
+        },
+        Token::Text(t) => {
+            let t = t.trim();
+            if !t.is_empty() {
+                blocks.push(text(t, 16));
+            }
+            i + 1
+        }
+        Token::Close(_) => i + 1,
+    }
+}
+
+fn html_list(tokens: &[Token], mut i: usize, tag: &str) -> (Vec<ViewNode>, usize) {
+    let mut items = vec![];
+    let mut number = 1;
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Close(t) if t == tag => return (items, i + 1),
+            Token::Open(t, _) if t == "li" => {
+                let (mut runs, next) = collect_inline(tokens, i + 1, "li");
+                let marker = if tag == "ol" { format!("{}. ", number) } else { "• ".to_string() };
+                number += 1;
+                match runs.first_mut() {
+                    Some(Inline::Text(t)) => *t = format!("{}{}", marker, t.trim_start()),
+                    Some(Inline::Link(label, _)) => *label = format!("{}{}", marker, label),
+                    _ => runs.insert(0, Inline::Text(marker)),
+                }
+                items.extend(inline(runs, 16));
+                i = next;
+            }
+            Token::Open(t, _) if t == "ul" || t == "ol" => {
+                let (nested, next) = html_list(tokens, i + 1, t);
+                let mut nested = node(NodeKind::Column, vec![("gap", Value::Int(6))], nested);
+                nested.props.insert("padding".to_string(), PropValue::Static(Value::Int(8)));
+                items.push(nested);
+                i = next;
+            }
+            _ => i += 1,
+        }
+    }
+    (items, i)
+}
+
+fn html_table(tokens: &[Token], mut i: usize) -> (ViewNode, usize) {
+    let mut header = vec![];
+    let mut rows = vec![];
+    let mut row: Vec<String> = vec![];
+    let mut row_is_header = false;
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Close(t) if t == "table" => {
+                i += 1;
+                break;
+            }
+            Token::Open(t, _) if t == "tr" => {
+                row.clear();
+                row_is_header = true;
+                i += 1;
+            }
+            Token::Open(t, _) if t == "th" || t == "td" => {
+                let (runs, next) = collect_inline(tokens, i + 1, t);
+                row_is_header &= t == "th";
+                row.push(plain(&runs).trim().to_string());
+                i = next;
+            }
+            Token::Close(t) if t == "tr" => {
+                if row_is_header && header.is_empty() {
+                    header = std::mem::take(&mut row);
+                } else {
+                    rows.push(std::mem::take(&mut row));
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    (table(header, rows), i)
+}
+
+/// Text, links and images up to the closing `tag`
+fn collect_inline(tokens: &[Token], mut i: usize, tag: &str) -> (Vec<Inline>, usize) {
+    let mut runs = vec![];
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Close(t) if t == tag => return (runs, i + 1),
+            // A new block implicitly ends an unclosed paragraph or item
+            Token::Open(t, _) | Token::Close(t) if (tag == "p" || tag == "li") && (is_block(t) || t == "body") => return (runs, i),
+            Token::Open(t, attrs) if t == "a" => {
+                let (inner, next) = collect_inline(tokens, i + 1, "a");
+                runs.push(Inline::Link(plain(&inner), attrs.get("href").cloned().unwrap_or_default()));
+                i = next;
+                continue;
+            }
+            Token::Open(t, attrs) if t == "img" => {
+                runs.push(Inline::Image(attrs.get("src").cloned().unwrap_or_default(), attrs.get("alt").cloned().unwrap_or_default()));
+            }
+            Token::Open(t, _) if t == "br" => push_text(&mut runs, "\n"),
+            Token::Text(t) => push_text(&mut runs, t),
+            _ => {}
+        }
+        i += 1;
+    }
+    (runs, i)
+}
+
+fn is_block(tag: &str) -> bool {
+    matches!(tag, "p" | "li" | "ul" | "ol" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "table" | "pre" | "blockquote" | "hr" | "div")
+}
+
+fn plain(runs: &[Inline]) -> String {
+    runs.iter()
+        .map(|r| match r {
+            Inline::Text(t) | Inline::Link(t, _) => t.as_str(),
+            Inline::Image(_, alt) => alt.as_str(),
+        })
+        .collect()
+}
+
+fn tokenize_html(source: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut rest = source;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map(|end| &after[end + 3..]).unwrap_or("");
+            continue;
+        }
+        if rest.starts_with('<') {
+            // A tag left open at the end of the page runs to the end
+            let end = rest.find('>').unwrap_or(rest.len());
+            let inner = rest.get(1..end).unwrap_or("");
+            rest = rest.get(end + 1..).unwrap_or("");
+            if inner.starts_with('!') || inner.starts_with('?') {
+                continue;
+            }
+            if let Some(name) = inner.strip_prefix('/') {
+                tokens.push(Token::Close(name.trim().to_lowercase()));
+                continue;
+            }
+            let inner = inner.trim_end_matches('/');
+            let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+            let name = inner[..name_end].to_lowercase();
+            let attrs = parse_attrs(&inner[name_end..]);
+            // Skip content that is never shown
+            if name == "script" || name == "style" {
+                let close = format!("</{}", name);
+                rest = rest.to_ascii_lowercase().find(&close).map(|p| &rest[p..]).unwrap_or("");
+                continue;
+            }
+            // Preformatted text keeps its whitespace; tags inside it are dropped
+            if name == "pre" {
+                let end = rest.to_ascii_lowercase().find("</pre").unwrap_or(rest.len());
+                let mut raw = String::new();
+                let mut in_tag = false;
+                for c in rest[..end].chars() {
+                    match c {
+                        '<' => in_tag = true,
+                        '>' => in_tag = false,
+                        c if !in_tag => raw.push(c),
+                        _ => {}
+                    }
+                }
+                tokens.push(Token::Open(name, attrs));
+                tokens.push(Token::Text(decode_entities(&raw)));
+                rest = &rest[end..];
+                continue;
+            }
+            tokens.push(Token::Open(name, attrs));
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let raw = &rest[..end];
+            rest = &rest[end..];
+            let collapsed: String = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+            let padded = match (raw.starts_with(char::is_whitespace), raw.ends_with(char::is_whitespace)) {
+                _ if collapsed.is_empty() => " ".to_string(),
+                (true, true) => format!(" {} ", collapsed),
+                (true, false) => format!(" {}", collapsed),
+                (false, true) => format!("{} ", collapsed),
+                (false, false) => collapsed,
+            };
+            tokens.push(Token::Text(decode_entities(&padded)));
+        }
+    }
+    tokens
+}
+
+fn parse_attrs(s: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(rest.len());
+        let name = rest[..name_end].to_lowercase();
+        rest = rest[name_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            if let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') {
+                let body = &after[1..];
+                let end = body.find(quote).unwrap_or(body.len());
+                value = body[..end].to_string();
+                rest = body.get(end + 1..).unwrap_or("").trim_start();
+            } else {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                value = after[..end].to_string();
+                rest = after[end..].trim_start();
+            }
+        }
+        if !name.is_empty() {
+            attrs.insert(name, decode_entities(&value));
+        }
+    }
+    attrs
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+// ============================================================================
+// MARKDOWN
+// ============================================================================
+
+/// Convert a Markdown document
+pub fn from_markdown(source: &str) -> PrismApp {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut blocks = vec![];
+    let mut paragraph: Vec<&str> = vec![];
+    let mut i = 0;
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<ViewNode>| {
+        if !paragraph.is_empty() {
+            blocks.extend(inline(markdown_inline(&paragraph.join(" ")), 16));
+            paragraph.clear();
+        }
+    };
+
+    while i < lines.len() {
+        let line = lines[i].trim_end();
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+        } else if let Some(info) = trimmed.strip_prefix("```") {
+            flush(&mut paragraph, &mut blocks);
+            let language = info.split_whitespace().next().unwrap_or("");
+            let mut body = vec![];
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                body.push(lines[i]);
+                i += 1;
+            }
+            blocks.push(code(&body.join("\n"), language));
+        } else if let Some(level) = heading_level(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(heading(level, trimmed[level..].trim().trim_end_matches('#').trim()));
+        } else if is_rule(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(node(NodeKind::Divider, vec![], vec![]));
+        } else if trimmed.starts_with('>') {
+            flush(&mut paragraph, &mut blocks);
+            let mut body = vec![];
+            while i < lines.len() && lines[i].trim_start().starts_with('>') {
+                body.push(lines[i].trim_start()[1..].trim());
+                i += 1;
+            }
+            blocks.push(quote(inline(markdown_inline(&body.join(" ")), 16).into_iter().collect()));
+            continue;
+        } else if trimmed.starts_with('|') {
+            flush(&mut paragraph, &mut blocks);
+            let mut rows: Vec<Vec<String>> = vec![];
+            let mut has_header = false;
+            while i < lines.len() && lines[i].trim_start().starts_with('|') {
+                let cells: Vec<String> = lines[i].trim().trim_matches('|').split('|').map(|c| c.trim().to_string()).collect();
+                if cells.iter().all(|c| !c.is_empty() && c.chars().all(|ch| matches!(ch, '-' | ':'))) {
+                    has_header = rows.len() == 1;
+                } else {
+                    rows.push(cells);
+                }
+                i += 1;
+            }
+            let header = if has_header { rows.remove(0) } else { vec![] };
+            blocks.push(table(header, rows));
+            continue;
+        } else if list_marker(trimmed).is_some() {
+            flush(&mut paragraph, &mut blocks);
+            let mut items = vec![];
+            while i < lines.len() {
+                let Some((marker, body)) = list_marker(lines[i].trim_start()) else {
+                    break;
+                };
+                let mut runs = markdown_inline(body);
+                match runs.first_mut() {
+                    Some(Inline::Text(t)) => *t = format!("{}{}", marker, t),
+                    Some(Inline::Link(label, _)) => *label = format!("{}{}", marker, label),
+                    _ => runs.insert(0, Inline::Text(marker)),
+                }
+                items.extend(inline(runs, 16));
+                i += 1;
+            }
+            blocks.push(node(NodeKind::Column, vec![("gap", Value::Int(6))], items));
+            continue;
+        } else {
+            paragraph.push(trimmed);
+        }
+        i += 1;
+    }
+    flush(&mut paragraph, &mut blocks);
+    document(None, blocks)
+}
+
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    ((1..=6).contains(&level) && line[level..].starts_with(' ')).then_some(level)
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3 && (compact.chars().all(|c| c == '-') || compact.chars().all(|c| c == '*') || compact.chars().all(|c| c == '_'))
+}
+
+/// `- item`, `* item` or `1. item`: the display marker and the item text
+fn list_marker(line: &str) -> Option<(String, &str)> {
+    if let Some(body) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ ")) {
+        return Some(("• ".to_string(), body));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return Some((format!("{}. ", &line[..digits]), &line[digits + 2..]));
+    }
+    None
+}
+
+/// Split `[label](href)` and `![alt](src)` out of a line; emphasis markers are dropped
+fn markdown_inline(s: &str) -> Vec<Inline> {
+    let mut runs = vec![];
+    let mut rest = s;
+    while !rest.is_empty() {
+        let image = rest.starts_with("![");
+        if rest.starts_with('[') || image {
+            let open = if image { 2 } else { 1 };
+            if let Some((label, href, len)) = bracket_link(&rest[open..]) {
+                runs.push(if image { Inline::Image(href, label) } else { Inline::Link(label, href) });
+                rest = &rest[open + len..];
+                continue;
+            }
+        }
+        let first = rest.chars().next().map_or(1, char::len_utf8);
+        let next = rest[first..].find(['[', '!']).map(|p| p + first).unwrap_or(rest.len());
+        let piece: String = rest[..next].chars().filter(|c| !matches!(c, '*' | '`')).collect();
+        push_text(&mut runs, &piece);
+        rest = &rest[next..];
+    }
+    runs
+}
+
+/// `label](href)` after the opening bracket: label, href and consumed length
+fn bracket_link(s: &str) -> Option<(String, String, usize)> {
+    let close = s.find("](")?;
+    let end = s[close + 2..].find(')')? + close + 2;
+    Some((s[..close].to_string(), s[close + 2..end].trim().to_string(), end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kind and `content` (or image `src`) of each child of `node`
+    fn children(node: &ViewNode) -> Vec<(NodeKind, String)> {
+        node.children
+            .iter()
+            .map(|child| {
+                let text = match child.props.get("content").or_else(|| child.props.get("src")) {
+                    Some(PropValue::Static(Value::String(s))) => s.clone(),
+                    _ => String::new(),
+                };
+                (child.kind.clone(), text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn html_with_an_unclosed_tag_at_the_end() {
+        for (source, content) in [("<p>hi</p><", "hi"), ("<p>hi</p><é", "hi"), ("<p>café</p><b", "café")] {
+            let app = from_html(source);
+            assert_eq!(app.view.kind, NodeKind::Column);
+            assert_eq!(children(&app.view), vec![(NodeKind::Text, content.to_string())], "{}", source);
+        }
+    }
+
+    #[test]
+    fn markdown_starting_with_non_ascii() {
+        let app = from_markdown("Über alles");
+        assert_eq!(app.name, "Über alles");
+        assert_eq!(children(&app.view), vec![(NodeKind::Text, "Über alles".to_string())]);
+
+        let app = from_markdown("été [lien](a.md) 😀![x](y.png)");
+        let [paragraph] = &app.view.children[..] else {
+            panic!("expected one paragraph, got {:?}", children(&app.view));
+        };
+        assert_eq!(
+            children(paragraph),
+            vec![
+                (NodeKind::Text, "été".to_string()),
+                (NodeKind::Link, "lien".to_string()),
+                (NodeKind::Text, "😀".to_string()),
+                (NodeKind::Image, "y.png".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! Prism engine - parsing, state, layout and rendering of Prism documents
+//!
+//! The `prism` binary wraps this in a window with browser chrome; `ffi`
+//! exposes the same runtime to non-Rust hosts through a C ABI.
+
+pub mod address;
+pub mod ast;
+pub mod bus;
+pub mod clipboard;
+pub mod color;
+pub mod compiled;
+pub mod data;
+pub mod emoji;
+pub mod drive;
+pub mod dump;
+pub mod export;
+pub mod ffi;
+pub mod glyphs;
+pub mod highlight;
+pub mod image;
+pub mod import;
+pub mod integrity;
+pub mod lint;
+pub mod locale;
+pub mod navigation;
+pub mod optimize;
+pub mod package;
+pub mod parser;
+pub mod qr;
+pub mod query;
+pub mod state;
+pub mod sandbox;
+pub mod renderer;
+pub mod stdlib;
+pub mod stream;
+pub mod runtime;
+pub mod spell;
+pub mod vfs;
+pub mod video;
+
+/// Read a .prism source (parsing it), a pre-compiled .prismc document, or an
+/// HTML/Markdown page converted by the importer
+pub fn load_document(path: &std::path::Path) -> Result<ast::PrismApp, String> {
+    if compiled::is_compiled(&path.to_string_lossy()) {
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+        return compiled::decode(&bytes).map_err(|e| format!("Failed to load {}: {}", path.display(), e));
+    }
+    let source = std::fs::read_to_string(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+    if import::is_importable(&path.to_string_lossy()) {
+        return Ok(import::convert(&path.to_string_lossy(), &source));
+    }
+    parser::parse_with_assets(&source, &vfs::Vfs::for_location(&path.to_string_lossy()))
+        .map_err(|e| format!("Parse error in {}: {}", path.display(), e))
+}
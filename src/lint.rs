@@ -0,0 +1,601 @@
+//! Static checks for Prism documents (`prism check --lint`)
+//!
+//! The AST carries no positions, so spans point at the first occurrence of
+//! the offending name in the source; that is the declaration or the use in
+//! almost every real document.
+
+use crate::ast::*;
+use crate::vfs::Vfs;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// 1-based line and column
+#[derive(Debug, Clone, Copy)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+/// Undefined state variable or binding
+pub const UNDEFINED_NAME: &str = "L001";
+/// Handler or call naming an action that doesn't exist
+pub const UNKNOWN_ACTION: &str = "L002";
+/// `switch` branch that can never be shown
+pub const UNREACHABLE_BRANCH: &str = "L003";
+/// Property the node kind doesn't read
+pub const UNKNOWN_PROP: &str = "L004";
+/// Action nothing refers to
+pub const UNUSED_ACTION: &str = "L005";
+/// Assignment of `null`
+pub const NULL_ASSIGNMENT: &str = "L006";
+/// Division by a literal zero
+pub const DIVISION_BY_ZERO: &str = "L007";
+/// Call of a function that isn't built in
+pub const UNKNOWN_FUNCTION: &str = "L008";
+/// `t("key")` with a key some locale of the `strings` block lacks
+pub const MISSING_STRING: &str = "L009";
+/// Literal assigned to a state field declared with another type
+pub const TYPE_MISMATCH: &str = "L010";
+/// `style` naming a style the `styles` block doesn't declare
+pub const UNKNOWN_STYLE: &str = "L011";
+
+/// Locals the runtime binds before running an action from a widget
+const ACTION_LOCALS: &[&str] = &["item", "index", "column", "direction"];
+
+/// Actions the runtime runs by name rather than from a prop
+const HOOK_ACTIONS: &[&str] = &["on_broadcast", "on_before_leave", "on_idle"];
+
+/// Locals bound for `on_broadcast`
+const BROADCAST_LOCALS: &[&str] = &["channel", "data"];
+
+/// Locals bound for `on_before_leave`
+const LEAVE_LOCALS: &[&str] = &["to"];
+
+/// Props whose bare identifier names a state variable
+const BINDING_PROPS: &[&str] = &["bind", "items", "data", "selected", "page", "options", "column_widths", "value", "checked"];
+
+/// Props every node accepts
+const COMMON_PROPS: &[&str] = &["visible", "padding", "gap", "background", "width", "height", "loading", "lines", "content", "font", "id", "disabled", "on_double_click", "on_long_press", "on_hover", "on_leave", "provide", "style", "flex", "border", "border_color", "border_radius", "radius"];
+
+fn node_props(kind: &NodeKind) -> &'static [&'static str] {
+    match kind {
+        NodeKind::Column | NodeKind::Stack => &["on_refresh", "align", "justify"],
+        NodeKind::Row => &["on_refresh", "align", "justify", "wrap"],
+        NodeKind::Box | NodeKind::Center | NodeKind::Scroll => &["on_refresh"],
+        NodeKind::Grid => &["columns"],
+        NodeKind::Text | NodeKind::Markdown => &["color", "size"],
+        NodeKind::Link => &["href", "size"],
+        NodeKind::Code => &["language", "color", "size"],
+        NodeKind::Button => &["color", "on_click"],
+        NodeKind::Input => &["bind", "placeholder", "type", "mask", "min", "max", "step", "on_change", "spellcheck"],
+        NodeKind::TextArea => &["bind", "placeholder", "spellcheck"],
+        NodeKind::Combobox => &["bind", "options", "placeholder", "on_select"],
+        NodeKind::Tags => &["bind", "options", "placeholder", "on_select"],
+        NodeKind::Rating => &["bind", "max", "size", "color", "on_change"],
+        NodeKind::Stepper => &["bind", "min", "max", "step", "on_change"],
+        NodeKind::Checkbox => &["bind", "checked", "label", "on_change"],
+        NodeKind::Toggle => &["bind", "value", "on_change"],
+        NodeKind::Radio => &["bind", "label", "selected", "value"],
+        NodeKind::Select => &["bind", "options", "value"],
+        NodeKind::Slider => &["bind", "min", "max", "step", "value"],
+        NodeKind::FilePicker => &["bind", "accept", "on_change"],
+        NodeKind::Divider => &["color", "direction"],
+        NodeKind::Image => &["src", "alt", "fit", "aspect_ratio"],
+        NodeKind::Icon => &["src", "name", "size", "color"],
+        NodeKind::Video | NodeKind::Audio => &["src", "autoplay", "loop", "aspect_ratio"],
+        NodeKind::Table => &[
+            "data", "columns", "selected", "on_activate", "page_size", "sortable", "filterable", "on_sort",
+            "resizable", "column_widths", "sticky_first_column",
+        ],
+        NodeKind::List | NodeKind::Each => &["items", "as", "page", "page_size", "selected", "on_activate", "on_end_reached"],
+        NodeKind::Badge => &["color", "dot", "variant"],
+        NodeKind::Progress => &["value", "max"],
+        NodeKind::Avatar => &["name", "size", "src"],
+        NodeKind::Modal => &["open"],
+        NodeKind::Skeleton => &["lines", "shape"],
+        NodeKind::Slot => &["separator"],
+        // Components take arbitrary props; the rest aren't implemented enough to check
+        _ => &[],
+    }
+}
+
+/// Run every check over a parsed document
+pub fn lint(app: &PrismApp, source: &str) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        app,
+        source,
+        diagnostics: vec![],
+        used_actions: HashSet::new(),
+        components: vec![],
+        used_components: HashSet::new(),
+        used_component_actions: HashSet::new(),
+    };
+    let mut scope: Vec<String> = app.state.fields.keys().chain(app.computed.keys()).cloned().collect();
+    scope.push("env".to_string());
+    scope.push("route".to_string());
+
+    let mut computed: Vec<&String> = app.computed.keys().collect();
+    computed.sort();
+    for name in computed {
+        linter.expression(&app.computed[name], &mut scope);
+    }
+
+    linter.node(&app.view, &mut scope);
+    let mut routes: Vec<&String> = app.routes.keys().collect();
+    routes.sort();
+    for route in routes {
+        linter.node(&app.routes[route], &mut scope);
+    }
+
+    let mut names: Vec<&String> = app.actions.keys().collect();
+    names.sort();
+    for name in names {
+        let action = &app.actions[name];
+        let mut locals = scope.clone();
+        locals.extend(action.params.iter().cloned());
+        locals.extend(ACTION_LOCALS.iter().map(|s| s.to_string()));
+        if name == "on_broadcast" {
+            locals.extend(BROADCAST_LOCALS.iter().map(|s| s.to_string()));
+        }
+        if name == "on_before_leave" {
+            locals.extend(LEAVE_LOCALS.iter().map(|s| s.to_string()));
+        }
+        linter.statements(&action.statements, &mut locals);
+    }
+
+    // Actions of the components the view uses, with their props and state in scope
+    let mut components: Vec<&String> = app.components.keys().filter(|c| linter.used_components.contains(*c)).collect();
+    components.sort();
+    for component in components {
+        let def = &app.components[component];
+        linter.components = vec![component.clone()];
+        let mut names: Vec<&String> = def.actions.keys().collect();
+        names.sort();
+        for name in names {
+            let action = &def.actions[name];
+            let mut locals = scope.clone();
+            locals.extend(def.props.iter().map(|p| p.name.clone()));
+            locals.extend(def.state.fields.keys().cloned());
+            locals.extend(def.inject.iter().cloned());
+            locals.extend(action.params.iter().cloned());
+            locals.extend(ACTION_LOCALS.iter().map(|s| s.to_string()));
+            linter.statements(&action.statements, &mut locals);
+        }
+        linter.components.clear();
+        let mut unused: Vec<&String> = def.actions.keys().filter(|a| !linter.used_component_actions.contains(&(component.clone(), a.to_string()))).collect();
+        unused.sort();
+        for name in unused {
+            linter.report(UNUSED_ACTION, Severity::Warning, format!("action '{}' of component '{}' is never used", name, component), name);
+        }
+    }
+
+    let mut unused: Vec<&String> = app.actions.keys().filter(|a| !linter.used_actions.contains(*a) && !HOOK_ACTIONS.contains(&a.as_str())).collect();
+    unused.sort();
+    for name in unused {
+        linter.report(UNUSED_ACTION, Severity::Warning, format!("action '{}' is never used", name), name);
+    }
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| d.span.map(|s| (s.line, s.col)).unwrap_or((usize::MAX, 0)));
+    diagnostics
+}
+
+struct Linter<'a> {
+    app: &'a PrismApp,
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+    used_actions: HashSet<String>,
+    /// Components whose view or actions are being checked, innermost last
+    components: Vec<String>,
+    used_components: HashSet<String>,
+    /// Component actions referenced, as (component, action)
+    used_component_actions: HashSet<(String, String)>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, code: &'static str, severity: Severity, message: String, near: &str) {
+        let span = find_word(self.source, near);
+        self.diagnostics.push(Diagnostic { code, severity, message, span });
+    }
+
+    fn string_key(&mut self, key: &str) {
+        let missing: Vec<&str> = self.app.strings.iter()
+            .filter(|table| !table.entries.contains_key(key))
+            .map(|table| table.locale.as_str())
+            .collect();
+        if self.app.strings.is_empty() || !missing.is_empty() {
+            let locales = if missing.is_empty() { "strings".to_string() } else { missing.join(", ") };
+            self.report(MISSING_STRING, Severity::Warning, format!("'{}' is missing from {}", key, locales), &format!("\"{}\"", key));
+        }
+    }
+
+    /// Whether `node` is the root of the view or of a route's view
+    fn is_view_root(&self, node: &ViewNode) -> bool {
+        std::ptr::eq(node, &self.app.view) || self.app.routes.values().any(|view| std::ptr::eq(node, view))
+    }
+
+    fn node(&mut self, node: &ViewNode, scope: &mut Vec<String>) {
+        let known = node_props(&node.kind);
+        let mark = scope.len();
+        if node.props.contains_key("on_hover") || node.props.contains_key("on_leave") {
+            scope.push("hovering".to_string());
+        }
+        let mut props: Vec<(&String, &PropValue)> = node.props.iter().collect();
+        props.sort_by_key(|(name, _)| name.as_str());
+
+        // A style may be shared by nodes that read different props
+        let mut styled = HashSet::new();
+        for style in node.style_names() {
+            match self.app.styles.get(style) {
+                Some(style) => styled.extend(style.keys()),
+                None => self.report(UNKNOWN_STYLE, Severity::Error, format!("no style named '{}'", style), style),
+            }
+        }
+
+        for (name, prop) in props {
+            if !known.is_empty() && !COMMON_PROPS.contains(&name.as_str()) && !known.contains(&name.as_str()) && !styled.contains(name) {
+                self.report(UNKNOWN_PROP, Severity::Warning, format!("'{}' is not a property of {}", name, format!("{:?}", node.kind).to_lowercase()), &format!("{}:", name));
+            }
+            // The runtime only reads it from the page's root, so it does nothing elsewhere
+            if name == "on_refresh" && !self.is_view_root(node) {
+                self.report(UNKNOWN_PROP, Severity::Warning, "'on_refresh' only works on the view's root container".to_string(), "on_refresh:");
+            }
+            match prop {
+                PropValue::Expression(expr) => self.expression(expr, scope),
+                PropValue::Handler(target) if name.starts_with("on_") => self.action_ref(target),
+                PropValue::Handler(var) if BINDING_PROPS.contains(&name.as_str()) && !scope.contains(var) => {
+                    self.report(UNDEFINED_NAME, Severity::Error, format!("'{}' is not a state variable", var), var);
+                }
+                PropValue::EventHandler(handler) => {
+                    self.action_ref(&handler.action);
+                    for arg in &handler.args {
+                        self.expression(arg, scope);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if node.kind == NodeKind::Switch {
+            self.switch_branches(node);
+        }
+
+        // Names bound for the children
+        match &node.kind {
+            NodeKind::List | NodeKind::Each => {
+                let item = match node.props.get("as") {
+                    Some(PropValue::Handler(name)) => name.clone(),
+                    Some(PropValue::Static(Value::String(name))) => name.clone(),
+                    _ => "item".to_string(),
+                };
+                scope.push(item);
+                scope.push("index".to_string());
+                scope.push("hovering".to_string());
+            }
+            NodeKind::Component(name) => {
+                scope.extend(node.props.keys().cloned());
+                if let Some(def) = self.app.components.get(name) {
+                    scope.extend(def.state.fields.keys().cloned());
+                    scope.extend(def.inject.iter().cloned());
+                }
+                self.used_components.insert(name.clone());
+                self.components.push(name.clone());
+            }
+            _ => {}
+        }
+        for child in &node.children {
+            self.node(child, scope);
+        }
+        if matches!(node.kind, NodeKind::Component(_)) {
+            self.components.pop();
+        }
+        scope.truncate(mark);
+    }
+
+    /// Branches after the first, which is the only one a switch draws
+    fn switch_branches(&mut self, node: &ViewNode) {
+        for child in node.children.iter().skip(1) {
+            self.report(UNREACHABLE_BRANCH, Severity::Warning, format!("this {} branch of switch is never shown", format!("{:?}", child.kind).to_lowercase()), "switch");
+        }
+    }
+
+    /// The component being checked, if it has an action `name`
+    fn component_with_action(&self, name: &str) -> Option<String> {
+        let component = self.components.last()?;
+        self.app.components.get(component)?.actions.contains_key(name).then(|| component.clone())
+    }
+
+    fn action_ref(&mut self, name: &str) {
+        if let Some(component) = self.component_with_action(name) {
+            self.used_component_actions.insert((component, name.to_string()));
+        } else if self.app.actions.contains_key(name) {
+            self.used_actions.insert(name.to_string());
+        } else {
+            self.report(UNKNOWN_ACTION, Severity::Error, format!("no action named '{}'", name), name);
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement], scope: &mut Vec<String>) {
+        for statement in statements {
+            self.statement(statement, scope);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, scope: &mut Vec<String>) {
+        match statement {
+            Statement::Assign { target, value } => {
+                let name = match target {
+                    AssignTarget::Variable(name) => {
+                        if let (Some(ty), Expression::Literal(literal)) = (self.app.state.types.get(name), value) {
+                            if !ty.accepts(literal) {
+                                let severity = if self.app.strict { Severity::Error } else { Severity::Warning };
+                                self.report(TYPE_MISMATCH, severity, format!("'{}' is {} but is assigned {}", name, ty, literal.type_name()), name);
+                            }
+                        }
+                        name
+                    }
+                    AssignTarget::Index { object, index } => {
+                        self.expression(index, scope);
+                        object
+                    }
+                    AssignTarget::Property { object, .. } => object,
+                };
+                if !scope.contains(name) {
+                    self.report(UNDEFINED_NAME, Severity::Error, format!("assignment to undeclared state '{}'", name), name);
+                }
+                // `null` isn't a keyword in expressions: it reads an undefined name
+                if matches!(value, Expression::Variable(v) if v == "null") {
+                    self.report(NULL_ASSIGNMENT, Severity::Warning, format!("'{}' is assigned null", name), &format!("{}: null", name));
+                } else {
+                    self.expression(value, scope);
+                }
+            }
+            Statement::If { condition, then_block, else_block } => {
+                self.expression(condition, scope);
+                self.statements(then_block, scope);
+                self.statements(else_block, scope);
+            }
+            Statement::ForEach { item, index, collection, body } => {
+                self.expression(collection, scope);
+                let mark = scope.len();
+                scope.push(item.clone());
+                scope.extend(index.clone());
+                self.statements(body, scope);
+                scope.truncate(mark);
+            }
+            Statement::While { condition, body } => {
+                self.expression(condition, scope);
+                self.statements(body, scope);
+            }
+            Statement::Return(Some(expr)) | Statement::Log(expr) | Statement::Navigate(expr) | Statement::Copy(expr) | Statement::Focus(expr) | Statement::ScrollTo(expr) => {
+                self.expression(expr, scope)
+            }
+            Statement::Call { action, args } => {
+                // Without an action of that name, builtins such as `broadcast` run instead
+                if self.app.actions.contains_key(action) || self.component_with_action(action).is_some() || !crate::state::is_builtin_function(action) {
+                    self.action_ref(action);
+                }
+                for arg in args {
+                    self.expression(arg, scope);
+                }
+            }
+            Statement::Emit { data: Some(expr), .. } => self.expression(expr, scope),
+            Statement::Notify { title, body } => {
+                self.expression(title, scope);
+                if let Some(body) = body {
+                    self.expression(body, scope);
+                }
+            }
+            Statement::Fetch { url, body, headers, on_success, on_error, .. } => {
+                self.expression(url, scope);
+                if let Some(body) = body {
+                    self.expression(body, scope);
+                }
+                for (_, value) in headers {
+                    self.expression(value, scope);
+                }
+                self.action_ref(on_success);
+                self.action_ref(on_error);
+            }
+            Statement::Delay { ms, then } => {
+                self.expression(ms, scope);
+                self.statements(then, scope);
+            }
+            Statement::ListPush { target, value } => {
+                self.list_target(target, scope);
+                self.expression(value, scope);
+            }
+            Statement::ListInsert { target, index, value } => {
+                self.list_target(target, scope);
+                self.expression(index, scope);
+                self.expression(value, scope);
+            }
+            Statement::ListRemove { target, index } => {
+                self.list_target(target, scope);
+                self.expression(index, scope);
+            }
+            Statement::ListPop { target } | Statement::ListClear { target } => {
+                self.list_target(target, scope);
+            }
+            _ => {}
+        }
+    }
+
+    fn list_target(&mut self, target: &str, scope: &[String]) {
+        if !scope.iter().any(|s| s == target) {
+            self.report(UNDEFINED_NAME, Severity::Error, format!("'{}' is not a state variable", target), target);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression, scope: &mut Vec<String>) {
+        match expr {
+            Expression::Literal(_) => {}
+            Expression::Variable(name) => {
+                // Braces around prose in a string literal aren't meant as references
+                let identifier = name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                if identifier && !scope.contains(name) {
+                    self.report(UNDEFINED_NAME, Severity::Error, format!("'{}' is not defined", name), name);
+                }
+            }
+            Expression::PropertyAccess { object, property } | Expression::IndexAccess { object, index: property } => {
+                self.expression(object, scope);
+                self.expression(property, scope);
+            }
+            Expression::Binary { left, op, right } => {
+                if *op == BinaryOp::Div && matches!(**right, Expression::Literal(Value::Int(0))) {
+                    self.report(DIVISION_BY_ZERO, Severity::Warning, "division by zero".to_string(), "/ 0");
+                }
+                self.expression(left, scope);
+                self.expression(right, scope);
+            }
+            Expression::Unary { operand, .. } | Expression::Spread(operand) => self.expression(operand, scope),
+            Expression::Conditional { condition, then_expr, else_expr } => {
+                self.expression(condition, scope);
+                self.expression(then_expr, scope);
+                self.expression(else_expr, scope);
+            }
+            Expression::Call { function, args } => {
+                if !crate::state::is_builtin_function(function) {
+                    self.report(UNKNOWN_FUNCTION, Severity::Error, format!("unknown function '{}'", function), function);
+                }
+                if let (true, Some(Expression::Literal(Value::String(key)))) = (function == "t", args.first()) {
+                    self.string_key(key);
+                }
+                for arg in args {
+                    self.expression(arg, scope);
+                }
+            }
+            Expression::MethodCall { object, args, .. } => {
+                self.expression(object, scope);
+                for arg in args {
+                    self.expression(arg, scope);
+                }
+            }
+            Expression::ListLiteral(items) => {
+                for item in items {
+                    self.expression(item, scope);
+                }
+            }
+            Expression::ObjectLiteral(fields) => {
+                for (_, value) in fields {
+                    self.expression(value, scope);
+                }
+            }
+            Expression::Interpolation(parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expression(expr) = part {
+                        self.expression(expr, scope);
+                    }
+                }
+            }
+            Expression::Lambda { params, body } => {
+                let mark = scope.len();
+                scope.extend(params.iter().cloned());
+                self.expression(body, scope);
+                scope.truncate(mark);
+            }
+            Expression::Range { start, end, .. } => {
+                self.expression(start, scope);
+                self.expression(end, scope);
+            }
+            Expression::Pipe { value, transform } => {
+                self.expression(value, scope);
+                // The transform names a method or builtin rather than a variable
+                match &**transform {
+                    Expression::Variable(_) => {}
+                    Expression::Call { args, .. } => {
+                        for arg in args {
+                            self.expression(arg, scope);
+                        }
+                    }
+                    other => self.expression(other, scope),
+                }
+            }
+            Expression::NullCoalesce { value, default } => {
+                self.expression(value, scope);
+                self.expression(default, scope);
+            }
+        }
+    }
+}
+
+/// First whole-word occurrence of `needle` outside comments
+fn find_word(source: &str, needle: &str) -> Option<Span> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    for (i, line) in source.lines().enumerate() {
+        let code = line.split("--").next().unwrap_or("");
+        let mut from = 0;
+        while let Some(pos) = code[from..].find(needle) {
+            let start = from + pos;
+            let end = start + needle.len();
+            let before = code[..start].chars().next_back();
+            let after = code[end..].chars().next();
+            let starts_word = needle.starts_with(|c: char| !is_word(c)) || !before.is_some_and(is_word);
+            let ends_word = needle.ends_with(|c: char| !is_word(c)) || !after.is_some_and(is_word);
+            if starts_word && ends_word {
+                return Some(Span { line: i + 1, col: code[..start].chars().count() + 1 });
+            }
+            from = end;
+        }
+    }
+    None
+}
+
+/// `prism check [--lint] <file.prism>`, returning the exit code
+pub fn run(path: &str, lint_enabled: bool) -> i32 {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("[PRISM] Cannot read {}: {}", path, e);
+            return 1;
+        }
+    };
+    let app = match crate::parser::parse_with_assets(&source, &Vfs::for_location(path)) {
+        Ok(app) => app,
+        Err(errors) => {
+            for e in &errors.0 {
+                println!("{}:{}:{}: error[P001]: {}", path, e.line, e.col, e.message);
+                println!("{}", e.frame());
+            }
+            return 1;
+        }
+    };
+    if !lint_enabled {
+        println!("{}: ok", path);
+        return 0;
+    }
+
+    let diagnostics = lint(&app, &source);
+    for d in &diagnostics {
+        match d.span {
+            Some(span) => println!("{}:{}:{}: {}[{}]: {}", path, span.line, span.col, d.severity, d.code, d.message),
+            None => println!("{}: {}[{}]: {}", path, d.severity, d.code, d.message),
+        }
+    }
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    println!("{}: {} error(s), {} warning(s)", path, errors, diagnostics.len() - errors);
+    if errors > 0 { 1 } else { 0 }
+}
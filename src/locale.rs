@@ -0,0 +1,107 @@
+//! Locale-aware number formatting and parsing
+//!
+//! Separators follow CLDR for the common languages: `1,234.5` in English,
+//! `1.234,5` in German, `1 234,5` in French. Parsing accepts either style so
+//! values typed or loaded from another locale still read correctly.
+
+/// Decimal and grouping separators of a locale
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    pub decimal: char,
+    pub group: char,
+}
+
+impl NumberFormat {
+    /// Separators for a language tag such as "pt-BR"; English for unknown tags
+    pub fn for_locale(locale: &str) -> NumberFormat {
+        let mut parts = locale.split(['-', '_']);
+        let language = parts.next().unwrap_or("").to_ascii_lowercase();
+        let region = parts.next().unwrap_or("").to_ascii_uppercase();
+        let (decimal, group) = match (language.as_str(), region.as_str()) {
+            ("de" | "it", "CH") => ('.', '\''),
+            ("de" | "es" | "it" | "pt" | "nl" | "id" | "tr" | "da" | "vi" | "el" | "ro" | "hr" | "sl", _) => (',', '.'),
+            ("fr" | "ru" | "uk" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "hu" | "bg" | "lt" | "lv" | "et", _) => (',', '\u{a0}'),
+            _ => ('.', ','),
+        };
+        NumberFormat { decimal, group }
+    }
+
+    /// `n` with grouping, rounded to `decimals` places, or to at most six with
+    /// trailing zeros dropped when `decimals` is `None`
+    pub fn format(&self, n: f64, decimals: Option<usize>) -> String {
+        if !n.is_finite() {
+            return n.to_string();
+        }
+        let fixed = match decimals {
+            Some(places) => format!("{:.*}", places, n.abs()),
+            None => {
+                let s = format!("{:.6}", n.abs());
+                s.trim_end_matches('0').trim_end_matches('.').to_string()
+            }
+        };
+        let (int_part, frac_part) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+        let mut out = String::new();
+        if n < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        for (i, digit) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                out.push(self.group);
+            }
+            out.push(digit);
+        }
+        if !frac_part.is_empty() {
+            out.push(self.decimal);
+            out.push_str(frac_part);
+        }
+        out
+    }
+
+    /// Parse a number written with either separator style; see [`parse_number`]
+    pub fn parse(&self, text: &str) -> Option<f64> {
+        parse_number(text, self.decimal)
+    }
+}
+
+/// Parse `text` as a number, tolerating grouping and either decimal mark
+///
+/// When both `.` and `,` appear the last one is the decimal mark. A mark that
+/// appears more than once groups digits. A single mark is decimal when it is
+/// `preferred_decimal` or isn't followed by exactly three digits, so
+/// "1.234" is 1234 in German and 1.234 in English. Anything else reads as
+/// `str::parse` does, so "inf" and "NaN" still parse.
+pub fn parse_number(text: &str, preferred_decimal: char) -> Option<f64> {
+    let cleaned: String = text.trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '\'' | '’' | '_'))
+        .collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    let last_dot = cleaned.rfind('.');
+    let last_comma = cleaned.rfind(',');
+    let decimal = match (last_dot, last_comma) {
+        (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+        (Some(_), None) => single_mark_decimal(&cleaned, '.', preferred_decimal),
+        (None, Some(_)) => single_mark_decimal(&cleaned, ',', preferred_decimal),
+        (None, None) => None,
+    };
+    let normalized: String = cleaned.chars()
+        .filter_map(|c| match c {
+            '.' | ',' if Some(c) == decimal => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    normalized.parse::<f64>().ok()
+}
+
+/// Whether the only kind of mark in `text` is a decimal mark
+fn single_mark_decimal(text: &str, mark: char, preferred_decimal: char) -> Option<char> {
+    if text.matches(mark).count() > 1 {
+        return None;
+    }
+    let digits_after = text.rsplit(mark).next().map_or(0, |rest| rest.chars().take_while(char::is_ascii_digit).count());
+    (mark == preferred_decimal || digits_after != 3).then_some(mark)
+}
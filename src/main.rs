@@ -3,26 +3,40 @@
 //! Usage: prism [file.prism]
 //! If no file is specified, opens the home page.
 
-mod ast;
-mod parser;
-mod state;
-mod sandbox;
-mod renderer;
-mod runtime;
+mod chrome;
+mod gamepad;
+mod power;
+mod replay;
+mod settings;
+mod tasks;
+mod tls;
+
+use chrome::{ChromeLayout, ChromeStyle, ToolbarPosition};
+use gamepad::{Gamepads, PadButton};
+use replay::{InputEvent, Player, Recorder};
+use settings::{BatterySaver, CertificatePolicy, Settings, ToolbarButton};
+use prism::ast::PrismApp;
+use prism::integrity::{self, Integrity};
+use prism::navigation::{NavigationObserver, NavigationObservers};
+use prism::package::{self, Package};
+use prism::stream::{DocumentStream, StreamUpdate, STREAM_MIN_BYTES};
+use prism::vfs::Vfs;
+use prism::{load_document, address, clipboard, color, compiled, drive, dump, emoji, export, highlight, import, lint, parser, qr, renderer, runtime, sandbox, spell};
 
 use renderer::FrameBuffer;
-use runtime::Runtime;
+use runtime::{Activity, Direction, Gesture, Notice, Runtime};
 use sandbox::Sandbox;
+use std::collections::HashSet;
 use std::path::PathBuf;
 use fontdue::{Font, FontSettings};
-use std::sync::OnceLock;
-use fontdue::layout::{Layout, LayoutSettings, TextStyle, CoordinateSystem};
+use std::sync::{Arc, OnceLock};
+use fontdue::layout::{Layout, LayoutSettings, CoordinateSystem};
 use reqwest::blocking;
 use winit::{
-    dpi::PhysicalSize,
-    event::{ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{Event, ModifiersState, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Theme, Window, WindowBuilder},
 };
 use winit::window::CursorIcon;
 use softbuffer::{Context, Surface};
@@ -31,7 +45,51 @@ use std::time::{Duration, Instant};
 
 const DEFAULT_WIDTH: usize = 1024;
 const DEFAULT_HEIGHT: usize = 768;
-const CHROME_HEIGHT: usize = 50;
+/// Smallest window that still fits the toolbar and some of the page
+const MIN_WIDTH: u32 = 480;
+const MIN_HEIGHT: u32 = 320;
+
+/// Background beside the page column in reader mode
+const READER_MARGIN_COLOR: u32 = 0xF3EFE6;
+/// Over-scroll past the top needed to trigger `on_refresh`
+const PULL_THRESHOLD: i32 = 64;
+/// Page notifications stay in the notification center this long unless clicked away
+const NOTICE_SECONDS: u64 = 8;
+const NOTICE_WIDTH: i32 = 300;
+const NOTICE_HEIGHT: i32 = 56;
+/// Most notices stacked at once; older ones wait until newer ones go
+const MAX_VISIBLE_NOTICES: usize = 4;
+const PULL_MAX: i32 = 96;
+/// The pull springs back once the wheel has been idle this long
+const PULL_IDLE_MS: u64 = 200;
+/// The refresh spinner stays up at least this long so it never just flickers
+const REFRESH_MIN_MS: u64 = 600;
+/// Actions that last ran at least this long show the progress bar before running again
+const SLOW_ACTION_MS: u64 = 100;
+/// A second click within this many frames (~400ms) is a double click
+const DOUBLE_CLICK_TICKS: u64 = 24;
+/// Holding the button this many frames (~500ms) is a long press
+const LONG_PRESS_TICKS: u64 = 30;
+/// Pointer travel in pixels that still counts as the same spot for gestures
+const GESTURE_SLOP: i32 = 6;
+/// Sideways trackpad travel that navigates back or forward
+const SWIPE_DISTANCE: i32 = 160;
+/// The page behind the task manager gets its broadcasts every this many frames (~1s)
+const BACKGROUND_PUMP_TICKS: u64 = 60;
+/// While the window is hidden, the event loop sleeps and frames advance this often
+const HIDDEN_TICK: Duration = Duration::from_secs(1);
+/// Time between frames at 60fps; the frame counts above are in these
+const FRAME: Duration = Duration::from_millis(16);
+/// Frame rate in battery saver, unless the settings ask for less
+const BATTERY_SAVER_FRAME_RATE: u32 = 30;
+/// Lines of a load error shown on the error page; the rest are counted
+const MAX_ERROR_LINES: usize = 12;
+/// Time an idle frame may spend rasterizing text below the viewport
+const PRERASTER_SLICE: Duration = Duration::from_millis(4);
+/// A pause this many frames (~200ms) between scrolls starts a new swipe
+const SWIPE_IDLE_TICKS: u64 = 12;
+/// Corrections offered for a misspelled word
+const SPELLING_SUGGESTIONS: usize = 5;
 
 static UI_FONT: OnceLock<Font> = OnceLock::new();
 
@@ -51,12 +109,127 @@ struct Browser {
     address_focused: bool,
     address_text: String,
     address_cursor: usize,
+    /// Other end of the address bar selection; none, or the cursor itself, when nothing is selected
+    address_anchor: Option<usize>,
+    /// A press in the address bar is dragging out a selection
+    address_dragging: bool,
+    /// IME composition at the address bar caret, and the byte offset of its caret
+    address_preedit: String,
+    address_preedit_cursor: Option<usize>,
     cursor_blink_timer: u32,
     cursor_visible: bool,
     last_error: Option<String>,
     scroll_y: i32,
     max_scroll_y: i32,
+    viewport_height: i32,
+    /// Pull-to-refresh progress, in pixels past the top
+    pull_distance: i32,
+    last_pull: Option<Instant>,
+    refresh_started: Option<Instant>,
+    /// The refresh action runs after the spinner's first frame is on screen
+    refresh_pending: bool,
     base_dir: PathBuf,
+    /// Seed for page randomness, recorded with sessions so replays repeat it
+    seed: u64,
+    chrome: ChromeStyle,
+    /// Saved preferences, including the configured toolbar buttons
+    settings: Settings,
+    /// Toolbar and page positions for the current window size
+    layout: ChromeLayout,
+    /// System theme, passed to pages as `env.dark_mode`
+    dark_mode: bool,
+    /// Notification center: page notices not shown by the OS, newest last
+    notices: Vec<(Notice, Instant)>,
+    /// Page actions whose last run took at least `SLOW_ACTION_MS`
+    slow_actions: HashSet<String>,
+    /// Work that blocks the window, run once the progress bar is on screen
+    busy: Option<BusyWork>,
+    /// A large document still arriving, shown as far as it has parsed
+    stream: Option<PageStream>,
+    /// The page left running while the task manager is open, with its location
+    background: Option<(String, Runtime)>,
+    /// Last time the task manager's figures were refreshed
+    tasks_updated: Option<Instant>,
+    /// How the background page's lists have grown across the task manager's refreshes
+    state_watch: tasks::StateWatch,
+    /// TLS details of the current `https://` page, for the security indicator
+    tls: Option<tls::Connection>,
+    /// The security indicator's certificate panel is open
+    security_open: bool,
+    /// Told when pages start and finish loading or fail to
+    observers: NavigationObservers,
+    /// Where the user was going when the page's `on_before_leave` kept them,
+    /// while the "Discard changes?" dialog asks
+    leave_prompt: Option<PendingLeave>,
+    /// Ctrl+Shift+C: the page region being dragged out to copy or save
+    region: Option<chrome::RegionSelect>,
+    /// Dictionary pages' text inputs are checked against, when spell check is on
+    spelling: Option<Arc<spell::Dictionary>>,
+    /// Corrections offered for the misspelled word right-clicked on
+    spelling_menu: Option<chrome::SpellingMenu>,
+    /// What the Share button copied, while its dialog is open
+    share: Option<chrome::Share>,
+    /// The user chose to close the window
+    quit: bool,
+    /// The window is minimized or fully covered, so nothing on it can be seen
+    occluded: bool,
+    /// When the user last did anything, for the page's `on_idle`
+    last_input: Instant,
+    /// Whole idle seconds `on_idle` has been told about
+    idle_reported: u64,
+    /// Last time the power source was looked up for the battery saver
+    power_checked: Option<Instant>,
+}
+
+/// A document being loaded progressively, and where it goes once shown
+struct PageStream {
+    address: String,
+    location: String,
+    update_history: bool,
+    /// TLS details for the security indicator, applied when the page is first shown
+    connection: Option<tls::Connection>,
+    document: DocumentStream,
+    /// Part of the document is on screen in place of the previous page
+    shown: bool,
+    /// Percent received when the progress bar was last drawn
+    percent: u32,
+}
+
+/// A way of leaving the page that waits for the user to discard its changes
+enum PendingLeave {
+    /// Another location, and whether it goes into history
+    Navigate(String, bool),
+    /// Back or forward to this history entry
+    History(usize),
+    /// Another route of the same document
+    Route(String),
+    Close,
+}
+
+/// The window title to show next, kept by the title observer until the event loop applies it
+#[derive(Clone, Default)]
+struct WindowTitle(std::rc::Rc<std::cell::RefCell<Option<String>>>);
+
+impl WindowTitle {
+    fn take(&self) -> Option<String> {
+        self.0.borrow_mut().take()
+    }
+}
+
+impl NavigationObserver for WindowTitle {
+    fn load_failed(&mut self, _location: &str, _error: &str) {
+        *self.0.borrow_mut() = Some("Prism Browser".to_string());
+    }
+
+    fn title_changed(&mut self, title: &str) {
+        *self.0.borrow_mut() = Some(format!("{} - Prism Browser", title));
+    }
+}
+
+/// A slow click or remote page load waiting for the progress bar to be drawn
+enum BusyWork {
+    Click { x: i32, y: i32 },
+    Navigate(String),
 }
 
 impl Browser {
@@ -69,12 +242,325 @@ impl Browser {
             address_focused: false,
             address_text: String::new(),
             address_cursor: 0,
+            address_anchor: None,
+            address_dragging: false,
+            address_preedit: String::new(),
+            address_preedit_cursor: None,
             cursor_blink_timer: 0,
             cursor_visible: true,
             last_error: None,
             scroll_y: 0,
             max_scroll_y: 0,
+            viewport_height: 0,
+            pull_distance: 0,
+            last_pull: None,
+            refresh_started: None,
+            refresh_pending: false,
             base_dir,
+            seed: 0,
+            chrome: ChromeStyle::default(),
+            settings: Settings::default(),
+            layout: ChromeLayout::default(),
+            dark_mode: false,
+            notices: vec![],
+            slow_actions: HashSet::new(),
+            busy: None,
+            stream: None,
+            background: None,
+            tasks_updated: None,
+            state_watch: tasks::StateWatch::default(),
+            tls: None,
+            security_open: false,
+            observers: NavigationObservers::default(),
+            leave_prompt: None,
+            region: None,
+            spelling: None,
+            spelling_menu: None,
+            share: None,
+            quit: false,
+            occluded: false,
+            last_input: Instant::now(),
+            idle_reported: 0,
+            power_checked: None,
+        }
+    }
+
+    /// Open `prism://tasks`, keeping the current page running behind it
+    fn open_tasks(&mut self, update_history: bool) {
+        if self.current_path != tasks::TASKS_URL {
+            self.background = self.runtime.take().map(|rt| (self.current_path.clone(), rt));
+        }
+        self.start_page(tasks::TASKS_URL.to_string(), tasks::TASKS_URL, tasks::page(), update_history);
+        self.tasks_updated = None;
+        self.state_watch = tasks::StateWatch::default();
+        self.tick_tasks();
+    }
+
+    /// Refresh the task manager's figures and end the background page on request;
+    /// returns true if the page needs a redraw
+    fn tick_tasks(&mut self) -> bool {
+        if self.current_path != tasks::TASKS_URL {
+            return false;
+        }
+        let Some(ref mut rt) = self.runtime else {
+            return false;
+        };
+        let ended = tasks::take_end_request(rt);
+        if ended {
+            if let Some((location, _)) = self.background.take() {
+                eprintln!("[PRISM] Ended {}", location);
+            }
+        } else if self.tasks_updated.is_some_and(|t| t.elapsed() < tasks::REFRESH_INTERVAL) {
+            return false;
+        }
+        let task = self.background.as_ref().map(|(location, page)| (location.as_str(), page));
+        tasks::update(rt, task, &mut self.state_watch);
+        self.tasks_updated = Some(Instant::now());
+        true
+    }
+
+    /// Turn the battery saver on or off as the settings and the power source
+    /// say; returns true if it changed
+    fn tick_power(&mut self) -> bool {
+        if self.power_checked.is_some_and(|t| t.elapsed() < power::CHECK_INTERVAL) {
+            return false;
+        }
+        self.power_checked = Some(Instant::now());
+        let saving = match self.settings.battery_saver {
+            BatterySaver::On => true,
+            BatterySaver::Off => false,
+            BatterySaver::Auto => power::on_battery().unwrap_or(false),
+        };
+        if saving == self.chrome.battery_saver {
+            return false;
+        }
+        self.chrome.battery_saver = saving;
+        if let Some(ref mut rt) = self.runtime {
+            rt.set_battery_saver(saving);
+        }
+        true
+    }
+
+    /// Frames of `FRAME` each drawn frame stands for: 1 at 60fps, 2 at 30fps
+    ///
+    /// Frames are whole steps of `FRAME`, so only rates dividing 60 can be
+    /// kept; any other rate drops to the next one below it (45 to 30, 25 to 20).
+    fn frame_steps(&self) -> u64 {
+        let mut rate = self.settings.frame_rate.clamp(1, 60);
+        if self.chrome.battery_saver {
+            rate = rate.min(BATTERY_SAVER_FRAME_RATE);
+        }
+        let rate = (1..=rate).rev().find(|r| 60 % r == 0).unwrap_or(1);
+        (60 / rate) as u64
+    }
+
+    /// Run the page's `on_idle` for each whole second passed since the last
+    /// input, including seconds a slow frame skipped over; returns true if it ran
+    fn tick_idle(&mut self) -> bool {
+        let seconds = self.last_input.elapsed().as_secs();
+        if seconds <= self.idle_reported {
+            return false;
+        }
+        let missed = self.idle_reported + 1..=seconds;
+        self.idle_reported = seconds;
+        let Some(ref mut rt) = self.runtime else {
+            return false;
+        };
+        missed.fold(false, |ran, second| rt.idle(second) | ran)
+    }
+
+    /// Learn which actions are slow from the page's action timings
+    fn track_activity(&mut self) {
+        let Some(ref mut rt) = self.runtime else {
+            return;
+        };
+        for event in rt.take_activity() {
+            if let Activity::Finished { action, elapsed } = event {
+                if elapsed >= Duration::from_millis(SLOW_ACTION_MS) {
+                    self.slow_actions.insert(action);
+                } else {
+                    self.slow_actions.remove(&action);
+                }
+            }
+        }
+    }
+
+    /// Whether a click in page coordinates would run an action known to be slow
+    fn click_is_slow(&self, x: i32, y: i32) -> bool {
+        let Some(ref rt) = self.runtime else {
+            return false;
+        };
+        let action = rt.renderer.hit_test(x, y).and_then(|b| b.action.as_ref());
+        action.is_some_and(|a| self.slow_actions.contains(a))
+    }
+
+    /// Run deferred work now that the progress bar is visible
+    fn run_busy_work(&mut self) {
+        match self.busy.take() {
+            Some(BusyWork::Click { x, y }) => {
+                if let Some(ref mut rt) = self.runtime {
+                    rt.handle_click(x, y);
+                    rt.renderer.set_focus(rt.focused_input.clone());
+                }
+            }
+            Some(BusyWork::Navigate(href)) => self.navigate(&href),
+            None => {}
+        }
+        self.track_activity();
+    }
+
+    /// Navigate, loading remote pages behind the progress bar
+    fn navigate_or_defer(&mut self, href: &str) {
+        if href.starts_with("http://") || href.starts_with("https://") || integrity::is_pinned(href) {
+            self.busy = Some(BusyWork::Navigate(href.to_string()));
+        } else {
+            self.navigate(href);
+        }
+    }
+
+    /// Collect new page notices and expire old ones; returns whether any changed
+    fn tick_notices(&mut self) -> bool {
+        let before = self.notices.len();
+        self.notices.retain(|(_, shown)| shown.elapsed() < Duration::from_secs(NOTICE_SECONDS));
+        let mut changed = self.notices.len() != before;
+        if let Some(ref mut rt) = self.runtime {
+            for notice in rt.take_notices() {
+                self.notices.push((notice, Instant::now()));
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Lay the chrome out for a window size
+    fn relayout(&mut self, width: usize, height: usize) {
+        self.layout = ChromeLayout::new(width, height, self.chrome, self.settings.toolbar.len());
+    }
+
+    /// Remember the chrome style for the next session
+    fn save_chrome_style(&mut self) {
+        self.settings.compact = self.chrome.compact;
+        self.settings.toolbar_bottom = self.chrome.position == ToolbarPosition::Bottom;
+        self.settings.save();
+    }
+
+    fn home_path(&self) -> PathBuf {
+        self.base_dir.join("examples").join("home.prism")
+    }
+
+    /// Activate the configured toolbar button at `index`
+    fn run_toolbar_button(&mut self, index: usize) {
+        let Some(button) = self.settings.toolbar.get(index).cloned() else {
+            return;
+        };
+        match button {
+            ToolbarButton::Home => self.navigate(&self.home_path().to_string_lossy()),
+            ToolbarButton::Reader => self.chrome.reader = !self.chrome.reader,
+            ToolbarButton::Screenshot => self.save_screenshot(),
+            ToolbarButton::Share => self.share_page(),
+            ToolbarButton::Action { label, action } => {
+                let ran = self.runtime.as_mut().is_some_and(|rt| rt.run_action(&action));
+                if !ran {
+                    eprintln!("[PRISM] Toolbar button \"{}\": the page has no action {}", label, action);
+                }
+            }
+        }
+    }
+
+    /// Save the visible page area as a PNG chosen by the user
+    fn save_screenshot(&mut self) {
+        let content = self.layout.content;
+        let scroll_y = self.scroll_y;
+        let Some(rt) = self.runtime.as_mut() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG image", &["png"])
+            .set_file_name(format!("{}.png", rt.title()))
+            .save_file()
+        else {
+            return;
+        };
+        let mut fb = FrameBuffer::new(content.width as usize, content.height as usize);
+        rt.render(&mut fb, scroll_y);
+        if let Err(e) = fb.save_png(&path) {
+            eprintln!("[PRISM] Failed to save screenshot to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Whether the Share button works here: only pages with a web address
+    /// can be opened elsewhere, and a local page's path would tell others
+    /// about this machine's files
+    fn can_share(&self) -> bool {
+        self.runtime.is_some() && (self.current_path.starts_with("https://") || self.current_path.starts_with("http://"))
+    }
+
+    /// Copy the page's address to the clipboard and open the dialog showing
+    /// it as a QR code
+    fn share_page(&mut self) {
+        if !self.can_share() {
+            return;
+        }
+        let address = self.current_path.clone();
+        let copied = match clipboard::copy("text/plain", address.as_bytes()) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("[PRISM] Failed to copy the page address: {}", e);
+                false
+            }
+        };
+        let code = qr::QrCode::encode(address.as_bytes());
+        self.share = Some(chrome::Share { address, code, copied });
+    }
+
+    /// Copy the selected page region to the clipboard as a PNG, or save it
+    /// when `save` is set or the clipboard can't take it
+    fn capture_region(&mut self, save: bool) {
+        let content = self.layout.content;
+        let scroll_y = self.scroll_y;
+        let Some(rect) = self.region.take().and_then(|region| region.rect(content)) else {
+            return;
+        };
+        let Some(rt) = self.runtime.as_mut() else {
+            return;
+        };
+        let mut fb = FrameBuffer::new(content.width as usize, content.height as usize);
+        rt.render(&mut fb, scroll_y);
+        let region = fb.crop(rect.x - content.x, rect.y - content.y, rect.width as u32, rect.height as u32);
+        let title = rt.title();
+        let png = match region.encode_png() {
+            Ok(png) => png,
+            Err(e) => {
+                eprintln!("[PRISM] Failed to encode region: {}", e);
+                return;
+            }
+        };
+        if !save {
+            match clipboard::copy("image/png", &png) {
+                Ok(()) => {
+                    let body = format!("{} × {} pixels", region.width, region.height);
+                    self.notices.push((runtime::Notice { title: "Region copied".to_string(), body }, Instant::now()));
+                    return;
+                }
+                Err(e) => eprintln!("[PRISM] Clipboard unavailable, saving region instead: {}", e),
+            }
+        }
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG image", &["png"])
+            .set_file_name(format!("{}.png", title))
+            .save_file()
+        else {
+            return;
+        };
+        if let Err(e) = std::fs::write(&path, png) {
+            eprintln!("[PRISM] Failed to save region to {}: {}", path.display(), e);
+        }
+    }
+
+    fn set_dark_mode(&mut self, dark: bool) {
+        self.dark_mode = dark;
+        if let Some(ref mut rt) = self.runtime {
+            rt.set_dark_mode(dark);
         }
     }
 
@@ -88,61 +574,194 @@ impl Browser {
         }
     }
 
+    /// Devtools: save the page's state to a JSON file chosen by the user
+    fn save_state_snapshot(&self) {
+        let Some(rt) = self.runtime.as_ref() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("State snapshot", &["json"])
+            .set_file_name(format!("{}.state.json", rt.title()))
+            .save_file()
+        else {
+            return;
+        };
+        if let Err(e) = std::fs::write(&path, rt.export_state()) {
+            eprintln!("[PRISM] Failed to save state to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Devtools: restore the page's state from a snapshot file
+    fn load_state_snapshot(&mut self) {
+        let Some(rt) = self.runtime.as_mut() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new().add_filter("State snapshot", &["json"]).pick_file() else {
+            return;
+        };
+        let result = std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|json| rt.import_state(&json));
+        match result {
+            Ok(count) => eprintln!("[PRISM] Restored {} state variables from {}", count, path.display()),
+            Err(e) => eprintln!("[PRISM] Failed to load state from {}: {}", path.display(), e),
+        }
+    }
+
+    fn can_refresh(&self) -> bool {
+        self.runtime.as_ref().is_some_and(|rt| rt.refresh_action().is_some())
+    }
+
+    /// Over-scroll at the top of the page; returns true if the indicator changed
+    fn pull(&mut self, amount: i32) -> bool {
+        if self.refresh_started.is_some() || !self.can_refresh() {
+            return false;
+        }
+        self.pull_distance = (self.pull_distance + amount / 2).min(PULL_MAX);
+        self.last_pull = Some(Instant::now());
+        if self.pull_distance >= PULL_THRESHOLD {
+            self.start_refresh();
+        }
+        true
+    }
+
+    fn start_refresh(&mut self) {
+        if self.refresh_started.is_none() && self.can_refresh() {
+            self.pull_distance = 0;
+            self.refresh_started = Some(Instant::now());
+            self.refresh_pending = true;
+        }
+    }
+
+    /// Advance the pull indicator and spinner; returns true if a redraw is needed
+    fn tick_refresh(&mut self) -> bool {
+        if self.pull_distance > 0 {
+            let idle = self.last_pull.map(|t| t.elapsed() >= Duration::from_millis(PULL_IDLE_MS)).unwrap_or(true);
+            if idle {
+                self.pull_distance = 0;
+                return true;
+            }
+        }
+        match self.refresh_started {
+            Some(start) if !self.refresh_pending && start.elapsed() >= Duration::from_millis(REFRESH_MIN_MS) => {
+                self.refresh_started = None;
+                true
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
     fn reset_cursor_blink(&mut self) {
         self.cursor_visible = true;
         self.cursor_blink_timer = 0;
     }
 
+    /// Show `text` in the address bar with the cursor at its end
+    fn set_address(&mut self, text: &str) {
+        self.address_text = address::display(text);
+        self.address_cursor = self.address_text.chars().count();
+        self.address_anchor = None;
+    }
+
+    /// Selected character range of the address text, if any
+    fn address_selection(&self) -> Option<(usize, usize)> {
+        let len = self.address_text.chars().count();
+        let anchor = self.address_anchor.filter(|&a| a != self.address_cursor)?;
+        Some((anchor.min(self.address_cursor).min(len), anchor.max(self.address_cursor).min(len)))
+    }
+
+    /// Remove the selected text; false when nothing was selected
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.address_selection() else {
+            return false;
+        };
+        let (start_byte, end_byte) = (self.char_to_byte_pos(start), self.char_to_byte_pos(end));
+        self.address_text.drain(start_byte..end_byte);
+        self.address_cursor = start;
+        self.address_anchor = None;
+        self.reset_cursor_blink();
+        true
+    }
+
+    /// Typing replaces the selection
     fn insert_char(&mut self, ch: char) {
+        self.delete_selection();
         let byte_pos = self.char_to_byte_pos(self.address_cursor);
         self.address_text.insert(byte_pos, ch);
         self.address_cursor += 1;
         self.reset_cursor_blink();
     }
 
-    fn delete_char_before(&mut self) {
-        if self.address_cursor > 0 {
-            self.address_cursor -= 1;
-            let byte_pos = self.char_to_byte_pos(self.address_cursor);
-            let next_byte = self.char_to_byte_pos(self.address_cursor + 1);
-            self.address_text.drain(byte_pos..next_byte);
-            self.reset_cursor_blink();
+    /// Backspace/Delete: the selection, else the character or (`word`) word
+    /// before or after the cursor
+    fn delete_address(&mut self, forward: bool, word: bool) {
+        if self.delete_selection() {
+            return;
         }
+        let to = match (forward, word) {
+            (false, false) => self.address_cursor.saturating_sub(1),
+            (false, true) => self.word_left(),
+            (true, false) => (self.address_cursor + 1).min(self.address_text.chars().count()),
+            (true, true) => self.word_right(),
+        };
+        self.address_anchor = Some(to);
+        self.delete_selection();
     }
 
-    fn delete_char_after(&mut self) {
-        let char_count = self.address_text.chars().count();
-        if self.address_cursor < char_count {
-            let byte_pos = self.char_to_byte_pos(self.address_cursor);
-            let next_byte = self.char_to_byte_pos(self.address_cursor + 1);
-            self.address_text.drain(byte_pos..next_byte);
-            self.reset_cursor_blink();
+    /// Move the cursor, extending the selection from where it was when `extend`
+    /// Without `extend`, Left/Right over a selection collapse it to that side instead
+    fn move_address_cursor(&mut self, to: usize, extend: bool) {
+        if extend {
+            self.address_anchor.get_or_insert(self.address_cursor);
+        } else {
+            self.address_anchor = None;
         }
+        self.address_cursor = to.min(self.address_text.chars().count());
+        self.reset_cursor_blink();
     }
 
-    fn move_cursor_left(&mut self) {
-        if self.address_cursor > 0 {
-            self.address_cursor -= 1;
-            self.reset_cursor_blink();
-        }
+    /// Left/Right arrow, by character or (`word`) word, extending with Shift
+    fn step_address_cursor(&mut self, forward: bool, word: bool, extend: bool) {
+        let to = match (self.address_selection(), forward, word, extend) {
+            (Some((start, _)), false, false, false) => start,
+            (Some((_, end)), true, false, false) => end,
+            (_, false, false, _) => self.address_cursor.saturating_sub(1),
+            (_, true, false, _) => self.address_cursor + 1,
+            (_, false, true, _) => self.word_left(),
+            (_, true, true, _) => self.word_right(),
+        };
+        self.move_address_cursor(to, extend);
     }
 
-    fn move_cursor_right(&mut self) {
-        let char_count = self.address_text.chars().count();
-        if self.address_cursor < char_count {
-            self.address_cursor += 1;
-            self.reset_cursor_blink();
-        }
+    fn select_all_address(&mut self) {
+        self.address_anchor = Some(0);
+        self.address_cursor = self.address_text.chars().count();
+        self.reset_cursor_blink();
     }
 
-    fn move_cursor_home(&mut self) {
-        self.address_cursor = 0;
+    /// Select the word (run of letters and digits) around a character index
+    fn select_address_word(&mut self, at: usize) {
+        let chars: Vec<char> = self.address_text.chars().collect();
+        let at = at.min(chars.len());
+        let start = chars[..at].iter().rposition(|c| !c.is_alphanumeric()).map_or(0, |i| i + 1);
+        let end = chars[at..].iter().position(|c| !c.is_alphanumeric()).map_or(chars.len(), |i| at + i);
+        self.address_anchor = Some(start);
+        self.address_cursor = end;
         self.reset_cursor_blink();
     }
 
-    fn move_cursor_end(&mut self) {
-        self.address_cursor = self.address_text.chars().count();
-        self.reset_cursor_blink();
+    /// Start of the word before the cursor; `/`, `.` and other punctuation separate words
+    fn word_left(&self) -> usize {
+        let chars: Vec<char> = self.address_text.chars().take(self.address_cursor).collect();
+        let word_end = chars.iter().rposition(|c| c.is_alphanumeric()).map_or(0, |i| i + 1);
+        chars[..word_end].iter().rposition(|c| !c.is_alphanumeric()).map_or(0, |i| i + 1)
+    }
+
+    /// End of the word after the cursor
+    fn word_right(&self) -> usize {
+        let chars: Vec<char> = self.address_text.chars().collect();
+        let at = self.address_cursor.min(chars.len());
+        let word_start = chars[at..].iter().position(|c| c.is_alphanumeric()).map_or(chars.len(), |i| at + i);
+        chars[word_start..].iter().position(|c| !c.is_alphanumeric()).map_or(chars.len(), |i| word_start + i)
     }
 
     fn char_to_byte_pos(&self, char_pos: usize) -> usize {
@@ -154,30 +773,101 @@ impl Browser {
     }
 
     fn go_back(&mut self) {
-        if self.history_index > 0 {
-            self.history_index -= 1;
-            let path = self.history[self.history_index].clone();
-            self.navigate_without_history(&path);
+        if self.history_index > 0 && !self.keeps_page(PendingLeave::History(self.history_index - 1)) {
+            self.go_to_history(self.history_index - 1);
         }
     }
 
     fn go_forward(&mut self) {
-        if self.history_index + 1 < self.history.len() {
-            self.history_index += 1;
-            let path = self.history[self.history_index].clone();
-            self.navigate_without_history(&path);
+        if self.history_index + 1 < self.history.len() && !self.keeps_page(PendingLeave::History(self.history_index + 1)) {
+            self.go_to_history(self.history_index + 1);
         }
     }
 
-    fn navigate_without_history(&mut self, path: &str) {
-        self.navigate_internal(path, false);
+    fn go_to_history(&mut self, index: usize) {
+        self.history_index = index;
+        let path = self.history[index].clone();
+        self.open_location(&path, false);
+    }
+
+    /// Ask the page whether it may be left; when its `on_before_leave` says
+    /// no, `leave` waits for the user to confirm in the "Discard changes?" dialog
+    fn keeps_page(&mut self, leave: PendingLeave) -> bool {
+        let to = match &leave {
+            PendingLeave::Navigate(path, _) | PendingLeave::Route(path) => Some(path.clone()),
+            PendingLeave::History(index) => Some(self.history[*index].clone()),
+            PendingLeave::Close => None,
+        };
+        let Some(ref mut rt) = self.runtime else {
+            return false;
+        };
+        if rt.may_leave(to.as_deref()) {
+            return false;
+        }
+        self.leave_prompt = Some(leave);
+        true
+    }
+
+    /// Show the dialog for a route change the page vetoed; true if one was
+    fn take_blocked_route(&mut self) -> bool {
+        let Some(route) = self.runtime.as_mut().and_then(|rt| rt.take_blocked_route()) else {
+            return false;
+        };
+        self.leave_prompt = Some(PendingLeave::Route(route));
+        true
+    }
+
+    /// "Discard changes?" answered: leave as the user asked, or stay
+    fn answer_leave_prompt(&mut self, discard: bool) {
+        let Some(leave) = self.leave_prompt.take().filter(|_| discard) else {
+            return;
+        };
+        match leave {
+            PendingLeave::Navigate(path, update_history) => self.open_location(&path, update_history),
+            PendingLeave::History(index) => self.go_to_history(index),
+            PendingLeave::Route(route) => {
+                if let Some(ref mut rt) = self.runtime {
+                    rt.set_route(&route);
+                }
+            }
+            PendingLeave::Close => self.quit = true,
+        }
     }
 
     fn navigate_internal(&mut self, path: &str, update_history: bool) {
+        // The task manager keeps the page running behind it, so nothing is lost
+        if path != tasks::TASKS_URL && self.keeps_page(PendingLeave::Navigate(path.to_string(), update_history)) {
+            return;
+        }
+        self.open_location(path, update_history);
+    }
+
+    /// Load `path` in place of the current page without asking the page first
+    fn open_location(&mut self, path: &str, update_history: bool) {
+        // A document still arriving is abandoned
+        self.stream = None;
+        self.observers.will_navigate(path);
+        if path == tasks::TASKS_URL {
+            self.open_tasks(update_history);
+            return;
+        }
+        // Leaving the task manager for the page behind it resumes that page
+        match self.background.take() {
+            Some((location, runtime)) if location == path => {
+                self.show_runtime(location, runtime, update_history);
+                return;
+            }
+            _ => {}
+        }
+
         if path.starts_with("http://") || path.starts_with("https://") {
             self.navigate_url(path, update_history);
             return;
         }
+        if integrity::is_pinned(path) {
+            self.navigate_pinned(path, update_history);
+            return;
+        }
 
         let full_path = if path.starts_with('/') || path.contains(':') {
             PathBuf::from(path)
@@ -186,149 +876,327 @@ impl Browser {
         };
 
         let path_str = full_path.to_string_lossy().to_string();
+        if package::is_package(&path_str) {
+            self.navigate_package(&full_path, update_history);
+            return;
+        }
 
         // Validate path
         let sandbox = Sandbox::new();
         if let Err(e) = sandbox.validate_file_path(&full_path) {
-            eprintln!("Security error: {}", e);
-            self.current_path = path_str.clone();
-            self.address_text = path_str.clone();
-            self.address_cursor = path_str.chars().count();
-            self.runtime = None;
-            self.last_error = Some(format!("Security error: {}", e));
+            self.show_load_error(&path_str, format!("Security error: {}", e));
             return;
         }
 
-        // Load file
-        let source = match std::fs::read_to_string(&full_path) {
-            Ok(s) => s,
+        // Large sources are shown while they parse
+        if !compiled::is_compiled(&path_str) && !import::is_importable(&path_str) {
+            let file = std::fs::File::open(&full_path).ok();
+            let len = file.as_ref().and_then(|f| f.metadata().ok()).map(|m| m.len());
+            if let (Some(file), Some(len @ STREAM_MIN_BYTES..)) = (file, len) {
+                self.start_stream(path_str.clone(), &path_str, DocumentStream::spawn(file, Some(len), Vfs::for_location(&path_str)), None, update_history);
+                return;
+            }
+        }
+
+        // Load and parse (pre-compiled documents skip the parser)
+        let app = match load_document(&full_path) {
+            Ok(app) => app,
             Err(e) => {
-                eprintln!("Failed to load {}: {}", full_path.display(), e);
-                self.current_path = path_str.clone();
-                self.address_text = path_str.clone();
-                self.address_cursor = path_str.chars().count();
-                self.runtime = None;
-                self.last_error = Some(format!("Failed to load {}: {}", full_path.display(), e));
+                self.show_load_error(&path_str, e);
                 return;
             }
         };
 
-        // Parse
-        let app = match parser::parse(&source) {
+        println!("Loaded: {} (v{})", app.name, app.version);
+
+        self.start_page(path_str.clone(), &path_str, app, update_history);
+    }
+
+    /// Run a loaded document as the current page; `address` is shown and kept
+    /// in history, `location` is where it was loaded from
+    fn start_page(&mut self, address: String, location: &str, app: PrismApp, update_history: bool) {
+        let runtime = self.page_runtime(location, app);
+        self.show_runtime(address, runtime, update_history);
+    }
+
+    fn page_runtime(&self, location: &str, app: PrismApp) -> Runtime {
+        let mut runtime = Runtime::new(app);
+        runtime.set_location(location);
+        runtime.set_content_policy(&self.settings.policy);
+        runtime.sandbox.set_seed(self.seed);
+        runtime.set_dark_mode(self.dark_mode);
+        runtime.set_spelling(self.spelling.clone());
+        runtime.set_battery_saver(self.chrome.battery_saver);
+        runtime
+    }
+
+    /// Load the dictionary named in the settings, if spell check is on
+    fn load_dictionary(&mut self) {
+        if !self.settings.spell_check {
+            return;
+        }
+        let language = &self.settings.spell_check_language;
+        let dirs: Vec<PathBuf> = settings::dictionary_dir().into_iter().collect();
+        let Some(path) = spell::Dictionary::find(language, &dirs) else {
+            eprintln!("[PRISM] Spell check is on, but no {} dictionary was found", language);
+            return;
+        };
+        match spell::Dictionary::open(&path) {
+            Ok(dictionary) => self.spelling = Some(Arc::new(dictionary)),
+            Err(e) => eprintln!("[PRISM] Failed to load dictionary {}", e),
+        }
+    }
+
+    /// Load a document progressively; the current page stays up until its
+    /// first part has parsed
+    fn start_stream(&mut self, address: String, location: &str, document: DocumentStream, connection: Option<tls::Connection>, update_history: bool) {
+        self.stream = Some(PageStream {
+            address,
+            location: location.to_string(),
+            update_history,
+            connection,
+            document,
+            shown: false,
+            percent: 0,
+        });
+    }
+
+    /// Show what has arrived of a streaming document; returns true if a redraw is needed
+    fn tick_stream(&mut self) -> bool {
+        let Some(mut stream) = self.stream.take() else {
+            return false;
+        };
+        match stream.document.poll() {
+            StreamUpdate::Pending => {
+                let percent = stream.document.progress().map(|p| (p * 100.0) as u32).unwrap_or(0);
+                let changed = percent != stream.percent;
+                stream.percent = percent;
+                self.stream = Some(stream);
+                changed
+            }
+            StreamUpdate::Preview(app) => {
+                let runtime = self.page_runtime(&stream.location, *app);
+                if stream.shown {
+                    self.observers.title_changed(runtime.title());
+                    self.runtime = Some(runtime);
+                } else {
+                    self.enter_page(stream.address.clone(), runtime, stream.update_history);
+                    self.tls = stream.connection.take();
+                    stream.shown = true;
+                }
+                self.stream = Some(stream);
+                true
+            }
+            StreamUpdate::Complete(source) => {
+                let app = match parser::parse_with_assets(&source, &Vfs::for_location(&stream.location)) {
+                    Ok(app) => app,
+                    Err(e) => {
+                        self.show_load_error(&stream.address, format!("Parse error in {}: {}", stream.location, e));
+                        return true;
+                    }
+                };
+                println!("Loaded: {} (v{})", app.name, app.version);
+                if stream.shown {
+                    // Keep the reader's place in the part already shown
+                    let runtime = self.page_runtime(&stream.location, app);
+                    self.observers.did_load(&stream.address);
+                    self.observers.title_changed(runtime.title());
+                    self.runtime = Some(runtime);
+                } else {
+                    self.start_page(stream.address, &stream.location, app, stream.update_history);
+                    self.tls = stream.connection;
+                }
+                true
+            }
+            StreamUpdate::Failed(e) => {
+                self.show_load_error(&stream.address, format!("Failed to read {}: {}", stream.location, e));
+                true
+            }
+        }
+    }
+
+    /// Open a signed package; it runs only if its signature and files verify
+    fn navigate_package(&mut self, path: &std::path::Path, update_history: bool) {
+        let path_str = path.to_string_lossy().to_string();
+        if let Err(e) = Sandbox::new().validate_file_path(path) {
+            self.show_load_error(&path_str, format!("Security error: {}", e));
+            return;
+        }
+        let package = match Package::open(path, &self.settings.trusted_publishers) {
+            Ok(package) => std::sync::Arc::new(package),
+            Err(e) => {
+                self.show_load_error(&path_str, e);
+                return;
+            }
+        };
+        let publisher = package::fingerprint(&package.manifest.public_key);
+        if self.settings.trusted_publishers.is_empty() {
+            match package::check_pinned(&mut self.settings.package_publishers, &package.manifest) {
+                Ok(true) => {
+                    self.settings.save();
+                    let body = format!("{} is signed by {}. Packages named {} will only open with this publisher's signature.", package.manifest.name, publisher, package.manifest.name);
+                    self.notices.push((runtime::Notice { title: "New publisher".to_string(), body }, Instant::now()));
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    self.show_load_error(&path_str, format!("Untrusted package: {}", e));
+                    return;
+                }
+            }
+        }
+        let app = match parser::parse_with_assets(&package.main_source(), &Vfs::Package(package.clone())) {
             Ok(app) => app,
             Err(e) => {
-                eprintln!("Parse error in {}: {}", full_path.display(), e);
-                self.current_path = path_str.clone();
-                self.address_text = path_str.clone();
-                self.address_cursor = path_str.chars().count();
-                self.runtime = None;
-                self.last_error = Some(format!("Parse error in {}: {}", full_path.display(), e));
+                self.show_load_error(&path_str, format!("Parse error in {}: {}", package.manifest.main, e));
                 return;
             }
         };
+        println!("Loaded: {} (v{}) from package {} by publisher {}", app.name, app.version, package.manifest.name, publisher);
+        self.start_page(path_str.clone(), &path_str, app, update_history);
+        if let Some(rt) = &mut self.runtime {
+            rt.mount_package(package);
+        }
+    }
 
-        println!("Loaded: {} (v{})", app.name, app.version);
+    /// Make `runtime` the current page, scrolled to the top
+    fn show_runtime(&mut self, location: String, runtime: Runtime, update_history: bool) {
+        self.observers.did_load(&location);
+        self.enter_page(location, runtime, update_history);
+    }
 
+    /// [`Self::show_runtime`] for a page that hasn't finished loading
+    fn enter_page(&mut self, location: String, runtime: Runtime, update_history: bool) {
         // Update history
         if update_history
-            && (self.history.is_empty() || self.history[self.history_index] != path_str) {
+            && (self.history.is_empty() || self.history[self.history_index] != location) {
                 // Truncate forward history if navigating from middle
                 self.history.truncate(self.history_index + 1);
-                self.history.push(path_str.clone());
+                self.history.push(location.clone());
                 self.history_index = self.history.len() - 1;
             }
 
-        self.current_path = path_str.clone();
-        self.address_text = path_str.clone();
-        self.address_cursor = path_str.chars().count();
-        self.runtime = Some(Runtime::new(app));
+        self.set_address(&location);
+        self.observers.title_changed(runtime.title());
+        self.current_path = location;
+        self.runtime = Some(runtime);
+        self.tls = None;
+        self.security_open = false;
         self.last_error = None;
         self.scroll_y = 0;
         self.max_scroll_y = 0;
+        self.pull_distance = 0;
+        self.refresh_started = None;
+        self.refresh_pending = false;
+        self.slow_actions.clear();
     }
 
     fn navigate_url(&mut self, url: &str, update_history: bool) {
-        let url_str = url.to_string();
+        match address::normalize(url) {
+            Ok(url) => self.load_remote(&url, &url, None, update_history),
+            Err(e) => self.show_load_error(url, e),
+        }
+    }
 
+    /// Open a `prism://pin` address: the document runs only if its hash matches
+    fn navigate_pinned(&mut self, address: &str, update_history: bool) {
+        match integrity::parse_pin(address) {
+            Ok((url, expected)) => self.load_remote(address, &url, Some(&expected), update_history),
+            Err(e) => self.show_load_error(address, e),
+        }
+    }
+
+    /// Fetch and run a remote document, shown in the address bar and history as `address`
+    fn load_remote(&mut self, address: &str, url: &str, expected: Option<&Integrity>, update_history: bool) {
+        let url = match address::normalize(url) {
+            Ok(url) => url,
+            Err(e) => {
+                self.show_load_error(address, e);
+                return;
+            }
+        };
+        let url = url.as_str();
         // Allow http:// only for localhost during development; require https:// for remote hosts
-        let is_local = url_str.starts_with("http://localhost") || url_str.starts_with("http://127.0.0.1");
-        if url_str.starts_with("http://") && !is_local {
-            let msg = "Only https:// is allowed for remote URLs (http:// is limited to localhost)".to_string();
-            eprintln!("Network error: {}", msg);
-            self.current_path = url_str.clone();
-            self.address_text = url_str.clone();
-            self.address_cursor = url_str.chars().count();
-            self.runtime = None;
-            self.last_error = Some(msg);
+        let is_local = url.starts_with("http://localhost") || url.starts_with("http://127.0.0.1");
+        if url.starts_with("http://") && !is_local {
+            self.show_load_error(address, "Only https:// is allowed for remote URLs (http:// is limited to localhost)".to_string());
             return;
         }
 
-        let response = match blocking::get(url) {
+        // The verifier records what the connection the page comes over presented;
+        // a certificate that doesn't verify gets through only under the warn policy
+        let (config, recorder) = tls::client_config(self.settings.invalid_certificates == CertificatePolicy::Warn);
+        let client = blocking::Client::builder().use_preconfigured_tls(config).build();
+        let response = match client.and_then(|client| client.get(url).send()) {
             Ok(resp) => resp,
             Err(e) => {
-                eprintln!("Network error while loading {}: {}", url, e);
-                self.current_path = url_str.clone();
-                self.address_text = url_str.clone();
-                self.address_cursor = url_str.chars().count();
-                self.runtime = None;
-                self.last_error = Some(format!("Network error while loading {}: {}", url, e));
+                let message = match recorder.take().and_then(|c| c.error) {
+                    Some(invalid) => format!("Refusing to load {}: its certificate is not valid ({})", url, invalid),
+                    None => format!("Network error while loading {}: {}", url, e),
+                };
+                self.show_load_error(address, message);
                 return;
             }
         };
+        let connection = recorder.take();
 
         let status = response.status();
         if !status.is_success() {
-            eprintln!("HTTP error {} while loading {}", status, url);
-            self.current_path = url_str.clone();
-            self.address_text = url_str.clone();
-            self.address_cursor = url_str.chars().count();
-            self.runtime = None;
-            self.last_error = Some(format!("HTTP error {} while loading {}", status, url));
+            self.show_load_error(address, format!("HTTP error {} while loading {}", status, url));
+            return;
+        }
+
+        // Large or open-ended documents are shown as they arrive, except pinned
+        // ones, which have to be checked whole before any of them runs
+        let plain = !compiled::is_compiled(url.split(['?', '#']).next().unwrap_or(url)) && !import::is_importable(url);
+        if plain && expected.is_none() && response.content_length().is_none_or(|len| len >= STREAM_MIN_BYTES) {
+            let total = response.content_length();
+            self.start_stream(address.to_string(), url, DocumentStream::spawn(response, total, Vfs::Empty), connection, update_history);
             return;
         }
 
-        let source = match response.text() {
-            Ok(text) => text,
+        let body = match response.bytes() {
+            Ok(bytes) => bytes,
             Err(e) => {
-                eprintln!("Failed to read response body from {}: {}", url, e);
-                self.current_path = url_str.clone();
-                self.address_text = url_str.clone();
-                self.address_cursor = url_str.chars().count();
-                self.runtime = None;
-                self.last_error = Some(format!("Failed to read response body from {}: {}", url, e));
+                self.show_load_error(address, format!("Failed to read response body from {}: {}", url, e));
                 return;
             }
         };
+        if let Some(Err(e)) = expected.map(|integrity| integrity.verify(&body)) {
+            self.show_load_error(address, format!("Refusing to run {}: {}", url, e));
+            return;
+        }
 
-        let app = match parser::parse(&source) {
+        let app = if compiled::is_compiled(url.split(['?', '#']).next().unwrap_or(url)) {
+            compiled::decode(&body).map_err(|e| format!("Failed to load {}: {}", url, e))
+        } else if import::is_importable(url) {
+            Ok(import::convert(url, &String::from_utf8_lossy(&body)))
+        } else {
+            parser::parse_with_assets(&String::from_utf8_lossy(&body), &Vfs::Empty).map_err(|e| format!("Parse error in {}: {}", url, e))
+        };
+        let app = match app {
             Ok(app) => app,
             Err(e) => {
-                eprintln!("Parse error in {}: {}", url, e);
-                self.current_path = url_str.clone();
-                self.address_text = url_str.clone();
-                self.address_cursor = url_str.chars().count();
-                self.runtime = None;
-                self.last_error = Some(format!("Parse error in {}: {}", url, e));
+                self.show_load_error(address, e);
                 return;
             }
         };
 
         println!("Loaded: {} (v{})", app.name, app.version);
 
-        if update_history
-            && (self.history.is_empty() || self.history[self.history_index] != url_str) {
-                self.history.truncate(self.history_index + 1);
-                self.history.push(url_str.clone());
-                self.history_index = self.history.len() - 1;
-            }
+        self.start_page(address.to_string(), url, app, update_history);
+        self.tls = connection;
+    }
 
-        self.current_path = url_str.clone();
-        self.address_text = url_str.clone();
-        self.address_cursor = url_str.chars().count();
-        self.runtime = Some(Runtime::new(app));
-        self.last_error = None;
-        self.scroll_y = 0;
-        self.max_scroll_y = 0;
+    /// Replace the page with an error for a document that failed to load
+    fn show_load_error(&mut self, address: &str, message: String) {
+        eprintln!("{}", message);
+        self.current_path = address.to_string();
+        self.set_address(address);
+        self.runtime = None;
+        self.tls = None;
+        self.security_open = false;
+        self.observers.load_failed(address, &message);
+        self.last_error = Some(message);
     }
 
     fn can_go_back(&self) -> bool {
@@ -342,11 +1210,93 @@ impl Browser {
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("ast") {
+        let json = args.iter().any(|a| a == "--json");
+        let Some(path) = args.iter().skip(2).find(|a| !a.starts_with("--")) else {
+            eprintln!("Usage: {} ast <file.prism> [--json]", args[0]);
+            std::process::exit(2);
+        };
+        std::process::exit(dump::run(path, json));
+    }
+    if args.get(1).map(String::as_str) == Some("highlight") {
+        let format = args.iter().skip(2).find(|a| a.starts_with("--")).map(String::as_str).unwrap_or("--ansi");
+        let Some(path) = args.iter().skip(2).find(|a| !a.starts_with("--")) else {
+            eprintln!("Usage: {} highlight <file.prism> [--html | --tokens]", args[0]);
+            std::process::exit(2);
+        };
+        std::process::exit(highlight::run(path, format));
+    }
+    if args.get(1).map(String::as_str) == Some("check") {
+        let lint = args.iter().any(|a| a == "--lint");
+        let Some(path) = args.iter().skip(2).find(|a| !a.starts_with("--")) else {
+            eprintln!("Usage: {} check [--lint] <file.prism>", args[0]);
+            std::process::exit(2);
+        };
+        std::process::exit(lint::run(path, lint));
+    }
+    if args.get(1).map(String::as_str) == Some("export-html") {
+        let output = args.iter().position(|a| a == "-o").and_then(|i| args.get(i + 1));
+        let input = args.iter().skip(2).enumerate().find(|(i, a)| !a.starts_with('-') && args[*i + 1] != "-o");
+        let Some((_, input)) = input else {
+            eprintln!("Usage: {} export-html <file.prism> [-o file.html]", args[0]);
+            std::process::exit(2);
+        };
+        std::process::exit(export::run(input, output.map(String::as_str)));
+    }
+    if args.get(1).map(String::as_str) == Some("compile") {
+        let output = args.iter().position(|a| a == "-o").and_then(|i| args.get(i + 1));
+        let input = args.iter().skip(2).enumerate().find(|(i, a)| !a.starts_with('-') && args[*i + 1] != "-o");
+        let Some((_, input)) = input else {
+            eprintln!("Usage: {} compile <file.prism> [-o file.prismc]", args[0]);
+            std::process::exit(2);
+        };
+        std::process::exit(compiled::run(input, output.map(String::as_str)));
+    }
+    if args.get(1).map(String::as_str) == Some("pin") {
+        let Some(url) = args.get(2) else {
+            eprintln!("Usage: {} pin <https://…/app.prism>", args[0]);
+            std::process::exit(2);
+        };
+        std::process::exit(integrity::run(url));
+    }
+    if args.get(1).map(String::as_str) == Some("package") {
+        let option = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+        let dir = args.iter().skip(2).enumerate().find(|(i, a)| !a.starts_with('-') && !args[*i + 1].starts_with('-'));
+        let (Some((_, dir)), Some(key)) = (dir, option("--key")) else {
+            eprintln!("Usage: {} package <dir> --key <signing.key> [--main main.prism] [-o app.prismpkg]", args[0]);
+            std::process::exit(2);
+        };
+        let main = option("--main").map_or("main.prism", String::as_str);
+        std::process::exit(package::run(dir, key, main, option("-o").map(String::as_str)));
+    }
+    if args.get(1).map(String::as_str) == Some("drive") {
+        let (Some(page), Some(script)) = (args.get(2), args.get(3)) else {
+            eprintln!("Usage: {} drive <file.prism> <script.json>", args[0]);
+            std::process::exit(2);
+        };
+        std::process::exit(drive::run(page, script));
+    }
     let mut layout_log = false;
     let mut file_arg: Option<String> = None;
     for a in args.iter().skip(1) {
-        if a == "--layout-log" { layout_log = true; } else if a.ends_with(".prism") { file_arg = Some(a.clone()); }
+        if a == "--layout-log" { layout_log = true; } else if a.ends_with(".prism") || compiled::is_compiled(a) || import::is_importable(a) { file_arg = Some(a.clone()); }
     }
+    let flag_value = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned();
+    let record_path = flag_value("--record");
+    let mut player = match flag_value("--replay") {
+        Some(path) => match Player::load(&path) {
+            Ok(player) => Some(player),
+            Err(e) => {
+                eprintln!("[PRISM] Cannot replay {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    // First argument that is neither a flag nor a flag's value
+    let page_arg = args.iter().enumerate().skip(1)
+        .find(|(i, a)| !a.starts_with("--") && !matches!(args[i - 1].as_str(), "--record" | "--replay"))
+        .map(|(_, a)| a.clone());
 
     // Determine base directory
     let exe_dir = std::env::current_exe()
@@ -355,27 +1305,49 @@ fn main() {
         .unwrap_or_else(|| std::env::current_dir().unwrap());
     let base_dir = std::env::current_dir().unwrap_or(exe_dir);
 
-    // Create browser
+    // Create browser; a replay reuses the recorded seed so page randomness repeats
     let mut browser = Browser::new(base_dir.clone());
+    let window_title = WindowTitle::default();
+    browser.observers.add(Box::new(window_title.clone()));
+    browser.settings = Settings::load();
+    browser.load_dictionary();
+    let toolbar_bottom = browser.settings.toolbar_bottom || args.iter().any(|a| a == "--toolbar-bottom");
+    let private = args.iter().any(|a| a == "--private");
+    browser.settings.ephemeral = private;
+    browser.chrome = ChromeStyle {
+        compact: browser.settings.compact || args.iter().any(|a| a == "--compact"),
+        position: if toolbar_bottom { ToolbarPosition::Bottom } else { ToolbarPosition::Top },
+        reader: false,
+        private,
+        battery_saver: false,
+    };
+    browser.seed = match &player {
+        Some(player) => player.header.seed,
+        None => std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos() as u64,
+    };
 
     if layout_log {
         let target = file_arg.unwrap_or_else(|| {
             base_dir.join("examples").join("counter.prism").to_string_lossy().into()
         });
         let full_path = if target.starts_with('/') || target.contains(':') { std::path::PathBuf::from(&target) } else { base_dir.join(&target) };
-        let source = std::fs::read_to_string(&full_path).expect("Failed to read prism file");
-        let app = parser::parse(&source).expect("Failed to parse prism file");
+        let app = load_document(&full_path).expect("Failed to load prism file");
         let mut rt = Runtime::new(app);
-        rt.renderer.print_layout_report(&rt.app.view, &rt.state, DEFAULT_WIDTH as u32);
+        rt.set_location(&full_path.to_string_lossy());
+        let view = rt.app.view_for(rt.route());
+        rt.state.index_view(view, rt.app.route_pattern(view));
+        rt.renderer.print_layout_report(view, &rt.state, DEFAULT_WIDTH as u32);
         return;
     }
 
     // Load initial page
-    if args.len() >= 2 {
-        browser.navigate(&args[1]);
+    if let Some(player) = &player {
+        browser.navigate(&player.header.document);
+    } else if let Some(page) = &page_arg {
+        browser.navigate(page);
     } else {
         // Try to load home page
-        let home_path = base_dir.join("examples").join("home.prism");
+        let home_path = browser.home_path();
         if home_path.exists() {
             browser.navigate(&home_path.to_string_lossy());
         } else {
@@ -390,162 +1362,676 @@ fn main() {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_inner_size(PhysicalSize::new(DEFAULT_WIDTH as u32, DEFAULT_HEIGHT as u32))
-        .with_title("Prism Browser")
+        .with_min_inner_size(PhysicalSize::new(MIN_WIDTH, MIN_HEIGHT))
+        .with_title(if browser.chrome.private { "Prism Browser (Private)" } else { "Prism Browser" })
         .build(&event_loop)
         .expect("Failed to create window");
+    browser.set_dark_mode(window.theme() == Some(Theme::Dark));
+    // Composed (CJK) text arrives as `Ime` events rather than characters
+    window.set_ime_allowed(true);
 
     let context = unsafe { Context::new(&window) }.expect("Failed to create softbuffer context");
     let mut surface = unsafe { Surface::new(&context, &window) }.expect("Failed to create surface");
 
+    if let Some(player) = &player {
+        window.set_inner_size(PhysicalSize::new(player.header.width, player.header.height));
+    }
+    let mut recorder = match record_path {
+        Some(path) => {
+            let size = window.inner_size();
+            let header = replay::Header::new(&browser.current_path, size.width, size.height, browser.seed);
+            match Recorder::create(&path, &header) {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    eprintln!("[PRISM] {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
     let size = window.inner_size();
     let mut fb = FrameBuffer::new(size.width as usize, size.height as usize);
+    browser.relayout(fb.width, fb.height);
+
+    let mut needs_redraw = true;
+    let mut needs_present = false;
+    let mut pointer = PointerState::default();
+    let gamepads = Gamepads::start();
+    let mut last_ime_position = None;
+    let mut last_tick = Instant::now();
+    // Frames since start; recorded input is keyed by it so replays follow the same clock
+    let mut tick: u64 = 0;
+
+    event_loop.run(move |event, _, control_flow| {
+        // A hidden window wakes once per `HIDDEN_TICK` instead of spinning
+        *control_flow = if browser.occluded { ControlFlow::WaitUntil(last_tick + HIDDEN_TICK) } else { ControlFlow::Poll };
+
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => {
+                    if browser.keeps_page(PendingLeave::Close) {
+                        needs_redraw = true;
+                    } else {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+                WindowEvent::ThemeChanged(theme) => {
+                    browser.set_dark_mode(theme == Theme::Dark);
+                }
+                WindowEvent::Occluded(hidden) => {
+                    browser.occluded = hidden;
+                    if !hidden {
+                        needs_redraw = true;
+                    }
+                }
+                WindowEvent::Resized(new_size) => {
+                    let width = NonZeroU32::new(new_size.width.max(1)).expect("width nonzero");
+                    let height = NonZeroU32::new(new_size.height.max(1)).expect("height nonzero");
+                    surface
+                        .resize(width, height)
+                        .expect("Failed to resize surface");
+                    fb = FrameBuffer::new(new_size.width as usize, new_size.height as usize);
+                    if let Some(ref mut recorder) = recorder {
+                        recorder.record(tick, &InputEvent::Resize { width: new_size.width, height: new_size.height });
+                    }
+                    // Lay the chrome and page out for the new size right away, so
+                    // clicks before the next frame land where the controls now are
+                    render_browser(&mut fb, &mut browser);
+                    needs_redraw = false;
+                    needs_present = true;
+                }
+                event => {
+                    let Some(input) = InputEvent::from_window_event(&event) else {
+                        return;
+                    };
+                    // Live input is ignored while a session replays
+                    if player.is_some() {
+                        return;
+                    }
+                    if let Some(ref mut recorder) = recorder {
+                        recorder.record(tick, &input);
+                    }
+                    if handle_input(&mut browser, &input, &mut pointer, &window, fb.width) {
+                        needs_redraw = true;
+                    }
+                    if browser.quit {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+            },
+            Event::MainEventsCleared => {
+                if let Some(title) = window_title.take() {
+                    window.set_title(&if browser.chrome.private { format!("{} (Private)", title) } else { title });
+                }
+                // Tick cursor blink at ~60fps; animations and blinking all but
+                // stop while the window is hidden
+                let now = Instant::now();
+                let steps = browser.frame_steps();
+                let frame = if browser.occluded { HIDDEN_TICK } else { FRAME * steps as u32 };
+                if now.duration_since(last_tick) >= frame {
+                    // Replayed input lands between the same two frames it did when recorded
+                    if let Some(ref mut session) = player {
+                        for input in session.due(tick) {
+                            match input {
+                                // The window manager applies it; `Resized` then rebuilds the buffers
+                                InputEvent::Resize { width, height } => window.set_inner_size(PhysicalSize::new(width, height)),
+                                input => needs_redraw |= handle_input(&mut browser, &input, &mut pointer, &window, fb.width),
+                            }
+                        }
+                        if session.finished() {
+                            eprintln!("[PRISM] Replay finished at tick {}", tick);
+                            player = None;
+                        }
+                    }
+                    // Gamepad presses are recorded and replayed like window input
+                    if player.is_none() {
+                        for button in gamepads.poll() {
+                            let input = InputEvent::Pad { button };
+                            if let Some(ref mut recorder) = recorder {
+                                recorder.record(tick, &input);
+                            }
+                            needs_redraw |= handle_input(&mut browser, &input, &mut pointer, &window, fb.width);
+                        }
+                    }
+                    tick += steps;
+                    pointer.tick = tick;
+                    if pointer.press.is_some_and(|(start, _, _)| tick - start >= LONG_PRESS_TICKS) {
+                        if let (Some((_, x, y)), Some(rt)) = (pointer.press.take(), browser.runtime.as_mut()) {
+                            if rt.handle_gesture(Gesture::LongPress, x, y) {
+                                needs_redraw = true;
+                            }
+                        }
+                    }
+                    // Blinking is counted in frames, so a slower frame rate advances it more per frame
+                    let old_visible = browser.cursor_visible;
+                    for _ in 0..steps {
+                        browser.tick_cursor();
+                    }
+                    if browser.address_focused && browser.cursor_visible != old_visible {
+                        needs_redraw = true;
+                    }
+                    if let Some(ref mut rt) = browser.runtime {
+                        for _ in 0..steps {
+                            if rt.renderer.tick() {
+                                needs_redraw = true;
+                            }
+                        }
+                        rt.pump_broadcasts();
+                        rt.pump_data();
+                    }
+                    // Messages for the hidden page wait in its inbox and arrive together
+                    if let Some((_, page)) = browser.background.as_mut().filter(|_| tick % BACKGROUND_PUMP_TICKS < steps) {
+                        page.pump_broadcasts();
+                    }
+                    if browser.tick_tasks() {
+                        needs_redraw = true;
+                    }
+                    if browser.tick_idle() {
+                        needs_redraw = true;
+                    }
+                    if browser.tick_power() {
+                        needs_redraw = true;
+                    }
+                    browser.track_activity();
+                    if browser.take_blocked_route() {
+                        needs_redraw = true;
+                    }
+                    if browser.tick_notices() {
+                        needs_redraw = true;
+                    }
+                    if browser.tick_refresh() {
+                        needs_redraw = true;
+                    }
+                    if browser.tick_stream() {
+                        needs_redraw = true;
+                    }
+                    // Animated images repaint in place unless a full render is due anyway
+                    if !needs_redraw && repaint_animations(&mut fb, &mut browser) {
+                        needs_present = true;
+                    }
+                    // Frames with nothing to draw warm the glyphs of the text below the fold
+                    if !needs_redraw && !browser.occluded {
+                        if let Some(ref mut rt) = browser.runtime {
+                            rt.prerasterize(now + PRERASTER_SLICE);
+                        }
+                    }
+                    last_tick = now;
+                }
+                if needs_redraw || browser.runtime.as_ref().map(|r| r.state.is_dirty()).unwrap_or(false) {
+                    needs_redraw = true;
+                    window.request_redraw();
+                } else if needs_present {
+                    window.request_redraw();
+                }
+            }
+            Event::RedrawRequested(_) => {
+                // `fb` already holds the current frame when only animations changed
+                if needs_redraw || !needs_present {
+                    render_browser(&mut fb, &mut browser);
+                    // The IME candidate window opens just below the caret
+                    if let Some((x, y)) = ime_position(&browser).filter(|p| Some(*p) != last_ime_position) {
+                        window.set_ime_position(PhysicalPosition::new(x, y));
+                        last_ime_position = Some((x, y));
+                    }
+                }
+
+                // Present framebuffer
+                let mut buffer = surface.buffer_mut().expect("buffer mut");
+                debug_assert_eq!(buffer.len(), fb.pixels.len());
+                fb.present(browser.settings.pixel_format, &mut buffer);
+                buffer.present().expect("present");
+                needs_redraw = false;
+                needs_present = false;
+
+                // Slow work runs now that the progress bar is visible
+                if browser.busy.is_some() {
+                    browser.run_busy_work();
+                    needs_redraw = true;
+                }
+
+                // Run the refresh action now that its spinner is visible
+                if browser.refresh_pending {
+                    browser.refresh_pending = false;
+                    if let Some(ref mut rt) = browser.runtime {
+                        rt.refresh();
+                    }
+                    needs_redraw = true;
+                }
+            }
+            _ => {}
+        }
+    });
+}
 
-    let mut needs_redraw = true;
-    let mut last_mouse_pos: Option<(i32, i32)> = None;
-    let mut modifiers = ModifiersState::empty();
-    let mut last_tick = Instant::now();
+/// Pointer position and modifier keys, updated by the input they come with
+#[derive(Default)]
+struct PointerState {
+    last_mouse_pos: Option<(i32, i32)>,
+    modifiers: ModifiersState,
+    /// Frame tick gestures are timed by, so replays recognize them the same way
+    tick: u64,
+    /// Tick and window position of the last click, for double clicks
+    last_click: Option<(u64, i32, i32)>,
+    /// Tick and content position of a press that may become a long press
+    press: Option<(u64, i32, i32)>,
+    /// Sideways trackpad swipe: tick of its last scroll, distance so far, and
+    /// whether it already navigated
+    swipe: (u64, i32, bool),
+}
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
+impl PointerState {
+    /// Add a sideways scroll to the current swipe; `Some(forward)` once it
+    /// has gone far enough to navigate, at most once per swipe
+    fn swipe(&mut self, dx: i32) -> Option<bool> {
+        let (last, distance, done) = self.swipe;
+        self.swipe = if self.tick - last > SWIPE_IDLE_TICKS { (self.tick, dx, false) } else { (self.tick, distance + dx, done) };
+        if self.swipe.2 || self.swipe.1.abs() < SWIPE_DISTANCE {
+            return None;
+        }
+        self.swipe.2 = true;
+        Some(self.swipe.1 < 0)
+    }
+}
 
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
+/// Apply one live or replayed input event; returns true when a redraw is needed
+fn handle_input(browser: &mut Browser, input: &InputEvent, pointer: &mut PointerState, window: &Window, width: usize) -> bool {
+    if !matches!(input, InputEvent::Resize { .. }) {
+        browser.last_input = Instant::now();
+        browser.idle_reported = 0;
+    }
+    if browser.leave_prompt.is_some() {
+        return handle_leave_prompt_input(browser, input, pointer, window);
+    }
+    if browser.share.is_some() {
+        return handle_share_input(browser, input, pointer, window);
+    }
+    if browser.region.is_some() {
+        return handle_region_input(browser, input, pointer, window);
+    }
+    if browser.spelling_menu.is_some() {
+        return handle_spelling_menu_input(browser, input, pointer, window);
+    }
+    let mut needs_redraw = false;
+    match *input {
+        InputEvent::Resize { .. } => {}
+        InputEvent::Modifiers { state } => {
+            pointer.modifiers = state;
+        }
+        InputEvent::CursorMoved { x: mx, y: my } => {
+            pointer.last_mouse_pos = Some((mx, my));
+            if browser.address_dragging {
+                chrome::drag_address(browser, mx);
+                return true;
+            }
+            let mut hand = false;
+            let content = browser.layout.content;
+            if browser.layout.bar.contains(mx, my) {
+                hand = chrome::over_button(browser, mx, my);
+                if let Some(ref mut rt) = browser.runtime {
+                    needs_redraw |= rt.handle_hover(None);
                 }
-                WindowEvent::Resized(new_size) => {
-                    let width = NonZeroU32::new(new_size.width.max(1)).expect("width nonzero");
-                    let height = NonZeroU32::new(new_size.height.max(1)).expect("height nonzero");
-                    surface
-                        .resize(width, height)
-                        .expect("Failed to resize surface");
-                    fb = FrameBuffer::new(new_size.width as usize, new_size.height as usize);
-                    if let Some(ref mut rt) = browser.runtime {
-                        rt.invalidate();
+            } else if let Some(ref mut rt) = browser.runtime {
+                let (content_x, content_y) = (mx - content.x, my - content.y);
+                needs_redraw |= rt.handle_hover(Some((content_x, content_y)));
+                needs_redraw |= rt.clear_spatial_focus();
+                if rt.is_resizing_column() || rt.over_column_divider(content_x, content_y) {
+                    if rt.drag_column_resize(content_x) {
+                        needs_redraw = true;
                     }
-                    needs_redraw = true;
+                    window.set_cursor_icon(CursorIcon::ColResize);
+                    return needs_redraw;
                 }
-                WindowEvent::ModifiersChanged(m) => {
-                    modifiers = m;
-                }
-                WindowEvent::CursorMoved { position, .. } => {
-                    last_mouse_pos = Some((position.x as i32, position.y as i32));
-                    let (mx, my) = (position.x as i32, position.y as i32);
-                    let mut hand = false;
-                    if my < CHROME_HEIGHT as i32 {
-                        if ((10..=38).contains(&mx) && (12..=40).contains(&my) && browser.can_go_back()) ||
-                           ((45..=73).contains(&mx) && (12..=40).contains(&my) && browser.can_go_forward()) {
-                            hand = true;
-                        }
-                    } else if let Some(ref mut rt) = browser.runtime {
-                        let content_y = my - CHROME_HEIGHT as i32;
-                        if let Some(layout_box) = rt.renderer.hit_test(mx, content_y) {
-                            if layout_box.action.is_some() || layout_box.link_href.is_some() {
-                                hand = true;
-                            }
-                        }
-                    }
-                    window.set_cursor_icon(if hand { CursorIcon::Hand } else { CursorIcon::Default });
+                // Moving away turns a press into a drag rather than a long press
+                if pointer.press.is_some_and(|(_, x, y)| (content_x - x).abs() > GESTURE_SLOP || (content_y - y).abs() > GESTURE_SLOP) {
+                    pointer.press = None;
                 }
-                WindowEvent::MouseInput { state, button, .. } => {
-                    if button == MouseButton::Left && state == ElementState::Pressed {
-                        if let Some((mx, my)) = last_mouse_pos {
-                            if my < CHROME_HEIGHT as i32 {
-                                handle_chrome_click(&mut browser, mx, my, fb.width);
-                                needs_redraw = true;
-                            } else if let Some(ref mut rt) = browser.runtime {
-                                let content_y = my - CHROME_HEIGHT as i32;
-                                let mut nav_target: Option<String> = None;
-                                if let Some(layout_box) = rt.renderer.hit_test(mx, content_y) {
-                                    if let Some(ref href) = layout_box.link_href {
-                                        nav_target = Some(href.clone());
-                                    }
-                                }
-                                if let Some(href) = nav_target {
-                                    browser.navigate(&href);
-                                } else {
-                                    rt.handle_click(mx, content_y);
-                                    rt.renderer.set_focus(rt.focused_input.clone());
-                                }
-                                needs_redraw = true;
-                            }
-                        }
+                if let Some(layout_box) = rt.renderer.hit_test(content_x, content_y) {
+                    if layout_box.action.is_some() || layout_box.link_href.is_some() {
+                        hand = true;
                     }
                 }
-                WindowEvent::MouseWheel { delta, .. } => {
-                    if browser.runtime.is_some() {
-                        let scroll_delta = match delta {
-                            MouseScrollDelta::LineDelta(_, y) => (y * 40.0) as i32,
-                            MouseScrollDelta::PixelDelta(pos) => pos.y as i32,
-                        };
-                        let mut new_scroll = browser.scroll_y - scroll_delta;
-                        if new_scroll < 0 {
-                            new_scroll = 0;
+            }
+            window.set_cursor_icon(if hand { CursorIcon::Hand } else { CursorIcon::Default });
+        }
+        InputEvent::CursorLeft => {
+            pointer.last_mouse_pos = None;
+            pointer.press = None;
+            if let Some(ref mut rt) = browser.runtime {
+                needs_redraw = rt.handle_hover(None);
+            }
+        }
+        InputEvent::MouseDown => {
+            if let Some((mx, my)) = pointer.last_mouse_pos {
+                let near = |(_, x, y): (u64, i32, i32)| (mx - x).abs() <= GESTURE_SLOP && (my - y).abs() <= GESTURE_SLOP;
+                let double = pointer.last_click.is_some_and(|click| pointer.tick - click.0 <= DOUBLE_CLICK_TICKS && near(click));
+                // A third click starts over rather than making another double click
+                pointer.last_click = if double { None } else { Some((pointer.tick, mx, my)) };
+                if chrome::security_panel(browser).is_some_and(|panel| panel.contains(mx, my)) {
+                    return false;
+                }
+                // Clicking anywhere but the panel or its icon closes it
+                if browser.security_open && !browser.layout.security.contains(mx, my) {
+                    browser.security_open = false;
+                    needs_redraw = true;
+                }
+                if let Some(i) = notice_at(browser, width, mx, my) {
+                    browser.notices.remove(i);
+                    needs_redraw = true;
+                } else if browser.layout.bar.contains(mx, my) {
+                    chrome::click(browser, mx, my, double);
+                    needs_redraw = true;
+                } else if let Some(ref mut rt) = browser.runtime {
+                    let content = browser.layout.content;
+                    let (content_x, content_y) = (mx - content.x, my - content.y);
+                    if rt.begin_column_resize(content_x, content_y) {
+                        return false;
+                    }
+                    if rt.has_long_press(content_x, content_y) {
+                        pointer.press = Some((pointer.tick, content_x, content_y));
+                    }
+                    let mut nav_target: Option<String> = None;
+                    if let Some(layout_box) = rt.renderer.hit_test(content_x, content_y) {
+                        if let Some(ref href) = layout_box.link_href {
+                            nav_target = Some(href.clone());
                         }
-                        if new_scroll > browser.max_scroll_y {
-                            new_scroll = browser.max_scroll_y;
+                    }
+                    if let Some(href) = nav_target.filter(|href| !rt.follow_route(href)) {
+                        match rt.sandbox.check_link(&href) {
+                            Ok(()) => browser.navigate_or_defer(&href),
+                            Err(e) => eprintln!("[PRISM] Not following {}: {}", href, e),
                         }
-                        if new_scroll != browser.scroll_y {
-                            browser.scroll_y = new_scroll;
-                            needs_redraw = true;
+                    } else if browser.click_is_slow(content_x, content_y) {
+                        browser.busy = Some(BusyWork::Click { x: content_x, y: content_y });
+                    } else if let Some(ref mut rt) = browser.runtime {
+                        rt.handle_click(content_x, content_y);
+                        rt.renderer.set_focus(rt.focused_input.clone());
+                        // The second click has already run as a click too
+                        if double {
+                            rt.handle_gesture(Gesture::DoubleClick, content_x, content_y);
                         }
                     }
+                    needs_redraw = true;
                 }
-                WindowEvent::KeyboardInput { input, .. } => {
-                    if input.state == ElementState::Pressed
-                        && handle_key_input(&mut browser, &input, modifiers) {
-                            needs_redraw = true;
-                        }
+            }
+        }
+        InputEvent::MouseUp => {
+            pointer.press = None;
+            browser.address_dragging = false;
+            if let Some(ref mut rt) = browser.runtime {
+                rt.end_column_resize();
+            }
+        }
+        InputEvent::Wheel { dx, dy, precise } => {
+            if browser.runtime.is_some() {
+                let (mut dx, mut scroll_delta) = (dx, dy);
+                // Shift+wheel scrolls sideways, e.g. through a wide table
+                if pointer.modifiers.shift() && dx == 0 {
+                    (dx, scroll_delta) = (scroll_delta, 0);
                 }
-                WindowEvent::ReceivedCharacter(ch) => {
-                    if handle_received_char(&mut browser, ch) {
+                let mut table_scrolled = false;
+                if let (true, Some((mx, my)), Some(rt)) = (dx != 0, pointer.last_mouse_pos, browser.runtime.as_mut()) {
+                    let content = browser.layout.content;
+                    if rt.scroll_table(mx - content.x, my - content.y, -dx) {
                         needs_redraw = true;
+                        table_scrolled = true;
                     }
                 }
-                _ => {}
-            },
-            Event::MainEventsCleared => {
-                // Tick cursor blink at ~60fps
-                let now = Instant::now();
-                if now.duration_since(last_tick) >= Duration::from_millis(16) {
-                    let old_visible = browser.cursor_visible;
-                    browser.tick_cursor();
-                    if browser.address_focused && browser.cursor_visible != old_visible {
-                        needs_redraw = true;
+                // A sideways trackpad swipe no table took goes back (right) or forward (left)
+                if precise && !table_scrolled && dx.abs() > scroll_delta.abs() {
+                    if let Some(forward) = pointer.swipe(dx) {
+                        if forward { browser.go_forward() } else { browser.go_back() }
+                        return true;
                     }
-                    if let Some(ref mut rt) = browser.runtime {
-                        rt.renderer.tick();
+                }
+                if scroll_delta == 0 {
+                    return needs_redraw;
+                }
+                let mut new_scroll = browser.scroll_y - scroll_delta;
+                if new_scroll < 0 {
+                    if browser.pull(-new_scroll) {
+                        needs_redraw = true;
                     }
-                    last_tick = now;
+                    new_scroll = 0;
                 }
-                if needs_redraw || browser.runtime.as_ref().map(|r| r.state.is_dirty()).unwrap_or(false) {
-                    window.request_redraw();
+                if new_scroll > browser.max_scroll_y {
+                    new_scroll = browser.max_scroll_y;
+                }
+                if new_scroll != browser.scroll_y {
+                    browser.scroll_y = new_scroll;
+                    needs_redraw = true;
                 }
             }
-            Event::RedrawRequested(_) => {
-                render_browser(&mut fb, &mut browser);
+        }
+        InputEvent::ContextMenu => {
+            let content = browser.layout.content;
+            let point = pointer.last_mouse_pos.filter(|&(x, y)| content.contains(x, y));
+            if let (Some((x, y)), Some(rt)) = (point, browser.runtime.as_ref()) {
+                if let Some((misspelling, suggestions)) = rt.misspelling_at(x - content.x, y - content.y, SPELLING_SUGGESTIONS) {
+                    browser.spelling_menu = Some(chrome::SpellingMenu { misspelling, suggestions, x, y, hovered: None });
+                    needs_redraw = true;
+                }
+            }
+        }
+        InputEvent::Back => {
+            browser.go_back();
+            needs_redraw = true;
+        }
+        InputEvent::Forward => {
+            browser.go_forward();
+            needs_redraw = true;
+        }
+        InputEvent::Key { key } => needs_redraw = handle_key_input(browser, key, pointer.modifiers),
+        InputEvent::Pad { button } => needs_redraw = handle_pad(browser, button, pointer, window, width),
+        InputEvent::Char { ch } => needs_redraw = handle_received_char(browser, ch),
+        InputEvent::ImePreedit { ref text, cursor } => {
+            if browser.address_focused {
+                browser.address_preedit = text.clone();
+                browser.address_preedit_cursor = cursor;
+                needs_redraw = true;
+            } else if let Some(ref mut rt) = browser.runtime {
+                needs_redraw = rt.set_preedit(text, cursor);
+            }
+        }
+        InputEvent::ImeCommit { ref text } => {
+            browser.address_preedit.clear();
+            if let Some(ref mut rt) = browser.runtime {
+                rt.renderer.preedit.clear();
+            }
+            for ch in text.chars() {
+                needs_redraw |= handle_received_char(browser, ch);
+            }
+        }
+    }
+    needs_redraw
+}
 
-                // Present framebuffer
-                let mut buffer = surface.buffer_mut().expect("buffer mut");
-                debug_assert_eq!(buffer.len(), fb.pixels.len());
-                buffer.copy_from_slice(&fb.pixels);
-                buffer.present().expect("present");
-                needs_redraw = false;
+/// While "Discard changes?" is open only its buttons, Escape and Return (which
+/// both stay on the page) do anything
+fn handle_leave_prompt_input(browser: &mut Browser, input: &InputEvent, pointer: &mut PointerState, window: &Window) -> bool {
+    match *input {
+        InputEvent::Modifiers { state } => pointer.modifiers = state,
+        InputEvent::CursorMoved { x, y } => {
+            pointer.last_mouse_pos = Some((x, y));
+            let hand = chrome::leave_dialog(browser).is_some_and(|dialog| dialog.button_at(x, y).is_some());
+            window.set_cursor_icon(if hand { CursorIcon::Hand } else { CursorIcon::Default });
+        }
+        InputEvent::CursorLeft => pointer.last_mouse_pos = None,
+        InputEvent::MouseDown => {
+            let answer = pointer.last_mouse_pos.zip(chrome::leave_dialog(browser)).and_then(|((x, y), dialog)| dialog.button_at(x, y));
+            if let Some(discard) = answer {
+                browser.answer_leave_prompt(discard);
+                return true;
             }
-            _ => {}
         }
-    });
+        InputEvent::Key { key: VirtualKeyCode::Escape | VirtualKeyCode::Return } => {
+            browser.answer_leave_prompt(false);
+            return true;
+        }
+        _ => {}
+    }
+    false
+}
+
+/// While the share dialog is open, Done, a click outside it, Escape or Return
+/// close it and nothing else does anything
+fn handle_share_input(browser: &mut Browser, input: &InputEvent, pointer: &mut PointerState, window: &Window) -> bool {
+    let Some(dialog) = chrome::share_dialog(browser) else {
+        return false;
+    };
+    match *input {
+        InputEvent::Modifiers { state } => pointer.modifiers = state,
+        InputEvent::CursorMoved { x, y } => {
+            pointer.last_mouse_pos = Some((x, y));
+            window.set_cursor_icon(if dialog.done.contains(x, y) { CursorIcon::Hand } else { CursorIcon::Default });
+        }
+        InputEvent::CursorLeft => pointer.last_mouse_pos = None,
+        InputEvent::MouseDown if pointer.last_mouse_pos.is_some_and(|(x, y)| dialog.done.contains(x, y) || !dialog.panel.contains(x, y)) => {
+            window.set_cursor_icon(CursorIcon::Default);
+            browser.share = None;
+            return true;
+        }
+        InputEvent::Key { key: VirtualKeyCode::Escape | VirtualKeyCode::Return } => {
+            browser.share = None;
+            return true;
+        }
+        _ => {}
+    }
+    false
+}
+
+/// While selecting a region the page takes no input: a drag over it picks the
+/// region, released with Shift to save it, and Escape gives up
+fn handle_region_input(browser: &mut Browser, input: &InputEvent, pointer: &mut PointerState, window: &Window) -> bool {
+    let content = browser.layout.content;
+    let Some(region) = browser.region.as_mut() else {
+        return false;
+    };
+    match *input {
+        InputEvent::Modifiers { state } => pointer.modifiers = state,
+        InputEvent::CursorMoved { x, y } => {
+            pointer.last_mouse_pos = Some((x, y));
+            window.set_cursor_icon(if content.contains(x, y) { CursorIcon::Crosshair } else { CursorIcon::Default });
+            region.cursor = (x, y);
+            return region.anchor.is_some();
+        }
+        InputEvent::CursorLeft => pointer.last_mouse_pos = None,
+        InputEvent::MouseDown => {
+            if let Some((x, y)) = pointer.last_mouse_pos.filter(|&(x, y)| content.contains(x, y)) {
+                region.anchor = Some((x, y));
+                region.cursor = (x, y);
+                return true;
+            }
+        }
+        InputEvent::MouseUp if region.anchor.is_some() => {
+            window.set_cursor_icon(CursorIcon::Default);
+            browser.capture_region(pointer.modifiers.shift());
+            return true;
+        }
+        InputEvent::Key { key: VirtualKeyCode::Escape } => {
+            window.set_cursor_icon(CursorIcon::Default);
+            browser.region = None;
+            return true;
+        }
+        _ => {}
+    }
+    false
+}
+
+/// While the spelling menu is open a click picks a correction or closes it;
+/// arrows and Return pick one from the keyboard, and Escape closes it
+fn handle_spelling_menu_input(browser: &mut Browser, input: &InputEvent, pointer: &mut PointerState, window: &Window) -> bool {
+    let rows = chrome::spelling_menu_rows(browser).map(|(_, rows)| rows).unwrap_or_default();
+    let Some(menu) = browser.spelling_menu.as_mut() else {
+        return false;
+    };
+    let choice = match *input {
+        InputEvent::Modifiers { state } => {
+            pointer.modifiers = state;
+            return false;
+        }
+        InputEvent::CursorMoved { x, y } => {
+            pointer.last_mouse_pos = Some((x, y));
+            let hovered = rows.iter().position(|row| row.contains(x, y));
+            window.set_cursor_icon(if hovered.is_some() { CursorIcon::Hand } else { CursorIcon::Default });
+            let changed = hovered != menu.hovered;
+            menu.hovered = hovered;
+            return changed;
+        }
+        InputEvent::Key { key: VirtualKeyCode::Up | VirtualKeyCode::Down } if !rows.is_empty() => {
+            let down = matches!(*input, InputEvent::Key { key: VirtualKeyCode::Down });
+            let last = rows.len() - 1;
+            menu.hovered = Some(match (menu.hovered, down) {
+                (None, true) => 0,
+                (None, false) => last,
+                (Some(i), true) => (i + 1).min(last),
+                (Some(i), false) => i.saturating_sub(1),
+            });
+            return true;
+        }
+        InputEvent::Key { key: VirtualKeyCode::Return } => menu.hovered,
+        InputEvent::MouseDown => pointer.last_mouse_pos.and_then(|(x, y)| rows.iter().position(|row| row.contains(x, y))),
+        InputEvent::Key { key: VirtualKeyCode::Escape } | InputEvent::ContextMenu => None,
+        _ => return false,
+    };
+    let menu = browser.spelling_menu.take();
+    window.set_cursor_icon(CursorIcon::Default);
+    if let (Some(menu), Some(i), Some(rt)) = (menu, choice, browser.runtime.as_mut()) {
+        rt.correct_spelling(&menu.misspelling, &menu.suggestions[i]);
+    }
+    true
+}
+
+/// Gamepad: the D-pad moves the page's highlight, A clicks it and B goes back
+fn handle_pad(browser: &mut Browser, button: PadButton, pointer: &mut PointerState, window: &Window, width: usize) -> bool {
+    let direction = match button {
+        PadButton::Up => Direction::Up,
+        PadButton::Down => Direction::Down,
+        PadButton::Left => Direction::Left,
+        PadButton::Right => Direction::Right,
+        PadButton::Activate => {
+            let Some((x, y)) = browser.runtime.as_ref().and_then(|rt| rt.spatial_focus_point()) else {
+                return false;
+            };
+            let content = browser.layout.content;
+            pointer.last_mouse_pos = Some((x + content.x, y + content.y));
+            let needs_redraw = handle_input(browser, &InputEvent::MouseDown, pointer, window, width);
+            handle_input(browser, &InputEvent::MouseUp, pointer, window, width);
+            return needs_redraw;
+        }
+        PadButton::Back => {
+            browser.go_back();
+            return true;
+        }
+    };
+    browser.runtime.as_mut().is_some_and(|rt| rt.move_spatial_focus(direction))
+}
+
+/// Bottom of the caret of the focused text field in window coordinates, if any
+fn ime_position(browser: &Browser) -> Option<(i32, i32)> {
+    if browser.address_focused {
+        let (x, top, height) = chrome::address_caret(browser);
+        return Some((x, top + height as i32));
+    }
+    let content = browser.layout.content;
+    let (x, top, height) = browser.runtime.as_ref()?.renderer.ime_cursor_area?;
+    Some((content.x + x, content.y + top + height as i32))
 }
 
 fn render_browser(fb: &mut FrameBuffer, browser: &mut Browser) {
     fb.clear(0xFFFFFF);
-    draw_chrome(fb, browser);
+    browser.relayout(fb.width, fb.height);
+    chrome::draw(fb, browser);
 
+    let content = browser.layout.content;
     if let Some(ref mut rt) = browser.runtime {
-        let viewport_height = fb.height.saturating_sub(CHROME_HEIGHT).max(1);
-        let mut content_fb = FrameBuffer::new(fb.width, viewport_height);
+        let viewport_height = content.height as usize;
+        let viewport_width = content.width as usize;
+        let mut content_fb = FrameBuffer::new(viewport_width, viewport_height);
 
-        let full_height = rt.content_height(fb.width as u32) as i32;
+        let full_height = rt.content_height(viewport_width as u32) as i32;
+        browser.viewport_height = viewport_height as i32;
         browser.max_scroll_y = (full_height - viewport_height as i32).max(0);
         if browser.scroll_y > browser.max_scroll_y {
             browser.scroll_y = browser.max_scroll_y;
@@ -555,15 +2041,32 @@ fn render_browser(fb: &mut FrameBuffer, browser: &mut Browser) {
         }
 
         rt.render(&mut content_fb, browser.scroll_y);
+        // An action's `scroll_to` brings its node to the top of the viewport
+        if let Some(y) = rt.take_scroll_request() {
+            browser.scroll_y = y.clamp(0, browser.max_scroll_y);
+            rt.render(&mut content_fb, browser.scroll_y);
+        }
+        // Reader mode leaves margins around the page column
+        if viewport_width < fb.width {
+            fb.fill_rect(0, content.y, fb.width as u32, viewport_height as u32, READER_MARGIN_COLOR);
+        }
         for y in 0..viewport_height {
-            let dst_start = (y + CHROME_HEIGHT) * fb.width;
-            let src_start = y * fb.width;
-            fb.pixels[dst_start..dst_start + fb.width]
-                .copy_from_slice(&content_fb.pixels[src_start..src_start + fb.width]);
+            let dst_start = (y + content.y as usize) * fb.width + content.x as usize;
+            let src_start = y * viewport_width;
+            fb.pixels[dst_start..dst_start + viewport_width]
+                .copy_from_slice(&content_fb.pixels[src_start..src_start + viewport_width]);
         }
 
+        draw_refresh_indicator(fb, browser);
+
         let effective_full_height = full_height.max(viewport_height as i32);
-        draw_scrollbar(fb, viewport_height, effective_full_height, browser.scroll_y, browser.max_scroll_y);
+        draw_scrollbar(fb, content, effective_full_height, browser.scroll_y, browser.max_scroll_y);
+        draw_notices(fb, browser);
+        chrome::draw_security_panel(fb, browser);
+        chrome::draw_leave_dialog(fb, browser);
+        chrome::draw_share_dialog(fb, browser);
+        chrome::draw_region_select(fb, browser);
+        chrome::draw_spelling_menu(fb, browser);
     } else if let Some(ref err) = browser.last_error {
         draw_error(fb, err);
     } else {
@@ -571,8 +2074,135 @@ fn render_browser(fb: &mut FrameBuffer, browser: &mut Browser) {
     }
 }
 
-fn draw_scrollbar(fb: &mut FrameBuffer, viewport_height: usize, full_height: i32, scroll_y: i32, max_scroll_y: i32) {
-    if full_height <= viewport_height as i32 {
+/// Repaint just the animated image regions of the content area
+fn repaint_animations(fb: &mut FrameBuffer, browser: &mut Browser) -> bool {
+    // Images stand still behind the dimmed "Discard changes?" and share dialogs
+    let Some(rt) = browser.runtime.as_mut().filter(|_| browser.leave_prompt.is_none() && browser.share.is_none()) else {
+        return false;
+    };
+    let content = browser.layout.content;
+    if !rt.renderer.repaint_animations(fb, content.x, content.y) {
+        return false;
+    }
+    // Images may sit under the scrollbar or notices and must not paint over them
+    let full_height = browser.max_scroll_y + content.height;
+    draw_scrollbar(fb, content, full_height, browser.scroll_y, browser.max_scroll_y);
+    draw_notices(fb, browser);
+    chrome::draw_security_panel(fb, browser);
+    chrome::draw_spelling_menu(fb, browser);
+    true
+}
+
+/// Notice cards stacked upward from the bottom-right corner, as (index, x, y)
+fn notice_rects(browser: &Browser, width: usize) -> Vec<(usize, i32, i32)> {
+    let x = width as i32 - NOTICE_WIDTH - 16;
+    let bottom = browser.layout.content.bottom() - 16;
+    (0..browser.notices.len()).rev()
+        .take(MAX_VISIBLE_NOTICES)
+        .enumerate()
+        .map(|(slot, i)| (i, x, bottom - (slot as i32 + 1) * (NOTICE_HEIGHT + 8)))
+        .collect()
+}
+
+fn notice_at(browser: &Browser, width: usize, x: i32, y: i32) -> Option<usize> {
+    notice_rects(browser, width).into_iter()
+        .find(|&(_, nx, ny)| (nx..nx + NOTICE_WIDTH).contains(&x) && (ny..ny + NOTICE_HEIGHT).contains(&y))
+        .map(|(i, _, _)| i)
+}
+
+fn draw_notices(fb: &mut FrameBuffer, browser: &Browser) {
+    for (i, x, y) in notice_rects(browser, fb.width) {
+        let (notice, _) = &browser.notices[i];
+        let (top, bottom) = browser.chrome.shades(0x3A3F47, 0x30343B);
+        fb.fill_rounded_rect_vertical_gradient(x, y, NOTICE_WIDTH as u32, NOTICE_HEIGHT as u32, 8, top, bottom);
+        let max_width = (NOTICE_WIDTH - 24) as u32;
+        draw_text_fb(fb, &fit_text(&notice.title, 14.0, max_width), x + 12, y + 22, 14.0, 0xFFFFFF);
+        draw_text_fb(fb, &fit_text(&notice.body, 12.0, max_width), x + 12, y + 42, 12.0, 0xC8CDD4);
+    }
+}
+
+/// `text` cut with an ellipsis to fit `max_width` pixels
+fn fit_text(text: &str, size: f32, max_width: u32) -> String {
+    renderer::ellipsize(text, max_width, |t| measure_text_width(t, size))
+}
+
+/// Rotation of the refresh spinner, one turn per second
+fn spinner_angle(started: Instant) -> f32 {
+    started.elapsed().as_secs_f32() * std::f32::consts::TAU
+}
+
+/// Pull indicator while over-scrolling, spinner while `on_refresh` runs
+fn draw_refresh_indicator(fb: &mut FrameBuffer, browser: &Browser) {
+    let (center_y, progress, angle) = match browser.refresh_started {
+        Some(started) => (24.0, 1.0, spinner_angle(started)),
+        None if browser.pull_distance > 0 => {
+            let progress = browser.pull_distance as f32 / PULL_THRESHOLD as f32;
+            (progress * 40.0 - 16.0, progress, 0.0)
+        }
+        None => return,
+    };
+    let content = browser.layout.content;
+    let clip = (content.x, content.y, content.x + content.width, content.bottom());
+    let cx = fb.width as f32 / 2.0;
+    let cy = content.y as f32 + center_y;
+    fb.fill_circle_aa(cx, cy + 1.0, 17.0, 0xD0D0D0, clip);
+    fb.fill_circle_aa(cx, cy, 16.0, 0xFFFFFF, clip);
+    let color = if progress >= 1.0 { 0x4285F4 } else { 0x888888 };
+    draw_refresh_arc(fb, cx, cy, 8.0, angle, progress.min(1.0), color, clip);
+}
+
+/// Circular refresh arrow: `progress` of a 300° arc starting at `start`,
+/// with an arrowhead once complete
+#[allow(clippy::too_many_arguments)]
+fn draw_refresh_arc(fb: &mut FrameBuffer, cx: f32, cy: f32, radius: f32, start: f32, progress: f32, color: u32, clip: (i32, i32, i32, i32)) {
+    use std::f32::consts::TAU;
+    let sweep = progress * TAU * 300.0 / 360.0;
+    let half_stroke = 1.0;
+    let extent = radius + 4.0;
+    let x0 = ((cx - extent).floor() as i32).max(clip.0).max(0);
+    let x1 = ((cx + extent).ceil() as i32).min(clip.2).min(fb.width as i32);
+    let y0 = ((cy - extent).floor() as i32).max(clip.1).max(0);
+    let y1 = ((cy + extent).ceil() as i32).min(clip.3).min(fb.height as i32);
+
+    // Arrowhead triangle at the end of the arc, pointing along it
+    let end = start + sweep;
+    let (tip_x, tip_y) = (cx + radius * end.cos() - 3.5 * end.sin(), cy + radius * end.sin() + 3.5 * end.cos());
+    let (base_in, base_out) = (radius - 3.5, radius + 3.5);
+    let arrow = [
+        (tip_x, tip_y),
+        (cx + base_in * end.cos(), cy + base_in * end.sin()),
+        (cx + base_out * end.cos(), cy + base_out * end.sin()),
+    ];
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let (px, py) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let d = (px * px + py * py).sqrt();
+            let mut coverage = 0.0f32;
+            let angle = (py.atan2(px) - start).rem_euclid(TAU);
+            if angle <= sweep {
+                coverage = (half_stroke - (d - radius).abs() + 0.5).clamp(0.0, 1.0);
+            }
+            if progress >= 1.0 && in_triangle(x as f32 + 0.5, y as f32 + 0.5, &arrow) {
+                coverage = 1.0;
+            }
+            if coverage > 0.0 {
+                fb.blend_pixel(x as usize, y as usize, color, (coverage * 255.0) as u8);
+            }
+        }
+    }
+}
+
+fn in_triangle(x: f32, y: f32, t: &[(f32, f32); 3]) -> bool {
+    let side = |(ax, ay): (f32, f32), (bx, by): (f32, f32)| (bx - ax) * (y - ay) - (by - ay) * (x - ax);
+    let (a, b, c) = (side(t[0], t[1]), side(t[1], t[2]), side(t[2], t[0]));
+    (a >= 0.0 && b >= 0.0 && c >= 0.0) || (a <= 0.0 && b <= 0.0 && c <= 0.0)
+}
+
+/// Scrollbar along the right edge of the page area
+fn draw_scrollbar(fb: &mut FrameBuffer, content: chrome::Rect, full_height: i32, scroll_y: i32, max_scroll_y: i32) {
+    let viewport_height = content.height;
+    if full_height <= viewport_height {
         return;
     }
 
@@ -582,7 +2212,7 @@ fn draw_scrollbar(fb: &mut FrameBuffer, viewport_height: usize, full_height: i32
         return;
     }
 
-    let track_y = CHROME_HEIGHT as i32;
+    let track_y = content.y;
     let track_height = viewport_height as u32;
 
     fb.fill_rect(track_x, track_y, track_width, track_height, 0xF0F0F0);
@@ -599,57 +2229,6 @@ fn draw_scrollbar(fb: &mut FrameBuffer, viewport_height: usize, full_height: i32
     fb.fill_rect(track_x, thumb_y, track_width, thumb_height, 0xC0C0C0);
 }
 
-fn draw_chrome(fb: &mut FrameBuffer, browser: &Browser) {
-    let width = fb.width as u32;
-    fb.fill_rounded_rect_vertical_gradient(0, 0, width, CHROME_HEIGHT as u32, 0, 0xFBFCFE, 0xF3F5F8);
-    fb.fill_rect(0, CHROME_HEIGHT as i32 - 1, width, 1, 0xDDDDDD);
-
-    let back_color = if browser.can_go_back() { 0x333333 } else { 0x999999 };
-    fb.fill_rounded_rect_vertical_gradient(10, 12, 28, 28, 6, 0xEDEFF4, 0xD8DDE6);
-    {
-        let size = 16.0;
-        let base = baseline_for_box(12, 28, size);
-        let w = measure_text_width("‹", size);
-        let x = 12 + (28 - w) as i32 / 2;
-        draw_text_fb(fb, "‹", x, base, size, back_color);
-    }
-
-    let fwd_color = if browser.can_go_forward() { 0x333333 } else { 0x999999 };
-    fb.fill_rounded_rect_vertical_gradient(45, 12, 28, 28, 6, 0xEDEFF4, 0xD8DDE6);
-    {
-        let size = 16.0;
-        let base = baseline_for_box(12, 28, size);
-        let w = measure_text_width("›", size);
-        let x = 47 + (28 - w) as i32 / 2;
-        draw_text_fb(fb, "›", x, base, size, fwd_color);
-    }
-
-    let addr_x = 80 + 12;
-    let addr_width = (width as i32 - addr_x - 20).max(200) as u32;
-    let border_color = if browser.address_focused { 0x4285F4 } else { 0xCCCCCC };
-    fb.fill_rounded_rect_vertical_gradient(addr_x, 10, addr_width, 32, 6, 0xFFFFFF, 0xF4F6F8);
-    fb.draw_rect_outline(addr_x, 10, addr_width, 32, border_color, 1);
-
-    let text_size = 14.0;
-    let text_y = baseline_for_box(10, 32, text_size);
-    let text_x = addr_x + 10;
-
-    if browser.address_text.is_empty() && !browser.address_focused {
-        draw_text_fb(fb, "Enter path (examples/home.prism)", text_x, text_y, text_size, 0x999999);
-    } else {
-        draw_text_fb(fb, &browser.address_text, text_x, text_y, text_size, 0x333333);
-    }
-
-    if browser.address_focused && browser.cursor_visible {
-        let text_before_cursor: String = browser.address_text.chars().take(browser.address_cursor).collect();
-        let cursor_x = text_x + measure_text_width(&text_before_cursor, text_size) as i32;
-        let (ascent, descent, _) = line_metrics(text_size);
-        let cursor_height = (ascent + descent) as u32;
-        let cursor_top = text_y - ascent;
-        fb.fill_rect(cursor_x, cursor_top, 2, cursor_height, 0x333333);
-    }
-}
-
 fn measure_text_width(text: &str, size: f32) -> u32 {
     if text.is_empty() {
         return 0;
@@ -657,7 +2236,7 @@ fn measure_text_width(text: &str, size: f32) -> u32 {
     let font = ui_font();
     let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
     layout.reset(&LayoutSettings::default());
-    layout.append(&[font], &TextStyle::new(text, size, 0));
+    emoji::append_text(&mut layout, font, text, size);
     let glyphs = layout.glyphs();
     if glyphs.is_empty() {
         return 0;
@@ -689,20 +2268,47 @@ fn draw_error(fb: &mut FrameBuffer, message: &str) {
     let message_size = 14.0;
 
     let title_width = measure_text_width(title, title_size) as i32;
-    let msg = if message.len() > 160 {
-        let mut s = message.to_string();
-        s.truncate(160);
-        s
-    } else {
-        message.to_string()
-    };
-    let msg_width = measure_text_width(&msg, message_size) as i32;
-
     let title_base = baseline_for_box(cy - 30, 24, title_size);
-    let msg_base = baseline_for_box(cy + 10, 18, message_size);
-
     draw_text_fb(fb, title, cx - title_width / 2, title_base, title_size, 0xCC3333);
-    draw_text_fb(fb, &msg, cx - msg_width / 2, msg_base, message_size, 0x666666);
+
+    // A document with several parse errors lists each on its own line
+    let lines: Vec<String> = message.lines().map(|line| line.replace('\t', "    ")).collect();
+    let shown = lines.len().min(MAX_ERROR_LINES);
+    let mut texts: Vec<String> = lines[..shown].iter().map(|line| line.chars().take(160).collect()).collect();
+    if lines.len() > MAX_ERROR_LINES {
+        texts[shown - 1] = format!("and {} more lines", lines.len() - shown + 1);
+    }
+    // Source frames (` 7 | code` over `   |   ^`) need a shared left edge for
+    // their carets, so a message with one is drawn left-aligned
+    let framed = texts.iter().any(|text| frame_gutter(text).is_some());
+    let widest = texts.iter().map(|text| measure_text_width(text, message_size) as i32).max().unwrap_or(0);
+    let left = (cx - widest / 2).max(16);
+    for (i, text) in texts.iter().enumerate() {
+        let msg_base = baseline_for_box(cy + 10 + i as i32 * 20, 18, message_size);
+        let caret = frame_gutter(text).filter(|&gutter| text[gutter..].trim_start() == "^");
+        match (caret, i.checked_sub(1).map(|j| &texts[j])) {
+            // The caret goes under the char it points at in the proportional UI font
+            (Some(gutter), Some(source)) => {
+                let column = text[gutter..].len() - 1;
+                let code_start = frame_gutter(source).unwrap_or(0);
+                let prefix: String = source[code_start..].chars().take(column).collect();
+                let x = left + measure_text_width(&source[..code_start], message_size) as i32 + measure_text_width(&prefix, message_size) as i32;
+                draw_text_fb(fb, &text[..gutter], left, msg_base, message_size, 0x999999);
+                draw_text_fb(fb, "^", x, msg_base, message_size, 0xCC3333);
+            }
+            _ if framed => draw_text_fb(fb, text, left, msg_base, message_size, if frame_gutter(text).is_some() { 0x333333 } else { 0x666666 }),
+            _ => {
+                let msg_width = measure_text_width(text, message_size) as i32;
+                draw_text_fb(fb, text, cx - msg_width / 2, msg_base, message_size, 0x666666);
+            }
+        }
+    }
+}
+
+/// Length of the ` 7 | ` gutter if `line` is part of a source frame
+fn frame_gutter(line: &str) -> Option<usize> {
+    let bar = line.find(" | ")?;
+    line[..bar].trim().chars().all(|c| c.is_ascii_digit()).then_some(bar + 3)
 }
 
 fn baseline_for_box(top: i32, height: i32, size: f32) -> i32 {
@@ -734,7 +2340,7 @@ fn draw_text_fb(fb: &mut FrameBuffer, text: &str, x: i32, baseline_y: i32, size:
         y: 0.0,
         ..LayoutSettings::default()
     });
-    layout.append(&[font], &TextStyle::new(text, size, 0));
+    emoji::append_text(&mut layout, font, text, size);
     let baseline_in_layout = layout
         .lines()
         .and_then(|lines| lines.first().map(|l| l.baseline_y.round() as i32))
@@ -742,7 +2348,7 @@ fn draw_text_fb(fb: &mut FrameBuffer, text: &str, x: i32, baseline_y: i32, size:
     let dy = baseline_y - baseline_in_layout;
 
     for glyph in layout.glyphs() {
-        let (metrics, bitmap) = font.rasterize_config(glyph.key);
+        let (metrics, bitmap) = emoji::font_for(font, glyph.font_index).rasterize_config(glyph.key);
         let gx = glyph.x.round() as i32;
         let gy = glyph.y.round() as i32 + dy;
         let gw = metrics.width as i32;
@@ -761,118 +2367,52 @@ fn draw_text_fb(fb: &mut FrameBuffer, text: &str, x: i32, baseline_y: i32, size:
                 }
                 let idx = dy as usize * fb.width + dx as usize;
                 let dst = fb.pixels[idx];
-                fb.pixels[idx] = alpha_blend(dst, color, alpha);
+                fb.pixels[idx] = color::blend(dst, color, alpha);
             }
         }
     }
 }
 
-fn alpha_blend(dst: u32, src: u32, alpha: u8) -> u32 {
-    let a = alpha as f32 / 255.0;
-    let dr = ((dst >> 16) & 0xFF) as f32;
-    let dg = ((dst >> 8) & 0xFF) as f32;
-    let db = (dst & 0xFF) as f32;
-
-    let sr = ((src >> 16) & 0xFF) as f32;
-    let sg = ((src >> 8) & 0xFF) as f32;
-    let sb = (src & 0xFF) as f32;
-
-    let r = (sr * a + dr * (1.0 - a)) as u32;
-    let g = (sg * a + dg * (1.0 - a)) as u32;
-    let b = (sb * a + db * (1.0 - a)) as u32;
-
-    (r << 16) | (g << 8) | b
-}
-
-fn handle_chrome_click(browser: &mut Browser, x: i32, _y: i32, _width: usize) {
-    if (10..38).contains(&x) {
-        browser.go_back();
-        return;
-    }
-    if (45..73).contains(&x) {
-        browser.go_forward();
-        return;
-    }
-    let home_x = 80;
-    let home_width = 48i32;
-    if x >= home_x && x < home_x + home_width {
-        let home = browser.base_dir.join("examples").join("home.prism");
-        if home.exists() {
-            browser.navigate(&home.to_string_lossy());
-        }
-        return;
-    }
-    let addr_x = home_x + home_width + 12;
-    let addr_width = (_width as i32 - addr_x - 20).max(200) as u32;
-    if x >= addr_x && x < addr_x + addr_width as i32 {
-        browser.address_focused = true;
-        browser.reset_cursor_blink();
-
-        let text_size = 14.0;
-        let text_x = addr_x + 10;
-        let rel_x = (x - text_x).max(0) as u32;
-
-        let mut cursor = 0usize;
-        let mut accumulated = String::new();
-        let mut prev_width = 0u32;
-        for (i, ch) in browser.address_text.chars().enumerate() {
-            accumulated.push(ch);
-            let w = measure_text_width(&accumulated, text_size);
-            let mid = (prev_width + w) / 2;
-            if rel_x < mid {
-                cursor = i;
-                break;
-            }
-            prev_width = w;
-            cursor = i + 1;
-        }
-
-        browser.address_cursor = cursor;
+/// Start a second browser process in private mode
+fn open_private_window() {
+    let started = std::env::current_exe().and_then(|exe| std::process::Command::new(exe).arg("--private").spawn());
+    if let Err(e) = started {
+        eprintln!("[PRISM] Failed to open a private window: {}", e);
     }
 }
 
-fn handle_key_input(browser: &mut Browser, input: &KeyboardInput, modifiers: ModifiersState) -> bool {
-    let key = match input.virtual_keycode {
-        Some(k) => k,
-        None => return false,
-    };
-
+fn handle_key_input(browser: &mut Browser, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
     if browser.address_focused {
         match key {
             VirtualKeyCode::Return => {
                 browser.address_focused = false;
-                let path = browser.address_text.clone();
-                browser.navigate(&path);
+                let path = browser.address_text.trim().to_string();
+                browser.navigate_or_defer(&path);
                 return true;
             }
             VirtualKeyCode::Escape => {
                 browser.address_focused = false;
-                browser.address_text = browser.current_path.clone();
-                browser.address_cursor = browser.address_text.chars().count();
-                return true;
-            }
-            VirtualKeyCode::Left => {
-                browser.move_cursor_left();
+                browser.set_address(&browser.current_path.clone());
                 return true;
             }
-            VirtualKeyCode::Right => {
-                browser.move_cursor_right();
+            VirtualKeyCode::Left | VirtualKeyCode::Right => {
+                browser.step_address_cursor(key == VirtualKeyCode::Right, modifiers.ctrl(), modifiers.shift());
                 return true;
             }
             VirtualKeyCode::Home => {
-                browser.move_cursor_home();
+                browser.move_address_cursor(0, modifiers.shift());
                 return true;
             }
             VirtualKeyCode::End => {
-                browser.move_cursor_end();
+                browser.move_address_cursor(usize::MAX, modifiers.shift());
                 return true;
             }
-            VirtualKeyCode::Back => {
-                browser.delete_char_before();
+            VirtualKeyCode::Back | VirtualKeyCode::Delete => {
+                browser.delete_address(key == VirtualKeyCode::Delete, modifiers.ctrl());
                 return true;
             }
-            VirtualKeyCode::Delete => {
-                browser.delete_char_after();
+            VirtualKeyCode::A if modifiers.ctrl() => {
+                browser.select_all_address();
                 return true;
             }
             _ => {}
@@ -882,11 +2422,41 @@ fn handle_key_input(browser: &mut Browser, input: &KeyboardInput, modifiers: Mod
 
     if let Some(ref mut rt) = browser.runtime {
         if rt.focused_input.is_some() {
-            if let VirtualKeyCode::Back = key {
-                rt.handle_backspace();
+            let handled = match key {
+                VirtualKeyCode::Back => {
+                    rt.handle_backspace();
+                    true
+                }
+                VirtualKeyCode::Up => rt.move_suggestion(-1),
+                VirtualKeyCode::Down => rt.move_suggestion(1),
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => rt.accept_suggestion() || rt.commit_tag(),
+                VirtualKeyCode::Escape => rt.dismiss_suggestions(),
+                _ => false,
+            };
+            if handled {
+                return true;
+            }
+        }
+
+        if rt.focused_list.is_some() {
+            let handled = match key {
+                VirtualKeyCode::Up => rt.move_selection(-1),
+                VirtualKeyCode::Down => rt.move_selection(1),
+                VirtualKeyCode::Home => rt.move_selection(i64::MIN),
+                VirtualKeyCode::End => rt.move_selection(i64::MAX),
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => rt.activate_selection(),
+                _ => false,
+            };
+            if handled {
+                scroll_selection_into_view(browser);
                 return true;
             }
         }
+
+        if key == VirtualKeyCode::Tab && rt.focus_next(modifiers.shift()) {
+            rt.renderer.set_focus(rt.focused_input.clone());
+            return true;
+        }
     }
 
     if modifiers.alt() {
@@ -903,16 +2473,66 @@ fn handle_key_input(browser: &mut Browser, input: &KeyboardInput, modifiers: Mod
         }
     }
 
+    // Ctrl+Shift+N opens a private window
+    if key == VirtualKeyCode::N && modifiers.ctrl() && modifiers.shift() {
+        open_private_window();
+        return false;
+    }
+
+    // Ctrl+Shift+C selects a region of the page to copy as an image
+    if key == VirtualKeyCode::C && modifiers.ctrl() && modifiers.shift() && browser.runtime.is_some() {
+        browser.region = Some(chrome::RegionSelect::default());
+        return true;
+    }
+
+    // Shift+Esc opens the task manager, as in other browsers
+    if key == VirtualKeyCode::Escape && modifiers.shift() {
+        browser.navigate(tasks::TASKS_URL);
+        return true;
+    }
+
+    if key == VirtualKeyCode::F5 && browser.can_refresh() {
+        browser.start_refresh();
+        return true;
+    }
+
+    // F7 toggles compact chrome, Shift+F7 moves the toolbar between top and bottom
+    if key == VirtualKeyCode::F7 {
+        if modifiers.shift() {
+            browser.chrome.position = match browser.chrome.position {
+                ToolbarPosition::Top => ToolbarPosition::Bottom,
+                ToolbarPosition::Bottom => ToolbarPosition::Top,
+            };
+        } else {
+            browser.chrome.compact = !browser.chrome.compact;
+        }
+        browser.save_chrome_style();
+        return true;
+    }
+
     if key == VirtualKeyCode::F6 {
         browser.address_focused = true;
-        browser.address_cursor = browser.address_text.chars().count();
-        browser.reset_cursor_blink();
+        browser.select_all_address();
         return true;
     }
 
     false
 }
 
+/// Scroll just enough to show the focused list's selected row
+fn scroll_selection_into_view(browser: &mut Browser) {
+    let Some((top, height)) = browser.runtime.as_ref().and_then(|rt| rt.selected_row_extent()) else {
+        return;
+    };
+    let bottom = top + height as i32;
+    if top < browser.scroll_y {
+        browser.scroll_y = top;
+    } else if bottom > browser.scroll_y + browser.viewport_height {
+        browser.scroll_y = bottom - browser.viewport_height;
+    }
+    browser.scroll_y = browser.scroll_y.clamp(0, browser.max_scroll_y);
+}
+
 fn handle_received_char(browser: &mut Browser, ch: char) -> bool {
     if ch.is_control() {
         return false;
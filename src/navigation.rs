@@ -0,0 +1,62 @@
+//! Navigation events for hosts of the runtime
+//!
+//! A host that loads documents (the browser window, a tab strip, devtools)
+//! reports what its navigations do through [`NavigationObservers`], so the
+//! parts that follow along implement [`NavigationObserver`] instead of polling
+//! the host's fields.
+
+/// Callbacks for one host's navigations; every method defaults to doing nothing
+pub trait NavigationObserver {
+    /// The host is about to load `location`
+    fn will_navigate(&mut self, _location: &str) {}
+    /// `location` loaded and is the page now shown
+    fn did_load(&mut self, _location: &str) {}
+    /// `location` couldn't be loaded; an error page is shown instead
+    fn load_failed(&mut self, _location: &str, _error: &str) {}
+    /// The shown page's title is now `title`
+    fn title_changed(&mut self, _title: &str) {}
+}
+
+/// The observers registered with a host, notified in registration order
+#[derive(Default)]
+pub struct NavigationObservers {
+    observers: Vec<Box<dyn NavigationObserver>>,
+    /// Last title reported, so an unchanged title isn't reported again
+    title: Option<String>,
+}
+
+impl NavigationObservers {
+    pub fn add(&mut self, observer: Box<dyn NavigationObserver>) {
+        self.observers.push(observer);
+    }
+
+    pub fn will_navigate(&mut self, location: &str) {
+        for observer in &mut self.observers {
+            observer.will_navigate(location);
+        }
+    }
+
+    pub fn did_load(&mut self, location: &str) {
+        for observer in &mut self.observers {
+            observer.did_load(location);
+        }
+    }
+
+    pub fn load_failed(&mut self, location: &str, error: &str) {
+        // The next page's title is news even if it matches the one before the error
+        self.title = None;
+        for observer in &mut self.observers {
+            observer.load_failed(location, error);
+        }
+    }
+
+    pub fn title_changed(&mut self, title: &str) {
+        if self.title.as_deref() == Some(title) {
+            return;
+        }
+        self.title = Some(title.to_string());
+        for observer in &mut self.observers {
+            observer.title_changed(title);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Simplification pass run over a document before it is shown
+//!
+//! Expressions over literals only are folded into their value (so an
+//! interpolation without state becomes a plain string), and sub-expressions
+//! of `each`/`list` templates that don't depend on the item are hoisted onto
+//! the list node, which evaluates them once per frame instead of once per
+//! item. Tools that show the document as written (`prism ast`, the linter,
+//! the HTML exporter) work on the unoptimized AST.
+
+use crate::ast::*;
+use crate::state::{is_builtin_function, StateStore};
+use std::mem;
+
+/// Prop of an `each`/`list` node holding its hoisted expressions as an object
+/// literal; the renderer binds each entry by name while the node is drawn
+pub const HOISTED_PROP: &str = "$hoisted";
+
+/// Builtins whose result depends only on their arguments (`int`, `float` and
+/// `format_number` read `env.locale`, `t` the strings table)
+const PURE_FUNCTIONS: &[&str] = &[
+    "abs", "min", "max", "floor", "ceil", "round", "sqrt", "clamp", "lerp", "map_range",
+    "sin", "cos", "atan2", "pi", "distance", "len", "str", "bool", "type",
+    "is_null", "is_list", "is_object", "list", "range", "keys", "values", "json_encode",
+];
+
+/// Simplify the views, routes and computed values of `app`
+pub fn optimize(app: &mut PrismApp) {
+    let mut hoisted = 0;
+    for expr in app.computed.values_mut() {
+        fold(expr);
+    }
+    let views = std::iter::once(&mut app.view).chain(app.routes.values_mut());
+    for view in views {
+        fold_node(view);
+        hoist_node(view, &mut hoisted);
+    }
+}
+
+fn fold_node(node: &mut ViewNode) {
+    for prop in node.props.values_mut() {
+        match prop {
+            PropValue::Expression(expr) => fold(expr),
+            PropValue::EventHandler(handler) => handler.args.iter_mut().for_each(fold),
+            _ => {}
+        }
+    }
+    node.children.iter_mut().for_each(fold_node);
+}
+
+/// Replace `expr` with its value if it only involves literals, after folding
+/// its parts
+fn fold(expr: &mut Expression) {
+    // The transform of a pipe names a function, so only its arguments fold
+    if let Expression::Pipe { value, transform } = expr {
+        fold(value);
+        children_mut(transform).into_iter().for_each(fold);
+        return;
+    }
+    children_mut(expr).into_iter().for_each(fold);
+    match expr {
+        Expression::Literal(_) | Expression::Variable(_) | Expression::Lambda { .. } => {}
+        // A range would become a list literal as long as the range
+        Expression::Range { .. } => {}
+        Expression::Conditional { condition, then_expr, else_expr } => {
+            if let Expression::Literal(value) = &**condition {
+                let taken = if value.as_bool() { then_expr } else { else_expr };
+                let taken = mem::replace(&mut **taken, Expression::Literal(Value::Null));
+                *expr = taken;
+            }
+        }
+        Expression::Interpolation(parts) => merge_literal_parts(parts),
+        _ => {
+            if is_pure(expr) && children(expr).iter().all(|c| matches!(c, Expression::Literal(_))) {
+                *expr = Expression::Literal(StateStore::new().evaluate(expr));
+            }
+        }
+    }
+    if let Expression::Interpolation(parts) = expr {
+        match parts.as_slice() {
+            [] => *expr = Expression::Literal(Value::String(String::new())),
+            [InterpolationPart::Literal(text)] => *expr = Expression::Literal(Value::String(text.clone())),
+            _ => {}
+        }
+    }
+}
+
+/// Turn folded parts into text and join neighbouring text
+fn merge_literal_parts(parts: &mut Vec<InterpolationPart>) {
+    let mut merged: Vec<InterpolationPart> = vec![];
+    for part in parts.drain(..) {
+        let part = match part {
+            InterpolationPart::Expression(expr) => match *expr {
+                Expression::Literal(value) => InterpolationPart::Literal(value.as_string()),
+                expr => InterpolationPart::Expression(Box::new(expr)),
+            },
+            literal => literal,
+        };
+        match (merged.last_mut(), part) {
+            (Some(InterpolationPart::Literal(text)), InterpolationPart::Literal(more)) => text.push_str(&more),
+            (_, part) => merged.push(part),
+        }
+    }
+    *parts = merged;
+}
+
+/// Whether evaluating `expr` itself (not counting its parts) reads nothing
+/// but its parts and has no side effects
+fn is_pure(expr: &Expression) -> bool {
+    match expr {
+        Expression::Call { function, .. } => PURE_FUNCTIONS.contains(&function.as_str()),
+        // Lambdas evaluate to null on their own; they only mean something as arguments
+        Expression::Variable(_) | Expression::Lambda { .. } => false,
+        _ => true,
+    }
+}
+
+/// Hoist the invariant parts of every `each`/`list` template under `node`
+fn hoist_node(node: &mut ViewNode, counter: &mut usize) {
+    if matches!(node.kind, NodeKind::Each | NodeKind::List) {
+        let mut bound = item_names(node);
+        let mut hoisted = vec![];
+        for child in &mut node.children {
+            hoist_template(child, &mut bound, &mut hoisted, counter);
+        }
+        if !hoisted.is_empty() {
+            node.props.insert(HOISTED_PROP.to_string(), PropValue::Expression(Expression::ObjectLiteral(hoisted)));
+        }
+    }
+    for child in &mut node.children {
+        hoist_node(child, counter);
+    }
+}
+
+/// Names a list node binds for its items
+fn item_names(node: &ViewNode) -> Vec<String> {
+    let item = match node.props.get("as") {
+        Some(PropValue::Handler(name)) => name.clone(),
+        _ => "item".to_string(),
+    };
+    vec![item, "index".to_string(), "hovering".to_string()]
+}
+
+/// Move the largest sub-expressions of a template node's props that use none
+/// of the `bound` names into `hoisted`, leaving variables in their place
+fn hoist_template(node: &mut ViewNode, bound: &mut Vec<String>, hoisted: &mut Vec<(String, Expression)>, counter: &mut usize) {
+    // Component instances bind their props, state and injected values, which
+    // the template's expressions can't be told apart from
+    if matches!(node.kind, NodeKind::Component(_)) {
+        return;
+    }
+    let mark = bound.len();
+    for prop in node.props.values_mut() {
+        if let PropValue::Expression(expr) = prop {
+            hoist_expr(expr, bound, hoisted, counter);
+        }
+    }
+    if matches!(node.kind, NodeKind::Each | NodeKind::List) {
+        bound.extend(item_names(node));
+    }
+    for child in &mut node.children {
+        hoist_template(child, bound, hoisted, counter);
+    }
+    bound.truncate(mark);
+}
+
+fn hoist_expr(expr: &mut Expression, bound: &[String], hoisted: &mut Vec<(String, Expression)>, counter: &mut usize) {
+    let trivial = matches!(expr, Expression::Literal(_) | Expression::Variable(_) | Expression::Lambda { .. });
+    if !trivial && invariant(expr, bound) {
+        let name = format!("$hoist{}", counter);
+        *counter += 1;
+        let value = mem::replace(expr, Expression::Variable(name.clone()));
+        hoisted.push((name, value));
+        return;
+    }
+    match expr {
+        // Parts of a lambda body that read its parameters stay in the body
+        Expression::Lambda { params, body } => {
+            let inner: Vec<String> = bound.iter().chain(params.iter()).cloned().collect();
+            hoist_expr(body, &inner, hoisted, counter);
+        }
+        Expression::Pipe { value, transform } => {
+            hoist_expr(value, bound, hoisted, counter);
+            if let Expression::Lambda { .. } = **transform {
+                hoist_expr(transform, bound, hoisted, counter);
+            } else {
+                for arg in children_mut(transform) {
+                    hoist_expr(arg, bound, hoisted, counter);
+                }
+            }
+        }
+        _ => {
+            for child in children_mut(expr) {
+                hoist_expr(child, bound, hoisted, counter);
+            }
+        }
+    }
+}
+
+/// Whether `expr` evaluates the same for every item: it reads none of the
+/// `bound` names and calls nothing impure
+fn invariant(expr: &Expression, bound: &[String]) -> bool {
+    let own = match expr {
+        Expression::Variable(name) => !bound.contains(name),
+        Expression::Call { function, .. } => PURE_FUNCTIONS.contains(&function.as_str()),
+        Expression::Lambda { params, body } => {
+            let outer: Vec<String> = bound.iter().filter(|name| !params.contains(name)).cloned().collect();
+            return invariant(body, &outer);
+        }
+        Expression::Pipe { value, transform } => {
+            let pure = match &**transform {
+                Expression::Variable(name) | Expression::Call { function: name, .. } => {
+                    !is_builtin_function(name) || PURE_FUNCTIONS.contains(&name.as_str())
+                }
+                _ => true,
+            };
+            let operands = match &**transform {
+                Expression::Lambda { .. } => vec![&**transform],
+                other => children(other),
+            };
+            return pure && invariant(value, bound) && operands.into_iter().all(|operand| invariant(operand, bound));
+        }
+        _ => true,
+    };
+    own && children(expr).into_iter().all(|child| invariant(child, bound))
+}
+
+fn children(expr: &Expression) -> Vec<&Expression> {
+    match expr {
+        Expression::Literal(_) | Expression::Variable(_) => vec![],
+        Expression::PropertyAccess { object, property: index } | Expression::IndexAccess { object, index } => vec![object, index],
+        Expression::Binary { left, right, .. } => vec![left, right],
+        Expression::Unary { operand, .. } => vec![operand],
+        Expression::Conditional { condition, then_expr, else_expr } => vec![condition, then_expr, else_expr],
+        Expression::Call { args, .. } | Expression::ListLiteral(args) => args.iter().collect(),
+        Expression::MethodCall { object, args, .. } => std::iter::once(&**object).chain(args).collect(),
+        Expression::ObjectLiteral(fields) => fields.iter().map(|(_, value)| value).collect(),
+        Expression::Interpolation(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                InterpolationPart::Expression(expr) => Some(&**expr),
+                InterpolationPart::Literal(_) => None,
+            })
+            .collect(),
+        Expression::Lambda { body, .. } | Expression::Spread(body) => vec![body],
+        Expression::Range { start, end, .. } => vec![start, end],
+        Expression::Pipe { value, transform: other } | Expression::NullCoalesce { value, default: other } => vec![value, other],
+    }
+}
+
+/// The sub-expressions of an expression, for rewriting in place
+pub fn children_mut(expr: &mut Expression) -> Vec<&mut Expression> {
+    match expr {
+        Expression::Literal(_) | Expression::Variable(_) => vec![],
+        Expression::PropertyAccess { object, property: index } | Expression::IndexAccess { object, index } => vec![object, index],
+        Expression::Binary { left, right, .. } => vec![left, right],
+        Expression::Unary { operand, .. } => vec![operand],
+        Expression::Conditional { condition, then_expr, else_expr } => vec![condition, then_expr, else_expr],
+        Expression::Call { args, .. } | Expression::ListLiteral(args) => args.iter_mut().collect(),
+        Expression::MethodCall { object, args, .. } => std::iter::once(&mut **object).chain(args).collect(),
+        Expression::ObjectLiteral(fields) => fields.iter_mut().map(|(_, value)| value).collect(),
+        Expression::Interpolation(parts) => parts
+            .iter_mut()
+            .filter_map(|part| match part {
+                InterpolationPart::Expression(expr) => Some(&mut **expr),
+                InterpolationPart::Literal(_) => None,
+            })
+            .collect(),
+        Expression::Lambda { body, .. } | Expression::Spread(body) => vec![body],
+        Expression::Range { start, end, .. } => vec![start, end],
+        Expression::Pipe { value, transform: other } | Expression::NullCoalesce { value, default: other } => vec![value, other],
+    }
+}
@@ -0,0 +1,248 @@
+//! Signed Prism packages (.prismpkg)
+//!
+//! A package is a zip archive holding a main document, its assets (images,
+//! fonts, data) and two control files:
+//! - `manifest.json`: `{"name", "main", "public_key", "files": {path: "sha256-…"}}`
+//! - `manifest.sig`: the base64 ed25519 signature of `manifest.json` by `public_key`
+//!
+//! Opening a package verifies the signature and every file hash, and rejects
+//! files the manifest doesn't list, so a package either runs exactly as it
+//! was signed or not at all. Assets are then served from memory; the app
+//! runs without touching the network or the host file system.
+//!
+//! Unless the user lists the publishers they trust, a package name is pinned
+//! to the key it was first opened with (trust on first use), so a later
+//! package of the same name signed by someone else is refused.
+
+use crate::integrity::Integrity;
+use crate::sandbox::{MAX_FILE_SIZE_BYTES, MEMORY_LIMIT_BYTES};
+use crate::vfs::Vfs;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::path::Path;
+
+const MANIFEST: &str = "manifest.json";
+const SIGNATURE: &str = "manifest.sig";
+
+/// Whether a path names a package
+pub fn is_package(path: &str) -> bool {
+    path.ends_with(".prismpkg")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    /// Path of the main document inside the package
+    pub main: String,
+    /// Base64 ed25519 key the manifest is signed with
+    pub public_key: String,
+    /// SHA-256 of every file except the manifest and signature
+    pub files: BTreeMap<String, String>,
+}
+
+/// A verified package, with its files in memory
+#[derive(Debug)]
+pub struct Package {
+    pub manifest: Manifest,
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl Package {
+    /// Read and verify a package; with a non-empty `trusted_keys` it must also
+    /// be signed by one of them
+    pub fn open(path: &Path, trusted_keys: &[String]) -> Result<Package, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Package::from_bytes(&bytes, trusted_keys).map_err(|e| format!("Invalid package {}: {}", path.display(), e))
+    }
+
+    pub fn from_bytes(bytes: &[u8], trusted_keys: &[String]) -> Result<Package, String> {
+        let mut files = read_archive(bytes)?;
+        let manifest_bytes = files.remove(MANIFEST).ok_or("missing manifest.json")?;
+        let signature = files.remove(SIGNATURE).ok_or("missing manifest.sig")?;
+        let manifest: Manifest = serde_json::from_slice(&manifest_bytes).map_err(|e| format!("bad manifest: {}", e))?;
+
+        if !trusted_keys.is_empty() && !trusted_keys.contains(&manifest.public_key) {
+            return Err(format!("signed by an untrusted key ({})", manifest.public_key));
+        }
+        let key = decode_key(&manifest.public_key)?;
+        let signature = STANDARD
+            .decode(String::from_utf8_lossy(&signature).trim())
+            .ok()
+            .and_then(|sig| Signature::from_slice(&sig).ok())
+            .ok_or("malformed signature")?;
+        key.verify(&manifest_bytes, &signature).map_err(|_| "signature does not match the manifest")?;
+
+        for (name, content) in &files {
+            let expected = manifest.files.get(name).ok_or_else(|| format!("{} is not listed in the manifest", name))?;
+            Integrity::parse(expected)?.verify(content).map_err(|e| format!("{}: {}", name, e))?;
+        }
+        if let Some(missing) = manifest.files.keys().find(|name| !files.contains_key(*name)) {
+            return Err(format!("{} is listed but missing", missing));
+        }
+        if !files.contains_key(&manifest.main) {
+            return Err(format!("main document {} is missing", manifest.main));
+        }
+        Ok(Package { manifest, files })
+    }
+
+    /// Source of the main document
+    pub fn main_source(&self) -> String {
+        String::from_utf8_lossy(&self.files[&self.manifest.main]).into_owned()
+    }
+
+    /// A file by its path inside the package
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path.trim_start_matches("./")).map(Vec::as_slice)
+    }
+}
+
+/// Short fingerprint of a publisher key for showing to the user:
+/// the start of its SHA-256 in groups of four hex digits
+pub fn fingerprint(public_key: &str) -> String {
+    let digest = Sha256::digest(public_key.as_bytes());
+    let hex: String = digest[..8].iter().map(|b| format!("{:02X}", b)).collect();
+    hex.as_bytes().chunks(4).map(|group| String::from_utf8_lossy(group).into_owned()).collect::<Vec<_>>().join(" ")
+}
+
+/// Check a package's key against the one its name was first opened with,
+/// pinning it when the name is new; returns whether it was just pinned
+pub fn check_pinned(pins: &mut BTreeMap<String, String>, manifest: &Manifest) -> Result<bool, String> {
+    match pins.get(&manifest.name) {
+        Some(key) if *key == manifest.public_key => Ok(false),
+        Some(key) => Err(format!(
+            "{} is signed by publisher {}, not {} as when it was first opened",
+            manifest.name,
+            fingerprint(&manifest.public_key),
+            fingerprint(key)
+        )),
+        None => {
+            pins.insert(manifest.name.clone(), manifest.public_key.clone());
+            Ok(true)
+        }
+    }
+}
+
+/// Unpack every file, refusing archives that would exceed the memory limit
+fn read_archive(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>, String> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let mut files = HashMap::new();
+    let mut total = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.enclosed_name().ok_or_else(|| format!("unsafe path {}", entry.name()))?.to_string_lossy().replace('\\', "/");
+        let mut content = vec![];
+        // Sizes in the zip header can lie, so the read itself is capped
+        (&mut entry).take(MAX_FILE_SIZE_BYTES as u64 + 1).read_to_end(&mut content).map_err(|e| e.to_string())?;
+        if content.len() > MAX_FILE_SIZE_BYTES {
+            return Err(format!("{} exceeds the maximum file size", name));
+        }
+        total += content.len();
+        if total > MEMORY_LIMIT_BYTES {
+            return Err("package exceeds the memory limit".to_string());
+        }
+        files.insert(name, content);
+    }
+    Ok(files)
+}
+
+fn decode_key(text: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = STANDARD.decode(text).ok().and_then(|b| b.try_into().ok()).ok_or("malformed public key")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| "invalid public key".to_string())
+}
+
+/// Read a base64 signing key, creating one when the file doesn't exist
+fn load_or_create_key(path: &Path) -> Result<SigningKey, String> {
+    if path.exists() {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let bytes: [u8; 32] = STANDARD.decode(text.trim()).ok().and_then(|b| b.try_into().ok())
+            .ok_or_else(|| format!("{} is not a signing key", path.display()))?;
+        return Ok(SigningKey::from_bytes(&bytes));
+    }
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Cannot generate a key: {}", e))?;
+    std::fs::write(path, STANDARD.encode(bytes)).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+    eprintln!("[PRISM] Created signing key {} (keep it secret)", path.display());
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Build a signed package from every file under `dir`
+pub fn build(dir: &Path, main: &str, key: &SigningKey) -> Result<Vec<u8>, String> {
+    let mut files = BTreeMap::new();
+    collect_files(dir, dir, &mut files)?;
+    if !files.contains_key(main) {
+        return Err(format!("{} has no {}", dir.display(), main));
+    }
+    let assets = Vfs::Directory(dir.to_path_buf());
+    let name = crate::parser::parse_with_assets(&String::from_utf8_lossy(&files[main]), &assets)
+        .map_err(|e| format!("{}: {}", main, e))?
+        .name;
+    let manifest = Manifest {
+        name,
+        main: main.to_string(),
+        public_key: STANDARD.encode(key.verifying_key().as_bytes()),
+        files: files.iter().map(|(path, content)| (path.clone(), Integrity::of(content).to_string())).collect(),
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    let signature = STANDARD.encode(key.sign(&manifest_bytes).to_bytes());
+
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    let control = [(MANIFEST, manifest_bytes.as_slice()), (SIGNATURE, signature.as_bytes())];
+    let entries = files.iter().map(|(path, content)| (path.as_str(), content.as_slice()));
+    for (path, content) in control.into_iter().chain(entries) {
+        writer.start_file(path, options).map_err(|e| e.to_string())?;
+        writer.write_all(content).map_err(|e| e.to_string())?;
+    }
+    Ok(writer.finish().map_err(|e| e.to_string())?.into_inner())
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut BTreeMap<String, Vec<u8>>) -> Result<(), String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let relative = path.strip_prefix(root).map_err(|e| e.to_string())?.to_string_lossy().replace('\\', "/");
+            if relative == MANIFEST || relative == SIGNATURE {
+                continue;
+            }
+            let content = std::fs::read(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            files.insert(relative, content);
+        }
+    }
+    Ok(())
+}
+
+/// `prism package <dir> --key <file> [--main main.prism] [-o app.prismpkg]`, returning the exit code
+pub fn run(dir: &str, key_path: &str, main: &str, output: Option<&str>) -> i32 {
+    let dir_path = Path::new(dir);
+    let output = match output {
+        Some(path) => path.to_string(),
+        None => format!("{}.prismpkg", dir.trim_end_matches(['/', '\\'])),
+    };
+    let written = load_or_create_key(Path::new(key_path))
+        .and_then(|key| build(dir_path, main, &key))
+        .and_then(|bytes| {
+            std::fs::write(&output, &bytes).map_err(|e| format!("Cannot write {}: {}", output, e))?;
+            Ok(bytes.len())
+        });
+    match written {
+        Ok(size) => {
+            println!("Packaged {} -> {} ({} bytes)", dir, output, size);
+            0
+        }
+        Err(e) => {
+            eprintln!("[PRISM] {}", e);
+            1
+        }
+    }
+}
@@ -4,23 +4,97 @@
 //! The parser is hand-written for simplicity and zero dependencies.
 
 use crate::ast::*;
-use std::collections::HashMap;
-use std::iter::Peekable;
-use std::str::Chars;
-
+use crate::vfs::Vfs;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+
+/// Hand-written recursive-descent parser over a byte cursor
+///
+/// `pos` is a byte offset that always sits on a char boundary. Almost all of
+/// a document is ASCII, which is read straight from the bytes; other chars
+/// are decoded where they start, and the last one decoded is remembered so
+/// peeking at it again is free.
 pub struct Parser<'a> {
     input: &'a str,
-    chars: Peekable<Chars<'a>>,
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+    col: usize,
+    /// The last non-ASCII char decoded and its byte offset
+    decoded: Cell<Option<(usize, char)>>,
+    /// Nesting of the node being parsed inside the main `view` block
+    view_depth: Option<usize>,
+    /// Last point a document cut short there could be closed off, for
+    /// [`parse_partial`]
+    cut: Option<Cut>,
+    /// Where `@import`ed files are read from
+    assets: Vfs,
+    /// Path of this document within `assets`, and of the documents that
+    /// imported it, to catch import cycles
+    importing: Vec<String>,
+    /// Names given with `@import "..." as name`
+    aliases: Vec<String>,
+    /// Errors found so far, in source order
+    errors: Vec<ParseError>,
+}
+
+/// Where to end a truncated document, and the text that closes it there
+#[derive(Clone, Copy)]
+struct Cut {
+    pos: usize,
+    close: &'static str,
+}
+
+/// A cursor position to backtrack to
+#[derive(Clone, Copy)]
+struct Mark {
     pos: usize,
     line: usize,
     col: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParseError {
     pub message: String,
     pub line: usize,
     pub col: usize,
+    /// The source around the error, filled in when it is reported (kept
+    /// boxed: parse results carry errors around while trying alternatives)
+    pub source: Option<Box<Snippet>>,
+}
+
+/// The line an error is on, or the part of it around the error if it is long
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub text: String,
+    /// Chars of `text` before the error
+    pub caret: usize,
+}
+
+impl ParseError {
+    /// The error followed by its [`frame`](Self::frame):
+    ///
+    /// ```text
+    /// Parse error at 7:9: Expected a value
+    ///  7 |   name: ?
+    ///    |         ^
+    /// ```
+    pub fn diagnostic(&self) -> String {
+        match self.source {
+            Some(_) => format!("{}\n{}", self, self.frame()),
+            None => self.to_string(),
+        }
+    }
+
+    /// The error's line of source, and a caret under its column
+    pub fn frame(&self) -> String {
+        let Some(source) = &self.source else { return String::new() };
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // Tabs stay tabs so the caret lines up however they are shown
+        let indent: String = source.text.chars().take(source.caret).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+        format!(" {} | {}\n {} | {}^", number, source.text, gutter, indent)
+    }
 }
 
 impl std::fmt::Display for ParseError {
@@ -29,84 +103,373 @@ impl std::fmt::Display for ParseError {
     }
 }
 
+/// Every error found in a document, in source order; never empty
+#[derive(Debug, Clone)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl ParseErrors {
+    pub fn first(&self) -> &ParseError {
+        &self.0[0]
+    }
+}
+
+impl From<ParseError> for ParseErrors {
+    fn from(error: ParseError) -> Self {
+        ParseErrors(vec![error])
+    }
+}
+
+/// Each error with its source line and caret (see [`ParseError::diagnostic`])
+impl std::fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error.diagnostic())?;
+        }
+        Ok(())
+    }
+}
+
 type Result<T> = std::result::Result<T, ParseError>;
 
+/// Chars of source shown on each side of an error
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Props whose value may be a palette name as well as a `#hex` color
+const COLOR_PROPS: &[&str] = &["color", "background", "border_color"];
+
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             input,
-            chars: input.chars().peekable(),
+            bytes: input.as_bytes(),
             pos: 0,
             line: 1,
             col: 1,
+            decoded: Cell::new(None),
+            view_depth: None,
+            cut: None,
+            assets: Vfs::Empty,
+            importing: vec![],
+            aliases: vec![],
+            errors: vec![],
         }
     }
 
+    /// Read `@import`ed files from `assets`
+    pub fn with_assets(mut self, assets: Vfs) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Parse the document, returning its first error
     pub fn parse(mut self) -> Result<PrismApp> {
-        let mut name = String::from("Untitled");
-        let mut version = 1u32;
-        let mut state = StateBlock::default();
-        let mut view = ViewNode {
-            kind: NodeKind::Column,
-            props: HashMap::new(),
-            children: vec![],
-        };
-        let mut actions = HashMap::new();
+        self.parse_document()
+    }
+
+    /// Parse the document, returning every error found
+    pub fn parse_all(mut self) -> std::result::Result<PrismApp, ParseErrors> {
+        self.parse_document().map_err(|first| {
+            let mut errors = std::mem::take(&mut self.errors);
+            if errors.is_empty() {
+                errors.push(first);
+            }
+            ParseErrors(errors)
+        })
+    }
+
+    /// Parse the whole document. After an error, parsing resumes at the next
+    /// top-level section so later errors are found too; all of them are kept
+    /// in `errors` and the first is returned.
+    fn parse_document(&mut self) -> Result<PrismApp> {
+        let mut app = PrismApp::default();
+        let mut modules = vec![];
 
         self.skip_whitespace_and_comments();
 
         while self.peek().is_some() {
             self.skip_whitespace_and_comments();
-            
-            if self.peek() == Some('@') {
-                self.advance();
-                let directive = self.parse_identifier()?;
-                self.skip_horizontal_whitespace();
-                
-                match directive.as_str() {
-                    "app" => {
-                        name = self.parse_string_literal()?;
-                    }
-                    "version" => {
-                        let v = self.parse_number()?;
-                        version = v.as_int() as u32;
+            let start = self.pos;
+            if let Err(e) = self.parse_section(&mut app, &mut modules) {
+                self.errors.push(self.located(e));
+                self.recover(start);
+            }
+            self.skip_whitespace_and_comments();
+        }
+
+        if let Some(name) = app.computed.keys().find(|name| app.state.fields.contains_key(*name)) {
+            let e = self.located(self.error(&format!("Computed value '{}' is also declared in state", name)));
+            self.errors.push(e);
+        }
+        if let Some(first) = self.errors.first() {
+            return Err(first.clone());
+        }
+
+        // Imported modules have applied their own styles already
+        apply_styles(&mut app.view, &app.styles);
+        for view in app.routes.values_mut() {
+            apply_styles(view, &app.styles);
+        }
+        for component in app.components.values_mut() {
+            apply_styles(&mut component.view, &app.styles);
+        }
+
+        // Components and actions declared by the document override imported ones
+        for module in modules {
+            for (name, component) in module.components {
+                app.components.entry(name).or_insert(component);
+            }
+            for (name, action) in module.actions {
+                app.actions.entry(name).or_insert(action);
+            }
+        }
+        // A `when` condition's bare `width` is the page's width unless the
+        // document (or the component it's in) has its own `width`
+        let own_width = app.state.fields.contains_key("width") || app.computed.contains_key("width");
+        bind_viewport_width(&mut app.view, own_width);
+        for view in app.routes.values_mut() {
+            bind_viewport_width(view, own_width);
+        }
+        for component in app.components.values_mut() {
+            let shadowed = own_width || component.state.fields.contains_key("width") || component.props.iter().any(|prop| prop.name == "width");
+            bind_viewport_width(&mut component.view, shadowed);
+        }
+
+        app.view = expand_components(app.view, &app.components, 0);
+        app.routes = app.routes.into_iter().map(|(path, view)| (path, expand_components(view, &app.components, 0))).collect();
+        Ok(app)
+    }
+
+    /// Parse one directive or top-level block into `app`
+    fn parse_section(&mut self, app: &mut PrismApp, modules: &mut Vec<PrismApp>) -> Result<()> {
+        if self.peek() == Some('@') {
+            self.advance();
+            let (line, col) = (self.line, self.col);
+            let directive = self.parse_identifier()?;
+            self.skip_horizontal_whitespace();
+
+            match directive.as_str() {
+                "app" => {
+                    app.name = self.parse_string_literal()?;
+                }
+                "version" => {
+                    let v = self.parse_number()?;
+                    app.version = v.as_int() as u32;
+                }
+                "strict" => {
+                    app.strict = true;
+                }
+                "capability" => {
+                    app.capabilities.push(self.parse_identifier()?);
+                }
+                "import" => {
+                    let path = self.parse_import_path()?;
+                    self.skip_horizontal_whitespace();
+                    let mut alias = None;
+                    if self.check_keyword("as") {
+                        self.consume_keyword("as")?;
+                        self.skip_horizontal_whitespace();
+                        alias = Some(self.parse_identifier()?);
                     }
-                    _ => {
-                        return Err(self.error(&format!("Unknown directive: @{}", directive)));
+                    let mut module = self.load_module(&path)?;
+                    if let Some(alias) = &alias {
+                        qualify_module(&mut module, alias);
+                        self.aliases.push(alias.clone());
                     }
+                    modules.push(module);
+                    app.imports.push(Import { path, alias });
+                }
+                "font" => {
+                    let name = self.parse_string_literal()?;
+                    self.skip_horizontal_whitespace();
+                    let src = self.parse_string_literal()?;
+                    app.fonts.push(FontFace { name, src });
+                }
+                _ => {
+                    // Point at the directive's name rather than what follows it
+                    return Err(ParseError { line, col, ..self.error(&format!("Unknown directive: @{}", directive)) });
                 }
+            }
+        } else if self.check_keyword("state") {
+            self.consume_keyword("state")?;
+            app.state = self.parse_state_block()?;
+        } else if self.check_keyword("computed") {
+            self.consume_keyword("computed")?;
+            app.computed = self.parse_computed_block()?;
+        } else if self.check_keyword("view") {
+            self.consume_keyword("view")?;
+            self.view_depth = Some(0);
+            let view = self.parse_view_block();
+            self.view_depth = None;
+            app.view = view?;
+            self.cut = Some(Cut { pos: self.pos, close: "" });
+        } else if self.check_keyword("routes") {
+            self.consume_keyword("routes")?;
+            app.routes = self.parse_routes_block()?;
+        } else if self.check_keyword("actions") {
+            self.consume_keyword("actions")?;
+            app.actions = self.parse_actions_block()?;
+        } else if self.check_keyword("strings") {
+            self.consume_keyword("strings")?;
+            app.strings = self.parse_strings_block()?;
+        } else if self.check_keyword("styles") {
+            self.consume_keyword("styles")?;
+            self.parse_styles_block(&mut app.styles)?;
+        } else if self.check_keyword("component") {
+            self.consume_keyword("component")?;
+            let component = self.parse_component()?;
+            app.components.insert(component.name.clone(), component);
+        } else if self.peek() == Some('-') {
+            // Comment line like "-- State Declaration --"
+            self.skip_line();
+        } else if self.peek().map(|c| c.is_whitespace()).unwrap_or(true) {
+            self.advance();
+        } else {
+            let c = self.peek().unwrap_or(' ');
+            return Err(self.error(&format!("Unexpected character: '{}'", c)));
+        }
+        Ok(())
+    }
+
+    /// After an error in the section that began at `start`, skip to the next
+    /// line that starts, unindented, with a directive or a section keyword
+    fn recover(&mut self, start: usize) {
+        // The failed section may already have stopped at the next one
+        if self.pos > start && self.at_section_start() {
+            return;
+        }
+        loop {
+            self.skip_line();
+            if self.peek().is_none() || self.at_section_start() {
+                return;
+            }
+        }
+    }
+
+    fn at_section_start(&self) -> bool {
+        self.col == 1
+            && (self.peek() == Some('@')
+                || ["state", "computed", "view", "routes", "actions", "strings", "styles", "component"].iter().any(|kw| self.check_keyword(kw)))
+    }
+
+    /// Parse an imported standard module, or a file next to this document
+    fn load_module(&self, path: &str) -> Result<PrismApp> {
+        if path.starts_with("std/") {
+            let source = crate::stdlib::module_source(path).ok_or_else(|| self.error(&format!("Unknown module: {}", path)))?;
+            return Parser::new(source).parse();
+        }
+        // Paths are relative to the importing file, which may itself be imported
+        let dir = self.importing.last().and_then(|file| file.rsplit_once('/')).map(|(dir, _)| dir);
+        let file = match dir {
+            Some(dir) => format!("{}/{}", dir, path),
+            None => path.to_string(),
+        };
+        if self.importing.contains(&file) {
+            return Err(self.error(&format!("Import cycle: {} -> {}", self.importing.join(" -> "), file)));
+        }
+        let source = self
+            .assets
+            .read_to_string(&file, crate::sandbox::MAX_FILE_SIZE_BYTES)
+            .map_err(|e| self.error(&format!("Cannot import {}: {}", path, e)))?;
+        let mut parser = Parser::new(&source).with_assets(self.assets.clone());
+        parser.importing = self.importing.clone();
+        parser.importing.push(file);
+        parser.parse().map_err(|e| self.error(&format!("In {}: {}", path, e)))
+    }
+
+    /// An identifier, or `alias.name` for something imported `as alias`
+    fn parse_name(&mut self) -> Result<String> {
+        let name = self.parse_identifier()?;
+        if self.aliases.contains(&name) && self.peek() == Some('.') {
+            self.advance();
+            return Ok(format!("{}.{}", name, self.parse_identifier()?));
+        }
+        Ok(name)
+    }
+
+    /// `@import std/nav` or `@import "std/nav"`
+    fn parse_import_path(&mut self) -> Result<String> {
+        if self.peek() == Some('"') {
+            return self.parse_string_literal();
+        }
+        let mut path = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            path.push(c);
+            self.advance();
+        }
+        if path.is_empty() {
+            return Err(self.error("Expected module path after @import"));
+        }
+        Ok(path)
+    }
+
+    /// `component name { props { ... } state { ... } actions { ... } inject name view { ... } }`
+    fn parse_component(&mut self) -> Result<ComponentDef> {
+        self.skip_whitespace_and_comments();
+        let name = self.parse_identifier()?;
+        if self.is_node_kind(&name) {
+            return Err(self.error(&format!("Component name '{}' is a built-in node", name)));
+        }
+        self.skip_whitespace_and_comments();
+        self.expect('{')?;
+        self.skip_whitespace_and_comments();
+
+        let mut props = StateBlock::default();
+        let mut state = StateBlock::default();
+        let mut actions = HashMap::new();
+        let mut inject = vec![];
+        let mut view = None;
+        while self.peek() != Some('}') {
+            if self.check_keyword("props") {
+                self.consume_keyword("props")?;
+                props = self.parse_state_block()?;
             } else if self.check_keyword("state") {
                 self.consume_keyword("state")?;
                 state = self.parse_state_block()?;
-            } else if self.check_keyword("view") {
-                self.consume_keyword("view")?;
-                view = self.parse_view_block()?;
             } else if self.check_keyword("actions") {
                 self.consume_keyword("actions")?;
                 actions = self.parse_actions_block()?;
-            } else if self.peek() == Some('-') {
-                // Comment line like "-- State Declaration --"
-                self.skip_line();
-            } else if self.peek().map(|c| c.is_whitespace()).unwrap_or(true) {
-                self.advance();
+            } else if self.check_keyword("inject") {
+                self.consume_keyword("inject")?;
+                self.parse_inject_list(&mut inject)?;
+            } else if self.check_keyword("view") {
+                self.consume_keyword("view")?;
+                view = Some(self.parse_view_block()?);
             } else {
-                let c = self.peek().unwrap_or(' ');
-                return Err(self.error(&format!("Unexpected character: '{}'", c)));
+                return Err(self.error(&format!("Expected 'props', 'state', 'actions', 'inject' or 'view' in component '{}'", name)));
             }
-            
             self.skip_whitespace_and_comments();
         }
+        self.expect('}')?;
 
-        Ok(PrismApp {
+        let view = view.ok_or_else(|| self.error(&format!("Component '{}' has no view", name)))?;
+        let mut shared: Vec<&String> = props.fields.keys().filter(|k| state.fields.contains_key(*k)).collect();
+        shared.sort();
+        if let Some(field) = shared.first() {
+            return Err(self.error(&format!("'{}' is both a prop and state of component '{}'", field, name)));
+        }
+        if let Some(field) = inject.iter().find(|k| props.fields.contains_key(*k) || state.fields.contains_key(*k)) {
+            return Err(self.error(&format!("'{}' is injected and also a prop or state of component '{}'", field, name)));
+        }
+        let mut props: Vec<PropDef> = props
+            .fields
+            .into_iter()
+            .map(|(name, default)| PropDef { name, default: Some(default), required: false })
+            .collect();
+        props.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(ComponentDef {
             name,
-            version,
-            imports: vec![],
+            props,
             state,
-            computed: HashMap::new(),
-            components: HashMap::new(),
+            inject,
             view,
             actions,
-            routes: HashMap::new(),
         })
     }
 
@@ -116,6 +479,7 @@ impl<'a> Parser<'a> {
         self.skip_whitespace_and_comments();
 
         let mut fields = HashMap::new();
+        let mut types = HashMap::new();
 
         while self.peek() != Some('}') {
             self.skip_whitespace_and_comments();
@@ -127,14 +491,175 @@ impl<'a> Parser<'a> {
             self.skip_horizontal_whitespace();
             self.expect(':')?;
             self.skip_horizontal_whitespace();
-            let value = self.parse_value()?;
+            // `name: value`, `name: type = value`, or `name: type` for the type's default
+            let value = match self.parse_type()? {
+                Some(ty) => {
+                    self.skip_horizontal_whitespace();
+                    let value = if self.try_consume("=") { self.parse_value()? } else { ty.default_value() };
+                    if !ty.accepts(&value) {
+                        return Err(self.error(&format!(
+                            "'{}' is declared {} but its value is {}", field_name, ty, value.type_name()
+                        )));
+                    }
+                    types.insert(field_name.clone(), ty);
+                    value
+                }
+                None => self.parse_value()?,
+            };
             fields.insert(field_name, value);
             
             self.skip_whitespace_and_comments();
         }
 
         self.expect('}')?;
-        Ok(StateBlock { fields })
+        Ok(StateBlock { fields, types })
+    }
+
+    /// A type annotation (`int`, `list<object>`), if one comes next
+    fn parse_type(&mut self) -> Result<Option<ValueType>> {
+        let keyword = ["any", "bool", "int", "float", "string", "list", "object"].into_iter().find(|kw| self.check_keyword(kw));
+        let Some(keyword) = keyword else {
+            return Ok(None);
+        };
+        self.consume_keyword(keyword)?;
+        let mut ty = ValueType::from_keyword(keyword).unwrap_or(ValueType::Any);
+        if keyword == "list" && self.peek() == Some('<') {
+            self.advance();
+            self.skip_horizontal_whitespace();
+            let item = self.parse_type()?.ok_or_else(|| self.error("Expected an item type after 'list<'"))?;
+            self.skip_horizontal_whitespace();
+            self.expect('>')?;
+            ty = ValueType::List(Box::new(item));
+        }
+        Ok(Some(ty))
+    }
+
+    /// `routes { "/" { column { ... } } "/about" { ... } }`, a view per route
+    fn parse_routes_block(&mut self) -> Result<HashMap<String, ViewNode>> {
+        self.skip_whitespace_and_comments();
+        self.expect('{')?;
+        self.skip_whitespace_and_comments();
+
+        let mut routes = HashMap::new();
+        while self.peek() != Some('}') {
+            let path = self.parse_string_literal()?;
+            if !path.starts_with('/') {
+                return Err(self.error(&format!("Route '{}' must start with '/'", path)));
+            }
+            let view = self.parse_view_block()?;
+            if routes.insert(path.clone(), view).is_some() {
+                return Err(self.error(&format!("Route '{}' is declared twice", path)));
+            }
+            self.skip_whitespace_and_comments();
+        }
+
+        self.expect('}')?;
+        Ok(routes)
+    }
+
+    /// `computed { total: items.len() * price }`, one derived value per line
+    fn parse_computed_block(&mut self) -> Result<HashMap<String, Expression>> {
+        self.parse_expression_block("Computed value")
+    }
+
+    /// `{ name: expression ... }`, one entry per line; `what` names an entry in errors
+    fn parse_expression_block(&mut self, what: &str) -> Result<HashMap<String, Expression>> {
+        self.skip_whitespace_and_comments();
+        self.expect('{')?;
+        self.skip_whitespace_and_comments();
+
+        let mut entries = HashMap::new();
+        while self.peek() != Some('}') {
+            let name = self.parse_identifier()?;
+            self.skip_horizontal_whitespace();
+            self.expect(':')?;
+            self.skip_horizontal_whitespace();
+            let value = self.parse_expression()?;
+            if entries.insert(name.clone(), value).is_some() {
+                return Err(self.error(&format!("{} '{}' is declared twice", what, name)));
+            }
+            self.skip_whitespace_and_comments();
+        }
+
+        self.expect('}')?;
+        Ok(entries)
+    }
+
+    /// `inject theme, accent`: names a component reads from the nearest `provide`
+    fn parse_inject_list(&mut self, inject: &mut Vec<String>) -> Result<()> {
+        loop {
+            self.skip_horizontal_whitespace();
+            let name = self.parse_identifier()?;
+            if !inject.contains(&name) {
+                inject.push(name);
+            }
+            self.skip_horizontal_whitespace();
+            if !self.try_consume(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `strings { en { greet: "Hello" } pt_BR { ... } }`; `_` in a locale name stands for `-`
+    fn parse_strings_block(&mut self) -> Result<Vec<StringTable>> {
+        self.skip_whitespace_and_comments();
+        self.expect('{')?;
+        self.skip_whitespace_and_comments();
+
+        let mut tables = vec![];
+        while self.peek() != Some('}') {
+            let locale = self.parse_identifier()?.replace('_', "-");
+            let entries = self.parse_state_block()?.fields;
+            tables.push(StringTable { locale, entries });
+            self.skip_whitespace_and_comments();
+        }
+
+        self.expect('}')?;
+        Ok(tables)
+    }
+
+    /// `styles { name { prop: value, ... } }`, props separated by commas or
+    /// newlines
+    fn parse_styles_block(&mut self, styles: &mut HashMap<String, BTreeMap<String, PropValue>>) -> Result<()> {
+        self.skip_whitespace_and_comments();
+        self.expect('{')?;
+        self.skip_whitespace_and_comments();
+
+        while self.peek() != Some('}') {
+            let name = self.parse_identifier()?;
+            self.skip_whitespace_and_comments();
+            self.expect('{')?;
+            let mut props = BTreeMap::new();
+            loop {
+                self.skip_whitespace_and_comments();
+                if self.peek() == Some(',') {
+                    self.advance();
+                    continue;
+                }
+                if self.peek() == Some('}') {
+                    break;
+                }
+                let prop = self.parse_identifier()?;
+                if prop == "style" {
+                    return Err(self.error(&format!("Style '{}' can't use another style", name)));
+                }
+                self.skip_horizontal_whitespace();
+                self.expect(':')?;
+                self.skip_horizontal_whitespace();
+                let value = if COLOR_PROPS.contains(&prop.as_str()) {
+                    self.parse_color_prop_value()?
+                } else {
+                    self.parse_prop_value()?
+                };
+                props.insert(prop, value);
+            }
+            self.expect('}')?;
+            styles.insert(name, props);
+            self.skip_whitespace_and_comments();
+        }
+
+        self.expect('}')?;
+        Ok(())
     }
 
     fn parse_view_block(&mut self) -> Result<ViewNode> {
@@ -152,8 +677,11 @@ impl<'a> Parser<'a> {
 
     fn parse_view_node(&mut self) -> Result<ViewNode> {
         self.skip_whitespace_and_comments();
+        if let Some(depth) = &mut self.view_depth {
+            *depth += 1;
+        }
         
-        let kind_str = self.parse_identifier()?;
+        let kind_str = self.parse_name()?;
         let kind = match kind_str.as_str() {
             // Layout
             "column" => NodeKind::Column,
@@ -170,6 +698,7 @@ impl<'a> Parser<'a> {
             "text" => NodeKind::Text,
             "link" => NodeKind::Link,
             "markdown" => NodeKind::Markdown,
+            "code" => NodeKind::Code,
             // Interactive
             "button" => NodeKind::Button,
             "input" => NodeKind::Input,
@@ -179,6 +708,11 @@ impl<'a> Parser<'a> {
             "select" => NodeKind::Select,
             "slider" => NodeKind::Slider,
             "toggle" => NodeKind::Toggle,
+            "filepicker" => NodeKind::FilePicker,
+            "combobox" => NodeKind::Combobox,
+            "tags" => NodeKind::Tags,
+            "rating" => NodeKind::Rating,
+            "stepper" => NodeKind::Stepper,
             // Media
             "image" => NodeKind::Image,
             "icon" => NodeKind::Icon,
@@ -196,24 +730,36 @@ impl<'a> Parser<'a> {
             "toast" => NodeKind::Toast,
             "tooltip" => NodeKind::Tooltip,
             "popover" => NodeKind::Popover,
+            "skeleton" => NodeKind::Skeleton,
             // Control Flow
             "each" => NodeKind::Each,
-            "if" => NodeKind::If,
+            "if" | "when" => NodeKind::If,
             "show" => NodeKind::Show,
             "switch" => NodeKind::Switch,
             "slot" => NodeKind::Slot,
+            // Data container slots (only as blocks, so not in is_node_kind)
+            "empty" => NodeKind::Empty,
+            "error" => NodeKind::Error,
             // Custom component
             _ => NodeKind::Component(kind_str.clone()),
         };
 
         self.skip_horizontal_whitespace();
 
-        // Optional inline text content
         let mut props = HashMap::new();
+        // `when width < 600 { ... }`: an `if` on the page's width
+        if kind_str == "when" {
+            let mut condition = self.parse_expression()?;
+            mark_viewport_width(&mut condition);
+            props.insert("visible".to_string(), PropValue::Expression(condition));
+            self.skip_horizontal_whitespace();
+        }
+
+        // Optional inline text content
         if self.peek() == Some('"') {
             let content = self.parse_string_literal()?;
-            // Check if it contains interpolation
-            if content.contains('{') && content.contains('}') {
+            // Check if it contains interpolation; a code sample is taken as written
+            if kind != NodeKind::Code && content.contains('{') && content.contains('}') {
                 props.insert("content".to_string(), PropValue::Expression(
                     self.parse_interpolation(&content)?
                 ));
@@ -237,20 +783,20 @@ impl<'a> Parser<'a> {
                 }
 
                 // Check if this is a child node or a property
-                let saved_pos = self.pos;
-                let saved_line = self.line;
-                let saved_col = self.col;
-                let saved_chars = self.chars.clone();
+                let mark = self.mark();
+                let child_count = children.len();
 
-                let ident = self.parse_identifier()?;
+                let ident = self.parse_name()?;
                 self.skip_horizontal_whitespace();
 
-                if self.is_node_kind(&ident) || self.peek() == Some('"') && self.is_node_kind(&ident) {
+                if ident == "provide" && self.peek() == Some('{') {
+                    // `provide { theme: dark_theme }`: values for descendants' `inject`
+                    let mut entries: Vec<(String, Expression)> = self.parse_expression_block("Provided value")?.into_iter().collect();
+                    entries.sort_by(|a, b| a.0.cmp(&b.0));
+                    props.insert(ident, PropValue::Expression(Expression::ObjectLiteral(entries)));
+                } else if self.is_node_kind(&ident) || self.peek() == Some('"') && self.is_node_kind(&ident) {
                     // This is a child node, restore position and parse as node
-                    self.pos = saved_pos;
-                    self.line = saved_line;
-                    self.col = saved_col;
-                    self.chars = saved_chars;
+                    self.reset(mark);
                     
                     let child = self.parse_view_node()?;
                     children.push(child);
@@ -258,14 +804,15 @@ impl<'a> Parser<'a> {
                     // This is a property
                     self.advance();
                     self.skip_horizontal_whitespace();
-                    let prop_value = self.parse_prop_value()?;
+                    let prop_value = if COLOR_PROPS.contains(&ident.as_str()) {
+                        self.parse_color_prop_value()?
+                    } else {
+                        self.parse_prop_value()?
+                    };
                     props.insert(ident, prop_value);
                 } else if self.peek() == Some('"') || self.peek() == Some('{') {
                     // This is a child node with content
-                    self.pos = saved_pos;
-                    self.line = saved_line;
-                    self.col = saved_col;
-                    self.chars = saved_chars;
+                    self.reset(mark);
                     
                     let child = self.parse_view_node()?;
                     children.push(child);
@@ -273,22 +820,47 @@ impl<'a> Parser<'a> {
                     return Err(self.error(&format!("Expected ':' after property name '{}' or a child node", ident)));
                 }
 
+                let end = self.pos;
                 self.skip_whitespace_and_comments();
+                // A top-level child is known to be whole once something follows it
+                if self.view_depth == Some(1) && children.len() > child_count && self.peek().is_some() {
+                    self.cut = Some(Cut { pos: end, close: "\n}\n}\n" });
+                }
             }
 
             self.expect('}')?;
         }
 
+        if let Some(depth) = &mut self.view_depth {
+            *depth -= 1;
+        }
         Ok(ViewNode { kind, props, children })
     }
 
     fn is_node_kind(&self, s: &str) -> bool {
-        matches!(s, "column" | "row" | "text" | "button" | "input" | "box" | "spacer" | 
-            "stack" | "grid" | "scroll" | "center" | "divider" | "link" | "markdown" |
-            "textarea" | "checkbox" | "radio" | "select" | "slider" | "toggle" |
-            "image" | "icon" | "video" | "audio" | "table" | "list" | "card" |
-            "badge" | "progress" | "avatar" | "modal" | "toast" | "tooltip" | "popover" |
-            "each" | "if" | "show" | "switch" | "slot")
+        is_builtin_node(s)
+    }
+
+    /// A color prop also takes a bare palette name (`color: red`)
+    fn parse_color_prop_value(&mut self) -> Result<PropValue> {
+        if !self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            return self.parse_prop_value();
+        }
+        let start = self.mark();
+        let name = self.parse_identifier()?;
+        self.skip_horizontal_whitespace();
+        let bare = matches!(self.peek(), None | Some('\n' | '\r' | '}' | ',')) || self.rest().starts_with(b"--");
+        if !bare {
+            // Part of an expression
+            self.reset(start);
+            return self.parse_prop_value();
+        }
+        if let Some(color) = crate::color::named(&name) {
+            return Ok(PropValue::Color(color));
+        }
+        self.reset(start);
+        let names: Vec<&str> = crate::color::PALETTE.iter().map(|(name, _)| *name).collect();
+        Err(self.error(&format!("Unknown color '{}': expected #RRGGBB or one of {}", name, names.join(", "))))
     }
 
     fn parse_prop_value(&mut self) -> Result<PropValue> {
@@ -322,12 +894,65 @@ impl<'a> Parser<'a> {
             // If it contains operators, treat as expression, otherwise as handler
             return Ok(PropValue::Handler(name.clone()));
         }
+        // `alias.action` names an imported action
+        if let Expression::PropertyAccess { object, property } = &expr {
+            if let (Expression::Variable(alias), Expression::Literal(Value::String(name))) = (&**object, &**property) {
+                if self.aliases.contains(alias) {
+                    return Ok(PropValue::Handler(format!("{}.{}", alias, name)));
+                }
+            }
+        }
 
         Ok(PropValue::Expression(expr))
     }
 
     fn parse_expression(&mut self) -> Result<Expression> {
-        self.parse_or_expr()
+        self.parse_pipe()
+    }
+
+    /// `value |> transform`, where the transform is a function or method name,
+    /// a call taking the value as its first argument, or a lambda
+    fn parse_pipe(&mut self) -> Result<Expression> {
+        let mut value = self.parse_range()?;
+        self.skip_horizontal_whitespace();
+        while self.try_consume("|>") {
+            self.skip_horizontal_whitespace();
+            let start = self.mark();
+            let transform = self.parse_range()?;
+            if !matches!(transform, Expression::Variable(_) | Expression::Call { .. } | Expression::Lambda { .. }) {
+                self.reset(start);
+                return Err(self.error("Expected a function, method or lambda after '|>'"));
+            }
+            value = Expression::Pipe { value: Box::new(value), transform: Box::new(transform) };
+            self.skip_horizontal_whitespace();
+        }
+        Ok(value)
+    }
+
+    /// `start..end` (end excluded) or `start..=end`
+    fn parse_range(&mut self) -> Result<Expression> {
+        let start = self.parse_coalesce()?;
+        self.skip_horizontal_whitespace();
+        if !self.try_consume("..") {
+            return Ok(start);
+        }
+        let inclusive = self.try_consume("=");
+        self.skip_horizontal_whitespace();
+        let end = self.parse_coalesce()?;
+        Ok(Expression::Range { start: Box::new(start), end: Box::new(end), inclusive })
+    }
+
+    /// `value ?? default`: the default when the value is null
+    fn parse_coalesce(&mut self) -> Result<Expression> {
+        let mut value = self.parse_or_expr()?;
+        self.skip_horizontal_whitespace();
+        while self.try_consume("??") {
+            self.skip_horizontal_whitespace();
+            let default = self.parse_or_expr()?;
+            value = Expression::NullCoalesce { value: Box::new(value), default: Box::new(default) };
+            self.skip_horizontal_whitespace();
+        }
+        Ok(value)
     }
 
     fn parse_or_expr(&mut self) -> Result<Expression> {
@@ -414,7 +1039,7 @@ impl<'a> Parser<'a> {
             let op = if self.peek() == Some('+') {
                 self.advance();
                 Some(BinaryOp::Add)
-            } else if self.peek() == Some('-') {
+            } else if self.peek() == Some('-') && !self.rest().starts_with(b"--") {
                 self.advance();
                 Some(BinaryOp::Sub)
             } else {
@@ -439,7 +1064,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_multiplicative(&mut self) -> Result<Expression> {
-        let mut left = self.parse_primary()?;
+        let mut left = self.parse_unary()?;
         
         self.skip_horizontal_whitespace();
         loop {
@@ -455,7 +1080,7 @@ impl<'a> Parser<'a> {
             
             if let Some(op) = op {
                 self.skip_horizontal_whitespace();
-                let right = self.parse_primary()?;
+                let right = self.parse_unary()?;
                 left = Expression::Binary {
                     left: Box::new(left),
                     op,
@@ -470,7 +1095,46 @@ impl<'a> Parser<'a> {
         Ok(left)
     }
 
+    /// Prefix operators: `!x`, `-x`, `typeof x` and `len x`, binding looser
+    /// than postfix, so `-a.b` is `-(a.b)`
+    fn parse_unary(&mut self) -> Result<Expression> {
+        self.skip_horizontal_whitespace();
+        let op = if self.peek() == Some('!') {
+            self.advance();
+            Some(UnaryOp::Not)
+        } else if self.peek() == Some('-') && !self.rest().starts_with(b"--") {
+            self.advance();
+            Some(UnaryOp::Neg)
+        } else if self.check_keyword("typeof") {
+            self.consume_keyword("typeof")?;
+            Some(UnaryOp::Typeof)
+        } else if self.check_keyword("len") {
+            // `len(x)` stays a call, and a lone `len` a variable
+            let mark = self.mark();
+            self.consume_keyword("len")?;
+            self.skip_horizontal_whitespace();
+            if self.peek().is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '"' | '!' | '[')) {
+                Some(UnaryOp::Len)
+            } else {
+                self.reset(mark);
+                None
+            }
+        } else {
+            None
+        };
+        match op {
+            Some(op) => Ok(Expression::Unary { op, operand: Box::new(self.parse_unary()?) }),
+            None => self.parse_primary(),
+        }
+    }
+
     fn parse_primary(&mut self) -> Result<Expression> {
+        let expr = self.parse_atom()?;
+        self.parse_postfix(expr)
+    }
+
+    /// A literal, parenthesized expression, call or variable, before any postfix
+    fn parse_atom(&mut self) -> Result<Expression> {
         self.skip_horizontal_whitespace();
         
         if self.peek() == Some('"') {
@@ -493,6 +1157,10 @@ impl<'a> Parser<'a> {
             return Ok(Expression::Literal(Value::Bool(false)));
         }
 
+        if self.peek() == Some('|') {
+            return self.parse_lambda();
+        }
+
         if self.peek() == Some('(') {
             self.advance();
             let expr = self.parse_expression()?;
@@ -501,11 +1169,85 @@ impl<'a> Parser<'a> {
             return Ok(expr);
         }
 
-        // Variable
+        // Variable or function call
         let name = self.parse_identifier()?;
+        if self.peek() == Some('(') {
+            self.advance();
+            let args = self.parse_call_args()?;
+            return Ok(Expression::Call { function: name, args });
+        }
         Ok(Expression::Variable(name))
     }
 
+    /// `|x| body` or `|a, b| body`; the body stops before any `|>`, so a
+    /// lambda can sit in the middle of a pipeline
+    fn parse_lambda(&mut self) -> Result<Expression> {
+        self.expect('|')?;
+        let mut params = vec![];
+        self.skip_horizontal_whitespace();
+        while self.peek() != Some('|') {
+            params.push(self.parse_identifier()?);
+            self.skip_horizontal_whitespace();
+            if self.peek() == Some(',') {
+                self.advance();
+                self.skip_horizontal_whitespace();
+            } else {
+                break;
+            }
+        }
+        self.expect('|')?;
+        let body = self.parse_range()?;
+        Ok(Expression::Lambda { params, body: Box::new(body) })
+    }
+
+    /// Field access (`user.name`), method calls (`items.len()`) and indexing
+    /// (`list[0]`) directly after `expr`, chained left to right
+    fn parse_postfix(&mut self, mut expr: Expression) -> Result<Expression> {
+        loop {
+            if self.peek() == Some('.') && self.char_at(self.pos + 1).is_some_and(|c| c.is_alphabetic() || c == '_') {
+                self.advance();
+                let field = self.parse_identifier()?;
+                if self.peek() == Some('(') {
+                    self.advance();
+                    let args = self.parse_call_args()?;
+                    expr = Expression::MethodCall { object: Box::new(expr), method: field, args };
+                    continue;
+                }
+                expr = Expression::PropertyAccess {
+                    object: Box::new(expr),
+                    property: Box::new(Expression::Literal(Value::String(field))),
+                };
+            } else if self.peek() == Some('[') {
+                self.advance();
+                self.skip_horizontal_whitespace();
+                let index = self.parse_expression()?;
+                self.skip_horizontal_whitespace();
+                self.expect(']')?;
+                expr = Expression::IndexAccess { object: Box::new(expr), index: Box::new(index) };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Parse comma-separated call arguments up to and including ')'
+    fn parse_call_args(&mut self) -> Result<Vec<Expression>> {
+        let mut args = vec![];
+        self.skip_horizontal_whitespace();
+        while self.peek() != Some(')') {
+            args.push(self.parse_expression()?);
+            self.skip_horizontal_whitespace();
+            if self.peek() == Some(',') {
+                self.advance();
+                self.skip_horizontal_whitespace();
+            } else {
+                break;
+            }
+        }
+        self.expect(')')?;
+        Ok(args)
+    }
+
     fn parse_interpolation(&self, s: &str) -> Result<Expression> {
         let mut parts = vec![];
         let mut current = String::new();
@@ -520,10 +1262,13 @@ impl<'a> Parser<'a> {
                 }
                 in_var = true;
             } else if c == '}' && in_var {
-                if !var_name.is_empty() {
-                    parts.push(InterpolationPart::Expression(Box::new(Expression::Variable(var_name.clone()))));
-                    var_name.clear();
+                // Braces around something that isn't an expression (a code
+                // sample, say) stay as written
+                match parse_expression(&var_name) {
+                    Ok(expr) if !var_name.trim().is_empty() => parts.push(InterpolationPart::Expression(Box::new(expr))),
+                    _ => parts.push(InterpolationPart::Literal(format!("{{{}}}", var_name))),
                 }
+                var_name.clear();
                 in_var = false;
             } else if in_var {
                 var_name.push(c);
@@ -553,39 +1298,199 @@ impl<'a> Parser<'a> {
             }
 
             let name = self.parse_identifier()?;
+            self.skip_horizontal_whitespace();
+            // `name(a, b) { ... }` takes parameters
+            let mut params = vec![];
+            if self.peek() == Some('(') {
+                self.advance();
+                self.skip_horizontal_whitespace();
+                while self.peek() != Some(')') {
+                    params.push(self.parse_identifier()?);
+                    self.skip_horizontal_whitespace();
+                    if self.peek() == Some(',') {
+                        self.advance();
+                        self.skip_horizontal_whitespace();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(')')?;
+            }
             self.skip_whitespace_and_comments();
-            self.expect('{')?;
+            let statements = self.parse_statement_block()?;
+            actions.insert(name, ActionBlock { params, statements });
+
             self.skip_whitespace_and_comments();
+        }
 
-            let mut statements = vec![];
+        self.expect('}')?;
+        Ok(actions)
+    }
 
-            while self.peek() != Some('}') {
-                self.skip_whitespace_and_comments();
-                if self.peek() == Some('}') {
-                    break;
-                }
+    /// Parse `{ statement* }`, one statement per line
+    fn parse_statement_block(&mut self) -> Result<Vec<Statement>> {
+        self.expect('{')?;
+        self.skip_whitespace_and_comments();
+        let mut statements = vec![];
+        while self.peek() != Some('}') {
+            if self.peek().is_none() {
+                return Err(self.error("Unclosed statement block"));
+            }
+            statements.push(self.parse_statement()?);
+            self.skip_whitespace_and_comments();
+        }
+        self.expect('}')?;
+        Ok(statements)
+    }
 
-                let target = self.parse_identifier()?;
+    fn parse_statement(&mut self) -> Result<Statement> {
+        if self.check_statement_keyword("if") {
+            return self.parse_if_statement();
+        }
+        if self.check_statement_keyword("for") {
+            self.consume_keyword("for")?;
+            self.skip_horizontal_whitespace();
+            let item = self.parse_identifier()?;
+            self.skip_horizontal_whitespace();
+            let mut index = None;
+            if self.peek() == Some(',') {
+                self.advance();
                 self.skip_horizontal_whitespace();
-                self.expect(':')?;
+                index = Some(self.parse_identifier()?);
                 self.skip_horizontal_whitespace();
-                let value = self.parse_expression()?;
-                statements.push(Statement::Assign {
-                    target: AssignTarget::Variable(target),
-                    value,
-                });
-
-                self.skip_whitespace_and_comments();
             }
+            self.consume_keyword("in")?;
+            let collection = self.parse_expression()?;
+            self.skip_horizontal_whitespace();
+            let body = self.parse_statement_block()?;
+            return Ok(Statement::ForEach { item, index, collection, body });
+        }
+        if self.check_statement_keyword("while") {
+            self.consume_keyword("while")?;
+            let condition = self.parse_expression()?;
+            self.skip_horizontal_whitespace();
+            let body = self.parse_statement_block()?;
+            return Ok(Statement::While { condition, body });
+        }
+        if self.check_statement_keyword("return") {
+            self.consume_keyword("return")?;
+            self.skip_horizontal_whitespace();
+            // A bare `return` ends its line
+            let bare = matches!(self.peek(), None | Some('\n' | '\r' | '}')) || self.rest().starts_with(b"--");
+            let value = if bare { None } else { Some(self.parse_expression()?) };
+            return Ok(Statement::Return(value));
+        }
+        if self.check_statement_keyword("break") {
+            self.consume_keyword("break")?;
+            return Ok(Statement::Break);
+        }
+        if self.check_statement_keyword("continue") {
+            self.consume_keyword("continue")?;
+            return Ok(Statement::Continue);
+        }
 
-            self.expect('}')?;
-            actions.insert(name, ActionBlock { params: vec![], statements });
+        let target = self.parse_name()?;
+        self.skip_horizontal_whitespace();
+        // `name(args)` calls another action, or a builtin such as `broadcast`
+        if self.peek() == Some('(') {
+            self.advance();
+            let mut args = self.parse_call_args()?;
+            return match target.as_str() {
+                "navigate" if args.len() == 1 => Ok(Statement::Navigate(args.remove(0))),
+                "navigate" => Err(self.error("navigate takes one route")),
+                "log" if args.len() == 1 => Ok(Statement::Log(args.remove(0))),
+                "log" => Err(self.error("log takes one value")),
+                "notify" if matches!(args.len(), 1 | 2) => {
+                    let title = args.remove(0);
+                    Ok(Statement::Notify { title, body: args.pop() })
+                }
+                "notify" => Err(self.error("notify takes a title and an optional body")),
+                "copy" if args.len() == 1 => Ok(Statement::Copy(args.remove(0))),
+                "copy" => Err(self.error("copy takes one value")),
+                "focus" if args.len() == 1 => Ok(Statement::Focus(args.remove(0))),
+                "scroll_to" if args.len() == 1 => Ok(Statement::ScrollTo(args.remove(0))),
+                "focus" | "scroll_to" => Err(self.error(&format!("{} takes one node id", target))),
+                "emit" => match (args.first(), args.len()) {
+                    (Some(Expression::Literal(Value::String(event))), 1 | 2) => {
+                        let event = event.clone();
+                        Ok(Statement::Emit { event, data: args.into_iter().nth(1) })
+                    }
+                    _ => Err(self.error("emit takes an event name string and optional data")),
+                },
+                _ => Ok(Statement::Call { action: target, args }),
+            };
+        }
+        // `list.push(value)` and the other list methods, or `object.field: value`
+        if self.peek() == Some('.') {
+            self.advance();
+            let member = self.parse_identifier()?;
+            self.skip_horizontal_whitespace();
+            if self.peek() == Some('(') {
+                self.advance();
+                let mut args = self.parse_call_args()?;
+                return match (member.as_str(), args.len()) {
+                    ("push", 1) => Ok(Statement::ListPush { target, value: args.remove(0) }),
+                    ("pop", 0) => Ok(Statement::ListPop { target }),
+                    ("insert", 2) => {
+                        let value = args.remove(1);
+                        Ok(Statement::ListInsert { target, index: args.remove(0), value })
+                    }
+                    ("remove", 1) => Ok(Statement::ListRemove { target, index: args.remove(0) }),
+                    ("clear", 0) => Ok(Statement::ListClear { target }),
+                    ("push" | "remove", _) => Err(self.error(&format!("{} takes one argument", member))),
+                    ("insert", _) => Err(self.error("insert takes an index and a value")),
+                    ("pop" | "clear", _) => Err(self.error(&format!("{} takes no arguments", member))),
+                    _ => Err(self.error(&format!("Unknown list method '{}'", member))),
+                };
+            }
+            self.expect(':')?;
+            let value = self.parse_expression()?;
+            return Ok(Statement::Assign { target: AssignTarget::Property { object: target, property: member }, value });
+        }
+        // `list[index]: value`
+        if self.peek() == Some('[') {
+            self.advance();
+            let index = self.parse_expression()?;
+            self.skip_horizontal_whitespace();
+            self.expect(']')?;
+            self.skip_horizontal_whitespace();
+            self.expect(':')?;
+            let value = self.parse_expression()?;
+            return Ok(Statement::Assign { target: AssignTarget::Index { object: target, index }, value });
+        }
+        self.expect(':')?;
+        self.skip_horizontal_whitespace();
+        let value = self.parse_expression()?;
+        Ok(Statement::Assign { target: AssignTarget::Variable(target), value })
+    }
 
-            self.skip_whitespace_and_comments();
+    /// `if cond { ... }`, optionally followed by `else if` and `else` blocks
+    fn parse_if_statement(&mut self) -> Result<Statement> {
+        self.consume_keyword("if")?;
+        let condition = self.parse_expression()?;
+        self.skip_horizontal_whitespace();
+        let then_block = self.parse_statement_block()?;
+        let resume = (self.pos, self.line, self.col);
+        self.skip_whitespace_and_comments();
+        if !self.check_keyword("else") {
+            (self.pos, self.line, self.col) = resume;
+            return Ok(Statement::If { condition, then_block, else_block: vec![] });
         }
+        self.consume_keyword("else")?;
+        self.skip_horizontal_whitespace();
+        let else_block = if self.check_keyword("if") {
+            vec![self.parse_if_statement()?]
+        } else {
+            self.parse_statement_block()?
+        };
+        Ok(Statement::If { condition, then_block, else_block })
+    }
 
-        self.expect('}')?;
-        Ok(actions)
+    /// Whether a statement keyword starts here, rather than a state variable
+    /// of the same name being assigned (`break: true`) or an action called
+    fn check_statement_keyword(&self, kw: &str) -> bool {
+        let next = self.rest()[kw.len().min(self.rest().len())..].iter().find(|b| !matches!(b, b' ' | b'\t'));
+        self.check_keyword(kw) && !matches!(next, Some(b':' | b'(' | b'.' | b'['))
     }
 
     fn parse_value(&mut self) -> Result<Value> {
@@ -720,7 +1625,8 @@ impl<'a> Parser<'a> {
         while let Some(c) = self.peek() {
             if c.is_ascii_digit() {
                 s.push(self.advance().unwrap());
-            } else if c == '.' && !is_float {
+            } else if c == '.' && !is_float && self.bytes.get(self.pos + 1).is_some_and(u8::is_ascii_digit) {
+                // `1..5` is a range, not the float `1.`
                 is_float = true;
                 s.push(self.advance().unwrap());
             } else {
@@ -738,32 +1644,29 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_identifier(&mut self) -> Result<String> {
-        let mut s = String::new();
-        
-        if let Some(c) = self.peek() {
-            if c.is_alphabetic() || c == '_' {
-                s.push(self.advance().unwrap());
-            } else {
-                return Err(self.error(&format!("Expected identifier, found '{}'", c)));
+        let start = self.pos;
+        match self.peek() {
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                self.advance();
             }
-        } else {
-            return Err(self.error("Expected identifier, found end of input"));
+            Some(c) => return Err(self.error(&format!("Expected identifier, found '{}'", c))),
+            None => return Err(self.error("Expected identifier, found end of input")),
         }
 
         while let Some(c) = self.peek() {
             if c.is_alphanumeric() || c == '_' {
-                s.push(self.advance().unwrap());
+                self.advance();
             } else {
                 break;
             }
         }
 
-        Ok(s)
+        Ok(self.input[start..self.pos].to_string())
     }
 
     fn check_keyword(&self, kw: &str) -> bool {
-        self.input[self.pos..].starts_with(kw)
-            && self.input[self.pos..].chars().nth(kw.len()).map(|c| !c.is_alphanumeric() && c != '_').unwrap_or(true)
+        self.rest().starts_with(kw.as_bytes())
+            && self.char_at(self.pos + kw.len()).is_none_or(|c| !c.is_alphanumeric() && c != '_')
     }
 
     fn consume_keyword(&mut self, kw: &str) -> Result<()> {
@@ -778,7 +1681,7 @@ impl<'a> Parser<'a> {
     }
 
     fn try_consume(&mut self, s: &str) -> bool {
-        if self.input[self.pos..].starts_with(s) {
+        if self.rest().starts_with(s.as_bytes()) {
             for _ in 0..s.len() {
                 self.advance();
             }
@@ -788,12 +1691,43 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// The unread part of the input
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    fn mark(&self) -> Mark {
+        Mark { pos: self.pos, line: self.line, col: self.col }
+    }
+
+    fn reset(&mut self, mark: Mark) {
+        self.pos = mark.pos;
+        self.line = mark.line;
+        self.col = mark.col;
+    }
+
     fn peek(&self) -> Option<char> {
-        self.input[self.pos..].chars().next()
+        self.char_at(self.pos)
+    }
+
+    /// The char starting at byte offset `pos`, which must be a char boundary
+    fn char_at(&self, pos: usize) -> Option<char> {
+        let byte = *self.bytes.get(pos)?;
+        if byte.is_ascii() {
+            return Some(byte as char);
+        }
+        if let Some((at, c)) = self.decoded.get() {
+            if at == pos {
+                return Some(c);
+            }
+        }
+        let c = self.input[pos..].chars().next()?;
+        self.decoded.set(Some((pos, c)));
+        Some(c)
     }
 
     fn advance(&mut self) -> Option<char> {
-        let c = self.input[self.pos..].chars().next()?;
+        let c = self.peek()?;
         self.pos += c.len_utf8();
         if c == '\n' {
             self.line += 1;
@@ -826,7 +1760,7 @@ impl<'a> Parser<'a> {
             }
             
             // Skip line comments
-            if self.peek() == Some('-') && self.input[self.pos..].starts_with("--") {
+            if self.rest().starts_with(b"--") {
                 self.skip_line();
             } else {
                 break;
@@ -835,20 +1769,23 @@ impl<'a> Parser<'a> {
     }
 
     fn skip_horizontal_whitespace(&mut self) {
-        while let Some(c) = self.peek() {
-            if c == ' ' || c == '\t' {
-                self.advance();
-            } else {
-                break;
-            }
+        while matches!(self.bytes.get(self.pos), Some(b' ' | b'\t')) {
+            self.pos += 1;
+            self.col += 1;
         }
     }
 
+    /// Skip past the next newline, or to the end of the input
     fn skip_line(&mut self) {
-        while let Some(c) = self.peek() {
-            self.advance();
-            if c == '\n' {
-                break;
+        match self.rest().iter().position(|&b| b == b'\n') {
+            Some(newline) => {
+                self.pos += newline + 1;
+                self.line += 1;
+                self.col = 1;
+            }
+            None => {
+                self.col += self.input[self.pos..].chars().count();
+                self.pos = self.bytes.len();
             }
         }
     }
@@ -858,10 +1795,252 @@ impl<'a> Parser<'a> {
             message: message.to_string(),
             line: self.line,
             col: self.col,
+            source: None,
+        }
+    }
+
+    /// Fill in the source snippet of an error about to be reported
+    fn located(&self, mut error: ParseError) -> ParseError {
+        let Some(line) = self.input.split('\n').nth(error.line.saturating_sub(1)) else { return error };
+        let line = line.trim_end_matches('\r');
+        // Long lines are cut to a window around the error
+        let before = error.col.saturating_sub(1);
+        let skip = before.saturating_sub(SNIPPET_CONTEXT_CHARS);
+        let text = line.chars().skip(skip).take(2 * SNIPPET_CONTEXT_CHARS).collect();
+        error.source = Some(Box::new(Snippet { text, caret: before - skip }));
+        error
+    }
+}
+
+/// Node kinds built into the language, as opposed to components
+pub fn is_builtin_node(s: &str) -> bool {
+    matches!(s, "column" | "row" | "text" | "button" | "input" | "box" | "spacer" | 
+        "stack" | "grid" | "scroll" | "center" | "divider" | "link" | "markdown" | "code" |
+        "textarea" | "checkbox" | "radio" | "select" | "slider" | "toggle" | "filepicker" | "combobox" | "tags" | "rating" | "stepper" |
+        "image" | "icon" | "video" | "audio" | "table" | "list" | "card" |
+        "badge" | "progress" | "avatar" | "modal" | "toast" | "tooltip" | "popover" | "skeleton" |
+        "each" | "if" | "when" | "show" | "switch" | "slot")
+}
+
+/// Stands in for a `when` condition's bare `width` until the document's
+/// names are known; `#` keeps it from clashing with one
+const WHEN_WIDTH: &str = "#when_width";
+
+/// Mark the bare `width`s of a `when` condition for [`bind_viewport_width`]
+fn mark_viewport_width(expr: &mut Expression) {
+    if matches!(expr, Expression::Variable(name) if name == "width") {
+        *expr = Expression::Variable(WHEN_WIDTH.to_string());
+        return;
+    }
+    crate::optimize::children_mut(expr).into_iter().for_each(mark_viewport_width);
+}
+
+/// Bind the `when` conditions' marked `width`s under `node` to the page's
+/// width, `env.viewport_width`, or back to the variable `width` when
+/// `shadowed` (a state field, computed value or prop of that name)
+fn bind_viewport_width(node: &mut ViewNode, shadowed: bool) {
+    fn bind(expr: &mut Expression, shadowed: bool) {
+        if matches!(expr, Expression::Variable(name) if name == WHEN_WIDTH) {
+            *expr = if shadowed {
+                Expression::Variable("width".to_string())
+            } else {
+                Expression::PropertyAccess {
+                    object: Box::new(Expression::Variable("env".to_string())),
+                    property: Box::new(Expression::Literal(Value::String("viewport_width".to_string()))),
+                }
+            };
+            return;
+        }
+        crate::optimize::children_mut(expr).into_iter().for_each(|child| bind(child, shadowed));
+    }
+    walk_nodes(node, &mut |node| {
+        if let Some(PropValue::Expression(condition)) = node.props.get_mut("visible") {
+            bind(condition, shadowed);
+        }
+    });
+}
+
+/// Rename a module's components and actions to `alias.name`, along with the
+/// module's own uses of them, so they can't clash with the importing document
+fn qualify_module(module: &mut PrismApp, alias: &str) {
+    let components: Vec<String> = module.components.keys().cloned().collect();
+    let actions: Vec<String> = module.actions.keys().cloned().collect();
+    let qualify = |name: &mut String, names: &[String]| {
+        if names.contains(name) {
+            *name = format!("{}.{}", alias, name);
+        }
+    };
+    let qualify_statements = |statements: &mut Vec<Statement>| {
+        walk_statements(statements, &mut |statement| match statement {
+            Statement::Call { action, .. } => qualify(action, &actions),
+            Statement::Fetch { on_success, on_error, .. } => {
+                qualify(on_success, &actions);
+                qualify(on_error, &actions);
+            }
+            _ => {}
+        });
+    };
+
+    for action in module.actions.values_mut() {
+        qualify_statements(&mut action.statements);
+    }
+    module.actions = module.actions.drain().map(|(name, action)| (format!("{}.{}", alias, name), action)).collect();
+
+    for component in module.components.values_mut() {
+        component.name = format!("{}.{}", alias, component.name);
+        for action in component.actions.values_mut() {
+            qualify_statements(&mut action.statements);
+        }
+        walk_nodes(&mut component.view, &mut |node| {
+            if let NodeKind::Component(name) = &mut node.kind {
+                qualify(name, &components);
+            }
+            for (prop, value) in node.props.iter_mut() {
+                match value {
+                    PropValue::Handler(name) if prop.starts_with("on_") => qualify(name, &actions),
+                    PropValue::EventHandler(handler) => qualify(&mut handler.action, &actions),
+                    _ => {}
+                }
+            }
+        });
+    }
+    module.components = module.components.drain().map(|(name, component)| (format!("{}.{}", alias, name), component)).collect();
+}
+
+fn walk_nodes(node: &mut ViewNode, f: &mut impl FnMut(&mut ViewNode)) {
+    f(node);
+    for child in &mut node.children {
+        walk_nodes(child, f);
+    }
+}
+
+/// Visit every statement, including those nested in blocks
+fn walk_statements(statements: &mut [Statement], f: &mut impl FnMut(&mut Statement)) {
+    for statement in statements {
+        f(statement);
+        match statement {
+            Statement::If { then_block, else_block, .. } => {
+                walk_statements(then_block, f);
+                walk_statements(else_block, f);
+            }
+            Statement::ForEach { body, .. } | Statement::While { body, .. } | Statement::Delay { then: body, .. } => walk_statements(body, f),
+            _ => {}
+        }
+    }
+}
+
+/// Deepest chain of components using components before expansion gives up
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+/// Replace component instances with their definition's view
+///
+/// The instance node keeps its kind and props (filled with prop defaults) so
+/// the renderer can bind them while drawing; its only child becomes the
+/// expanded view, with each `slot` replaced by the instance's children.
+fn expand_components(node: ViewNode, components: &HashMap<String, ComponentDef>, depth: usize) -> ViewNode {
+    let ViewNode { kind, mut props, children } = node;
+    let def = match &kind {
+        NodeKind::Component(name) => components.get(name),
+        _ => None,
+    };
+    let Some(def) = def else {
+        if let NodeKind::Component(name) = &kind {
+            eprintln!("[PRISM] Unknown component: {}", name);
+        }
+        let children = children.into_iter().map(|c| expand_components(c, components, depth)).collect();
+        return ViewNode { kind, props, children };
+    };
+    if depth >= MAX_COMPONENT_DEPTH {
+        eprintln!("[PRISM] Component '{}' nested too deeply", def.name);
+        return ViewNode { kind: NodeKind::Error, props: HashMap::new(), children: vec![] };
+    }
+
+    for prop in &def.props {
+        if let (false, Some(default)) = (props.contains_key(&prop.name), &prop.default) {
+            props.insert(prop.name.clone(), PropValue::Static(default.clone()));
+        }
+    }
+    let view = fill_slots(def.view.clone(), &children);
+    let view = expand_components(view, components, depth + 1);
+    ViewNode { kind, props, children: vec![view] }
+}
+
+/// Give each node with a `style` prop the props of the styles it names,
+/// later names first; props set on the node itself win
+fn apply_styles(node: &mut ViewNode, styles: &HashMap<String, BTreeMap<String, PropValue>>) {
+    let mut styled = BTreeMap::new();
+    for name in node.style_names() {
+        match styles.get(name) {
+            Some(style) => styled.extend(style.iter()),
+            None => eprintln!("[PRISM] Unknown style: {}", name),
+        }
+    }
+    let styled: Vec<(String, PropValue)> = styled.into_iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    for (prop, value) in styled {
+        node.props.entry(prop).or_insert(value);
+    }
+    for child in &mut node.children {
+        apply_styles(child, styles);
+    }
+}
+
+/// Splice `content` in place of every `slot`, falling back to the slot's own
+/// children when the instance has none
+fn fill_slots(node: ViewNode, content: &[ViewNode]) -> ViewNode {
+    let mut children = vec![];
+    for child in node.children {
+        if child.kind != NodeKind::Slot {
+            children.push(fill_slots(child, content));
+            continue;
+        }
+        let filling = if content.is_empty() { child.children.clone() } else { content.to_vec() };
+        for (i, item) in filling.into_iter().enumerate() {
+            if let (true, Some(separator)) = (i > 0, child.props.get("separator")) {
+                let mut props = HashMap::new();
+                props.insert("content".to_string(), separator.clone());
+                props.insert("color".to_string(), PropValue::Color(Color::GRAY));
+                children.push(ViewNode { kind: NodeKind::Text, props, children: vec![] });
+            }
+            children.push(item);
         }
     }
+    ViewNode { kind: node.kind, props: node.props, children }
 }
 
 pub fn parse(input: &str) -> Result<PrismApp> {
     Parser::new(input).parse()
 }
+
+/// Parse a document whose `@import`ed files are read from `assets`, reporting
+/// every error found rather than only the first
+pub fn parse_with_assets(input: &str, assets: &Vfs) -> std::result::Result<PrismApp, ParseErrors> {
+    Parser::new(input).with_assets(assets.clone()).parse_all()
+}
+
+/// Parse the start of a document that is still arriving, as far as the last
+/// complete top-level child of its `view`; `None` until one has arrived
+///
+/// Sections after the view, and any that haven't arrived yet, are left out.
+pub fn parse_partial(input: &str, assets: &Vfs) -> Option<PrismApp> {
+    let mut parser = Parser::new(input).with_assets(assets.clone());
+    let app = match parser.parse_document() {
+        Ok(app) => app,
+        Err(_) => {
+            let cut = parser.cut?;
+            parse_with_assets(&format!("{}{}", &input[..cut.pos], cut.close), assets).ok()?
+        }
+    };
+    Some(app).filter(|app| !app.view.children.is_empty())
+}
+
+/// Parse a standalone expression, such as a condition given on the command line
+pub fn parse_expression(input: &str) -> Result<Expression> {
+    let mut parser = Parser::new(input);
+    parser.skip_whitespace_and_comments();
+    let expr = parser.parse_expression().map_err(|e| parser.located(e))?;
+    parser.skip_whitespace_and_comments();
+    match parser.peek() {
+        Some(c) => Err(parser.located(parser.error(&format!("Unexpected '{}' after expression", c)))),
+        None => Ok(expr),
+    }
+}
@@ -0,0 +1,43 @@
+//! Whether the computer is running on battery, for the battery saver
+//!
+//! Linux reports it under `/sys/class/power_supply`; macOS through
+//! `pmset -g batt`. Elsewhere, and on machines without a battery, the answer
+//! is unknown and the battery saver only follows the setting.
+
+use std::path::Path;
+use std::time::Duration;
+
+/// How often the power source is looked up again
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// True on battery, false on mains power, `None` when the system doesn't say
+pub fn on_battery() -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        linux_on_battery(Path::new("/sys/class/power_supply"))
+    } else if cfg!(target_os = "macos") {
+        let output = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+        let report = String::from_utf8_lossy(&output.stdout);
+        let source = report.lines().next()?;
+        Some(source.contains("'Battery Power'"))
+    } else {
+        None
+    }
+}
+
+/// On battery when a battery is discharging; on mains when there are
+/// batteries and none is
+fn linux_on_battery(supplies: &Path) -> Option<bool> {
+    let read = |supply: &Path, name: &str| std::fs::read_to_string(supply.join(name)).map(|s| s.trim().to_string()).unwrap_or_default();
+    let statuses: Vec<String> = std::fs::read_dir(supplies)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|supply| read(supply, "type") == "Battery")
+        .map(|battery| read(&battery, "status"))
+        .collect();
+    if statuses.is_empty() {
+        None
+    } else {
+        Some(statuses.iter().any(|status| status == "Discharging"))
+    }
+}
@@ -0,0 +1,386 @@
+//! QR codes, for handing an address to a phone
+//!
+//! Only what sharing a link needs: data in byte mode, error correction level
+//! M (a symbol still scans with about 15% of it damaged), versions 1 to 10,
+//! which hold up to 213 bytes. Of the eight masks, the one the standard's
+//! penalty rules score lowest is used.
+
+/// Error correction blocks of each version at level M: EC codewords per
+/// block, blocks in the first group, data codewords in each of those, and
+/// blocks in the second group, which hold one data codeword more
+const BLOCKS: [(usize, usize, usize, usize); 10] = [
+    (10, 1, 16, 0),
+    (16, 1, 28, 0),
+    (26, 1, 44, 0),
+    (18, 2, 32, 0),
+    (24, 2, 43, 0),
+    (16, 4, 27, 0),
+    (18, 4, 31, 0),
+    (22, 2, 38, 2),
+    (22, 3, 36, 2),
+    (26, 4, 43, 1),
+];
+
+/// Centre rows and columns of each version's alignment patterns
+const ALIGNMENT: [&[usize]; 10] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+const MAX_VERSION: usize = BLOCKS.len();
+/// Light modules the standard asks for around a symbol
+pub const QUIET_ZONE: usize = 4;
+
+/// A square of dark and light modules
+#[derive(Debug, Clone)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// The smallest symbol holding `data`, or `None` when it doesn't fit in any
+    pub fn encode(data: &[u8]) -> Option<QrCode> {
+        let version = (1..=MAX_VERSION).find(|&v| capacity(v) >= data.len())?;
+        let mut symbol = Symbol::new(version);
+        symbol.draw_codewords(&codewords(version, data));
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut trial = symbol.clone();
+                trial.apply_mask(mask);
+                trial.draw_format(mask);
+                trial.penalty()
+            })
+            .unwrap_or(0);
+        symbol.apply_mask(mask);
+        symbol.draw_format(mask);
+        Some(QrCode { size: symbol.size, modules: symbol.modules })
+    }
+
+    /// Modules along each side, without the quiet zone
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module in column `x` of row `y` is dark
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+}
+
+/// Data codewords of a version
+fn data_codewords(version: usize) -> usize {
+    let (_, group1, per_block, group2) = BLOCKS[version - 1];
+    group1 * per_block + group2 * (per_block + 1)
+}
+
+/// Bits of the byte count, which grows from version 10
+fn count_bits(version: usize) -> usize {
+    if version < 10 { 8 } else { 16 }
+}
+
+/// Bytes a version holds after the mode and count
+fn capacity(version: usize) -> usize {
+    (data_codewords(version) * 8 - 4 - count_bits(version)) / 8
+}
+
+/// The codewords of `data` in the order they're placed: the blocks' data
+/// codewords interleaved, then their error correction interleaved
+fn codewords(version: usize, data: &[u8]) -> Vec<u8> {
+    let total_bits = data_codewords(version) * 8;
+    let mut bits = BitBuffer::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, count_bits(version));
+    for &byte in data {
+        bits.push(byte as u32, 8);
+    }
+    bits.push(0, (total_bits - bits.len).min(4));
+    bits.push(0, (8 - bits.len % 8) % 8);
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if bits.len >= total_bits {
+            break;
+        }
+        bits.push(pad, 8);
+    }
+
+    let (ec_len, group1, per_block, group2) = BLOCKS[version - 1];
+    let divisor = reed_solomon_divisor(ec_len);
+    let mut blocks = vec![];
+    let mut rest = bits.bytes.as_slice();
+    for i in 0..group1 + group2 {
+        let (block, tail) = rest.split_at(per_block + (i >= group1) as usize);
+        blocks.push((block, reed_solomon_remainder(block, &divisor)));
+        rest = tail;
+    }
+    let mut out = Vec::with_capacity(data_codewords(version) + ec_len * blocks.len());
+    for i in 0..=per_block {
+        out.extend(blocks.iter().filter_map(|(block, _)| block.get(i)));
+    }
+    for i in 0..ec_len {
+        out.extend(blocks.iter().map(|(_, ec)| ec[i]));
+    }
+    out
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitBuffer {
+    /// Append the low `count` bits of `value`, most significant first
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if value >> i & 1 == 1 {
+                self.bytes[self.len / 8] |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+/// Product in GF(2^8) with the QR polynomial x^8 + x^4 + x^3 + x^2 + 1
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z = 0u32;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= (y as u32 >> i & 1) * x as u32;
+    }
+    z as u8
+}
+
+/// Coefficients of the generator polynomial of `degree`, highest first,
+/// without its leading 1
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 2);
+    }
+    result
+}
+
+/// Error correction codewords of a block
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+/// A symbol being built; `function` marks the finder, timing, alignment,
+/// format and version modules, which data and masks leave alone
+#[derive(Clone)]
+struct Symbol {
+    version: usize,
+    size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl Symbol {
+    fn new(version: usize) -> Symbol {
+        let size = version * 4 + 17;
+        let mut symbol = Symbol { version, size, modules: vec![false; size * size], function: vec![false; size * size] };
+        symbol.draw_function_patterns();
+        symbol
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        // Finders with their light separators, in three corners
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if (0..size as i32).contains(&x) && (0..size as i32).contains(&y) {
+                        let ring = dx.abs().max(dy.abs());
+                        self.set_function(x as usize, y as usize, ring != 2 && ring != 4);
+                    }
+                }
+            }
+        }
+        let centres = ALIGNMENT[self.version - 1];
+        let last = centres.len().saturating_sub(1);
+        for (i, &cy) in centres.iter().enumerate() {
+            for (j, &cx) in centres.iter().enumerate() {
+                // The corners taken by finders have none
+                if matches!((i, j), (0, 0)) || (i == 0 && j == last) || (i == last && j == 0) {
+                    continue;
+                }
+                for dy in -2i32..=2 {
+                    for dx in -2i32..=2 {
+                        let ring = dx.abs().max(dy.abs());
+                        self.set_function((cx as i32 + dx) as usize, (cy as i32 + dy) as usize, ring != 1);
+                    }
+                }
+            }
+        }
+        // Reserve the format modules; the chosen mask fills them in
+        self.draw_format(0);
+        if self.version >= 7 {
+            let mut remainder = self.version as u32;
+            for _ in 0..12 {
+                remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+            }
+            let bits = (self.version as u32) << 12 | remainder;
+            for i in 0..18 {
+                let dark = bits >> i & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    /// Both copies of the format information: level M and the mask
+    fn draw_format(&mut self, mask: u32) {
+        // Level M is 00, so the mask is all the data
+        let mut remainder = mask;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (mask << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| bits >> i & 1 == 1;
+        let size = self.size;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // Always dark
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Fill the data modules two columns at a time, zigzagging up and down
+    /// from the bottom right and skipping the vertical timing pattern
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let total = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                let y = if upward { size - 1 - vert } else { vert };
+                for x in [right as usize, right as usize - 1] {
+                    if !self.function[y * size + x] && i < total {
+                        self.modules[y * size + x] = codewords[i / 8] >> (7 - i % 8) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let flip = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let i = y * self.size + x;
+                if flip && !self.function[i] {
+                    self.modules[i] = !self.modules[i];
+                }
+            }
+        }
+    }
+
+    /// The standard's score of how hard the symbol is to scan; lower is better
+    fn penalty(&self) -> u32 {
+        let size = self.size;
+        let at = |x: usize, y: usize| self.modules[y * size + x];
+        let mut score = 0;
+        let lines = (0..size).flat_map(|i| [(0..size).map(|j| at(j, i)).collect::<Vec<_>>(), (0..size).map(|j| at(i, j)).collect()]);
+        for line in lines {
+            // Runs of five or more alike
+            let mut run = 1u32;
+            for j in 1..=size {
+                if j < size && line[j] == line[j - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    score += run - 2;
+                }
+                run = 1;
+            }
+            // Lookalikes of a finder, with light space on either side
+            const FINDER: [bool; 7] = [true, false, true, true, true, false, true];
+            let light = |from: i32| (from..from + 4).all(|k| k < 0 || k >= size as i32 || !line[k as usize]);
+            for start in 0..=size - 7 {
+                if line[start..start + 7] == FINDER && (light(start as i32 - 4) || light(start as i32 + 7)) {
+                    score += 40;
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = at(x, y);
+                if at(x + 1, y) == dark && at(x, y + 1) == dark && at(x + 1, y + 1) == dark {
+                    score += 3;
+                }
+            }
+        }
+        // Distance of the dark share from half, in steps of 5%
+        let dark = self.modules.iter().filter(|&&m| m).count() as i64;
+        let total = (size * size) as i64;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        score + k.max(0) as u32 * 10
+    }
+}
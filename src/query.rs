@@ -0,0 +1,81 @@
+//! Selectors over the nodes of the last render
+//!
+//! A selector is an optional node keyword (`button`, `kind:text`, `*`) followed
+//! by any number of `#id` and `[prop]` / `[prop="value"]` conditions, e.g.
+//! `button[content="Save"]` or `#email`.
+
+use crate::renderer::RenderedNode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    pub kind: Option<String>,
+    /// `[prop]` requires the prop; `[prop="value"]` also its current value
+    pub props: Vec<(String, Option<String>)>,
+}
+
+impl Selector {
+    pub fn parse(source: &str) -> Result<Selector, String> {
+        let source = source.trim();
+        let source = source.strip_prefix("kind:").unwrap_or(source);
+        let kind_end = source.find(['#', '[']).unwrap_or(source.len());
+        let kind = match source[..kind_end].trim() {
+            "" | "*" => None,
+            kind if kind.chars().all(|c| c.is_alphanumeric() || c == '_') => Some(kind.to_string()),
+            kind => return Err(format!("invalid node name {:?}", kind)),
+        };
+
+        let mut props = vec![];
+        let mut rest = &source[kind_end..];
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('#') {
+                let end = after.find(['#', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err("empty #id".to_string());
+                }
+                props.push(("id".to_string(), Some(after[..end].to_string())));
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = closing_bracket(after).ok_or("unclosed [")?;
+                props.push(condition(&after[..end])?);
+                rest = &after[end + 1..];
+            } else {
+                return Err(format!("unexpected {:?}", rest));
+            }
+        }
+        Ok(Selector { kind, props })
+    }
+
+    pub fn matches(&self, node: &RenderedNode) -> bool {
+        self.kind.as_ref().is_none_or(|kind| *kind == node.kind)
+            && self.props.iter().all(|(name, expected)| match (node.props.get(name), expected) {
+                (Some(value), Some(expected)) => value == expected,
+                (Some(_), None) => true,
+                (None, _) => false,
+            })
+    }
+}
+
+/// Index of the `]` ending a condition, skipping quoted values
+fn closing_bracket(s: &str) -> Option<usize> {
+    let mut quoted = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ']' if !quoted => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn condition(s: &str) -> Result<(String, Option<String>), String> {
+    let Some((name, value)) = s.split_once('=') else {
+        return Ok((s.trim().to_string(), None));
+    };
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    Ok((name.trim().to_string(), Some(value.to_string())))
+}
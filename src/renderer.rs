@@ -3,36 +3,26 @@
 //! Converts the view tree into pixels using a simple software renderer.
 //! No GPU dependencies for maximum portability and minimal footprint.
 
-use crate::ast::{ViewNode, NodeKind, PropValue, Color, Value};
-use crate::state::StateStore;
+use crate::ast::{ViewNode, NodeKind, PropValue, Color, Value, FontFace};
+use crate::color;
+use crate::emoji;
+use crate::state::{InstanceKey, StateStore};
+use crate::optimize::HOISTED_PROP;
+use crate::glyphs::GlyphCache;
+use crate::highlight;
+use crate::image::{Image, MAX_IMAGE_FILE_BYTES};
+use crate::sandbox::{OriginPolicy, Permission};
+use crate::spell::Dictionary;
+use crate::video::VideoPlayer;
+use crate::vfs::Vfs;
 use fontdue::{Font, FontSettings};
-use fontdue::layout::{Layout, TextStyle, CoordinateSystem, LayoutSettings};
-
-fn lerp_color(c1: u32, c2: u32, t: f32) -> u32 {
-    let r1 = ((c1 >> 16) & 0xFF) as f32;
-    let g1 = ((c1 >> 8) & 0xFF) as f32;
-    let b1 = (c1 & 0xFF) as f32;
-    let r2 = ((c2 >> 16) & 0xFF) as f32;
-    let g2 = ((c2 >> 8) & 0xFF) as f32;
-    let b2 = (c2 & 0xFF) as f32;
-    let r = (r1 + (r2 - r1) * t).round() as u32;
-    let g = (g1 + (g2 - g1) * t).round() as u32;
-    let b = (b1 + (b2 - b1) * t).round() as u32;
-    (r << 16) | (g << 8) | b
-}
-
-fn mix_color(c1: u32, c2: u32, t: f32) -> u32 {
-    let r1 = ((c1 >> 16) & 0xFF) as f32;
-    let g1 = ((c1 >> 8) & 0xFF) as f32;
-    let b1 = (c1 & 0xFF) as f32;
-    let r2 = ((c2 >> 16) & 0xFF) as f32;
-    let g2 = ((c2 >> 8) & 0xFF) as f32;
-    let b2 = (c2 & 0xFF) as f32;
-    let r = (r1 + (r2 - r1) * t).round().clamp(0.0, 255.0) as u32;
-    let g = (g1 + (g2 - g1) * t).round().clamp(0.0, 255.0) as u32;
-    let b = (b1 + (b2 - b1) * t).round().clamp(0.0, 255.0) as u32;
-    (r << 16) | (g << 8) | b
-}
+use fontdue::layout::{Layout, CoordinateSystem, LayoutSettings};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 
 /// Pixel buffer for rendering
 pub struct FrameBuffer {
@@ -54,6 +44,40 @@ impl FrameBuffer {
         self.pixels.fill(color);
     }
 
+    /// Write the buffer as an RGB PNG file
+    pub fn save_png(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.encode_png()?).map_err(|e| e.to_string())
+    }
+
+    /// The buffer encoded as an RGB PNG
+    pub fn encode_png(&self) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let rgb: Vec<u8> = self.pixels.iter().flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8]).collect();
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&rgb))
+            .map_err(|e| e.to_string())?;
+        Ok(out)
+    }
+
+    /// A copy of a region, clipped to the buffer
+    pub fn crop(&self, x: i32, y: i32, w: u32, h: u32) -> FrameBuffer {
+        let x0 = x.clamp(0, self.width as i32) as usize;
+        let y0 = y.clamp(0, self.height as i32) as usize;
+        let x1 = (x + w as i32).clamp(0, self.width as i32) as usize;
+        let y1 = (y + h as i32).clamp(0, self.height as i32) as usize;
+        let (width, height) = (x1.saturating_sub(x0), y1.saturating_sub(y0));
+        FrameBuffer { width, height, pixels: self.read_rect(x0, y0, width, height) }
+    }
+
+    /// Copy the frame to a surface of the same size that expects `format`
+    pub fn present(&self, format: PixelFormat, surface: &mut [u32]) {
+        format.convert(&self.pixels, surface);
+    }
+
     pub fn set_pixel(&mut self, x: usize, y: usize, color: u32) {
         if x < self.width && y < self.height {
             self.pixels[y * self.width + x] = color;
@@ -82,6 +106,20 @@ impl FrameBuffer {
         }
     }
 
+    /// Gray out a rect: desaturate and lighten, for disabled controls
+    pub fn fade_rect(&mut self, x: i32, y: i32, w: u32, h: u32) {
+        let x_start = x.max(0) as usize;
+        let y_start = y.max(0) as usize;
+        let x_end = (x + w as i32).clamp(0, self.width as i32) as usize;
+        let y_end = (y + h as i32).clamp(0, self.height as i32) as usize;
+        for py in y_start..y_end {
+            for px in x_start..x_end {
+                let p = &mut self.pixels[py * self.width + px];
+                *p = color::mix(color::grayscale(*p), 0xFFFFFF, 0.55);
+            }
+        }
+    }
+
     pub fn draw_rect_outline(&mut self, x: i32, y: i32, w: u32, h: u32, color: u32, thickness: u32) {
         // Top
         self.fill_rect(x, y, w, thickness, color);
@@ -93,6 +131,45 @@ impl FrameBuffer {
         self.fill_rect(x + w as i32 - thickness as i32, y, thickness, h, color);
     }
 
+    /// Outline a rectangle whose corners are rounded by `radius`, `thickness`
+    /// pixels wide inside it; the inner edge is rounded by what the border
+    /// leaves, and both curves are anti-aliased
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_rounded_outline(&mut self, x: i32, y: i32, w: u32, h: u32, radius: u32, color: u32, thickness: u32) {
+        if radius == 0 {
+            self.draw_rect_outline(x, y, w, h, color, thickness);
+            return;
+        }
+        if w == 0 || h == 0 {
+            return;
+        }
+        let outer = RoundedRect::new(x, y, w, h, radius);
+        let t = thickness.min(w / 2).min(h / 2);
+        let inner = RoundedRect::new(x + t as i32, y + t as i32, w - t * 2, h - t * 2, radius.saturating_sub(t));
+        let (x1, y1) = (x + w as i32, y + h as i32);
+        for py in y.max(0)..y1.min(self.height as i32) {
+            // Between the corners only the sides are drawn, and they are solid
+            if inner.straight_row(py) {
+                self.fill_rect(x, py, t, 1, color);
+                self.fill_rect(x1 - t as i32, py, t, 1, color);
+                continue;
+            }
+            for px in x.max(0)..x1.min(self.width as i32) {
+                let coverage = outer.coverage(px, py) - inner.coverage(px, py);
+                self.cover_pixel(px, py, color, coverage);
+            }
+        }
+    }
+
+    /// Paint `color` over the share `coverage` (0 to 1) of a pixel
+    fn cover_pixel(&mut self, x: i32, y: i32, color: u32, coverage: f32) {
+        if coverage >= 1.0 {
+            self.set_pixel(x as usize, y as usize, color);
+        } else if coverage > 0.0 {
+            self.blend_pixel(x as usize, y as usize, color, (coverage * 255.0).round() as u8);
+        }
+    }
+
     /// Blend a pixel with alpha
     pub fn blend_pixel(&mut self, x: usize, y: usize, color: u32, alpha: u8) {
         if x >= self.width || y >= self.height {
@@ -100,24 +177,141 @@ impl FrameBuffer {
         }
 
         let idx = y * self.width + x;
-        let bg = self.pixels[idx];
+        self.pixels[idx] = color::blend(self.pixels[idx], color, alpha);
+    }
 
-        let bg_r = (bg >> 16) & 0xFF;
-        let bg_g = (bg >> 8) & 0xFF;
-        let bg_b = bg & 0xFF;
+    /// Draw a source image scaled (nearest neighbor) into the given rect
+    #[allow(clippy::too_many_arguments)]
+    pub fn blit_scaled(&mut self, x: i32, y: i32, w: u32, h: u32, src_w: usize, src_h: usize, src: &[u32]) {
+        if w == 0 || h == 0 || src_w == 0 || src_h == 0 || src.len() < src_w * src_h {
+            return;
+        }
+        for dy in 0..h as i32 {
+            let py = y + dy;
+            if py < 0 || py >= self.height as i32 {
+                continue;
+            }
+            let sy = (dy as usize * src_h) / h as usize;
+            for dx in 0..w as i32 {
+                let px = x + dx;
+                if px < 0 || px >= self.width as i32 {
+                    continue;
+                }
+                let sx = (dx as usize * src_w) / w as usize;
+                self.pixels[py as usize * self.width + px as usize] = src[sy * src_w + sx];
+            }
+        }
+    }
 
-        let fg_r = (color >> 16) & 0xFF;
-        let fg_g = (color >> 8) & 0xFF;
-        let fg_b = color & 0xFF;
+    /// Draw an ARGB source image scaled (nearest neighbor) into the given rect,
+    /// alpha blending and only touching pixels inside `clip` (x0, y0, x1, y1)
+    #[allow(clippy::too_many_arguments)]
+    pub fn blend_scaled(&mut self, x: i32, y: i32, w: u32, h: u32, src_w: usize, src_h: usize, src: &[u32], clip: (i32, i32, i32, i32)) {
+        if w == 0 || h == 0 || src_w == 0 || src_h == 0 || src.len() < src_w * src_h {
+            return;
+        }
+        let y_start = y.max(clip.1).max(0);
+        let y_end = (y + h as i32).min(clip.3).min(self.height as i32);
+        let x_start = x.max(clip.0).max(0);
+        let x_end = (x + w as i32).min(clip.2).min(self.width as i32);
+        for py in y_start..y_end {
+            let sy = ((py - y) as usize * src_h) / h as usize;
+            for px in x_start..x_end {
+                let sx = ((px - x) as usize * src_w) / w as usize;
+                let pixel = src[sy * src_w + sx];
+                match (pixel >> 24) as u8 {
+                    0 => {}
+                    255 => self.pixels[py as usize * self.width + px as usize] = pixel & 0xFFFFFF,
+                    alpha => self.blend_pixel(px as usize, py as usize, pixel & 0xFFFFFF, alpha),
+                }
+            }
+        }
+    }
 
-        let a = alpha as u32;
-        let inv_a = 255 - a;
+    /// Copy out a rect of pixels (must lie inside the buffer)
+    pub fn read_rect(&self, x: usize, y: usize, w: usize, h: usize) -> Vec<u32> {
+        let mut out = Vec::with_capacity(w * h);
+        for row in y..y + h {
+            out.extend_from_slice(&self.pixels[row * self.width + x..row * self.width + x + w]);
+        }
+        out
+    }
 
-        let r = (fg_r * a + bg_r * inv_a) / 255;
-        let g = (fg_g * a + bg_g * inv_a) / 255;
-        let b = (fg_b * a + bg_b * inv_a) / 255;
+    /// Write back a rect previously captured with `read_rect`, clipped to the buffer
+    pub fn write_rect(&mut self, x: i32, y: i32, w: usize, pixels: &[u32]) {
+        if w == 0 {
+            return;
+        }
+        for (row, line) in pixels.chunks(w).enumerate() {
+            let py = y + row as i32;
+            if py < 0 || py >= self.height as i32 {
+                continue;
+            }
+            for (col, &pixel) in line.iter().enumerate() {
+                let px = x + col as i32;
+                if px >= 0 && px < self.width as i32 {
+                    self.pixels[py as usize * self.width + px as usize] = pixel;
+                }
+            }
+        }
+    }
 
-        self.pixels[idx] = (r << 16) | (g << 8) | b;
+    /// Anti-aliased filled circle, clipped to (x0, y0, x1, y1)
+    pub fn fill_circle_aa(&mut self, cx: f32, cy: f32, radius: f32, color: u32, clip: (i32, i32, i32, i32)) {
+        let x0 = ((cx - radius).floor() as i32).max(clip.0).max(0);
+        let x1 = ((cx + radius).ceil() as i32).min(clip.2).min(self.width as i32);
+        let y0 = ((cy - radius).floor() as i32).max(clip.1).max(0);
+        let y1 = ((cy + radius).ceil() as i32).min(clip.3).min(self.height as i32);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let d = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+                let coverage = (radius - d + 0.5).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend_pixel(x as usize, y as usize, color, (coverage * 255.0) as u8);
+                }
+            }
+        }
+    }
+
+    /// Anti-aliased filled polygon (even-odd rule), 4x4 samples per pixel
+    pub fn fill_polygon_aa(&mut self, points: &[(f32, f32)], color: u32) {
+        if points.len() < 3 {
+            return;
+        }
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for &(x, y) in points {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let inside = |px: f32, py: f32| {
+            let mut inside = false;
+            let mut j = points.len() - 1;
+            for i in 0..points.len() {
+                let ((xi, yi), (xj, yj)) = (points[i], points[j]);
+                if (yi > py) != (yj > py) && px < (xj - xi) * (py - yi) / (yj - yi) + xi {
+                    inside = !inside;
+                }
+                j = i;
+            }
+            inside
+        };
+        for y in (min_y.floor() as i32).max(0)..(max_y.ceil() as i32).min(self.height as i32) {
+            for x in (min_x.floor() as i32).max(0)..(max_x.ceil() as i32).min(self.width as i32) {
+                let mut hits = 0u32;
+                for sy in 0..4 {
+                    for sx in 0..4 {
+                        if inside(x as f32 + (sx as f32 + 0.5) / 4.0, y as f32 + (sy as f32 + 0.5) / 4.0) {
+                            hits += 1;
+                        }
+                    }
+                }
+                if hits > 0 {
+                    self.blend_pixel(x as usize, y as usize, color, (hits * 255 / 16) as u8);
+                }
+            }
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -136,41 +330,166 @@ impl FrameBuffer {
         let x1 = x + w as i32 - 1; // inclusive
         let y1 = y + h as i32 - 1; // inclusive
 
-        let r = (radius.min(w / 2).min(h / 2)) as i32;
+        let shape = RoundedRect::new(x, y, w, h, radius);
         for py in (y0.max(y_min))..=(y1.min(y_max)) {
             let t = if h > 1 { ((py - y0) as f32 / (h as f32 - 1.0)).clamp(0.0, 1.0) } else { 0.0 };
-            let color = lerp_color(top_color, bottom_color, t);
-
-            let mut left = x0;
-            let mut right = x1;
-            if r > 0 {
-                if py < y0 + r {
-                    let dy = (y0 + r - py) as f32;
-                    let dx = ((r * r) as f32 - dy * dy).max(0.0).sqrt().floor() as i32;
-                    left = x0 + r - dx;
-                    right = x1 - r + dx;
-                } else if py > y1 - r {
-                    let dy = (py - (y1 - r)) as f32;
-                    let dx = ((r * r) as f32 - dy * dy).max(0.0).sqrt().floor() as i32;
-                    left = x0 + r - dx;
-                    right = x1 - r + dx;
+            let color = color::mix(top_color, bottom_color, t);
+            let (xs, xe) = (x0.max(x_min), x1.min(x_max));
+            if shape.straight_row(py) {
+                if xs <= xe {
+                    self.fill_rect(xs, py, (xe - xs + 1) as u32, 1, color);
                 }
-            }
-
-            let xs = left.max(x_min);
-            let xe = right.min(x_max);
-            if xe < xs {
                 continue;
             }
             for px in xs..=xe {
-                self.set_pixel(px as usize, py as usize, color);
+                self.cover_pixel(px, py, color, shape.coverage(px, py));
             }
         }
     }
 }
 
+/// A rectangle with rounded corners, for anti-aliased fills and outlines
+struct RoundedRect {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+    radius: f32,
+}
+
+impl RoundedRect {
+    fn new(x: i32, y: i32, w: u32, h: u32, radius: u32) -> RoundedRect {
+        let radius = radius.min(w / 2).min(h / 2) as f32;
+        RoundedRect { left: x as f32, top: y as f32, right: x as f32 + w as f32, bottom: y as f32 + h as f32, radius }
+    }
+
+    /// Whether row `py` lies between the corners, so it is solid from side to side
+    fn straight_row(&self, py: i32) -> bool {
+        let cy = py as f32 + 0.5;
+        cy >= self.top + self.radius && cy <= self.bottom - self.radius
+    }
+
+    /// Share of pixel (`px`, `py`) inside the shape, from 0 to 1
+    fn coverage(&self, px: i32, py: i32) -> f32 {
+        let (cx, cy) = (px as f32 + 0.5, py as f32 + 0.5);
+        if cx < self.left || cx > self.right || cy < self.top || cy > self.bottom {
+            return 0.0;
+        }
+        // Distance past the straight edges into a corner's square, on each axis
+        let dx = (self.left + self.radius - cx).max(cx - (self.right - self.radius)).max(0.0);
+        let dy = (self.top + self.radius - cy).max(cy - (self.bottom - self.radius)).max(0.0);
+        if dx == 0.0 || dy == 0.0 {
+            return 1.0;
+        }
+        (self.radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+/// Channel order of the 32-bit pixels a presentation surface expects
+///
+/// The frame buffer always holds 0x00RRGGBB, and images keep their straight
+/// alpha until they are composited into it; pixels are converted only when a
+/// finished frame is copied to the surface. Formats with an alpha channel get
+/// opaque pixels, since a presented frame has nothing behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PixelFormat {
+    /// 0x00RRGGBB, what softbuffer documents for every backend
+    #[default]
+    Xrgb8888,
+    /// 0x00BBGGRR
+    Xbgr8888,
+    /// 0xRRGGBB00
+    Rgbx8888,
+    /// 0xBBGGRR00
+    Bgrx8888,
+    /// 0xFFRRGGBB
+    Argb8888,
+    /// 0xFFBBGGRR
+    Abgr8888,
+}
+
+impl PixelFormat {
+    /// Convert one 0x00RRGGBB pixel
+    pub fn encode(self, pixel: u32) -> u32 {
+        let rgb = pixel & 0x00FF_FFFF;
+        let bgr = ((rgb & 0xFF) << 16) | (rgb & 0xFF00) | (rgb >> 16);
+        match self {
+            PixelFormat::Xrgb8888 => rgb,
+            PixelFormat::Xbgr8888 => bgr,
+            PixelFormat::Rgbx8888 => rgb << 8,
+            PixelFormat::Bgrx8888 => bgr << 8,
+            PixelFormat::Argb8888 => 0xFF00_0000 | rgb,
+            PixelFormat::Abgr8888 => 0xFF00_0000 | bgr,
+        }
+    }
+
+    /// Convert a frame into `surface`, which holds as many pixels
+    pub fn convert(self, pixels: &[u32], surface: &mut [u32]) {
+        match self {
+            PixelFormat::Xrgb8888 => surface.copy_from_slice(pixels),
+            format => surface.iter_mut().zip(pixels).for_each(|(out, &pixel)| *out = format.encode(pixel)),
+        }
+    }
+}
+
+/// Place an image of `intrinsic` size inside a box according to `fit`
+/// (`fill`, `contain`, `cover` or `none`), returning the draw rect
+/// `cover` and `none` may overflow the box and rely on clipping
+fn fit_rect(fit: &str, bounds: (i32, i32, u32, u32), intrinsic: (usize, usize)) -> (i32, i32, u32, u32) {
+    let (x, y, w, h) = bounds;
+    let (iw, ih) = (intrinsic.0.max(1) as f64, intrinsic.1.max(1) as f64);
+    let (dw, dh) = match fit {
+        "contain" | "cover" => {
+            let (sx, sy) = (w as f64 / iw, h as f64 / ih);
+            let scale = if fit == "contain" { sx.min(sy) } else { sx.max(sy) };
+            (((iw * scale).round() as u32).max(1), ((ih * scale).round() as u32).max(1))
+        }
+        "none" => (iw as u32, ih as u32),
+        _ => return bounds,
+    };
+    (x + (w as i32 - dw as i32) / 2, y + (h as i32 - dh as i32) / 2, dw, dh)
+}
+
+/// Parse "16/9", "16:9" or "1.5" into a width / height ratio
+fn parse_ratio(text: &str) -> Option<f64> {
+    match text.split_once(['/', ':']) {
+        Some((w, h)) => Some(w.trim().parse::<f64>().ok()? / h.trim().parse::<f64>().ok()?),
+        None => text.trim().parse().ok(),
+    }
+}
+
+const BADGE_TEXT_SIZE: f32 = 12.0;
+/// `border_color` when a node has a `border` but no color for it, as inputs are outlined
+const BORDER_COLOR: Color = Color { r: 0xCC, g: 0xCC, b: 0xCC, a: 255 };
+
+/// Index in `Renderer::fonts` of the embedded monospace font, for `code`
+const MONO_FONT: usize = 1;
+const CODE_TEXT_SIZE: i64 = 13;
+const CODE_PADDING: u32 = 12;
+const CODE_BG: Color = Color { r: 0xF6, g: 0xF8, b: 0xFA, a: 255 };
+/// Columns a tab advances to the next multiple of
+const CODE_TAB_WIDTH: usize = 4;
+
+/// Largest `@font` file a document may load
+pub const MAX_FONT_FILE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Glyph cache budget until the runtime sets one from the sandbox's limit
+const DEFAULT_GLYPH_CACHE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Background and text colors for a badge `variant`
+fn badge_variant_colors(variant: &str) -> (Color, Color) {
+    match variant {
+        "success" => (Color::GREEN, Color::WHITE),
+        "warning" => (Color::ORANGE, Color::WHITE),
+        "error" => (Color::RED, Color::WHITE),
+        "info" => (Color::BLUE, Color::WHITE),
+        _ => (Color::RED, Color::WHITE),
+    }
+}
+
 /// Layout box for hit testing
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct LayoutBox {
     pub x: i32,
     pub y: i32,
@@ -179,17 +498,656 @@ pub struct LayoutBox {
     pub action: Option<String>,
     pub input_binding: Option<String>,
     pub link_href: Option<String>,
+    pub file_picker: Option<FilePicker>,
+    pub media_toggle: Option<String>,
+    /// State change applied on click, e.g. a pager button setting `page`
+    pub set_state: Option<(String, Value)>,
+    /// Sortable table header: the table's key and the column it sorts by
+    pub sort_column: Option<(usize, String)>,
+    /// Draggable divider on the right edge of a resizable table column
+    pub column_resize: Option<ColumnResize>,
+    /// Combobox suggestion: the combobox's binding and the match index
+    pub suggestion: Option<(String, usize)>,
+    /// Component instance the box was drawn by, whose actions `action` may name
+    pub component: Option<InstanceKey>,
+}
+
+/// A piece of text drawn in the last render, in viewport coordinates
+#[derive(Debug, Clone)]
+pub struct TextRun {
+    pub text: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A node drawn in the last render: its keyword, evaluated props and the
+/// rect it was given, in viewport coordinates
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RenderedNode {
+    pub kind: String,
+    pub props: BTreeMap<String, String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Where a node with an `id` was drawn in the last render
+#[derive(Debug, Clone)]
+pub struct NodeAnchor {
+    /// Top edge in content coordinates (unaffected by scrolling)
+    pub y: i32,
+    pub height: u32,
+    /// The first input or list inside the node, if any
+    pub focus: Option<FocusTarget>,
+}
+
+/// A combobox in the last render and the options matching its text
+#[derive(Debug, Clone)]
+pub struct Combobox {
+    pub binding: String,
+    pub matches: Vec<Value>,
+    pub on_select: Option<String>,
+    /// Whether the suggestion list is showing
+    pub open: bool,
+}
+
+/// A word underlined as misspelled in the last render, in viewport coordinates
+#[derive(Debug, Clone)]
+pub struct Misspelling {
+    /// Binding of the input the word is in
+    pub binding: String,
+    /// Byte range of the word in the bound value
+    pub range: Range<usize>,
+    pub word: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Suggestion list drawn above the page once the rest of the view is rendered
+struct Dropdown {
+    binding: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    options: Vec<String>,
+    highlighted: Option<usize>,
+}
+
+/// A line of input text as drawn, for spell checking
+struct SpellLine<'a> {
+    text: &'a str,
+    /// Byte offset of the first char drawn
+    from: usize,
+    x: i32,
+    baseline: i32,
+    size: f32,
+}
+
+/// Chip and draft positions inside a tags box, relative to its top-left corner
+struct TagsLayout {
+    chips: Vec<(i32, i32, u32)>,
+    draft: (i32, i32, u32),
+    height: u32,
+}
+
+const RATING_COLOR: Color = Color { r: 0xF5, g: 0xA6, b: 0x23, a: 255 };
+const RATING_GAP: u32 = 4;
+const STEPPER_BUTTON: u32 = 28;
+const STEPPER_VALUE_WIDTH: u32 = 56;
+
+const CHIP_HEIGHT: u32 = 24;
+const TAGS_GAP: u32 = 6;
+const TAGS_PADDING: u32 = 6;
+const TAGS_MIN_DRAFT_WIDTH: u32 = 80;
+const TAGS_MAX_WIDTH: u32 = 400;
+
+const MAX_SUGGESTIONS: usize = 8;
+const MISSPELLING_COLOR: u32 = 0xE53935;
+const SUGGESTION_HEIGHT: u32 = 30;
+
+/// Column whose width a divider drag changes
+#[derive(Debug, Clone)]
+pub struct ColumnResize {
+    /// State variable holding the table's `{ column: width }` object
+    pub binding: String,
+    pub column: String,
+    pub width: u32,
+}
+
+/// How an `input` filters and formats what is typed into it
+#[derive(Debug, Clone)]
+pub struct InputSpec {
+    /// `text`, `number`, `email`, `password` or `tel`
+    pub kind: String,
+    /// Pattern such as `"(##) ####-####"`: `#` digit, `A` letter, `*` either
+    pub mask: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub step: f64,
+}
+
+impl InputSpec {
+    pub fn clamp(&self, value: f64) -> f64 {
+        let value = self.min.map_or(value, |min| value.max(min));
+        self.max.map_or(value, |max| value.min(max))
+    }
+}
+
+const NUMBER_SPINNER_WIDTH: u32 = 24;
+const PASSWORD_TOGGLE_WIDTH: u32 = 44;
+
+/// Horizontally scrollable area of a table or code block wider than its box
+#[derive(Debug, Clone)]
+pub struct HorizontalScroller {
+    pub table: usize,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub max_scroll: i32,
+}
+
+/// Screen placement of data table columns after resizing and scrolling
+struct ColumnLayout {
+    /// Left edge and width of each column
+    cells: Vec<(i32, u32)>,
+    /// Scrolling columns are drawn only within [clip_left, clip_right)
+    clip_left: i32,
+    clip_right: i32,
+    sticky: bool,
+}
+
+impl ColumnLayout {
+    /// On-screen span of column `i`, or `None` when it is scrolled out of view
+    fn visible(&self, i: usize) -> Option<(i32, i32)> {
+        let (x, w) = self.cells[i];
+        let (lo, hi) = if self.sticky && i == 0 { (x, self.clip_right) } else { (self.clip_left, self.clip_right) };
+        let (a, b) = (x.max(lo), (x + w as i32).min(hi));
+        (a < b).then_some((a, b))
+    }
+
+    /// Span available to a cell's content, which is hidden once its left edge scrolls away
+    fn content(&self, i: usize, inset: i32) -> Option<(i32, u32)> {
+        let (a, b) = self.visible(i)?;
+        let x = self.cells[i].0 + inset;
+        (x >= a && b - inset > x).then(|| (x, (b - inset - x) as u32))
+    }
+}
+
+pub const MIN_COLUMN_WIDTH: u32 = 40;
+
+/// File picker target registered by a `filepicker` node
+#[derive(Debug, Clone)]
+pub struct FilePicker {
+    pub binding: String,
+    pub accept: Vec<String>,
+}
+
+/// A node with `on_double_click` or `on_long_press` in the last render
+#[derive(Debug, Clone)]
+pub struct GestureBox {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub on_double_click: Option<String>,
+    pub on_long_press: Option<String>,
+    /// Item variables (`item`, `index`) in scope where the node was drawn
+    pub locals: Vec<(String, Value)>,
+}
+
+/// A list item, or a node with `on_hover` or `on_leave`, in the last render
+/// Inside it `hovering` is true while the pointer is over it
+#[derive(Debug, Clone)]
+pub struct HoverScope {
+    pub x: i32,
+    /// Top edge in content coordinates (unaffected by scrolling)
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub on_hover: Option<String>,
+    pub on_leave: Option<String>,
+    /// Item variables (`item`, `index`) in scope where the node was drawn
+    pub locals: Vec<(String, Value)>,
+}
+
+impl HoverScope {
+    /// Whether two renders drew the same scope, even if its item changed
+    pub fn same_place(&self, other: &HoverScope) -> bool {
+        (self.x, self.y, self.width, self.height) == (other.x, other.y, other.width, other.height)
+            && self.on_hover == other.on_hover
+            && self.on_leave == other.on_leave
+    }
+
+    /// Whether a point in content coordinates is over the scope
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width as i32 && y >= self.y && y < self.y + self.height as i32
+    }
+}
+
+/// A list or table with a `selected` binding, navigable from the keyboard
+#[derive(Debug, Clone)]
+pub struct Selectable {
+    pub binding: String,
+    pub items: Vec<Value>,
+    /// Name the item is bound to (`as`, default `item`)
+    pub item_name: String,
+    pub on_activate: Option<String>,
+    /// Item indices in display order (tables may be sorted or filtered)
+    pub order: Vec<usize>,
+    /// Page binding and size when the list is paginated
+    pub page: Option<(String, usize)>,
+    /// Document-space (index, top, height) of each rendered row
+    pub rows: Vec<(usize, i32, u32)>,
+}
+
+/// Something the Tab key can move focus to, in document order
+#[derive(Debug, Clone, PartialEq)]
+pub enum FocusTarget {
+    Input(String),
+    List(String),
+}
+
+const SELECTED_ROW_BG: u32 = 0xE8F0FE;
+const FOCUS_RING: u32 = 0x4285F4;
+
+/// Skeleton shimmer colors and timing
+const SKELETON_BASE: u32 = 0xE6E6E6;
+const SKELETON_HIGHLIGHT: u32 = 0xF6F6F6;
+const SHIMMER_STEP_MS: u128 = 30;
+const SHIMMER_PERIOD_STEPS: u32 = 45;
+const SKELETON_LINE_GAP: u32 = 8;
+const PAGER_HEIGHT: u32 = 28;
+
+/// Where a row or column's first child starts along `room` pixels from
+/// `start`, and the gap between children, for a `justify` value; content
+/// wider than the room starts at `start`
+fn distribute(start: i32, room: u32, content: u32, gap: u32, count: usize, justify: &str) -> (i32, u32) {
+    let spare = room.saturating_sub(content);
+    match justify {
+        "center" => (start + (spare / 2) as i32, gap),
+        "end" => (start + spare as i32, gap),
+        "space_between" if count > 1 => (start, gap + spare / (count as u32 - 1)),
+        _ => (start, gap),
+    }
+}
+
+/// Grow `lengths` by `spare` pixels shared out in proportion to `weights`;
+/// the last weighted length takes what rounding leaves over
+fn grow(lengths: &mut [u32], weights: &[u32], spare: u32) {
+    let mut weight_left: u32 = weights.iter().sum();
+    let mut left = spare;
+    for (length, &weight) in lengths.iter_mut().zip(weights) {
+        if weight == 0 {
+            continue;
+        }
+        let share = (left as u64 * weight as u64 / weight_left as u64) as u32;
+        *length += share;
+        left -= share;
+        weight_left -= weight;
+    }
+}
+
+/// `text` with each tab replaced by spaces up to the next tab stop
+fn expand_tabs(text: &str) -> String {
+    if !text.contains('\t') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut column = 0;
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = CODE_TAB_WIDTH - column % CODE_TAB_WIDTH;
+                out.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' => {
+                out.push(c);
+                column = 0;
+            }
+            _ => {
+                out.push(c);
+                column += 1;
+            }
+        }
+    }
+    out
+}
+
+/// State and props of a component instance, bound by name while its view is drawn
+pub fn component_bindings(node: &ViewNode, state: &StateStore) -> Vec<(String, Value)> {
+    let NodeKind::Component(name) = &node.kind else {
+        return vec![];
+    };
+    let (_, mut bindings) = state.component_instance(state.node_key(node), name);
+    let props = node.props
+        .iter()
+        .filter_map(|(name, prop)| {
+            let value = match prop {
+                PropValue::Static(v) => v.clone(),
+                PropValue::Expression(expr) => state.evaluate(expr),
+                PropValue::Handler(var) => state.get(var).unwrap_or_else(|| Value::String(var.clone())),
+                PropValue::Color(_) | PropValue::EventHandler(_) => return None,
+            };
+            Some((name.clone(), value))
+        });
+    bindings.extend(props);
+    bindings
+}
+
+/// Entries of an object-valued prop (`provide`, or a list's hoisted
+/// expressions), to bind by name while the node is drawn
+fn object_prop(node: &ViewNode, name: &str, state: &StateStore) -> Vec<(String, Value)> {
+    match node.props.get(name) {
+        Some(PropValue::Expression(expr)) => match state.evaluate(expr) {
+            Value::Object(entries) => entries.into_iter().collect(),
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// Five-pointed star filling the `size`-wide square at (x, y)
+fn star_points(x: f32, y: f32, size: f32) -> Vec<(f32, f32)> {
+    let (cx, cy) = (x + size / 2.0, y + size * 0.53);
+    let outer = size / 2.0;
+    let inner = outer * 0.4;
+    (0..10)
+        .map(|i| {
+            let radius = if i % 2 == 0 { outer } else { inner };
+            let angle = -std::f32::consts::FRAC_PI_2 + i as f32 * std::f32::consts::PI / 5.0;
+            (cx + radius * angle.cos(), cy + radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Place items left to right, starting a new line when one doesn't fit
+/// Returns each item's (x, y) and the total height
+fn flow_layout(widths: &[u32], max_width: u32, gap: u32, line_height: u32) -> (Vec<(u32, u32)>, u32) {
+    let mut positions = Vec::with_capacity(widths.len());
+    let (mut x, mut y) = (0u32, 0u32);
+    for &w in widths {
+        if x > 0 && x + w > max_width {
+            x = 0;
+            y += line_height + gap;
+        }
+        positions.push((x, y));
+        x += w + gap;
+    }
+    let height = if widths.is_empty() { 0 } else { y + line_height };
+    (positions, height)
+}
+
+/// Hidden state key holding the text typed into a tags input before it becomes a chip
+pub fn tag_draft_key(binding: &str) -> String {
+    format!("#draft:{}", binding)
+}
+
+/// Hidden state key holding the keyboard-highlighted combobox suggestion
+pub fn suggestion_highlight_key(binding: &str) -> String {
+    format!("#highlight:{}", binding)
+}
+
+/// Hidden state key set once a suggestion is picked, until the user types again
+pub fn suggestions_closed_key(binding: &str) -> String {
+    format!("#closed:{}", binding)
+}
+
+/// Hidden state key holding whether a password input is shown in clear text
+fn reveal_binding(binding: &str) -> String {
+    format!("#reveal:{}", binding)
+}
+
+/// Whole numbers stay integers so `{count}` doesn't render as `3.0`
+pub fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::Int(n as i64)
+    } else {
+        Value::Float(n)
+    }
+}
+
+/// `text` cut with an ellipsis to fit `max_width` pixels as `width` measures them;
+/// shared by the page and the browser chrome
+pub fn ellipsize(text: &str, max_width: u32, width: impl Fn(&str) -> u32) -> String {
+    if width(text) <= max_width {
+        return text.to_string();
+    }
+    let room = max_width.saturating_sub(width("…"));
+    let mut fitted = String::new();
+    for c in text.chars() {
+        fitted.push(c);
+        if width(&fitted) > room {
+            fitted.pop();
+            break;
+        }
+    }
+    format!("{}…", fitted.trim_end())
+}
+
+fn looks_like_email(text: &str) -> bool {
+    match text.split_once('@') {
+        Some((user, domain)) => !user.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.'),
+        None => false,
+    }
+}
+
+/// Hidden state key holding a table column's filter text
+/// `#` cannot start an identifier, so documents can't collide with it
+fn filter_binding(node: &ViewNode, column: &str, state: &StateStore) -> String {
+    format!("#filter:{}:{}", state.node_key(node), column)
+}
+
+/// State variable holding a table's column widths: `column_widths: var`,
+/// or a hidden per-table key when the document doesn't bind one
+fn widths_binding(node: &ViewNode, state: &StateStore) -> String {
+    match node.props.get("column_widths") {
+        Some(PropValue::Handler(var)) => var.clone(),
+        _ => format!("#widths:{}", state.node_key(node)),
+    }
+}
+
+fn column_width(widths: Option<&Value>, column: &str, default: u32) -> u32 {
+    match widths.map(|w| w.get(&Value::String(column.to_string()))) {
+        Some(Value::Int(w)) => (w.max(0) as u32).max(MIN_COLUMN_WIDTH),
+        Some(Value::Float(w)) => (w.max(0.0) as u32).max(MIN_COLUMN_WIDTH),
+        _ => default,
+    }
+}
+
+/// Table sort order: numbers numerically, then text case-insensitively, nulls last
+fn compare_cells(a: &Value, b: &Value) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    let is_number = |v: &Value| matches!(v, Value::Int(_) | Value::Float(_));
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        _ if is_number(a) && is_number(b) => a.as_float().partial_cmp(&b.as_float()).unwrap_or(Ordering::Equal),
+        _ => a.as_string().to_lowercase().cmp(&b.as_string().to_lowercase()),
+    }
+}
+
+/// Small filled triangle marking a sorted column
+fn draw_sort_arrow(fb: &mut FrameBuffer, x: i32, y: i32, descending: bool, color: u32) {
+    for row in 0..4 {
+        let width = if descending { 7 - row * 2 } else { 1 + row * 2 };
+        fb.fill_rect(x + (7 - width) / 2, y + row, width as u32, 1, color);
+    }
+}
+
+/// Zigzag underline, 3px tall, starting at `y`
+fn draw_squiggle(fb: &mut FrameBuffer, x: i32, y: i32, width: u32, color: u32) {
+    for i in 0..width as i32 {
+        let dy = [0, 1, 2, 1][(i % 4) as usize];
+        if x + i >= 0 && y + dy >= 0 {
+            fb.set_pixel((x + i) as usize, (y + dy) as usize, color);
+        }
+    }
+}
+
+/// Name list items are bound to: `as: name`, default `item`
+fn item_name(node: &ViewNode) -> String {
+    match node.props.get("as") {
+        Some(PropValue::Handler(name)) => name.clone(),
+        _ => "item".to_string(),
+    }
+}
+
+/// State variable bound with `selected: var` on a list or table
+fn selection_binding(node: &ViewNode) -> Option<String> {
+    if !matches!(node.kind, NodeKind::List | NodeKind::Table) {
+        return None;
+    }
+    match node.props.get("selected") {
+        Some(PropValue::Handler(var)) => Some(var.clone()),
+        _ => None,
+    }
+}
+
+/// State variable bound with `page: var` on a paginated list
+fn page_binding(node: &ViewNode) -> Option<String> {
+    match node.props.get("page") {
+        Some(PropValue::Handler(var)) => Some(var.clone()),
+        _ => None,
+    }
+}
+
+/// Fill a rounded block with the skeleton shimmer for `step`, touching only
+/// pixels inside `clip` (x0, y0, x1, y1). The highlight band sweeps across the
+/// whole framebuffer so neighbouring blocks shimmer together.
+#[allow(clippy::too_many_arguments)]
+fn draw_shimmer(fb: &mut FrameBuffer, x: i32, y: i32, w: u32, h: u32, radius: u32, step: u32, clip: (i32, i32, i32, i32)) {
+    let band = 160.0;
+    let t = (step % SHIMMER_PERIOD_STEPS) as f32 / SHIMMER_PERIOD_STEPS as f32;
+    let center = t * (fb.width as f32 + band * 2.0) - band;
+    let r = radius.min(w / 2).min(h / 2) as i32;
+    let (x1, y1) = (x + w as i32, y + h as i32);
+
+    for py in y.max(clip.1).max(0)..y1.min(clip.3).min(fb.height as i32) {
+        let dy = if py < y + r { y + r - py } else if py >= y1 - r { py - (y1 - r - 1) } else { 0 };
+        for px in x.max(clip.0).max(0)..x1.min(clip.2).min(fb.width as i32) {
+            let dx = if px < x + r { x + r - px } else if px >= x1 - r { px - (x1 - r - 1) } else { 0 };
+            if dx > 0 && dy > 0 && dx * dx + dy * dy > r * r {
+                continue;
+            }
+            let falloff = ((px as f32 - center).abs() / band).min(1.0);
+            fb.pixels[py as usize * fb.width + px as usize] = color::mix(SKELETON_HIGHLIGHT, SKELETON_BASE, falloff);
+        }
+    }
+}
+
+/// What an animated region shows
+enum AnimatedContent {
+    /// Animated image by `src`, with the frame index on screen
+    Image { src: String, frame: usize },
+    /// Skeleton block, with the shimmer step on screen
+    Shimmer { radius: u32, step: u32 },
+}
+
+/// Where animated content was drawn during the last full render
+/// Lets frame changes repaint just that region instead of the whole page
+struct AnimatedRegion {
+    content: AnimatedContent,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    /// Visible part of the image rect, clipped to the framebuffer
+    clip: (i32, i32, i32, i32),
+    /// Pixels under the visible part before the content was drawn
+    background: Vec<u32>,
 }
 
 /// The renderer
 pub struct Renderer {
-    font: Font,
+    /// The embedded UI and monospace fonts followed by the document's `@font` fonts
+    fonts: Vec<Font>,
+    /// Index into `fonts` of each loaded `@font` name
+    font_names: HashMap<String, usize>,
+    /// Font of the node being drawn or measured, inherited by its children
+    active_font: Cell<usize>,
     layout: Layout,
     pub layout_boxes: Vec<LayoutBox>,
+    /// Visible text from the last render, for automation and tests
+    pub text_runs: Vec<TextRun>,
+    /// Every node drawn in the last render, in drawing order, for queries
+    pub rendered_nodes: Vec<RenderedNode>,
+    /// Record each drawn node's props in `rendered_nodes` so selectors can
+    /// match them; only hosts that query turn it on, as it formats every
+    /// prop of every node on every render
+    pub record_props: bool,
     pub focused_input: Option<String>,
     pub cursor_visible: bool,
     cursor_blink_timer: u32,
+    /// IME composition shown at the focused field's caret, not yet typed
+    pub preedit: String,
+    /// Byte offset of the caret in `preedit`; `None` puts it at the end
+    pub preedit_cursor: Option<usize>,
+    /// Caret of the focused field in the last render (x, top, height), where
+    /// the host places the IME candidate window
+    pub ime_cursor_area: Option<(i32, i32, u32)>,
     pub log_enabled: bool,
+    glyphs: GlyphCache,
+    /// Text runs laid out within a viewport below the visible area in the last
+    /// render, as (font, text, size), for `prerasterize`
+    upcoming_text: Vec<(usize, String, f32)>,
+    /// Where relative media `src` paths are read from
+    assets: Vfs,
+    /// Content policy deciding whether media may load
+    policy: OriginPolicy,
+    videos: HashMap<String, Result<VideoPlayer, String>>,
+    images: RefCell<HashMap<String, Result<Image, String>>>,
+    animated_regions: Vec<AnimatedRegion>,
+    shimmer_start: Instant,
+    /// Pages revealed so far by incrementally loaded lists, keyed by node
+    revealed_pages: HashMap<usize, usize>,
+    /// `on_end_reached` actions from the last render with each list's item count
+    end_reached: Vec<(String, usize)>,
+    needs_rerender: bool,
+    pub selectables: Vec<Selectable>,
+    pub focus_targets: Vec<FocusTarget>,
+    /// Nodes with gesture handlers in the last render, innermost first
+    pub gestures: Vec<GestureBox>,
+    /// Pointer position in viewport coordinates, while it is over the page
+    pub hover: Option<(i32, i32)>,
+    /// Hover scopes in the last render
+    pub hover_scopes: Vec<HoverScope>,
+    /// Focus targets inside disabled nodes in the last render
+    pub disabled_targets: Vec<FocusTarget>,
+    /// Nodes with an `id` in the last render, for `focus` and `scroll_to`
+    pub node_ids: HashMap<String, NodeAnchor>,
+    pub focused_list: Option<String>,
+    scroll_y: i32,
+    /// Sort column and direction (true = descending) of sortable tables, keyed by node
+    table_sorts: HashMap<usize, (String, bool)>,
+    /// Horizontal scroll offset of wide tables and code blocks, keyed by node
+    table_scroll: HashMap<usize, i32>,
+    pub horizontal_scrollers: Vec<HorizontalScroller>,
+    /// Typed-input settings of the inputs in the last render, by binding
+    pub input_specs: HashMap<String, InputSpec>,
+    pub comboboxes: Vec<Combobox>,
+    dropdowns: Vec<Dropdown>,
+    /// Draft key and list binding of each tags input in the last render
+    pub tag_inputs: Vec<(String, String)>,
+    /// Dictionary text inputs are checked against; none turns checking off
+    pub spelling: Option<Arc<Dictionary>>,
+    /// Words underlined as misspelled in the last render
+    pub misspellings: Vec<Misspelling>,
+    /// Battery saver: no card shadows or button bevels, and skeletons don't shimmer
+    pub plain: bool,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Renderer {
@@ -200,25 +1158,208 @@ impl Renderer {
             scale: 40.0,
             ..FontSettings::default()
         }).expect("Failed to load embedded font");
+        let mono = Font::from_bytes(include_bytes!("../assets/DejaVuSansMono.ttf") as &[u8], FontSettings {
+            scale: 40.0,
+            ..FontSettings::default()
+        }).expect("Failed to load embedded monospace font");
         
         Self {
-            font,
+            fonts: vec![font, mono],
+            font_names: HashMap::new(),
+            active_font: Cell::new(0),
             layout: Layout::new(CoordinateSystem::PositiveYDown),
             layout_boxes: vec![],
+            text_runs: vec![],
+            rendered_nodes: vec![],
+            record_props: false,
             focused_input: None,
+            preedit: String::new(),
+            preedit_cursor: None,
+            ime_cursor_area: None,
             cursor_visible: true,
             cursor_blink_timer: 0,
             log_enabled: false,
+            glyphs: GlyphCache::new(DEFAULT_GLYPH_CACHE_BYTES),
+            upcoming_text: vec![],
+            assets: Vfs::Empty,
+            policy: OriginPolicy::default(),
+            videos: HashMap::new(),
+            images: RefCell::new(HashMap::new()),
+            animated_regions: vec![],
+            shimmer_start: Instant::now(),
+            revealed_pages: HashMap::new(),
+            end_reached: vec![],
+            needs_rerender: false,
+            selectables: vec![],
+            focus_targets: vec![],
+            gestures: vec![],
+            hover: None,
+            hover_scopes: vec![],
+            disabled_targets: vec![],
+            node_ids: HashMap::new(),
+            focused_list: None,
+            scroll_y: 0,
+            table_sorts: HashMap::new(),
+            table_scroll: HashMap::new(),
+            horizontal_scrollers: vec![],
+            input_specs: HashMap::new(),
+            comboboxes: vec![],
+            dropdowns: vec![],
+            tag_inputs: vec![],
+            spelling: None,
+            misspellings: vec![],
+            plain: false,
         }
     }
 
-    /// Update cursor blink state (call each frame)
-    pub fn tick(&mut self) {
+    pub fn set_policy(&mut self, policy: OriginPolicy) {
+        self.policy = policy;
+    }
+
+    /// Load the document's `@font` files from its assets
+    /// Fonts that fail to load are skipped; text using them falls back to the default font
+    pub fn load_fonts(&mut self, faces: &[FontFace]) {
+        self.fonts.truncate(MONO_FONT + 1);
+        self.font_names.clear();
+        self.glyphs.clear();
+        for face in faces {
+            let font = self.assets.read(&face.src, MAX_FONT_FILE_BYTES).and_then(|bytes| {
+                Font::from_bytes(bytes.as_ref(), FontSettings { scale: 40.0, ..FontSettings::default() }).map_err(|e| e.to_string())
+            });
+            match font {
+                Ok(font) => {
+                    self.font_names.insert(face.name.clone(), self.fonts.len());
+                    self.fonts.push(font);
+                }
+                Err(e) => eprintln!("[PRISM FONT] {} ({}): {}", face.name, face.src, e),
+            }
+        }
+    }
+
+    /// Switch to the font named by the node's `font` prop, if it has a loaded one;
+    /// returns the previous font to restore afterwards
+    fn enter_font(&self, node: &ViewNode, state: &StateStore) -> usize {
+        let previous = self.active_font.get();
+        if node.props.contains_key("font") {
+            let name = self.get_string_prop(node, "font", state, "");
+            // Unknown names (including "default") use the embedded font
+            self.active_font.set(self.font_names.get(&name).copied().unwrap_or(0));
+        }
+        previous
+    }
+
+    /// Set the files that relative media `src` paths resolve against
+    pub fn set_assets(&mut self, assets: Vfs) {
+        self.assets = assets;
+        self.videos.clear();
+        self.images.get_mut().clear();
+        self.animated_regions.clear();
+        self.revealed_pages.clear();
+        self.table_sorts.clear();
+        self.table_scroll.clear();
+    }
+
+    /// Update cursor blink and media playback (call each frame)
+    /// Returns true when content changed and needs a full redraw; animated
+    /// images only advance here and are drawn by `repaint_animations`
+    pub fn tick(&mut self) -> bool {
         self.cursor_blink_timer += 1;
         if self.cursor_blink_timer >= 30 {  // Toggle every 30 frames (~0.5s at 60fps)
             self.cursor_visible = !self.cursor_visible;
             self.cursor_blink_timer = 0;
         }
+
+        let mut changed = std::mem::take(&mut self.needs_rerender);
+        for player in self.videos.values_mut().flatten() {
+            if player.is_playing() {
+                player.advance();
+                changed = true;
+            }
+        }
+        for image in self.images.get_mut().values_mut().flatten() {
+            image.advance();
+        }
+        changed
+    }
+
+    /// Redraw animated images and skeletons that changed since they were
+    /// last drawn. Only each region is touched; `(offset_x, offset_y)` maps
+    /// content coordinates into `fb`. Returns true if anything was repainted.
+    pub fn repaint_animations(&mut self, fb: &mut FrameBuffer, offset_x: i32, offset_y: i32) -> bool {
+        let images = self.images.borrow();
+        let step = self.shimmer_step();
+        let mut repainted = false;
+        for region in &mut self.animated_regions {
+            let (x0, y0, x1, y1) = region.clip;
+            let clip = (x0 + offset_x, y0 + offset_y, x1 + offset_x, y1 + offset_y);
+            match &mut region.content {
+                AnimatedContent::Image { src, frame } => {
+                    let Some(Ok(image)) = images.get(src.as_str()) else { continue };
+                    if image.frame_index() == *frame {
+                        continue;
+                    }
+                    *frame = image.frame_index();
+                    fb.write_rect(x0 + offset_x, y0 + offset_y, (x1 - x0) as usize, &region.background);
+                    let pixels = &image.frame().pixels;
+                    fb.blend_scaled(region.x + offset_x, region.y + offset_y, region.width, region.height, image.width, image.height, pixels, clip);
+                }
+                AnimatedContent::Shimmer { radius, step: drawn } => {
+                    if *drawn == step {
+                        continue;
+                    }
+                    *drawn = step;
+                    fb.write_rect(x0 + offset_x, y0 + offset_y, (x1 - x0) as usize, &region.background);
+                    draw_shimmer(fb, region.x + offset_x, region.y + offset_y, region.width, region.height, *radius, step, clip);
+                }
+            }
+            repainted = true;
+        }
+        repainted
+    }
+
+    /// Limit the memory kept by rasterized glyphs
+    pub fn set_glyph_budget(&mut self, bytes: usize) {
+        self.glyphs.set_budget(bytes);
+    }
+
+    /// Bytes held by rasterized glyphs
+    pub fn glyph_cache_bytes(&self) -> usize {
+        self.glyphs.bytes()
+    }
+
+    /// Rasterize the glyphs of text just below the viewport until `deadline`,
+    /// so scrolling to it doesn't stall; call in idle frames. Returns true if
+    /// text is left for later.
+    pub fn prerasterize(&mut self, deadline: Instant) -> bool {
+        while Instant::now() < deadline {
+            let Some((font, text, size)) = self.upcoming_text.pop() else { return false };
+            let Some(font) = self.fonts.get(font) else { continue };
+            self.layout.reset(&LayoutSettings::default());
+            emoji::append_text(&mut self.layout, font, &text, size);
+            for glyph in self.layout.glyphs() {
+                self.glyphs.get(emoji::font_for(font, glyph.font_index), glyph.key);
+            }
+        }
+        !self.upcoming_text.is_empty()
+    }
+
+    /// Take the `on_end_reached` actions triggered by the last render
+    pub fn take_end_reached(&mut self) -> Vec<(String, usize)> {
+        std::mem::take(&mut self.end_reached)
+    }
+
+    fn shimmer_step(&self) -> u32 {
+        if self.plain {
+            return 0;
+        }
+        (self.shimmer_start.elapsed().as_millis() / SHIMMER_STEP_MS) as u32
+    }
+
+    /// Toggle play/pause for a video by its `src`
+    pub fn toggle_media(&mut self, src: &str) {
+        if let Some(Ok(player)) = self.videos.get_mut(src) {
+            player.toggle();
+        }
     }
 
     /// Set which input is focused
@@ -227,12 +1368,36 @@ impl Renderer {
             self.focused_input = binding;
             self.cursor_visible = true;
             self.cursor_blink_timer = 0;
+            self.preedit.clear();
         }
     }
 
+    pub fn selectable(&self, binding: &str) -> Option<&Selectable> {
+        self.selectables.iter().find(|s| s.binding == binding)
+    }
+
     pub fn render(&mut self, fb: &mut FrameBuffer, view: &ViewNode, state: &StateStore, scroll_y: i32) {
         fb.clear(0xFFFFFF);
         self.layout_boxes.clear();
+        self.text_runs.clear();
+        self.rendered_nodes.clear();
+        self.animated_regions.clear();
+        self.end_reached.clear();
+        self.selectables.clear();
+        self.focus_targets.clear();
+        self.disabled_targets.clear();
+        self.gestures.clear();
+        self.hover_scopes.clear();
+        self.ime_cursor_area = None;
+        self.node_ids.clear();
+        self.horizontal_scrollers.clear();
+        self.input_specs.clear();
+        self.comboboxes.clear();
+        self.dropdowns.clear();
+        self.tag_inputs.clear();
+        self.misspellings.clear();
+        self.upcoming_text.clear();
+        self.scroll_y = scroll_y;
         
         let ctx = RenderContext {
             x: 0,
@@ -242,6 +1407,9 @@ impl Renderer {
         };
 
         self.render_node(fb, view, state, &ctx);
+        self.render_dropdowns(fb);
+        // `prerasterize` takes the nearest text first
+        self.upcoming_text.reverse();
     }
 
     pub fn total_content_height(&mut self, view: &ViewNode, state: &StateStore, width: u32) -> u32 {
@@ -266,14 +1434,20 @@ impl Renderer {
             NodeKind::Scroll => "Scroll",
             NodeKind::Text => "Text",
             NodeKind::Markdown => "Markdown",
+            NodeKind::Code => "Code",
             NodeKind::Link => "Link",
             NodeKind::Button => "Button",
             NodeKind::Input => "Input",
+            NodeKind::Combobox => "Combobox",
+            NodeKind::Tags => "Tags",
+            NodeKind::Rating => "Rating",
+            NodeKind::Stepper => "Stepper",
             NodeKind::TextArea => "TextArea",
             NodeKind::Divider => "Divider",
             NodeKind::Spacer => "Spacer",
             NodeKind::Checkbox => "Checkbox",
             NodeKind::Toggle => "Toggle",
+            NodeKind::FilePicker => "FilePicker",
             NodeKind::Radio => "Radio",
             NodeKind::Select => "Select",
             NodeKind::Slider => "Slider",
@@ -291,7 +1465,10 @@ impl Renderer {
             NodeKind::Toast => "Toast",
             NodeKind::Tooltip => "Tooltip",
             NodeKind::Popover => "Popover",
+            NodeKind::Skeleton => "Skeleton",
             NodeKind::Each => "Each",
+            NodeKind::Empty => "Empty",
+            NodeKind::Error => "Error",
             NodeKind::If => "If",
             NodeKind::Show => "Show",
             NodeKind::Switch => "Switch",
@@ -318,20 +1495,168 @@ impl Renderer {
             NodeKind::Grid => width_limit,
             _ => width_limit,
         };
-        for child in &node.children {
-            if !self.is_visible(child, state) { continue; }
-            self.report_node(child, state, child_limit, indent + 2);
-        }
+        state.with_scope(component_bindings(node, state), || {
+            for child in &node.children {
+                if !self.is_visible(child, state) { continue; }
+                self.report_node(child, state, child_limit, indent + 2);
+            }
+        });
     }
 
     fn render_node(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
-        // Check visibility
-        if let Some(PropValue::Expression(expr)) = node.props.get("visible") {
-            let val = state.evaluate(expr);
-            if !val.as_bool() {
-                return;
+        let previous = self.enter_font(node, state);
+        let (first_node, first_target) = (self.rendered_nodes.len(), self.focus_targets.len());
+        let (first_box, first_region) = (self.layout_boxes.len(), self.animated_regions.len());
+        state.with_provided(object_prop(node, "provide", state), || {
+            state.with_scope(object_prop(node, HOISTED_PROP, state), || {
+                // Containers hand children their full width; a set `width` narrows it
+                let width = match node.kind {
+                    NodeKind::Modal => None,
+                    _ => self.explicit_size(node, state).0,
+                };
+                let ctx = &RenderContext { width: width.map_or(ctx.width, |w| w.min(ctx.width)), ..ctx.clone() };
+                match self.enter_hover_scope(node, state, ctx) {
+                    Some(hovering) => state.with_scope(vec![("hovering".to_string(), Value::Bool(hovering))], || self.render_node_content(fb, node, state, ctx)),
+                    None => self.render_node_content(fb, node, state, ctx),
+                }
+            })
+        });
+        if self.rendered_nodes.len() > first_node {
+            if self.get_bool_prop(node, "disabled", state, false) {
+                self.disable_rendered(fb, node, state, ctx, first_box, first_target, first_region);
+            } else {
+                self.register_gestures(node, state, ctx);
             }
         }
+        self.active_font.set(previous);
+
+        // Hidden nodes aren't recorded, so `focus` and `scroll_to` skip them
+        if node.props.contains_key("id") {
+            if let Some(rendered) = self.rendered_nodes.get(first_node) {
+                let anchor = NodeAnchor {
+                    y: rendered.y + self.scroll_y,
+                    height: rendered.height,
+                    focus: self.focus_targets.get(first_target).cloned(),
+                };
+                self.node_ids.insert(self.get_string_prop(node, "id", state, ""), anchor);
+            }
+        }
+    }
+
+    /// Record a node's `on_double_click` / `on_long_press` handlers over its drawn area
+    fn register_gestures(&mut self, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let handler = |name: &str| match node.props.get(name) {
+            Some(PropValue::Handler(action)) => Some(action.clone()),
+            _ => None,
+        };
+        let (on_double_click, on_long_press) = (handler("on_double_click"), handler("on_long_press"));
+        if on_double_click.is_none() && on_long_press.is_none() {
+            return;
+        }
+        let (width, _) = self.measure_node(node, state, ctx.width);
+        self.gestures.push(GestureBox {
+            x: ctx.x,
+            y: ctx.y,
+            width: width.min(ctx.width),
+            height: ctx.height,
+            on_double_click,
+            on_long_press,
+            locals: state.scope_bindings(),
+        });
+    }
+
+    /// Record a node with `on_hover` / `on_leave` as a hover scope, returning
+    /// whether the pointer is over it; `None` for other nodes
+    fn enter_hover_scope(&mut self, node: &ViewNode, state: &StateStore, ctx: &RenderContext) -> Option<bool> {
+        let handler = |name: &str| match node.props.get(name) {
+            Some(PropValue::Handler(action)) => Some(action.clone()),
+            _ => None,
+        };
+        let (on_hover, on_leave) = (handler("on_hover"), handler("on_leave"));
+        if on_hover.is_none() && on_leave.is_none() || self.get_bool_prop(node, "disabled", state, false) {
+            return None;
+        }
+        // Laid out as when not hovered, so hovering can't move the node away
+        let not_hovering = vec![("hovering".to_string(), Value::Bool(false))];
+        let (width, _) = state.with_scope(not_hovering, || self.measure_node(node, state, ctx.width));
+        Some(self.push_hover_scope(HoverScope {
+            x: ctx.x,
+            y: ctx.y + self.scroll_y,
+            width: width.min(ctx.width),
+            height: ctx.height,
+            on_hover,
+            on_leave,
+            locals: state.scope_bindings(),
+        }))
+    }
+
+    /// Record a hover scope, returning whether the pointer is over it
+    fn push_hover_scope(&mut self, scope: HoverScope) -> bool {
+        let hovering = self.hover.is_some_and(|(x, y)| scope.contains(x, y + self.scroll_y));
+        self.hover_scopes.push(scope);
+        hovering
+    }
+
+    /// Hover scopes under a point in viewport coordinates
+    pub fn hover_scopes_at(&self, x: i32, y: i32) -> Vec<HoverScope> {
+        self.hover_scopes.iter().filter(|s| s.contains(x, y + self.scroll_y)).cloned().collect()
+    }
+
+    /// The innermost node with gesture handlers at a point
+    pub fn gesture_at(&self, x: i32, y: i32) -> Option<&GestureBox> {
+        self.gestures.iter().find(|g| x >= g.x && x < g.x + g.width as i32 && y >= g.y && y < g.y + g.height as i32)
+    }
+
+    /// Make a node just rendered inert: its click targets, focus targets and
+    /// animations are dropped (one empty box swallows clicks on it) and it is
+    /// grayed out
+    #[allow(clippy::too_many_arguments)]
+    fn disable_rendered(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext, first_box: usize, first_target: usize, first_region: usize) {
+        let (width, _) = self.measure_node(node, state, ctx.width);
+        let width = width.min(ctx.width);
+        self.layout_boxes.truncate(first_box);
+        self.layout_boxes.push(LayoutBox {
+            x: ctx.x,
+            y: ctx.y,
+            width,
+            height: ctx.height,
+            ..Default::default()
+        });
+        let targets = self.focus_targets.split_off(first_target);
+        self.disabled_targets.extend(targets);
+        // Animations inside would repaint their frames over the fade
+        self.animated_regions.truncate(first_region);
+        fb.fade_rect(ctx.x, ctx.y, width, ctx.height);
+    }
+
+    fn render_node_content(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        // Check visibility
+        if !self.is_visible(node, state) {
+            return;
+        }
+
+        // Loading nodes are swapped for shimmering skeleton blocks
+        if self.is_loading(node, state) {
+            self.render_loading(fb, node, state, ctx);
+            return;
+        }
+
+        let props = if self.record_props {
+            node.props.keys()
+                .filter(|name| *name != HOISTED_PROP)
+                .map(|name| (name.clone(), self.prop_text(node, name, state)))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+        self.rendered_nodes.push(RenderedNode {
+            kind: node.kind.name(),
+            props,
+            x: ctx.x,
+            y: ctx.y,
+            width: ctx.width,
+            height: ctx.height,
+        });
 
         let padding = self.get_int_prop(node, "padding", state, 0) as u32;
         let gap = self.get_int_prop(node, "gap", state, 0) as u32;
@@ -339,6 +1664,26 @@ impl Renderer {
         // Get background color
         let bg_color = self.get_color_prop(node, "background", Color::WHITE);
 
+        // Draw background if not white (badges draw their own pill)
+        let radius = self.corner_radius(node, state).unwrap_or(0);
+        if bg_color != Color::WHITE && node.kind != NodeKind::Badge {
+            let bg = bg_color.to_u32();
+            fb.fill_rounded_rect_vertical_gradient(ctx.x, ctx.y, ctx.width, ctx.height, radius, bg, bg);
+        }
+
+        // The border is drawn inside the node's box, and its content inside the border
+        let border = self.border_width(node, state);
+        if border > 0 {
+            let color = self.get_color_prop(node, "border_color", BORDER_COLOR).to_u32();
+            fb.draw_rounded_outline(ctx.x, ctx.y, ctx.width, ctx.height, radius, color, border);
+        }
+        let ctx = &RenderContext {
+            x: ctx.x + border as i32,
+            y: ctx.y + border as i32,
+            width: ctx.width.saturating_sub(border * 2),
+            height: ctx.height.saturating_sub(border * 2),
+        };
+
         // Inner context after padding
         let inner = RenderContext {
             x: ctx.x + padding as i32,
@@ -347,14 +1692,17 @@ impl Renderer {
             height: ctx.height.saturating_sub(padding * 2),
         };
 
-        // Draw background if not white
-        if bg_color != Color::WHITE {
-            fb.fill_rect(ctx.x, ctx.y, ctx.width, ctx.height, bg_color.to_u32());
+        // Empty/failed collections show their slot instead of the container;
+        // it's laid out as a column here since slots are hidden from normal flow
+        if let Some(slot) = self.active_slot(node, state) {
+            let slot_gap = self.get_int_prop(slot, "gap", state, 0) as u32;
+            self.render_column(fb, slot, state, &inner, slot_gap);
+            return;
         }
 
         match &node.kind {
             // Layout nodes
-            NodeKind::Column | NodeKind::Stack => {
+            NodeKind::Column | NodeKind::Stack | NodeKind::Empty | NodeKind::Error => {
                 self.render_column(fb, node, state, &inner, gap);
             }
             NodeKind::Row => {
@@ -393,6 +1741,9 @@ impl Renderer {
             NodeKind::Link => {
                 self.render_link(fb, node, state, &inner);
             }
+            NodeKind::Code => {
+                self.render_code(fb, node, state, ctx);
+            }
 
             // Interactive nodes
             NodeKind::Button => {
@@ -401,6 +1752,18 @@ impl Renderer {
             NodeKind::Input => {
                 self.render_input(fb, node, state, ctx);
             }
+            NodeKind::Combobox => {
+                self.render_combobox(fb, node, state, ctx);
+            }
+            NodeKind::Tags => {
+                self.render_tags(fb, node, state, ctx);
+            }
+            NodeKind::Rating => {
+                self.render_rating(fb, node, state, ctx);
+            }
+            NodeKind::Stepper => {
+                self.render_stepper(fb, node, state, ctx);
+            }
             NodeKind::TextArea => {
                 self.render_textarea(fb, node, state, ctx);
             }
@@ -419,6 +1782,9 @@ impl Renderer {
             NodeKind::Slider => {
                 self.render_slider(fb, node, state, ctx);
             }
+            NodeKind::FilePicker => {
+                self.render_file_picker(fb, node, state, ctx);
+            }
 
             // Media nodes
             NodeKind::Image => {
@@ -427,7 +1793,10 @@ impl Renderer {
             NodeKind::Icon => {
                 self.render_icon(fb, node, state, ctx);
             }
-            NodeKind::Video | NodeKind::Audio => {
+            NodeKind::Video => {
+                self.render_video(fb, node, state, ctx);
+            }
+            NodeKind::Audio => {
                 self.render_media_placeholder(fb, node, state, ctx);
             }
 
@@ -461,6 +1830,9 @@ impl Renderer {
                     self.render_node(fb, child, state, &inner);
                 }
             }
+            NodeKind::Skeleton => {
+                self.render_skeleton(fb, node, state, ctx);
+            }
 
             // Control flow nodes
             NodeKind::Each => {
@@ -485,68 +1857,137 @@ impl Renderer {
                 }
             }
 
-            // Custom components
-            NodeKind::Component(_name) => {
-                // Component rendering would look up the component def
-                for child in &node.children {
-                    self.render_node(fb, child, state, &inner);
+            // Custom components: the expanded view, with the instance's state and props in scope
+            NodeKind::Component(name) => {
+                let (instance, _) = state.component_instance(state.node_key(node), name);
+                let bindings = component_bindings(node, state);
+                let first_box = self.layout_boxes.len();
+                state.with_scope(bindings, || self.render_column(fb, node, state, &inner, gap));
+                for layout_box in &mut self.layout_boxes[first_box..] {
+                    layout_box.component.get_or_insert(instance);
                 }
             }
         }
     }
 
+    /// Children top to bottom; `justify` places them in height the column has
+    /// to spare, `align` across its width (`stretch`, the default, gives each
+    /// child the full width)
     fn render_column(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext, gap: u32) {
-        let mut y = ctx.y;
-
-        for child in &node.children {
-            if !self.is_visible(child, state) {
-                continue;
+        let mut measures: Vec<(u32, u32, &ViewNode)> = node.children.iter()
+            .filter(|child| self.is_visible(child, state))
+            .map(|child| {
+                let (w, h) = self.measure_node(child, state, ctx.width);
+                (w, h, child)
+            })
+            .collect();
+        let mut total_h = measures.iter().map(|&(_, h, _)| h).sum::<u32>() + gap * (measures.len() as u32).saturating_sub(1);
+
+        // `flex` children share the height the column has to spare
+        let weights: Vec<u32> = measures.iter().map(|&(_, _, child)| self.flex_weight(child, state)).collect();
+        if weights.iter().any(|&weight| weight > 0) {
+            let mut heights: Vec<u32> = measures.iter().map(|&(_, h, _)| h).collect();
+            grow(&mut heights, &weights, ctx.height.saturating_sub(total_h));
+            for (measure, height) in measures.iter_mut().zip(heights) {
+                measure.1 = height;
             }
+            total_h = total_h.max(ctx.height);
+        }
+        let justify = self.get_keyword_prop(node, "justify", state, "start");
+        let (mut y, gap) = distribute(ctx.y, ctx.height, total_h, gap, measures.len(), &justify);
+        let align = self.get_keyword_prop(node, "align", state, "stretch");
 
-            let (_, child_h) = self.measure_node(child, state, ctx.width);
-            let child_ctx = RenderContext {
-                x: ctx.x,
-                y,
-                width: ctx.width,
-                height: child_h,
+        for (w, h, child) in measures {
+            let (x, width) = match align.as_str() {
+                "start" => (ctx.x, w),
+                "center" => (ctx.x + (ctx.width as i32 - w as i32) / 2, w),
+                "end" => (ctx.x + ctx.width as i32 - w as i32, w),
+                _ => (ctx.x, ctx.width),
             };
-
+            let child_ctx = RenderContext { x, y, width, height: h };
             self.render_node(fb, child, state, &child_ctx);
-            y += child_h as i32 + gap as i32;
+            y += h as i32 + gap as i32;
         }
     }
 
+    /// Children left to right; `justify` places them in the row's width
+    /// (centered by default), `align` across its height (also centered)
     fn render_row(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext, gap: u32) {
-        let mut max_h = 0u32;
-        let mut total_w = 0u32;
+        let lines = self.row_lines(node, state, ctx.width, gap);
+        let justify = self.get_keyword_prop(node, "justify", state, "center");
+        let align = self.get_keyword_prop(node, "align", state, "center");
+        let single = lines.len() == 1;
+        let mut line_y = ctx.y;
 
-        // Pre-measure children to layout naturally
-        let mut measures: Vec<(u32, u32, &ViewNode)> = vec![];
+        for line in lines {
+            let max_h = line.iter().map(|&(_, h, _)| h).max().unwrap_or(0);
+            let total_w = line.iter().map(|&(w, _, _)| w).sum::<u32>() + gap * (line.len() as u32 - 1);
+            let (mut x, line_gap) = distribute(ctx.x, ctx.width, total_w, gap, line.len(), &justify);
+            // A single line has the row's height to align in, wrapped ones their own
+            let room = if single { ctx.height.max(max_h) as i32 } else { max_h as i32 };
+
+            for (w, h, child) in line {
+                let (y, height) = match align.as_str() {
+                    "start" => (line_y, h),
+                    "end" => (line_y + room - h as i32, h),
+                    // A child's own `height` wins over stretching
+                    "stretch" if !child.props.contains_key("height") => (line_y, room as u32),
+                    "stretch" => (line_y, h),
+                    _ => (line_y + (room - h as i32) / 2, h),
+                };
+                let child_ctx = RenderContext { x, y, width: w, height };
+                self.render_node(fb, child, state, &child_ctx);
+                x += w as i32 + line_gap as i32;
+            }
+            line_y += room + gap as i32;
+        }
+    }
+
+    /// A row's visible children with their measured sizes, in lines: one line,
+    /// or with `wrap: true` as many as it takes to keep each within `width`
+    /// (a child wider than that gets a line to itself). `flex` children of a
+    /// line split the width the others leave by weight, whatever their own
+    /// width, and are measured again at the new width for their height
+    fn row_lines<'a>(&self, node: &'a ViewNode, state: &StateStore, width: u32, gap: u32) -> Vec<Vec<(u32, u32, &'a ViewNode)>> {
+        let wrap = self.get_bool_prop(node, "wrap", state, false);
+        let mut lines: Vec<Vec<(u32, u32, &ViewNode)>> = vec![];
+        let mut line_w = 0u32;
         for child in &node.children {
             if !self.is_visible(child, state) {
                 continue;
             }
-            let (w, h) = self.measure_node(child, state, ctx.width);
-            max_h = max_h.max(h);
-            total_w += w;
-            measures.push((w, h, child));
+            let (w, h) = self.measure_node(child, state, width);
+            match lines.last_mut() {
+                Some(line) if !wrap || line_w + gap + w <= width => {
+                    line_w += gap + w;
+                    line.push((w, h, child));
+                }
+                _ => {
+                    line_w = w;
+                    lines.push(vec![(w, h, child)]);
+                }
+            }
         }
-        if !measures.is_empty() {
-            total_w = total_w.saturating_add(gap * (measures.len() as u32 - 1));
+        for line in &mut lines {
+            let weights: Vec<u32> = line.iter().map(|&(_, _, child)| self.flex_weight(child, state)).collect();
+            if weights.iter().all(|&weight| weight == 0) {
+                continue;
+            }
+            let mut widths: Vec<u32> = line.iter().zip(&weights).map(|(&(w, _, _), &weight)| if weight > 0 { 0 } else { w }).collect();
+            let used = widths.iter().sum::<u32>() + gap * (line.len() as u32 - 1);
+            grow(&mut widths, &weights, width.saturating_sub(used));
+            for ((entry, w), weight) in line.iter_mut().zip(widths).zip(weights) {
+                if weight > 0 {
+                    *entry = (w, self.measure_node(entry.2, state, w).1, entry.2);
+                }
+            }
         }
+        lines
+    }
 
-        let mut x = ctx.x + ((ctx.width as i32 - total_w as i32) / 2).max(0);
-
-        for (w, h, child) in measures {
-            let child_ctx = RenderContext {
-                x,
-                y: ctx.y + (max_h as i32 - h as i32) / 2,
-                width: w,
-                height: h,
-            };
-            self.render_node(fb, child, state, &child_ctx);
-            x += w as i32 + gap as i32;
-        }
+    /// A child's `flex` weight in its row or column; 0 keeps its own size
+    fn flex_weight(&self, node: &ViewNode, state: &StateStore) -> u32 {
+        self.get_int_prop(node, "flex", state, 0).max(0) as u32
     }
 
     fn render_text(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
@@ -569,38 +2010,122 @@ impl Renderer {
         }
     }
 
+    /// Monospace lines on a tinted block, colored by `highlight::tokenize_code`
+    /// for the `language` prop; lines don't wrap, and a block wider than its
+    /// box scrolls sideways like a wide table
+    fn render_code(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let source = expand_tabs(&self.get_string_prop(node, "content", state, ""));
+        let language = self.get_keyword_prop(node, "language", state, "");
+        let size = self.get_int_prop(node, "size", state, CODE_TEXT_SIZE) as f32;
+        let color = self.get_color_prop(node, "color", Color::BLACK).to_u32();
+        let bg = self.get_color_prop(node, "background", CODE_BG).to_u32();
+        fb.fill_rounded_rect_vertical_gradient(ctx.x, ctx.y, ctx.width, ctx.height, 6, bg, bg);
+
+        // Each byte's color; a token colors its whole range
+        let mut ink = vec![color; source.len()];
+        for token in highlight::tokenize_code(&language, &source) {
+            if let Some(rgb) = token.class.rgb() {
+                ink[token.start..token.end].fill(rgb);
+            }
+        }
+
+        let previous = self.active_font.replace(MONO_FONT);
+        let advance = self.mono_advance(size);
+        let (asc, desc, gap) = self.line_metrics(size);
+        let line_height = asc + desc + gap;
+        let left = ctx.x + CODE_PADDING as i32;
+        let room = ctx.width.saturating_sub(2 * CODE_PADDING);
+        let widest = source.lines().map(|line| line.chars().count()).max().unwrap_or(0) as u32 * advance;
+        let max_scroll = widest.saturating_sub(room) as i32;
+        let scroll = self.table_scroll.get(&state.node_key(node)).copied().unwrap_or(0).clamp(0, max_scroll);
+        if max_scroll > 0 {
+            let thumb_w = (room as u64 * room as u64 / widest as u64) as u32;
+            let thumb_x = left + ((room - thumb_w) as i64 * scroll as i64 / max_scroll as i64) as i32;
+            fb.fill_rect(thumb_x, ctx.y + ctx.height as i32 - 4, thumb_w, 3, 0xBBBBBB);
+            self.horizontal_scrollers.push(HorizontalScroller {
+                table: state.node_key(node),
+                x: ctx.x,
+                y: ctx.y,
+                width: ctx.width,
+                height: ctx.height,
+                max_scroll,
+            });
+        }
+
+        // Only whole columns inside the box are drawn, so nothing spills past its edges
+        let first = (scroll as u32).div_ceil(advance.max(1)) as usize;
+        let last = ((scroll as u32 + room) / advance.max(1)) as usize;
+        let mut y = ctx.y + CODE_PADDING as i32;
+        let mut offset = 0;
+        for line in source.split('\n') {
+            let baseline = self.baseline_in_box(y, line_height, size);
+            let mut run = String::new();
+            let mut run_start = first;
+            let mut run_ink = color;
+            for (column, (i, c)) in line.char_indices().enumerate().take(last).skip(first) {
+                let c_ink = ink[offset + i];
+                if c_ink != run_ink && !run.is_empty() {
+                    let x = left + (run_start as u32 * advance) as i32 - scroll;
+                    self.draw_text(fb, &run, x, baseline, size, run_ink);
+                    run.clear();
+                }
+                if run.is_empty() {
+                    run_start = column;
+                    run_ink = c_ink;
+                }
+                run.push(c);
+            }
+            if !run.trim().is_empty() {
+                let x = left + (run_start as u32 * advance) as i32 - scroll;
+                self.draw_text(fb, &run, x, baseline, size, run_ink);
+            }
+            offset += line.len() + 1;
+            y += line_height;
+        }
+        self.active_font.set(previous);
+    }
+
+    /// Width of every char in the monospace font at `size`
+    fn mono_advance(&self, size: f32) -> u32 {
+        self.fonts[MONO_FONT].metrics('0', size).advance_width.round().max(1.0) as u32
+    }
+
     fn render_button(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
         let content = self.get_string_prop(node, "content", state, "Button");
         let color = self.get_color_prop(node, "color", Color::BLACK);
         let bg = self.get_color_prop(node, "background", Color::LIGHT_GRAY);
-        let btn_height = 36u32;
+        let (width, height) = self.explicit_size(node, state);
+        let btn_height = height.unwrap_or(36);
         let text_size = 14.0;
         let tw = self.line_pixel_width(&content, text_size).max(self.text_width(&content, text_size));
         let mut btn_width = tw.saturating_add(24).max(36).min(ctx.width);
         if content.chars().count() <= 2 { btn_width = 36; }
+        if width.is_some() { btn_width = ctx.width; }
         let btn_x = ctx.x;
         let btn_y = ctx.y + (ctx.height as i32 - btn_height as i32) / 2;
 
         let top = bg.to_u32();
         let bottom = bg.to_u32();
         fb.fill_rounded_rect_vertical_gradient(btn_x, btn_y, btn_width, btn_height, 10, top, bottom);
-        let top_hl = mix_color(top, 0xFFFFFF, 0.15);
-        let bot_sh = mix_color(bottom, 0x000000, 0.12);
-        fb.fill_rect(btn_x + 2, btn_y + 1, btn_width.saturating_sub(4), 1, top_hl);
-        fb.fill_rect(btn_x + 2, btn_y + btn_height as i32 - 2, btn_width.saturating_sub(4), 1, bot_sh);
+        if !self.plain {
+            let top_hl = color::mix(top, 0xFFFFFF, 0.15);
+            let bot_sh = color::mix(bottom, 0x000000, 0.12);
+            fb.fill_rect(btn_x + 2, btn_y + 1, btn_width.saturating_sub(4), 1, top_hl);
+            fb.fill_rect(btn_x + 2, btn_y + btn_height as i32 - 2, btn_width.saturating_sub(4), 1, bot_sh);
+        }
 
         if content.chars().count() <= 2 {
             let size = 16.0;
             let lines = self.wrap_text(&content, size, btn_width);
             if let Some(line) = lines.first() {
                 self.layout.reset(&LayoutSettings::default());
-                self.layout.append(&[&self.font], &TextStyle::new(line, size, 0));
+                emoji::append_text(&mut self.layout, &self.fonts[self.active_font.get()], line, size);
                 let mut min_x = f32::MAX;
                 let mut min_y = f32::MAX;
                 let mut max_x = f32::MIN;
                 let mut max_y = f32::MIN;
                 for g in self.layout.glyphs() {
-                    let (m, _) = self.font.rasterize_config(g.key);
+                    let (m, _) = self.glyphs.get(emoji::font_for(&self.fonts[self.active_font.get()], g.font_index), g.key);
                     min_x = min_x.min(g.x);
                     min_y = min_y.min(g.y);
                     max_x = max_x.max(g.x + m.width as f32);
@@ -611,7 +2136,7 @@ impl Renderer {
                 let left = btn_x + (btn_width as i32 - bw) / 2;
                 let top = btn_y + (btn_height as i32 - bh) / 2;
                 for g in self.layout.glyphs() {
-                    let (m, bitmap) = self.font.rasterize_config(g.key);
+                    let (m, bitmap) = self.glyphs.get(emoji::font_for(&self.fonts[self.active_font.get()], g.font_index), g.key);
                     let gx = left + (g.x - min_x).round() as i32;
                     let gy = top + (g.y - min_y).round() as i32;
                     for (i, alpha) in bitmap.iter().enumerate() {
@@ -636,8 +2161,7 @@ impl Renderer {
                 width: btn_width,
                 height: btn_height,
                 action: Some(action.clone()),
-                input_binding: None,
-                link_href: None,
+                ..Default::default()
             });
         }
     }
@@ -655,12 +2179,20 @@ impl Renderer {
             .map(|v| v.as_string())
             .unwrap_or_default();
 
-        let input_height = 36u32;
-        let input_width = ctx.width.saturating_sub(20).min(280);
+        let (input_width, input_height) = self.field_size(node, state, ctx);
         let input_x = ctx.x;
         let input_y = ctx.y + (ctx.height as i32 - input_height as i32) / 2;
         let text_size = 14.0;
 
+        let spec = self.input_spec(node, state);
+        let reveal_key = binding.as_ref().map(|b| reveal_binding(b));
+        let revealed = reveal_key.as_ref().and_then(|k| state.get(k)).map(|v| v.as_bool()).unwrap_or(false);
+        let controls_width = match spec.kind.as_str() {
+            "number" => NUMBER_SPINNER_WIDTH,
+            "password" => PASSWORD_TOGGLE_WIDTH,
+            _ => 0,
+        };
+
         // Check if this input is focused
         let is_focused = binding.as_ref()
             .map(|b| self.focused_input.as_ref() == Some(b))
@@ -669,14 +2201,22 @@ impl Renderer {
         // Draw input background
         fb.fill_rect(input_x, input_y, input_width, input_height, 0xFFFFFF);
         
-        // Draw border (blue if focused)
-        let border_color = if is_focused { 0x4285F4 } else { 0xCCCCCC };
+        // Draw border (blue if focused, red for a malformed email)
+        let invalid = spec.kind == "email" && !value.is_empty() && !is_focused && !looks_like_email(&value);
+        let border_color = if invalid { 0xE53935 } else if is_focused { 0x4285F4 } else { 0xCCCCCC };
         fb.draw_rect_outline(input_x, input_y, input_width, input_height, border_color, if is_focused { 2 } else { 1 });
 
         // Calculate text area
         let text_x = input_x + 10;
         let text_y = self.baseline_in_box(input_y, input_height as i32, text_size);
-        let max_text_width = input_width.saturating_sub(20) as usize;
+        let max_text_width = input_width.saturating_sub(20 + controls_width) as usize;
+
+        // Passwords show one bullet per character unless revealed
+        let value = if spec.kind == "password" && !revealed {
+            "•".repeat(value.chars().count())
+        } else {
+            value
+        };
 
         // Draw text or placeholder
         if value.is_empty() && !is_focused {
@@ -685,32 +2225,377 @@ impl Renderer {
             self.draw_text(fb, &display_text, text_x, text_y, text_size, 0x999999);
         } else {
             // Truncate value if too long (show end of text)
-            let display_text: String = if value.len() * 8 > max_text_width {
-                value.chars().skip(value.len().saturating_sub(max_text_width / 8)).collect()
+            let char_count = value.chars().count();
+            let display_text: String = if char_count * 8 > max_text_width {
+                value.chars().skip(char_count.saturating_sub(max_text_width / 8)).collect()
             } else {
                 value.clone()
             };
             self.draw_text(fb, &display_text, text_x, text_y, text_size, 0x000000);
-            
+            let checked = spec.kind == "text" && spec.mask.is_none();
+            if let (true, Some(b)) = (checked && self.get_bool_prop(node, "spellcheck", state, true), &binding) {
+                let line = SpellLine { text: &value, from: value.len() - display_text.len(), x: text_x, baseline: text_y, size: text_size };
+                self.underline_misspellings(fb, b, &line, 0, is_focused);
+            }
+
             // Draw cursor if focused
-            if is_focused && self.cursor_visible {
-                let cursor_x = text_x + self.text_width(&display_text, text_size) as i32;
+            if is_focused {
                 let (_, descent, _) = self.line_metrics(text_size);
                 let cursor_height = (text_size as i32 + descent).max(14);
-                fb.fill_rect(cursor_x, text_y - (text_size as i32), 2, cursor_height as u32, 0x000000);
+                let caret_x = text_x + self.text_width(&display_text, text_size) as i32;
+                let cursor_x = self.draw_preedit(fb, caret_x, text_y - text_size as i32, cursor_height as u32, text_y, text_size);
+                if self.cursor_visible {
+                    fb.fill_rect(cursor_x, text_y - (text_size as i32), 2, cursor_height as u32, 0x000000);
+                }
             }
         }
 
         // Register layout box for input
+        if let Some(b) = &binding {
+            self.focus_targets.push(FocusTarget::Input(b.clone()));
+            self.input_specs.insert(b.clone(), spec.clone());
+        }
+        let controls_x = input_x + input_width as i32 - controls_width as i32;
+        match (spec.kind.as_str(), &binding) {
+            ("number", Some(b)) => {
+                let current = state.get(b).map(|v| v.as_float()).unwrap_or(0.0);
+                self.render_number_spinner(fb, b, current, &spec, controls_x, input_y, input_height);
+            }
+            ("password", Some(_)) => {
+                let label = if revealed { "Hide" } else { "Show" };
+                let label_x = controls_x + (PASSWORD_TOGGLE_WIDTH as i32 - self.text_width(label, 12.0) as i32) / 2;
+                let baseline = self.baseline_in_box(input_y, input_height as i32, 12.0);
+                self.draw_text(fb, label, label_x, baseline, 12.0, 0x4285F4);
+                self.layout_boxes.push(LayoutBox {
+                    x: controls_x,
+                    y: input_y,
+                    width: PASSWORD_TOGGLE_WIDTH,
+                    height: input_height,
+                    set_state: reveal_key.map(|k| (k, Value::Bool(!revealed))),
+                    ..Default::default()
+                });
+            }
+            _ => {}
+        }
         self.layout_boxes.push(LayoutBox {
             x: input_x,
             y: input_y,
             width: input_width,
             height: input_height,
-            action: None,
             input_binding: binding,
-            link_href: None,
+            ..Default::default()
+        });
+    }
+
+    /// Input with a list of `options` containing the typed text, shown while focused
+    fn render_combobox(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        self.render_input(fb, node, state, ctx);
+        let Some(PropValue::Handler(binding)) = node.props.get("bind") else {
+            return;
+        };
+
+        let options = self.get_list_prop(node, "options", state).unwrap_or_default();
+        let (width, height) = self.field_size(node, state, ctx);
+        let input_y = ctx.y + (ctx.height as i32 - height as i32) / 2;
+        let anchor = (ctx.x, input_y + height as i32 + 2, width);
+        self.register_suggestions(node, state, binding, options, anchor);
+    }
+
+    /// Offer the `options` containing the text bound to `binding` below `anchor` (x, y, width)
+    fn register_suggestions(&mut self, node: &ViewNode, state: &StateStore, binding: &str, options: Vec<Value>, anchor: (i32, i32, u32)) {
+        let query = state.get(binding).map(|v| v.as_string()).unwrap_or_default().to_lowercase();
+        let matches: Vec<Value> = options.into_iter()
+            .filter(|option| option.as_string().to_lowercase().contains(&query))
+            .take(MAX_SUGGESTIONS)
+            .collect();
+        let closed = state.get(&suggestions_closed_key(binding)).map(|v| v.as_bool()).unwrap_or(false);
+        let open = self.focused_input.as_deref() == Some(binding) && !closed && !matches.is_empty();
+
+        if open {
+            let highlighted = state.get(&suggestion_highlight_key(binding))
+                .map(|v| v.as_int())
+                .and_then(|i| usize::try_from(i).ok());
+            self.dropdowns.push(Dropdown {
+                binding: binding.to_string(),
+                x: anchor.0,
+                y: anchor.1,
+                width: anchor.2,
+                options: matches.iter().map(|m| m.as_string()).collect(),
+                highlighted,
+            });
+        }
+        self.comboboxes.push(Combobox {
+            binding: binding.to_string(),
+            matches,
+            on_select: match node.props.get("on_select") {
+                Some(PropValue::Handler(action)) => Some(action.clone()),
+                _ => None,
+            },
+            open,
+        });
+    }
+
+    /// Width and height of a text field: 280 by 36 unless the node sets them
+    fn field_size(&self, node: &ViewNode, state: &StateStore, ctx: &RenderContext) -> (u32, u32) {
+        let (width, height) = self.explicit_size(node, state);
+        (width.map_or(ctx.width.saturating_sub(20).min(280), |_| ctx.width), height.unwrap_or(36))
+    }
+
+    /// Chips for each entry of the bound list followed by a text field for the next one
+    fn render_tags(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let Some(PropValue::Handler(binding)) = node.props.get("bind") else {
+            return;
+        };
+        let tags = self.get_list_prop(node, "bind", state).unwrap_or_default();
+        let labels: Vec<String> = tags.iter().map(|t| t.as_string()).collect();
+        let width = self.tags_width(node, state, ctx.width);
+        let layout = self.tags_layout(&labels, width);
+        let draft_key = tag_draft_key(binding);
+        let draft = state.get(&draft_key).map(|v| v.as_string()).unwrap_or_default();
+        let focused = self.focused_input.as_deref() == Some(draft_key.as_str());
+        let (x, y) = (ctx.x, ctx.y);
+
+        fb.fill_rect(x, y, width, layout.height, 0xFFFFFF);
+        fb.draw_rect_outline(x, y, width, layout.height, if focused { 0x4285F4 } else { 0xCCCCCC }, if focused { 2 } else { 1 });
+
+        // The field covers the whole box; chip remove buttons sit in front of it
+        let mut chip_boxes = vec![];
+        for (i, (label, &(cx, cy, cw))) in labels.iter().zip(&layout.chips).enumerate() {
+            let (chip_x, chip_y) = (x + cx, y + cy);
+            fb.fill_rounded_rect_vertical_gradient(chip_x, chip_y, cw, CHIP_HEIGHT, CHIP_HEIGHT / 2, 0xE8EAED, 0xE8EAED);
+            let baseline = self.baseline_in_box(chip_y, CHIP_HEIGHT as i32, 13.0);
+            let label = self.fit_text(label, 13.0, cw.saturating_sub(34));
+            self.draw_text(fb, &label, chip_x + 10, baseline, 13.0, 0x333333);
+            let close_x = chip_x + cw as i32 - 20;
+            self.draw_text(fb, "×", close_x + 4, baseline, 14.0, 0x777777);
+
+            let mut remaining = tags.clone();
+            remaining.remove(i);
+            chip_boxes.push(LayoutBox {
+                x: close_x,
+                y: chip_y,
+                width: 20,
+                height: CHIP_HEIGHT,
+                set_state: Some((binding.clone(), Value::List(remaining))),
+                ..Default::default()
+            });
+        }
+
+        let (dx, dy) = (x + layout.draft.0, y + layout.draft.1);
+        let baseline = self.baseline_in_box(dy, CHIP_HEIGHT as i32, 14.0);
+        if draft.is_empty() && !focused && labels.is_empty() {
+            let placeholder = self.get_string_prop(node, "placeholder", state, "");
+            self.draw_text(fb, &placeholder, dx + 4, baseline, 14.0, 0x999999);
+        } else {
+            let text = self.fit_text(&draft, 14.0, layout.draft.2.saturating_sub(8));
+            self.draw_text(fb, &text, dx + 4, baseline, 14.0, 0x000000);
+            if focused {
+                let caret_x = dx + 4 + self.text_width(&text, 14.0) as i32;
+                let cursor_x = self.draw_preedit(fb, caret_x, dy + 4, CHIP_HEIGHT - 8, baseline, 14.0);
+                if self.cursor_visible {
+                    fb.fill_rect(cursor_x, dy + 4, 2, CHIP_HEIGHT - 8, 0x000000);
+                }
+            }
+        }
+
+        self.layout_boxes.extend(chip_boxes);
+        self.layout_boxes.push(LayoutBox {
+            x,
+            y,
+            width,
+            height: layout.height,
+            input_binding: Some(draft_key.clone()),
+            ..Default::default()
         });
+        self.focus_targets.push(FocusTarget::Input(draft_key.clone()));
+        self.tag_inputs.push((draft_key.clone(), binding.clone()));
+
+        if let Some(options) = self.get_list_prop(node, "options", state) {
+            let unused = options.into_iter().filter(|o| !tags.contains(o)).collect();
+            if !draft.is_empty() {
+                self.register_suggestions(node, state, &draft_key, unused, (x, y + layout.height as i32 + 2, width));
+            }
+        }
+    }
+
+    /// Row of `max` stars; clicking star n sets the bound int to n
+    fn render_rating(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let count = self.get_int_prop(node, "max", state, 5).clamp(1, 20);
+        let size = self.get_int_prop(node, "size", state, 24).max(8) as u32;
+        let binding = match node.props.get("bind") {
+            Some(PropValue::Handler(b)) => Some(b.clone()),
+            _ => None,
+        };
+        let value = binding.as_ref().and_then(|b| state.get(b)).map(|v| v.as_int()).unwrap_or(0);
+        let filled = self.get_color_prop(node, "color", RATING_COLOR);
+        let on_change = match node.props.get("on_change") {
+            Some(PropValue::Handler(action)) => Some(action.clone()),
+            _ => None,
+        };
+
+        for i in 0..count {
+            let x = ctx.x + (i as u32 * (size + RATING_GAP)) as i32;
+            let color = if i < value { filled.to_u32() } else { 0xDDDDDD };
+            fb.fill_polygon_aa(&star_points(x as f32, ctx.y as f32, size as f32), color);
+            if let Some(b) = &binding {
+                self.layout_boxes.push(LayoutBox {
+                    x,
+                    y: ctx.y,
+                    width: size,
+                    height: size,
+                    action: on_change.clone(),
+                    set_state: Some((b.clone(), Value::Int(i + 1))),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// − value + control stepping the bound number by `step` within `min`/`max`
+    fn render_stepper(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let spec = self.input_spec(node, state);
+        let binding = match node.props.get("bind") {
+            Some(PropValue::Handler(b)) => Some(b.clone()),
+            _ => None,
+        };
+        let value = binding.as_ref().and_then(|b| state.get(b)).map(|v| v.as_float()).unwrap_or(0.0);
+        let on_change = match node.props.get("on_change") {
+            Some(PropValue::Handler(action)) => Some(action.clone()),
+            _ => None,
+        };
+        let clip = (0, 0, fb.width as i32, fb.height as i32);
+        let radius = STEPPER_BUTTON as f32 / 2.0;
+
+        let buttons = [(ctx.x, -1.0), (ctx.x + (STEPPER_BUTTON + STEPPER_VALUE_WIDTH) as i32, 1.0)];
+        for (x, direction) in buttons {
+            let next = spec.clamp(value + direction * spec.step);
+            let enabled = next != value;
+            let (cx, cy) = (x as f32 + radius, ctx.y as f32 + radius);
+            fb.fill_circle_aa(cx, cy, radius, 0xCCCCCC, clip);
+            fb.fill_circle_aa(cx, cy, radius - 1.0, if enabled { 0xF5F6F8 } else { 0xFBFBFB }, clip);
+
+            // Minus and plus signs
+            let sign_color = if enabled { 0x333333 } else { 0xBBBBBB };
+            let (mid_x, mid_y) = (x + STEPPER_BUTTON as i32 / 2, ctx.y + STEPPER_BUTTON as i32 / 2);
+            fb.fill_rect(mid_x - 5, mid_y - 1, 10, 2, sign_color);
+            if direction > 0.0 {
+                fb.fill_rect(mid_x - 1, mid_y - 5, 2, 10, sign_color);
+            }
+
+            if let (true, Some(b)) = (enabled, &binding) {
+                self.layout_boxes.push(LayoutBox {
+                    x,
+                    y: ctx.y,
+                    width: STEPPER_BUTTON,
+                    height: STEPPER_BUTTON,
+                    action: on_change.clone(),
+                    set_state: Some((b.clone(), number_value(next))),
+                    ..Default::default()
+                });
+            }
+        }
+
+        let text = number_value(value).as_string();
+        let text_x = ctx.x + STEPPER_BUTTON as i32 + (STEPPER_VALUE_WIDTH as i32 - self.text_width(&text, 15.0) as i32) / 2;
+        let baseline = self.baseline_in_box(ctx.y, STEPPER_BUTTON as i32, 15.0);
+        self.draw_text(fb, &text, text_x, baseline, 15.0, 0x222222);
+    }
+
+    /// Width of the tags box: capped at `TAGS_MAX_WIDTH` unless the node sets it
+    fn tags_width(&self, node: &ViewNode, state: &StateStore, room: u32) -> u32 {
+        match self.explicit_size(node, state).0 {
+            Some(_) => room,
+            None => room.saturating_sub(20).min(TAGS_MAX_WIDTH),
+        }
+    }
+
+    /// Wrap chips and the draft field into lines inside the tags box
+    fn tags_layout(&self, labels: &[String], width: u32) -> TagsLayout {
+        let inner = width.saturating_sub(TAGS_PADDING * 2);
+        let mut widths: Vec<u32> = labels.iter()
+            .map(|l| (self.text_width(l, 13.0) + 34).min(inner))
+            .collect();
+        widths.push(TAGS_MIN_DRAFT_WIDTH.min(inner));
+        let (positions, height) = flow_layout(&widths, inner, TAGS_GAP, CHIP_HEIGHT);
+
+        let offset = |(px, py): (u32, u32)| ((px + TAGS_PADDING) as i32, (py + TAGS_PADDING) as i32);
+        let chips = positions[..labels.len()].iter().zip(&widths)
+            .map(|(&p, &w)| { let (x, y) = offset(p); (x, y, w) })
+            .collect();
+        // The draft field stretches to the end of its line
+        let (draft_x, draft_y) = offset(positions[labels.len()]);
+        let draft_w = (width as i32 - TAGS_PADDING as i32 - draft_x).max(0) as u32;
+        TagsLayout { chips, draft: (draft_x, draft_y, draft_w), height: height + TAGS_PADDING * 2 }
+    }
+
+    /// Draw open combobox suggestions over the page; their boxes take hit-test priority
+    fn render_dropdowns(&mut self, fb: &mut FrameBuffer) {
+        let mut boxes = vec![];
+        for dropdown in std::mem::take(&mut self.dropdowns) {
+            let height = SUGGESTION_HEIGHT * dropdown.options.len() as u32;
+            // Animations underneath would repaint over the list (its shadow included)
+            let (left, top) = (dropdown.x, dropdown.y);
+            let (right, bottom) = (left + dropdown.width as i32 + 2, top + height as i32 + 2);
+            self.animated_regions.retain(|region| {
+                let (x0, y0, x1, y1) = region.clip;
+                x1 <= left || x0 >= right || y1 <= top || y0 >= bottom
+            });
+            fb.fill_rect(dropdown.x + 2, dropdown.y + 2, dropdown.width, height, 0xDDDDDD);
+            fb.fill_rect(dropdown.x, dropdown.y, dropdown.width, height, 0xFFFFFF);
+            for (i, option) in dropdown.options.iter().enumerate() {
+                let y = dropdown.y + (i as u32 * SUGGESTION_HEIGHT) as i32;
+                if dropdown.highlighted == Some(i) {
+                    fb.fill_rect(dropdown.x, y, dropdown.width, SUGGESTION_HEIGHT, SELECTED_ROW_BG);
+                }
+                let text = self.fit_text(option, 14.0, dropdown.width.saturating_sub(20));
+                let baseline = self.baseline_in_box(y, SUGGESTION_HEIGHT as i32, 14.0);
+                self.draw_text(fb, &text, dropdown.x + 10, baseline, 14.0, 0x222222);
+                boxes.push(LayoutBox {
+                    x: dropdown.x,
+                    y,
+                    width: dropdown.width,
+                    height: SUGGESTION_HEIGHT,
+                    suggestion: Some((dropdown.binding.clone(), i)),
+                    ..Default::default()
+                });
+            }
+            fb.draw_rect_outline(dropdown.x, dropdown.y, dropdown.width, height, 0xCCCCCC, 1);
+        }
+        self.layout_boxes.splice(0..0, boxes);
+    }
+
+    /// Up/down buttons stepping a number input by `step` within `min`/`max`
+    #[allow(clippy::too_many_arguments)]
+    fn render_number_spinner(&mut self, fb: &mut FrameBuffer, binding: &str, current: f64, spec: &InputSpec, x: i32, y: i32, height: u32) {
+        let half = height / 2;
+        fb.fill_rect(x, y + 1, 1, height.saturating_sub(2), 0xDDDDDD);
+        fb.fill_rect(x, y + half as i32, NUMBER_SPINNER_WIDTH, 1, 0xDDDDDD);
+        for (i, direction) in [1.0, -1.0].into_iter().enumerate() {
+            let top = y + (i as u32 * half) as i32;
+            let next = spec.clamp(current + direction * spec.step);
+            let arrow_y = top + half as i32 / 2 - 2;
+            draw_sort_arrow(fb, x + (NUMBER_SPINNER_WIDTH as i32 - 7) / 2, arrow_y, direction < 0.0, 0x666666);
+            self.layout_boxes.push(LayoutBox {
+                x,
+                y: top,
+                width: NUMBER_SPINNER_WIDTH,
+                height: half,
+                set_state: Some((binding.to_string(), number_value(next))),
+                ..Default::default()
+            });
+        }
+    }
+
+    /// Typed-input settings of an `input` node
+    fn input_spec(&self, node: &ViewNode, state: &StateStore) -> InputSpec {
+        let mask = self.get_string_prop(node, "mask", state, "");
+        InputSpec {
+            kind: self.get_keyword_prop(node, "type", state, "text"),
+            mask: (!mask.is_empty()).then_some(mask),
+            min: self.get_float_prop(node, "min", state),
+            max: self.get_float_prop(node, "max", state),
+            step: self.get_float_prop(node, "step", state).filter(|s| *s > 0.0).unwrap_or(1.0),
+        }
     }
 
     // ========================================================================
@@ -759,7 +2644,7 @@ impl Renderer {
 
     fn render_divider(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
         let color = self.get_color_prop(node, "color", Color::LIGHT_GRAY);
-        let vertical = self.get_string_prop(node, "direction", state, "horizontal") == "vertical";
+        let vertical = self.get_keyword_prop(node, "direction", state, "horizontal") == "vertical";
         
         if vertical {
             let x = ctx.x + ctx.width as i32 / 2;
@@ -798,9 +2683,8 @@ impl Renderer {
                 y: ctx.y,
                 width: max_w.max(20),
                 height: link_height,
-                action: None,
-                input_binding: None,
                 link_href: Some(href),
+                ..Default::default()
             });
         }
     }
@@ -817,8 +2701,9 @@ impl Renderer {
             .map(|v| v.as_string())
             .unwrap_or_default();
 
-        let area_height = self.get_int_prop(node, "height", state, 100) as u32;
-        let area_width = ctx.width.min(400);
+        let (width, height) = self.explicit_size(node, state);
+        let area_height = height.unwrap_or(100);
+        let area_width = width.map_or(ctx.width.min(400), |_| ctx.width);
 
         fb.fill_rect(ctx.x, ctx.y, area_width, area_height, 0xFFFFFF);
         fb.draw_rect_outline(ctx.x, ctx.y, area_width, area_height, 0xCCCCCC, 1);
@@ -826,18 +2711,55 @@ impl Renderer {
         let text = if value.is_empty() { &placeholder } else { &value };
         let color = if value.is_empty() { 0x999999 } else { 0x000000 };
         self.draw_text(fb, text, ctx.x + 8, ctx.y + 8, 14.0, color);
+        if let (true, Some(b)) = (self.get_bool_prop(node, "spellcheck", state, true), &binding) {
+            let focused = self.focused_input.as_ref() == Some(b);
+            let (ascent, descent, gap) = self.line_metrics(14.0);
+            let mut offset = 0;
+            for (i, line) in value.split('\n').enumerate() {
+                let baseline = ctx.y + 8 + i as i32 * (ascent + descent + gap);
+                let line = SpellLine { text: line, from: 0, x: ctx.x + 8, baseline, size: 14.0 };
+                self.underline_misspellings(fb, b, &line, offset, focused && offset + line.text.len() == value.len());
+                offset += line.text.len() + 1;
+            }
+        }
 
         self.layout_boxes.push(LayoutBox {
             x: ctx.x,
             y: ctx.y,
             width: area_width,
             height: area_height,
-            action: None,
             input_binding: binding,
-            link_href: None,
+            ..Default::default()
         });
     }
 
+    /// Underline the misspelled words of one line of an input's text, from
+    /// `line.from` (the start of what is visible); `offset` is the line's byte
+    /// offset in the bound value. While `typing`, the word at the end is left
+    /// alone as it may not be finished.
+    fn underline_misspellings(&mut self, fb: &mut FrameBuffer, binding: &str, line: &SpellLine, offset: usize, typing: bool) {
+        let Some(dictionary) = self.spelling.clone() else {
+            return;
+        };
+        for range in dictionary.misspelled(line.text) {
+            if range.start < line.from || typing && range.end == line.text.len() {
+                continue;
+            }
+            let x = line.x + self.text_width(&line.text[line.from..range.start], line.size) as i32;
+            let width = self.text_width(&line.text[range.clone()], line.size);
+            draw_squiggle(fb, x, line.baseline + 2, width, MISSPELLING_COLOR);
+            self.misspellings.push(Misspelling {
+                binding: binding.to_string(),
+                word: line.text[range.clone()].to_string(),
+                range: offset + range.start..offset + range.end,
+                x,
+                y: line.baseline - line.size as i32,
+                width,
+                height: line.size as u32 + 6,
+            });
+        }
+    }
+
     fn render_checkbox(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
         let checked = self.get_bool_prop(node, "checked", state, false);
         let label = self.get_string_prop(node, "label", state, "");
@@ -863,8 +2785,7 @@ impl Renderer {
                 width: box_size + 8 + (label.len() as u32 * 8),
                 height: box_size,
                 action: Some(action.clone()),
-                input_binding: None,
-                link_href: None,
+                ..Default::default()
             });
         }
     }
@@ -891,8 +2812,7 @@ impl Renderer {
                 width: track_width,
                 height: track_height,
                 action: Some(action.clone()),
-                input_binding: None,
-                link_href: None,
+                ..Default::default()
             });
         }
     }
@@ -918,8 +2838,9 @@ impl Renderer {
     fn render_select(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
         let value = self.get_string_prop(node, "value", state, "Select...");
         
-        let select_height = 36u32;
-        let select_width = ctx.width.min(200);
+        let (width, height) = self.explicit_size(node, state);
+        let select_height = height.unwrap_or(36);
+        let select_width = width.map_or(ctx.width.min(200), |_| ctx.width);
 
         fb.fill_rect(ctx.x, ctx.y, select_width, select_height, 0xFFFFFF);
         fb.draw_rect_outline(ctx.x, ctx.y, select_width, select_height, 0xCCCCCC, 1);
@@ -935,7 +2856,10 @@ impl Renderer {
         
         let track_height = 4u32;
         let track_y = ctx.y + ctx.height as i32 / 2 - 2;
-        let track_width = ctx.width.min(200);
+        let track_width = match self.explicit_size(node, state).0 {
+            Some(_) => ctx.width,
+            None => ctx.width.min(200),
+        };
 
         // Track
         fb.fill_rect(ctx.x, track_y, track_width, track_height, 0xE0E0E0);
@@ -950,25 +2874,275 @@ impl Renderer {
         fb.fill_rect(thumb_x, track_y - 6, 16, 16, 0x4285F4);
     }
 
+    fn render_file_picker(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let content = self.get_string_prop(node, "content", state, "Choose file…");
+        let binding = match node.props.get("bind") {
+            Some(PropValue::Handler(b)) => Some(b.clone()),
+            _ => None,
+        };
+        let chosen = binding.as_ref()
+            .and_then(|b| state.get(b))
+            .map(|v| v.get(&Value::String("name".to_string())).as_string())
+            .unwrap_or_default();
+
+        let (width, height) = self.explicit_size(node, state);
+        let btn_height = height.unwrap_or(36);
+        let text_size = 14.0;
+        let tw = self.line_pixel_width(&content, text_size).max(self.text_width(&content, text_size));
+        let btn_width = width.map_or(tw.saturating_add(24).max(36).min(ctx.width), |_| ctx.width);
+        let btn_y = ctx.y + (ctx.height as i32 - btn_height as i32) / 2;
+
+        let bg = self.get_color_prop(node, "background", Color::LIGHT_GRAY).to_u32();
+        fb.fill_rounded_rect_vertical_gradient(ctx.x, btn_y, btn_width, btn_height, 10, bg, bg);
+        let text_x = ctx.x + ((btn_width as i32 - tw as i32) / 2).max(0);
+        let text_y = self.baseline_in_box(btn_y, btn_height as i32, text_size);
+        self.draw_text(fb, &content, text_x, text_y, text_size, 0x000000);
+
+        let (label, label_color) = if chosen.is_empty() {
+            ("No file chosen".to_string(), 0x999999)
+        } else {
+            (chosen, 0x333333)
+        };
+        self.draw_text(fb, &label, ctx.x + btn_width as i32 + 10, text_y, text_size, label_color);
+
+        let action = match node.props.get("on_change") {
+            Some(PropValue::Handler(a)) => Some(a.clone()),
+            _ => None,
+        };
+        let accept: Vec<String> = self.get_string_prop(node, "accept", state, "")
+            .split(',')
+            .map(|e| e.trim().trim_start_matches('.').to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+        self.layout_boxes.push(LayoutBox {
+            x: ctx.x,
+            y: btn_y,
+            width: btn_width,
+            height: btn_height,
+            action,
+            file_picker: binding.map(|binding| FilePicker { binding, accept }),
+            ..Default::default()
+        });
+    }
+
     fn render_image(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
-        let alt = self.get_string_prop(node, "alt", state, "Image");
-        let width = self.get_int_prop(node, "width", state, 100) as u32;
-        let height = self.get_int_prop(node, "height", state, 100) as u32;
+        let src = self.get_string_prop(node, "src", state, "");
+        let (width, height) = self.image_size(node, state, ctx.width);
+
+        let mut images = self.images.borrow_mut();
+        if let Some(Ok(image)) = images.get_mut(&src) {
+            let fit = self.get_keyword_prop(node, "fit", state, "fill");
+            let draw = fit_rect(&fit, (ctx.x, ctx.y, width, height), (image.width, image.height));
+            let clip = (
+                ctx.x.max(0),
+                ctx.y.max(0),
+                (ctx.x + width as i32).min(fb.width as i32),
+                (ctx.y + height as i32).min(fb.height as i32),
+            );
+            if image.is_animated() && clip.2 > clip.0 && clip.3 > clip.1 {
+                let (cw, ch) = ((clip.2 - clip.0) as usize, (clip.3 - clip.1) as usize);
+                self.animated_regions.push(AnimatedRegion {
+                    content: AnimatedContent::Image { src: src.clone(), frame: image.frame_index() },
+                    x: draw.0,
+                    y: draw.1,
+                    width: draw.2,
+                    height: draw.3,
+                    clip,
+                    background: fb.read_rect(clip.0 as usize, clip.1 as usize, cw, ch),
+                });
+            }
+            let (src_w, src_h, pixels) = image.pixels_for_size(draw.2, draw.3);
+            fb.blend_scaled(draw.0, draw.1, draw.2, draw.3, src_w, src_h, pixels, clip);
+            return;
+        }
+        drop(images);
 
-        // Placeholder for image
+        // Placeholder when there is no source or it failed to load
+        let alt = self.get_string_prop(node, "alt", state, "Image");
         fb.fill_rect(ctx.x, ctx.y, width.min(ctx.width), height.min(ctx.height), 0xE0E0E0);
         self.draw_text(fb, &alt, ctx.x + 8, ctx.y + 8, 12.0, 0x666666);
     }
 
+    /// Decode an image on first use; later lookups hit the cache
+    fn load_image(&self, src: &str) {
+        if src.is_empty() || self.images.borrow().contains_key(src) {
+            return;
+        }
+        let image = self.policy.check(Permission::Media, src)
+            .map_err(|e| e.to_string())
+            .and_then(|_| self.assets.read(src, MAX_IMAGE_FILE_BYTES))
+            .and_then(|bytes| Image::decode(&bytes));
+        if let Err(e) = &image {
+            eprintln!("[PRISM IMAGE] {}: {}", src, e);
+        }
+        self.images.borrow_mut().insert(src.to_string(), image);
+    }
+
+    /// Image box size from `width`/`height` props
+    /// A missing dimension follows `aspect_ratio` (or the intrinsic ratio);
+    /// with neither prop the intrinsic size is used
+    fn image_size(&self, node: &ViewNode, state: &StateStore, width_limit: u32) -> (u32, u32) {
+        let src = self.get_string_prop(node, "src", state, "");
+        let (iw, ih) = self.intrinsic_image_size(&src);
+        let ratio = self.get_aspect_ratio(node, state).unwrap_or(iw as f64 / ih as f64);
+
+        let width = node.props.contains_key("width").then(|| self.get_int_prop(node, "width", state, 0).max(1) as u32);
+        let height = node.props.contains_key("height").then(|| self.get_int_prop(node, "height", state, 0).max(1) as u32);
+        let from_width = |w: u32| ((w as f64 / ratio).round() as u32).max(1);
+        match (width, height) {
+            (Some(w), Some(h)) => (w.min(width_limit), h),
+            (Some(w), None) => {
+                let w = w.min(width_limit);
+                (w, from_width(w))
+            }
+            (None, Some(h)) => {
+                let w = ((h as f64 * ratio).round() as u32).max(1);
+                if w > width_limit { (width_limit, from_width(width_limit)) } else { (w, h) }
+            }
+            (None, None) => {
+                let w = (iw as u32).min(width_limit);
+                (w, from_width(w))
+            }
+        }
+    }
+
+    /// Intrinsic size of an image source (loading it if needed), 100x100 if unavailable
+    fn intrinsic_image_size(&self, src: &str) -> (usize, usize) {
+        self.load_image(src);
+        match self.images.borrow().get(src) {
+            Some(Ok(image)) => (image.width.max(1), image.height.max(1)),
+            _ => (100, 100),
+        }
+    }
+
+    /// `aspect_ratio` as width / height: a number, or "16/9" / "16:9"
+    fn get_aspect_ratio(&self, node: &ViewNode, state: &StateStore) -> Option<f64> {
+        let ratio = match node.props.get("aspect_ratio")? {
+            PropValue::Static(Value::Int(i)) => *i as f64,
+            PropValue::Static(Value::Float(f)) => *f,
+            PropValue::Static(Value::String(s)) => parse_ratio(s)?,
+            PropValue::Expression(expr) => match state.evaluate(expr) {
+                Value::String(s) => parse_ratio(&s)?,
+                value => value.as_float(),
+            },
+            _ => return None,
+        };
+        (ratio.is_finite() && ratio > 0.0).then_some(ratio)
+    }
+
     fn render_icon(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
-        let name = self.get_string_prop(node, "name", state, "?");
+        let name = self.get_keyword_prop(node, "name", state, "?");
         let size = self.get_int_prop(node, "size", state, 24) as f32;
         let color = self.get_color_prop(node, "color", Color::BLACK);
+
+        // Image icons (usually SVG) drawn at `size`, tinted when `color` is set
+        let src = self.get_string_prop(node, "src", state, "");
+        self.load_image(&src);
+        if let Some(Ok(image)) = self.images.borrow_mut().get_mut(&src) {
+            let px = size.max(1.0) as u32;
+            let (src_w, src_h, pixels) = image.pixels_for_size(px, px);
+            let full = (0, 0, fb.width as i32, fb.height as i32);
+            if node.props.contains_key("color") {
+                let tint = color.to_u32() & 0xFFFFFF;
+                let tinted: Vec<u32> = pixels.iter().map(|p| (p & 0xFF000000) | tint).collect();
+                fb.blend_scaled(ctx.x, ctx.y, px, px, src_w, src_h, &tinted, full);
+            } else {
+                fb.blend_scaled(ctx.x, ctx.y, px, px, src_w, src_h, pixels, full);
+            }
+            return;
+        }
         
         // Render icon name as placeholder
         self.draw_text(fb, &name, ctx.x, ctx.y, size, color.to_u32());
     }
 
+    fn render_video(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let src = self.get_string_prop(node, "src", state, "");
+        if !self.videos.contains_key(&src) {
+            let player = self.policy.check(Permission::Media, &src)
+                .map_err(|e| e.to_string())
+                .and_then(|_| self.assets.host_path(&src))
+                .and_then(|path| VideoPlayer::open(&path))
+                .map_err(|e| format!("{}: {}", src, e));
+            let player = player.map(|mut p| {
+                p.set_looping(self.get_bool_prop(node, "loop", state, false));
+                if self.get_bool_prop(node, "autoplay", state, false) {
+                    p.play();
+                }
+                p
+            });
+            if let Err(e) = &player {
+                eprintln!("[PRISM VIDEO] {}", e);
+            }
+            self.videos.insert(src.clone(), player);
+        }
+
+        let (width, height) = self.video_size(node, state, ctx.width);
+        let controls_height = 28u32;
+        let video_height = height.saturating_sub(controls_height);
+
+        let (playing, progress) = match self.videos.get(&src) {
+            Some(Ok(player)) => {
+                fb.fill_rect(ctx.x, ctx.y, width, video_height, 0x000000);
+                if let Some(frame) = player.frame() {
+                    fb.blit_scaled(ctx.x, ctx.y, width, video_height, frame.width, frame.height, &frame.pixels);
+                }
+                (player.is_playing(), player.progress())
+            }
+            Some(Err(message)) => {
+                let message = message.clone();
+                fb.fill_rect(ctx.x, ctx.y, width, video_height, 0x333333);
+                self.draw_text(fb, &message, ctx.x + 10, ctx.y + 24, 12.0, 0xCCCCCC);
+                (false, 0.0)
+            }
+            None => (false, 0.0),
+        };
+
+        // Control bar: play/pause button and progress
+        let bar_y = ctx.y + video_height as i32;
+        fb.fill_rect(ctx.x, bar_y, width, controls_height, 0x222222);
+        let icon_x = ctx.x + 10;
+        let icon_y = bar_y + 8;
+        if playing {
+            fb.fill_rect(icon_x, icon_y, 4, 12, 0xFFFFFF);
+            fb.fill_rect(icon_x + 7, icon_y, 4, 12, 0xFFFFFF);
+        } else {
+            for row in 0..12i32 {
+                let half = 6 - (row - 6).abs();
+                fb.fill_rect(icon_x, icon_y + row, (half * 2).max(1) as u32, 1, 0xFFFFFF);
+            }
+        }
+        let track_x = ctx.x + 32;
+        let track_width = width.saturating_sub(44);
+        fb.fill_rect(track_x, bar_y + 12, track_width, 4, 0x555555);
+        fb.fill_rect(track_x, bar_y + 12, (track_width as f32 * progress) as u32, 4, 0x4285F4);
+
+        self.layout_boxes.push(LayoutBox {
+            x: ctx.x,
+            y: ctx.y,
+            width,
+            height,
+            media_toggle: Some(src),
+            ..Default::default()
+        });
+    }
+
+    /// Video size including the control bar
+    /// Uses `width`/`height` props, falling back to the video's aspect ratio
+    fn video_size(&self, node: &ViewNode, state: &StateStore, width_limit: u32) -> (u32, u32) {
+        let src = self.get_string_prop(node, "src", state, "");
+        let (vw, vh) = match self.videos.get(&src) {
+            Some(Ok(player)) if player.dimensions().0 > 0 => player.dimensions(),
+            _ => (16, 9),
+        };
+        let width = (self.get_int_prop(node, "width", state, 480).max(1) as u32).min(width_limit);
+        let height = match node.props.get("height") {
+            Some(_) => self.get_int_prop(node, "height", state, 270).max(1) as u32,
+            None => (width as u64 * vh as u64 / vw.max(1) as u64) as u32,
+        };
+        (width, height + 28)
+    }
+
     fn render_media_placeholder(&mut self, fb: &mut FrameBuffer, _node: &ViewNode, _state: &StateStore, ctx: &RenderContext) {
         fb.fill_rect(ctx.x, ctx.y, ctx.width.min(320), ctx.height.min(180), 0x333333);
         self.draw_text(fb, "▶ Media", ctx.x + 10, ctx.y + 10, 14.0, 0xFFFFFF);
@@ -976,9 +3150,12 @@ impl Renderer {
 
     fn render_card(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
         // Card with shadow effect (simplified)
-        fb.fill_rect(ctx.x + 2, ctx.y + 2, ctx.width, ctx.height, 0xDDDDDD); // Shadow
-        fb.fill_rect(ctx.x, ctx.y, ctx.width, ctx.height, 0xFFFFFF);
-        fb.draw_rect_outline(ctx.x, ctx.y, ctx.width, ctx.height, 0xE0E0E0, 1);
+        let radius = self.corner_radius(node, state).unwrap_or(0);
+        if !self.plain {
+            fb.fill_rounded_rect_vertical_gradient(ctx.x + 2, ctx.y + 2, ctx.width, ctx.height, radius, 0xDDDDDD, 0xDDDDDD); // Shadow
+        }
+        fb.fill_rounded_rect_vertical_gradient(ctx.x, ctx.y, ctx.width, ctx.height, radius, 0xFFFFFF, 0xFFFFFF);
+        fb.draw_rounded_outline(ctx.x, ctx.y, ctx.width, ctx.height, radius, 0xE0E0E0, 1);
         
         let inner = RenderContext {
             x: ctx.x + 16,
@@ -991,16 +3168,45 @@ impl Renderer {
         }
     }
 
-    fn render_badge(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+    fn render_badge(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let dot = self.get_bool_prop(node, "dot", state, false);
+        let content = self.get_string_prop(node, "content", state, "0");
+        let (variant_bg, variant_fg) = badge_variant_colors(&self.get_keyword_prop(node, "variant", state, ""));
+        let bg = self.get_color_prop(node, "background", variant_bg).to_u32();
+        let fg = self.get_color_prop(node, "color", variant_fg).to_u32();
+        let (badge_width, badge_height) = self.badge_size(node, state);
+
+        // With a child the badge overlaps the child's top-right corner
+        let (x, y) = match node.children.iter().find(|c| self.is_visible(c, state)) {
+            Some(child) => {
+                let (child_w, child_h) = self.measure_node(child, state, ctx.width);
+                let child_ctx = RenderContext { x: ctx.x, y: ctx.y, width: child_w, height: child_h };
+                self.render_node(fb, child, state, &child_ctx);
+                (ctx.x + child_w as i32 - badge_width as i32 / 2 - 2, ctx.y - badge_height as i32 / 2 + 2)
+            }
+            None => (ctx.x, ctx.y + (ctx.height as i32 - badge_height as i32) / 2),
+        };
+
+        // Pill shape: fully rounded ends (a circle for dots and single digits)
+        let radius = self.corner_radius(node, state).unwrap_or(badge_height / 2);
+        fb.fill_rounded_rect_vertical_gradient(x, y, badge_width, badge_height, radius, bg, bg);
+        if !dot {
+            let text_w = self.text_width(&content, BADGE_TEXT_SIZE);
+            let text_x = x + (badge_width as i32 - text_w as i32) / 2;
+            let baseline = self.baseline_in_box(y, badge_height as i32, BADGE_TEXT_SIZE);
+            self.draw_text(fb, &content, text_x, baseline, BADGE_TEXT_SIZE, fg);
+        }
+    }
+
+    /// Badge pill size from its text's glyph metrics, or the dot size
+    fn badge_size(&self, node: &ViewNode, state: &StateStore) -> (u32, u32) {
+        if self.get_bool_prop(node, "dot", state, false) {
+            return (10, 10);
+        }
         let content = self.get_string_prop(node, "content", state, "0");
-        let bg = self.get_color_prop(node, "background", Color::RED);
-        
-        let badge_width = (content.len() as u32 * 10 + 16).max(28);
-        let badge_height = 24u32;
-        let badge_y = ctx.y + (ctx.height as i32 - badge_height as i32) / 2;
-        
-        fb.fill_rect(ctx.x, badge_y, badge_width, badge_height, bg.to_u32());
-        self.draw_text(fb, &content, ctx.x + 8, badge_y + 5, 14.0, 0xFFFFFF);
+        let height = 20u32;
+        let width = self.text_width(&content, BADGE_TEXT_SIZE) + 12;
+        (width.max(height), height)
     }
 
     fn render_progress(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
@@ -1031,10 +3237,15 @@ impl Renderer {
     }
 
     fn render_table(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let row_height = 36u32;
+        if let Some(rows) = self.get_list_prop(node, "data", state) {
+            self.render_data_table(fb, node, state, ctx, &rows, row_height);
+            return;
+        }
+
         // Simplified table - render children as rows
         let mut y = ctx.y;
-        let row_height = 36u32;
-        
+
         for child in &node.children {
             let row_ctx = RenderContext {
                 x: ctx.x,
@@ -1048,9 +3259,547 @@ impl Renderer {
         }
     }
 
+    /// Render a table bound to a list of objects via `data`
+    /// Columns come from `columns: "a,b"` or the sorted keys of the first row
+    fn render_data_table(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext, rows: &[Value], row_height: u32) {
+        let columns = self.table_columns(node, state, rows);
+        if columns.is_empty() {
+            return;
+        }
+
+        let text_size = 14.0;
+        let layout = self.column_layout(node, state, &columns, ctx);
+        let mut y = ctx.y;
+        let sortable = self.get_bool_prop(node, "sortable", state, false);
+        let sort = self.table_sorts.get(&state.node_key(node)).cloned();
+        let on_sort = match node.props.get("on_sort") {
+            Some(PropValue::Handler(action)) => Some(action.clone()),
+            _ => None,
+        };
+
+        fb.fill_rect(ctx.x, y, ctx.width, row_height, 0xF5F5F5);
+
+        // Divider handles come first so they win hit tests over the headers
+        if self.get_bool_prop(node, "resizable", state, false) {
+            let binding = widths_binding(node, state);
+            for (i, column) in columns.iter().enumerate() {
+                let Some((_, b)) = layout.visible(i) else { continue };
+                let (cell_x, width) = layout.cells[i];
+                let edge = cell_x + width as i32;
+                if edge > b {
+                    continue;
+                }
+                fb.fill_rect(edge - 1, y + 8, 1, row_height.saturating_sub(16), 0xD0D0D0);
+                self.layout_boxes.push(LayoutBox {
+                    x: edge - 3,
+                    y,
+                    width: 6,
+                    height: row_height,
+                    column_resize: Some(ColumnResize { binding: binding.clone(), column: column.clone(), width }),
+                    ..Default::default()
+                });
+            }
+        }
+
+        for (i, column) in columns.iter().enumerate() {
+            let arrow = match &sort {
+                Some((sorted, descending)) if sorted == column => Some(*descending),
+                _ => None,
+            };
+            if let Some((x, room)) = layout.content(i, 8) {
+                let text = self.fit_text(column, text_size, room.saturating_sub(if arrow.is_some() { 14 } else { 0 }));
+                let baseline = self.baseline_in_box(y, row_height as i32, text_size);
+                self.draw_text(fb, &text, x, baseline, text_size, 0x333333);
+                if let Some(descending) = arrow {
+                    let arrow_x = x + self.text_width(&text, text_size) as i32 + 6;
+                    draw_sort_arrow(fb, arrow_x, y + row_height as i32 / 2 - 2, descending, 0x666666);
+                }
+            }
+            if let (true, Some((a, b))) = (sortable, layout.visible(i)) {
+                self.layout_boxes.push(LayoutBox {
+                    x: a,
+                    y,
+                    width: (b - a) as u32,
+                    height: row_height,
+                    action: on_sort.clone(),
+                    sort_column: Some((state.node_key(node), column.clone())),
+                    ..Default::default()
+                });
+            }
+        }
+        fb.draw_rect_outline(ctx.x, y, ctx.width, row_height, 0xE0E0E0, 1);
+        y += row_height as i32;
+
+        if self.get_bool_prop(node, "filterable", state, false) {
+            self.render_filter_row(fb, node, &columns, &layout, ctx, y, row_height, state);
+            y += row_height as i32;
+        }
+
+        let selection = selection_binding(node);
+        let selected = selection.as_ref().and_then(|var| state.get(var)).map(|v| v.as_int());
+        let body_top = y;
+        let mut row_extents = vec![];
+        let visible = self.visible_table_rows(node, state, &columns, rows);
+        let order: Vec<usize> = visible.iter().map(|&(index, _)| index).collect();
+        for (index, row) in visible {
+            if selected == Some(index as i64) {
+                fb.fill_rect(ctx.x, y, ctx.width, row_height, SELECTED_ROW_BG);
+            }
+            row_extents.push((index, y, row_height));
+            for (i, column) in columns.iter().enumerate() {
+                let Some((x, room)) = layout.content(i, 8) else { continue };
+                let cell = row.get(&Value::String(column.clone())).as_string();
+                let text = self.fit_text(&cell, text_size, room);
+                let baseline = self.baseline_in_box(y, row_height as i32, text_size);
+                self.draw_text(fb, &text, x, baseline, text_size, 0x000000);
+            }
+            fb.draw_rect_outline(ctx.x, y, ctx.width, row_height, 0xE0E0E0, 1);
+            y += row_height as i32;
+        }
+
+        // Wide tables scroll sideways under the sticky first column
+        let total_width: u32 = layout.cells.iter().map(|&(_, w)| w).sum();
+        if total_width > ctx.width {
+            let max_scroll = (total_width - ctx.width) as i32;
+            let scroll = self.table_scroll.get(&state.node_key(node)).copied().unwrap_or(0);
+            if layout.sticky {
+                let edge = ctx.x + layout.cells[0].1 as i32;
+                fb.fill_rect(edge, ctx.y, 1, (y - ctx.y) as u32, 0xC8C8C8);
+            }
+            let thumb_w = (ctx.width as u64 * ctx.width as u64 / total_width as u64) as u32;
+            let thumb_x = ctx.x + ((ctx.width - thumb_w) as i64 * scroll as i64 / max_scroll as i64) as i32;
+            fb.fill_rect(thumb_x, y - 4, thumb_w, 3, 0xBBBBBB);
+            self.horizontal_scrollers.push(HorizontalScroller {
+                table: state.node_key(node),
+                x: ctx.x,
+                y: ctx.y,
+                width: ctx.width,
+                height: (y - ctx.y) as u32,
+                max_scroll,
+            });
+        }
+
+        if let Some(var) = selection {
+            let bounds = (ctx.x, body_top, ctx.width, (y - body_top) as u32);
+            self.register_selectable(fb, node, state, &var, &row_extents, order, bounds);
+        }
+    }
+
+    /// Place columns at their resized widths, shifted by the table's horizontal scroll
+    fn column_layout(&self, node: &ViewNode, state: &StateStore, columns: &[String], ctx: &RenderContext) -> ColumnLayout {
+        let default_width = (ctx.width / columns.len() as u32).max(MIN_COLUMN_WIDTH);
+        let widths = state.get(&widths_binding(node, state));
+        let sticky = self.get_bool_prop(node, "sticky_first_column", state, false);
+        let total: u32 = columns.iter()
+            .map(|c| column_width(widths.as_ref(), c, default_width))
+            .sum();
+        let max_scroll = total.saturating_sub(ctx.width) as i32;
+        let scroll = self.table_scroll.get(&state.node_key(node)).copied().unwrap_or(0).clamp(0, max_scroll);
+
+        let mut cells = vec![];
+        let mut offset = 0i32;
+        for (i, column) in columns.iter().enumerate() {
+            let width = column_width(widths.as_ref(), column, default_width);
+            let x = if sticky && i == 0 { ctx.x } else { ctx.x + offset - scroll };
+            cells.push((x, width));
+            offset += width as i32;
+        }
+        let clip_left = if sticky { ctx.x + cells[0].1 as i32 } else { ctx.x };
+        ColumnLayout { cells, clip_left, clip_right: ctx.x + ctx.width as i32, sticky }
+    }
+
+    /// Scroll a wide table or code block sideways; returns true if it moved
+    pub fn scroll_table(&mut self, x: i32, y: i32, dx: i32) -> bool {
+        let Some(scroller) = self.horizontal_scrollers.iter().find(|s| {
+            x >= s.x && x < s.x + s.width as i32 && y >= s.y && y < s.y + s.height as i32
+        }) else {
+            return false;
+        };
+        let (table, max_scroll) = (scroller.table, scroller.max_scroll);
+        let current = self.table_scroll.get(&table).copied().unwrap_or(0).clamp(0, max_scroll);
+        let next = (current + dx).clamp(0, max_scroll);
+        self.table_scroll.insert(table, next);
+        next != current
+    }
+
+    /// One text field per column; rows are kept when each cell contains its filter
+    #[allow(clippy::too_many_arguments)]
+    fn render_filter_row(&mut self, fb: &mut FrameBuffer, node: &ViewNode, columns: &[String], layout: &ColumnLayout, ctx: &RenderContext, y: i32, row_height: u32, state: &StateStore) {
+        let text_size = 13.0;
+        for (i, column) in columns.iter().enumerate() {
+            let binding = filter_binding(node, column, state);
+            let Some((field_x, field_w)) = layout.content(i, 4) else { continue };
+            let (field_y, field_h) = (y + 4, row_height.saturating_sub(8));
+            let focused = self.focused_input.as_deref() == Some(binding.as_str());
+            let value = state.get(&binding).map(|v| v.as_string()).unwrap_or_default();
+
+            fb.fill_rect(field_x, field_y, field_w, field_h, 0xFFFFFF);
+            fb.draw_rect_outline(field_x, field_y, field_w, field_h, if focused { FOCUS_RING } else { 0xDDDDDD }, 1);
+            let baseline = self.baseline_in_box(field_y, field_h as i32, text_size);
+            if value.is_empty() && !focused {
+                self.draw_text(fb, "Filter", field_x + 6, baseline, text_size, 0xAAAAAA);
+            } else {
+                let text = self.fit_text(&value, text_size, field_w.saturating_sub(14));
+                self.draw_text(fb, &text, field_x + 6, baseline, text_size, 0x000000);
+                if focused {
+                    let caret_x = field_x + 6 + self.text_width(&text, text_size) as i32;
+                    let cursor_x = self.draw_preedit(fb, caret_x, field_y + 4, field_h.saturating_sub(8), baseline, text_size);
+                    if self.cursor_visible {
+                        fb.fill_rect(cursor_x, field_y + 4, 1, field_h.saturating_sub(8), 0x000000);
+                    }
+                }
+            }
+
+            self.focus_targets.push(FocusTarget::Input(binding.clone()));
+            self.layout_boxes.push(LayoutBox {
+                x: field_x,
+                y: field_y,
+                width: field_w,
+                height: field_h,
+                input_binding: Some(binding),
+                ..Default::default()
+            });
+        }
+        fb.draw_rect_outline(ctx.x, y, ctx.width, row_height, 0xE0E0E0, 1);
+    }
+
+    /// Rows of a data table after column filters and sorting, with their index in `data`
+    /// Tables with `on_sort` are sorted by the document instead
+    fn visible_table_rows(&self, node: &ViewNode, state: &StateStore, columns: &[String], rows: &[Value]) -> Vec<(usize, Value)> {
+        let filters: Vec<(String, String)> = if self.get_bool_prop(node, "filterable", state, false) {
+            columns.iter()
+                .filter_map(|c| {
+                    let needle = state.get(&filter_binding(node, c, state))?.as_string().to_lowercase();
+                    (!needle.is_empty()).then(|| (c.clone(), needle))
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let mut visible: Vec<(usize, Value)> = rows.iter().cloned().enumerate()
+            .filter(|(_, row)| filters.iter().all(|(column, needle)| {
+                row.get(&Value::String(column.clone())).as_string().to_lowercase().contains(needle)
+            }))
+            .collect();
+
+        if !node.props.contains_key("on_sort") {
+            if let Some((column, descending)) = self.table_sorts.get(&state.node_key(node)) {
+                let key = Value::String(column.clone());
+                // `sort_by` is stable, so equal cells keep their original order
+                visible.sort_by(|(_, a), (_, b)| {
+                    let ordering = compare_cells(&a.get(&key), &b.get(&key));
+                    if *descending { ordering.reverse() } else { ordering }
+                });
+            }
+        }
+        visible
+    }
+
+    /// Header click: sort ascending, then descending, by that column
+    /// Returns the new direction as `"asc"` or `"desc"`
+    pub fn toggle_sort(&mut self, table: usize, column: &str) -> &'static str {
+        let descending = match self.table_sorts.get(&table) {
+            Some((current, descending)) if current == column => !descending,
+            _ => false,
+        };
+        self.table_sorts.insert(table, (column.to_string(), descending));
+        if descending { "desc" } else { "asc" }
+    }
+
+    fn table_columns(&self, node: &ViewNode, state: &StateStore, rows: &[Value]) -> Vec<String> {
+        let declared = self.get_string_prop(node, "columns", state, "");
+        if !declared.is_empty() {
+            return declared.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect();
+        }
+        match rows.first() {
+            Some(Value::Object(obj)) => {
+                let mut keys: Vec<String> = obj.keys().cloned().collect();
+                keys.sort();
+                keys
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Truncate text with an ellipsis so it fits in the given width
+    fn fit_text(&self, text: &str, size: f32, max_width: u32) -> String {
+        ellipsize(text, max_width, |t| self.text_width(t, size))
+    }
+
     fn render_list(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext, gap: u32) {
-        // List renders like column
-        self.render_column(fb, node, state, ctx, gap);
+        // List renders like column, once per item when bound to `items`
+        self.render_items(fb, node, state, ctx, gap);
+    }
+
+    /// Render children once per item in the (paginated) `items` window
+    /// The item is bound to `as` (default `item`) and its position to `index`
+    fn render_items(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext, gap: u32) {
+        let Some((items, start, total)) = self.item_window(node, state) else {
+            self.render_column(fb, node, state, ctx, gap);
+            return;
+        };
+        let shown = items.len();
+        let selection = selection_binding(node);
+        let selected = selection.as_ref().and_then(|var| state.get(var)).map(|v| v.as_int());
+        let mut rows = vec![];
+        let mut y = ctx.y;
+        for (i, item) in items.into_iter().enumerate() {
+            let index = start + i;
+            let top = y;
+            let bindings = self.item_bindings(node, item, index);
+            state.with_scope(bindings, || {
+                let children: Vec<(&ViewNode, u32)> = node.children.iter()
+                    .filter(|child| self.is_visible(child, state))
+                    .map(|child| (child, self.measure_node(child, state, ctx.width).1))
+                    .collect();
+                let item_h: u32 = children.iter().map(|(_, h)| h + gap).sum::<u32>().saturating_sub(gap);
+                if selected == Some(index as i64) {
+                    fb.fill_rect(ctx.x, top, ctx.width, item_h, SELECTED_ROW_BG);
+                }
+                let item_scope = HoverScope { x: ctx.x, y: top + self.scroll_y, width: ctx.width, height: item_h, on_hover: None, on_leave: None, locals: vec![] };
+                let hovering = self.push_hover_scope(item_scope);
+                state.with_scope(vec![("hovering".to_string(), Value::Bool(hovering))], || {
+                    for (child, child_h) in children {
+                        let child_ctx = RenderContext { x: ctx.x, y, width: ctx.width, height: child_h };
+                        self.render_node(fb, child, state, &child_ctx);
+                        y += child_h as i32 + gap as i32;
+                    }
+                });
+            });
+            if y > top {
+                rows.push((index, top, (y - top - gap as i32).max(0) as u32));
+            }
+        }
+        if let Some(var) = selection {
+            let extent = (y - ctx.y - gap as i32).max(0) as u32;
+            let order = (0..total).collect();
+            self.register_selectable(fb, node, state, &var, &rows, order, (ctx.x, ctx.y, ctx.width, extent));
+        }
+
+        let page_size = self.get_int_prop(node, "page_size", state, 0).max(0) as usize;
+        if let Some(var) = page_binding(node) {
+            let pages = total.div_ceil(page_size.max(1)).max(1);
+            self.render_pager(fb, &var, start / page_size.max(1), pages, ctx.x, y, ctx.width);
+            return;
+        }
+
+        // The end of the list is on screen: reveal another page, or ask for more
+        if y - gap as i32 <= fb.height as i32 {
+            if page_size > 0 && start + shown < total {
+                *self.revealed_pages.entry(state.node_key(node)).or_insert(1) += 1;
+                self.needs_rerender = true;
+            } else if let Some(PropValue::Handler(action)) = node.props.get("on_end_reached") {
+                self.end_reached.push((action.clone(), total));
+            }
+        }
+    }
+
+    /// Make rows clickable and record the list for keyboard navigation
+    /// `bounds` is the rows' area, outlined while the list has focus
+    #[allow(clippy::too_many_arguments)]
+    fn register_selectable(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, binding: &str, rows: &[(usize, i32, u32)], order: Vec<usize>, bounds: (i32, i32, u32, u32)) {
+        let (x, y, width, height) = bounds;
+        if self.focused_list.as_deref() == Some(binding) {
+            fb.draw_rect_outline(x, y, width, height, FOCUS_RING, 2);
+        }
+        for &(index, top, row_h) in rows {
+            self.layout_boxes.push(LayoutBox {
+                x,
+                y: top,
+                width,
+                height: row_h,
+                set_state: Some((binding.to_string(), Value::Int(index as i64))),
+                ..Default::default()
+            });
+        }
+
+        let items_prop = if node.kind == NodeKind::Table { "data" } else { "items" };
+        let page_size = self.get_int_prop(node, "page_size", state, 0).max(0) as usize;
+        self.selectables.push(Selectable {
+            binding: binding.to_string(),
+            items: self.get_list_prop(node, items_prop, state).unwrap_or_default(),
+            item_name: item_name(node),
+            on_activate: match node.props.get("on_activate") {
+                Some(PropValue::Handler(action)) => Some(action.clone()),
+                _ => None,
+            },
+            order,
+            page: page_binding(node).filter(|_| page_size > 0).map(|var| (var, page_size)),
+            rows: rows.iter().map(|&(index, top, h)| (index, top + self.scroll_y, h)).collect(),
+        });
+        self.focus_targets.push(FocusTarget::List(binding.to_string()));
+    }
+
+    /// Previous/next buttons and a page counter for paginated lists
+    #[allow(clippy::too_many_arguments)]
+    fn render_pager(&mut self, fb: &mut FrameBuffer, binding: &str, page: usize, pages: usize, x: i32, y: i32, width: u32) {
+        let button_w = 72u32;
+        let buttons = [
+            ("‹ Prev", x, page > 0, page.saturating_sub(1)),
+            ("Next ›", x + width.min(320) as i32 - button_w as i32, page + 1 < pages, page + 1),
+        ];
+        for (label, bx, enabled, target) in buttons {
+            let text_color = if enabled { 0x333333 } else { 0xBBBBBB };
+            fb.draw_rect_outline(bx, y, button_w, PAGER_HEIGHT, 0xCCCCCC, 1);
+            let text_x = bx + (button_w as i32 - self.text_width(label, 13.0) as i32) / 2;
+            let baseline = self.baseline_in_box(y, PAGER_HEIGHT as i32, 13.0);
+            self.draw_text(fb, label, text_x, baseline, 13.0, text_color);
+            if enabled {
+                self.layout_boxes.push(LayoutBox {
+                    x: bx,
+                    y,
+                    width: button_w,
+                    height: PAGER_HEIGHT,
+                    set_state: Some((binding.to_string(), Value::Int(target as i64))),
+                    ..Default::default()
+                });
+            }
+        }
+
+        let counter = format!("Page {} of {}", page + 1, pages);
+        let counter_x = x + (width.min(320) as i32 - self.text_width(&counter, 13.0) as i32) / 2;
+        let baseline = self.baseline_in_box(y, PAGER_HEIGHT as i32, 13.0);
+        self.draw_text(fb, &counter, counter_x, baseline, 13.0, 0x666666);
+    }
+
+    /// Items a list/each lays out after pagination, as (items, first index, total)
+    /// `None` when the node has no `items` binding and renders its children once
+    fn item_window(&self, node: &ViewNode, state: &StateStore) -> Option<(Vec<Value>, usize, usize)> {
+        if !matches!(node.kind, NodeKind::List | NodeKind::Each) {
+            return None;
+        }
+        let items = self.get_list_prop(node, "items", state)?;
+        let total = items.len();
+        let page_size = self.get_int_prop(node, "page_size", state, 0).max(0) as usize;
+        if page_size == 0 {
+            return Some((items, 0, total));
+        }
+        let (start, end) = match page_binding(node) {
+            Some(var) => {
+                let pages = total.div_ceil(page_size).max(1);
+                let page = (state.get(&var).map(|v| v.as_int()).unwrap_or(0).max(0) as usize).min(pages - 1);
+                (page * page_size, ((page + 1) * page_size).min(total))
+            }
+            None => {
+                let revealed = self.revealed_pages.get(&state.node_key(node)).copied().unwrap_or(1);
+                (0, (page_size * revealed).min(total))
+            }
+        };
+        Some((items[start..end].to_vec(), start, total))
+    }
+
+    /// Items are laid out as when not hovered; `render_items` rebinds `hovering`
+    fn item_bindings(&self, node: &ViewNode, item: Value, index: usize) -> Vec<(String, Value)> {
+        vec![(item_name(node), item), ("index".to_string(), Value::Int(index as i64)), ("hovering".to_string(), Value::Bool(false))]
+    }
+
+    /// Size of a bound list/each: every item's children stacked, plus the pager
+    fn measure_items(&self, node: &ViewNode, state: &StateStore, width_limit: u32) -> (u32, u32) {
+        let gap = self.get_int_prop(node, "gap", state, 0) as u32;
+        let padding = self.get_int_prop(node, "padding", state, 0) as u32;
+        let inner_width = width_limit.saturating_sub(padding * 2);
+        let (items, start, _) = self.item_window(node, state).unwrap_or_default();
+
+        let (mut max_w, mut total_h, mut count) = (0u32, 0u32, 0u32);
+        for (i, item) in items.into_iter().enumerate() {
+            state.with_scope(self.item_bindings(node, item, start + i), || {
+                for child in &node.children {
+                    if !self.is_visible(child, state) {
+                        continue;
+                    }
+                    let (w, h) = self.measure_node(child, state, inner_width);
+                    max_w = max_w.max(w);
+                    total_h += h;
+                    count += 1;
+                }
+            });
+        }
+        total_h += gap * count.saturating_sub(1);
+        if page_binding(node).is_some() {
+            max_w = max_w.max(inner_width.min(320));
+            total_h += if count > 0 { gap + PAGER_HEIGHT } else { PAGER_HEIGHT };
+        }
+        (max_w + padding * 2, total_h + padding * 2)
+    }
+
+    fn render_skeleton(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        let (width, _) = self.skeleton_size(node, state, ctx.width);
+        let height = self.get_int_prop(node, "height", state, 16).max(1) as u32;
+        if self.get_keyword_prop(node, "shape", state, "rect") == "circle" {
+            self.draw_skeleton_block(fb, ctx.x, ctx.y, height, height, height / 2);
+            return;
+        }
+        let lines = self.get_int_prop(node, "lines", state, 1).max(1) as u32;
+        for i in 0..lines {
+            // The last of several lines is shorter, like the end of a paragraph
+            let w = if lines > 1 && i == lines - 1 { width * 3 / 5 } else { width };
+            let y = ctx.y + (i * (height + SKELETON_LINE_GAP)) as i32;
+            self.draw_skeleton_block(fb, ctx.x, y, w, height, 4);
+        }
+    }
+
+    /// Skeleton size: `width` (default full width) by `lines` bars of `height`
+    fn skeleton_size(&self, node: &ViewNode, state: &StateStore, width_limit: u32) -> (u32, u32) {
+        let height = self.get_int_prop(node, "height", state, 16).max(1) as u32;
+        if self.get_keyword_prop(node, "shape", state, "rect") == "circle" {
+            return (height, height);
+        }
+        let width = (self.get_int_prop(node, "width", state, width_limit as i64).max(1) as u32).min(width_limit);
+        let lines = self.get_int_prop(node, "lines", state, 1).max(1) as u32;
+        (width, lines * height + (lines - 1) * SKELETON_LINE_GAP)
+    }
+
+    /// `loading: true` on a card, text or image
+    fn is_loading(&self, node: &ViewNode, state: &StateStore) -> bool {
+        matches!(node.kind, NodeKind::Card | NodeKind::Text | NodeKind::Image)
+            && self.get_bool_prop(node, "loading", state, false)
+    }
+
+    /// Skeleton stand-in for a loading node, shaped like its content
+    fn render_loading(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
+        match node.kind {
+            NodeKind::Text => {
+                let size = self.get_int_prop(node, "size", state, 16) as u32;
+                let line_height = size + 6;
+                let bar_height = (size * 3 / 4).max(4);
+                let lines = self.get_int_prop(node, "lines", state, 1).max(1) as u32;
+                for i in 0..lines {
+                    let w = if lines > 1 && i == lines - 1 { ctx.width * 3 / 5 } else { ctx.width };
+                    let y = ctx.y + (i * line_height + (line_height - bar_height) / 2) as i32;
+                    self.draw_skeleton_block(fb, ctx.x, y, w, bar_height, 4);
+                }
+            }
+            NodeKind::Image => {
+                let (w, h) = self.image_size(node, state, ctx.width);
+                self.draw_skeleton_block(fb, ctx.x, ctx.y, w, h, 4);
+            }
+            _ => self.draw_skeleton_block(fb, ctx.x, ctx.y, ctx.width, ctx.height, 8),
+        }
+    }
+
+    /// Draw one shimmering block and register it for partial repaints
+    fn draw_skeleton_block(&mut self, fb: &mut FrameBuffer, x: i32, y: i32, w: u32, h: u32, radius: u32) {
+        let clip = (
+            x.max(0),
+            y.max(0),
+            (x + w as i32).min(fb.width as i32),
+            (y + h as i32).min(fb.height as i32),
+        );
+        if clip.2 <= clip.0 || clip.3 <= clip.1 {
+            return;
+        }
+        let step = self.shimmer_step();
+        let (cw, ch) = ((clip.2 - clip.0) as usize, (clip.3 - clip.1) as usize);
+        self.animated_regions.push(AnimatedRegion {
+            content: AnimatedContent::Shimmer { radius, step },
+            x,
+            y,
+            width: w,
+            height: h,
+            clip,
+            background: fb.read_rect(clip.0 as usize, clip.1 as usize, cw, ch),
+        });
+        draw_shimmer(fb, x, y, w, h, radius, step, clip);
     }
 
     fn render_modal(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore) {
@@ -1059,17 +3808,16 @@ impl Renderer {
             return;
         }
 
-        // Overlay
+        // Overlay (animations underneath must not repaint over it)
+        self.animated_regions.clear();
         for pixel in fb.pixels.iter_mut() {
-            let r = ((*pixel >> 16) & 0xFF) / 2;
-            let g = ((*pixel >> 8) & 0xFF) / 2;
-            let b = (*pixel & 0xFF) / 2;
-            *pixel = (r << 16) | (g << 8) | b;
+            *pixel = color::blend(*pixel, 0x000000, 128);
         }
 
         // Modal box
-        let modal_width = 400u32.min(fb.width as u32 - 40);
-        let modal_height = 300u32.min(fb.height as u32 - 40);
+        let (width, height) = self.explicit_size(node, state);
+        let modal_width = width.unwrap_or(400).min(fb.width as u32 - 40);
+        let modal_height = height.unwrap_or(300).min(fb.height as u32 - 40);
         let modal_x = (fb.width as i32 - modal_width as i32) / 2;
         let modal_y = (fb.height as i32 - modal_height as i32) / 2;
 
@@ -1088,10 +3836,8 @@ impl Renderer {
     }
 
     fn render_each(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext, gap: u32) {
-        // Each iterates over a list and renders children for each item
-        // This requires special handling with the state store
-        // For now, just render children
-        self.render_column(fb, node, state, ctx, gap);
+        // Each iterates over `items` and renders children for each item
+        self.render_items(fb, node, state, ctx, gap);
     }
 
     fn render_if(&mut self, fb: &mut FrameBuffer, node: &ViewNode, state: &StateStore, ctx: &RenderContext) {
@@ -1113,17 +3859,35 @@ impl Renderer {
         match node.props.get(name) {
             Some(PropValue::Static(Value::Bool(b))) => *b,
             Some(PropValue::Expression(expr)) => state.evaluate(expr).as_bool(),
+            Some(PropValue::Handler(var)) => state.get(var).map(|v| v.as_bool()).unwrap_or(default),
             _ => default,
         }
     }
 
+    /// Draw the IME composition, underlined, at the caret of the focused field
+    /// and note the caret for the host; returns where the caret goes after it
+    fn draw_preedit(&mut self, fb: &mut FrameBuffer, x: i32, top: i32, height: u32, baseline: i32, size: f32) -> i32 {
+        self.ime_cursor_area = Some((x, top, height));
+        if self.preedit.is_empty() {
+            return x;
+        }
+        let preedit = self.preedit.clone();
+        let width = self.text_width(&preedit, size);
+        self.draw_text(fb, &preedit, x, baseline, size, 0x000000);
+        fb.fill_rect(x, baseline + 2, width, 1, 0x000000);
+        match self.preedit_cursor.and_then(|i| preedit.get(..i)) {
+            Some(before) => x + self.text_width(before, size) as i32,
+            None => x + width as i32,
+        }
+    }
+
     fn draw_text(&mut self, fb: &mut FrameBuffer, text: &str, x: i32, y: i32, size: f32, color: u32) {
         self.layout.reset(&LayoutSettings {
             x: x as f32,
             y: 0.0,
             ..LayoutSettings::default()
         });
-        self.layout.append(&[&self.font], &TextStyle::new(text, size, 0));
+        emoji::append_text(&mut self.layout, &self.fonts[self.active_font.get()], text, size);
         let baseline_in_layout = self
             .layout
             .lines()
@@ -1131,8 +3895,26 @@ impl Renderer {
             .unwrap_or(0);
         let dy = y - baseline_in_layout;
 
+        let width = self.layout.glyphs().iter().map(|g| g.x + g.width as f32).fold(x as f32, f32::max) as i32 - x;
+        let top = y - size as i32;
+        let height = self.layout.height().ceil() as u32;
+        let visible = x < fb.width as i32 && top < fb.height as i32 && x + width > 0 && top + height as i32 > 0;
+        if visible && !text.trim().is_empty() {
+            self.text_runs.push(TextRun { text: text.to_string(), x, y: top, width: width.max(0) as u32, height });
+        }
+        // Offscreen text isn't rasterized; text the next scroll will reveal
+        // is queued for idle frames instead
+        if !visible {
+            let font = self.active_font.get();
+            let upcoming = top >= fb.height as i32 && top < 2 * fb.height as i32;
+            if upcoming && self.layout.glyphs().iter().any(|g| !self.glyphs.contains(&g.key)) {
+                self.upcoming_text.push((font, text.to_string(), size));
+            }
+            return;
+        }
+
         for glyph in self.layout.glyphs() {
-            let (metrics, bitmap) = self.font.rasterize_config(glyph.key);
+            let (metrics, bitmap) = self.glyphs.get(emoji::font_for(&self.fonts[self.active_font.get()], glyph.font_index), glyph.key);
             let gx = glyph.x.round() as i32;
             let gy = glyph.y.round() as i32 + dy;
 
@@ -1149,6 +3931,19 @@ impl Renderer {
         }
     }
 
+    fn get_list_prop(&self, node: &ViewNode, name: &str, state: &StateStore) -> Option<Vec<Value>> {
+        let value = match node.props.get(name) {
+            Some(PropValue::Static(v)) => v.clone(),
+            Some(PropValue::Expression(expr)) => state.evaluate(expr),
+            Some(PropValue::Handler(var)) => state.get(var).unwrap_or(Value::Null),
+            _ => return None,
+        };
+        match value {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
     fn get_int_prop(&self, node: &ViewNode, name: &str, state: &StateStore, default: i64) -> i64 {
         match node.props.get(name) {
             Some(PropValue::Static(Value::Int(i))) => *i,
@@ -1158,6 +3953,15 @@ impl Renderer {
         }
     }
 
+    fn get_float_prop(&self, node: &ViewNode, name: &str, state: &StateStore) -> Option<f64> {
+        match node.props.get(name) {
+            Some(PropValue::Static(v @ (Value::Int(_) | Value::Float(_)))) => Some(v.as_float()),
+            Some(PropValue::Expression(expr)) => Some(state.evaluate(expr).as_float()),
+            Some(PropValue::Handler(var)) => state.get(var).map(|v| v.as_float()),
+            _ => None,
+        }
+    }
+
     fn get_string_prop(&self, node: &ViewNode, name: &str, state: &StateStore, default: &str) -> String {
         match node.props.get(name) {
             Some(PropValue::Static(Value::String(s))) => s.clone(),
@@ -1166,6 +3970,25 @@ impl Renderer {
         }
     }
 
+    /// A prop naming one of a few choices, which may be written bare
+    /// (`fit: cover`); a bare name that is a state variable reads it instead
+    fn get_keyword_prop(&self, node: &ViewNode, name: &str, state: &StateStore, default: &str) -> String {
+        match node.props.get(name) {
+            Some(PropValue::Handler(name)) => state.get(name).map(|v| v.as_string()).unwrap_or_else(|| name.clone()),
+            _ => self.get_string_prop(node, name, state, default),
+        }
+    }
+
+    /// Any prop's current value as text; handlers give their action's name
+    fn prop_text(&self, node: &ViewNode, name: &str, state: &StateStore) -> String {
+        match node.props.get(name) {
+            Some(PropValue::Static(value)) => value.as_string(),
+            Some(PropValue::Color(c)) => format!("#{:02X}{:02X}{:02X}", c.r, c.g, c.b),
+            Some(PropValue::EventHandler(handler)) => handler.action.clone(),
+            _ => self.get_keyword_prop(node, name, state, ""),
+        }
+    }
+
     fn get_color_prop(&self, node: &ViewNode, name: &str, default: Color) -> Color {
         match node.props.get(name) {
             Some(PropValue::Color(c)) => *c,
@@ -1177,13 +4000,44 @@ impl Renderer {
     }
 
     fn is_visible(&self, node: &ViewNode, state: &StateStore) -> bool {
+        // Slots never take part in normal flow; `active_slot` renders them
+        if matches!(node.kind, NodeKind::Empty | NodeKind::Error) {
+            return false;
+        }
         match node.props.get("visible") {
             Some(PropValue::Expression(expr)) => state.evaluate(expr).as_bool(),
             Some(PropValue::Static(Value::Bool(b))) => *b,
+            Some(PropValue::Handler(var)) => state.get(var).map(|v| v.as_bool()).unwrap_or(true),
             _ => true,
         }
     }
 
+    /// The `empty` or `error` child to show in place of a data container
+    /// `error` applies when a `load_csv` read for the bound collection (`data`
+    /// for tables, `items` for list/each) failed; `empty` when it has no items.
+    /// A remote source still being fetched shows neither.
+    fn active_slot<'a>(&self, node: &'a ViewNode, state: &StateStore) -> Option<&'a ViewNode> {
+        let name = match node.kind {
+            NodeKind::Table => "data",
+            NodeKind::List | NodeKind::Each => "items",
+            _ => return None,
+        };
+        if !node.props.contains_key(name) {
+            return None;
+        }
+        let failures = state.load_failures();
+        let items = self.get_list_prop(node, name, state);
+        let wanted = if state.load_failures() > failures {
+            NodeKind::Error
+        } else {
+            match items {
+                Some(items) if items.is_empty() => NodeKind::Empty,
+                _ => return None,
+            }
+        };
+        node.children.iter().find(|c| c.kind == wanted)
+    }
+
     /// Find what was clicked at given coordinates
     pub fn hit_test(&self, x: i32, y: i32) -> Option<&LayoutBox> {
         self.layout_boxes.iter().find(|&layout_box| x >= layout_box.x
@@ -1194,47 +4048,70 @@ impl Renderer {
 
     /// Rough measurement for node size to drive layout without overlapping
     fn measure_node(&self, node: &ViewNode, state: &StateStore, width_limit: u32) -> (u32, u32) {
+        let previous = self.enter_font(node, state);
+        let size = state.with_provided(object_prop(node, "provide", state), || {
+            state.with_scope(object_prop(node, HOISTED_PROP, state), || {
+                // A modal's size is its dialog's, not the space it takes in the layout
+                let (width, height) = match node.kind {
+                    NodeKind::Modal => (None, None),
+                    _ => self.explicit_size(node, state),
+                };
+                let limit = width.map_or(width_limit, |w| w.min(width_limit));
+                let border = self.border_width(node, state);
+                let (w, h) = self.measure_node_content(node, state, limit.saturating_sub(border * 2));
+                (width.map_or(w + border * 2, |_| limit), height.unwrap_or(h + border * 2))
+            })
+        });
+        self.active_font.set(previous);
+        size
+    }
+
+    /// Thickness of the node's `border`, 0 without one
+    fn border_width(&self, node: &ViewNode, state: &StateStore) -> u32 {
+        self.get_int_prop(node, "border", state, 0).max(0) as u32
+    }
+
+    /// Corner radius of the node's background and border: its `radius` prop,
+    /// or `border_radius`, where it sets one
+    fn corner_radius(&self, node: &ViewNode, state: &StateStore) -> Option<u32> {
+        ["radius", "border_radius"]
+            .into_iter()
+            .find(|name| node.props.contains_key(*name))
+            .map(|name| self.get_int_prop(node, name, state, 0).max(0) as u32)
+    }
+
+    /// The node's `width` and `height` props, where it sets them
+    fn explicit_size(&self, node: &ViewNode, state: &StateStore) -> (Option<u32>, Option<u32>) {
+        let side = |name| node.props.contains_key(name).then(|| self.get_int_prop(node, name, state, 0).max(0) as u32);
+        (side("width"), side("height"))
+    }
+
+    fn measure_node_content(&self, node: &ViewNode, state: &StateStore, width_limit: u32) -> (u32, u32) {
+        if let Some(slot) = self.active_slot(node, state) {
+            let padding = self.get_int_prop(node, "padding", state, 0) as u32;
+            let (w, h) = self.measure_node(slot, state, width_limit.saturating_sub(padding * 2));
+            return (w + padding * 2, h + padding * 2);
+        }
         match node.kind {
             // Layout nodes - derive from children
-            NodeKind::Column | NodeKind::Box | NodeKind::Stack | NodeKind::Scroll => {
-                let gap = self.get_int_prop(node, "gap", state, 0) as u32;
-                let padding = self.get_int_prop(node, "padding", state, 0) as u32;
-                let mut total_h = padding * 2;
-                let mut max_w = 0u32;
-                let mut count = 0;
-                for child in &node.children {
-                    if !self.is_visible(child, state) {
-                        continue;
-                    }
-                    count += 1;
-                    let (cw, ch) = self.measure_node(child, state, width_limit.saturating_sub(padding * 2));
-                    max_w = max_w.max(cw);
-                    total_h += ch;
-                }
-                if count > 0 {
-                    total_h += gap * (count - 1) as u32;
-                }
-                (max_w + padding * 2, total_h)
+            NodeKind::Column | NodeKind::Box | NodeKind::Stack | NodeKind::Scroll | NodeKind::Empty | NodeKind::Error => {
+                self.measure_column(node, state, width_limit)
             }
             NodeKind::Row => {
                 let gap = self.get_int_prop(node, "gap", state, 0) as u32;
                 let padding = self.get_int_prop(node, "padding", state, 0) as u32;
-                let mut total_w = padding * 2;
-                let mut max_h = 0u32;
-                let mut count = 0;
-                for child in &node.children {
-                    if !self.is_visible(child, state) {
-                        continue;
-                    }
-                    count += 1;
-                    let (cw, ch) = self.measure_node(child, state, width_limit.saturating_sub(padding * 2));
-                    total_w += cw;
-                    max_h = max_h.max(ch);
+                let lines = self.row_lines(node, state, width_limit.saturating_sub(padding * 2), gap);
+                let mut max_w = 0u32;
+                let mut total_h = 0u32;
+                for line in &lines {
+                    let line_w = line.iter().map(|&(w, _, _)| w).sum::<u32>() + gap * (line.len() as u32 - 1);
+                    max_w = max_w.max(line_w);
+                    total_h += line.iter().map(|&(_, h, _)| h).max().unwrap_or(0);
                 }
-                if count > 0 {
-                    total_w += gap * (count - 1) as u32;
+                if lines.len() > 1 {
+                    total_h += gap * (lines.len() as u32 - 1);
                 }
-                (total_w, max_h + padding * 2)
+                (max_w + padding * 2, total_h + padding * 2)
             }
             NodeKind::Grid => {
                 let cols = self.get_int_prop(node, "columns", state, 2).max(1) as usize;
@@ -1261,6 +4138,11 @@ impl Renderer {
             NodeKind::Divider => (width_limit, 1),
             NodeKind::Spacer => (0, 0),
             // Text nodes
+            NodeKind::Text if self.is_loading(node, state) => {
+                let size = self.get_int_prop(node, "size", state, 16) as u32;
+                let lines = self.get_int_prop(node, "lines", state, 1).max(1) as u32;
+                (width_limit, lines * (size + 6))
+            }
             NodeKind::Text | NodeKind::Markdown => {
                 let content = self.get_string_prop(node, "content", state, "");
                 let size = self.get_int_prop(node, "size", state, 16) as f32;
@@ -1274,6 +4156,15 @@ impl Renderer {
                 let height = line_height * line_count;
                 (max_w, height)
             }
+            NodeKind::Code => {
+                let source = expand_tabs(&self.get_string_prop(node, "content", state, ""));
+                let size = self.get_int_prop(node, "size", state, CODE_TEXT_SIZE) as f32;
+                let previous = self.active_font.replace(MONO_FONT);
+                let (asc, desc, gap) = self.line_metrics(size);
+                self.active_font.set(previous);
+                let lines = source.split('\n').count() as u32;
+                (width_limit, lines * (asc + desc + gap) as u32 + 2 * CODE_PADDING)
+            }
             NodeKind::Link => {
                 let content = self.get_string_prop(node, "content", state, "Link");
                 let size = self.get_int_prop(node, "size", state, 16) as f32;
@@ -1297,7 +4188,23 @@ impl Renderer {
                 if self.log_enabled { println!("measure Button content='{}' base_w={} limit={} -> w={}", content, base_w, width_limit, w); }
                 (w, 36)
             }
-            NodeKind::Input => (width_limit.min(280), 36),
+            NodeKind::Input | NodeKind::Combobox => (width_limit.min(280), 36),
+            NodeKind::Rating => {
+                let count = self.get_int_prop(node, "max", state, 5).clamp(1, 20) as u32;
+                let size = self.get_int_prop(node, "size", state, 24).max(8) as u32;
+                (count * size + (count - 1) * RATING_GAP, size)
+            }
+            NodeKind::Stepper => (STEPPER_BUTTON * 2 + STEPPER_VALUE_WIDTH, STEPPER_BUTTON),
+            NodeKind::Tags => {
+                let labels: Vec<String> = self.get_list_prop(node, "bind", state)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|t| t.as_string())
+                    .collect();
+                let width = self.tags_width(node, state, width_limit);
+                (width, self.tags_layout(&labels, width).height)
+            }
+            NodeKind::FilePicker => (width_limit.min(320), 36),
             NodeKind::TextArea => {
                 let h = self.get_int_prop(node, "height", state, 100) as u32;
                 (width_limit.min(400), h)
@@ -1309,12 +4216,42 @@ impl Renderer {
             }
             NodeKind::Select | NodeKind::Slider => (width_limit.min(240), 32),
             // Media/Data display/feedback defaults
-            NodeKind::Image | NodeKind::Icon | NodeKind::Avatar => (64, 64),
-            NodeKind::Video | NodeKind::Audio => (width_limit, 120),
-            NodeKind::Table | NodeKind::List | NodeKind::Card => (width_limit, 120),
-            NodeKind::Badge => (48, 24),
+            NodeKind::Image => self.image_size(node, state, width_limit),
+            NodeKind::Icon if node.props.contains_key("src") => {
+                let size = self.get_int_prop(node, "size", state, 24).max(1) as u32;
+                (size, size)
+            }
+            NodeKind::Icon | NodeKind::Avatar => (64, 64),
+            NodeKind::Video => self.video_size(node, state, width_limit),
+            NodeKind::Audio => (width_limit, 120),
+            NodeKind::Table => match self.get_list_prop(node, "data", state) {
+                Some(rows) => {
+                    let columns = self.table_columns(node, state, &rows);
+                    let filter_row = self.get_bool_prop(node, "filterable", state, false) as u32;
+                    let shown = self.visible_table_rows(node, state, &columns, &rows).len() as u32;
+                    (width_limit, (shown + 1 + filter_row) * 36)
+                }
+                None => (width_limit, 120),
+            },
+            NodeKind::List | NodeKind::Each if node.props.contains_key("items") => {
+                self.measure_items(node, state, width_limit)
+            }
+            NodeKind::List | NodeKind::Card => (width_limit, 120),
+            NodeKind::Badge => match node.children.iter().find(|c| self.is_visible(c, state)) {
+                // Anchored badges take the child's space and overflow its corner
+                Some(child) => self.measure_node(child, state, width_limit),
+                None => self.badge_size(node, state),
+            },
             NodeKind::Progress => (width_limit, 16),
-            NodeKind::Modal | NodeKind::Toast | NodeKind::Tooltip | NodeKind::Popover => (width_limit, 40),
+            NodeKind::Modal => (width_limit, 40),
+            // Drawn over each other in place, so as large as the largest child
+            NodeKind::Toast | NodeKind::Tooltip | NodeKind::Popover => node
+                .children
+                .iter()
+                .filter(|c| self.is_visible(c, state))
+                .map(|c| self.measure_node(c, state, width_limit))
+                .fold((0, 0), |(w, h), (cw, ch)| (w.max(cw), h.max(ch))),
+            NodeKind::Skeleton => self.skeleton_size(node, state, width_limit),
             // Control flow nodes: measure children
             NodeKind::Each | NodeKind::If | NodeKind::Show | NodeKind::Switch | NodeKind::Slot => {
                 let mut max_w = 0;
@@ -1335,13 +4272,50 @@ impl Renderer {
                 (max_w, total_h)
             }
             NodeKind::Center => (width_limit, 0),
-            NodeKind::Component(_) => (width_limit, 0),
+            NodeKind::Component(_) => {
+                let bindings = component_bindings(node, state);
+                state.with_scope(bindings, || self.measure_column(node, state, width_limit))
+            }
+        }
+    }
+
+    fn measure_column(&self, node: &ViewNode, state: &StateStore, width_limit: u32) -> (u32, u32) {
+        let gap = self.get_int_prop(node, "gap", state, 0) as u32;
+        let padding = self.get_int_prop(node, "padding", state, 0) as u32;
+        let mut total_h = padding * 2;
+        let mut max_w = 0u32;
+        let mut count = 0;
+        for child in &node.children {
+            if !self.is_visible(child, state) {
+                continue;
+            }
+            count += 1;
+            let (cw, ch) = self.measure_node(child, state, width_limit.saturating_sub(padding * 2));
+            max_w = max_w.max(cw);
+            total_h += ch;
         }
+        if count > 0 {
+            total_h += gap * (count - 1) as u32;
+        }
+        (max_w + padding * 2, total_h)
     }
 
+    /// Advance width of a single line from the font's glyph metrics
     fn text_width(&self, content: &str, size: f32) -> u32 {
-        let avg = size * 0.55;
-        ((content.len() as f32 * avg) as u32).saturating_add(4)
+        let primary = &self.fonts[self.active_font.get()];
+        let mut width = 0.0f32;
+        for (run, index) in emoji::font_runs(primary, content) {
+            let font = emoji::font_for(primary, index);
+            let mut prev = None;
+            for ch in run.chars() {
+                if let Some(p) = prev {
+                    width += font.horizontal_kern(p, ch, size).unwrap_or(0.0);
+                }
+                width += font.metrics(ch, size).advance_width;
+                prev = Some(ch);
+            }
+        }
+        width.ceil() as u32
     }
 
     fn line_pixel_width(&mut self, content: &str, size: f32) -> u32 {
@@ -1350,7 +4324,7 @@ impl Renderer {
         }
 
         self.layout.reset(&LayoutSettings::default());
-        self.layout.append(&[&self.font], &TextStyle::new(content, size, 0));
+        emoji::append_text(&mut self.layout, &self.fonts[self.active_font.get()], content, size);
         let glyphs = self.layout.glyphs();
         if glyphs.is_empty() {
             return 0;
@@ -1369,7 +4343,7 @@ impl Renderer {
 
     fn line_metrics(&self, size: f32) -> (i32, i32, i32) {
         // Try to reuse the renderer's font metrics if available
-        if let Some(m) = self.font.horizontal_line_metrics(size) {
+        if let Some(m) = self.fonts[self.active_font.get()].horizontal_line_metrics(size) {
             let ascent = m.ascent.ceil() as i32;
             let descent_abs = (-m.descent).ceil() as i32;
             let gap = m.line_gap.ceil() as i32;
@@ -1396,22 +4370,18 @@ impl Renderer {
 
         let mut lines: Vec<String> = vec![];
         let mut current = String::new();
-        let mut current_width = 0u32;
-        let space_width = self.text_width(" ", size);
 
+        // Lines are measured whole, as `measure_node` measures them, so text
+        // given the width it measured keeps its breaks
         for word in content.split_whitespace() {
-            let word_width = self.text_width(word, size);
             if current.is_empty() {
                 current.push_str(word);
-                current_width = word_width;
-            } else if current_width + space_width + word_width <= width_limit {
+            } else if self.text_width(&format!("{} {}", current, word), size) <= width_limit {
                 current.push(' ');
                 current.push_str(word);
-                current_width += space_width + word_width;
             } else {
                 lines.push(current);
                 current = word.to_string();
-                current_width = word_width;
             }
         }
 
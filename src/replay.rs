@@ -0,0 +1,201 @@
+//! Recording and deterministic replay of browser input
+//!
+//! A session log is JSON lines: a header naming the document, the window size
+//! and the session's RNG seed, then one line per input event with the tick
+//! (16 ms frame) it arrived on and milliseconds since recording started.
+//! Replay feeds events back on the same ticks regardless of wall-clock time,
+//! so a renderer or runtime bug reproduces the same way every run.
+
+use crate::gamepad::PadButton;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::time::Instant;
+use winit::event::{ElementState, Ime, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    pub prism_session: u32,
+    pub document: String,
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+}
+
+impl Header {
+    pub fn new(document: &str, width: u32, height: u32, seed: u64) -> Header {
+        Header { prism_session: FORMAT_VERSION, document: document.to_string(), width, height, seed }
+    }
+}
+
+/// Input the browser reacts to, already reduced to what the handlers use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputEvent {
+    Resize { width: u32, height: u32 },
+    Modifiers { state: ModifiersState },
+    CursorMoved { x: i32, y: i32 },
+    CursorLeft,
+    MouseDown,
+    MouseUp,
+    /// Right button press, which opens a context menu
+    ContextMenu,
+    /// Scroll in pixels; lines are converted at 40 px each
+    /// `precise` scrolls come from a trackpad (pixel deltas) rather than wheel notches
+    Wheel {
+        dx: i32,
+        dy: i32,
+        #[serde(default)]
+        precise: bool,
+    },
+    /// Mouse back and forward (side) buttons
+    Back,
+    Forward,
+    Key { key: VirtualKeyCode },
+    Char { ch: char },
+    /// IME composition in progress (`cursor` is a byte offset); empty when it ends
+    ImePreedit { text: String, cursor: Option<usize> },
+    /// Text an IME finished composing
+    ImeCommit { text: String },
+    /// Gamepad press, from `gamepad` rather than the window
+    Pad { button: PadButton },
+}
+
+impl InputEvent {
+    /// The recordable part of a window event, if any (resizes are recorded separately)
+    pub fn from_window_event(event: &WindowEvent) -> Option<InputEvent> {
+        Some(match event {
+            WindowEvent::ModifiersChanged(state) => InputEvent::Modifiers { state: *state },
+            WindowEvent::CursorMoved { position, .. } => InputEvent::CursorMoved { x: position.x as i32, y: position.y as i32 },
+            WindowEvent::CursorLeft { .. } => InputEvent::CursorLeft,
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => match state {
+                ElementState::Pressed => InputEvent::MouseDown,
+                ElementState::Released => InputEvent::MouseUp,
+            },
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Right, .. } => InputEvent::ContextMenu,
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Other(button), .. } => {
+                if side_button(*button)? {
+                    InputEvent::Forward
+                } else {
+                    InputEvent::Back
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (dx, dy, precise) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => ((x * 40.0) as i32, (y * 40.0) as i32, false),
+                    MouseScrollDelta::PixelDelta(pos) => (pos.x as i32, pos.y as i32, true),
+                };
+                InputEvent::Wheel { dx, dy, precise }
+            }
+            WindowEvent::KeyboardInput { input, .. } if input.state == ElementState::Pressed => {
+                InputEvent::Key { key: input.virtual_keycode? }
+            }
+            WindowEvent::ReceivedCharacter(ch) => InputEvent::Char { ch: *ch },
+            WindowEvent::Ime(Ime::Preedit(text, cursor)) => InputEvent::ImePreedit { text: text.clone(), cursor: cursor.map(|(_, end)| end) },
+            WindowEvent::Ime(Ime::Commit(text)) => InputEvent::ImeCommit { text: text.clone() },
+            WindowEvent::Ime(Ime::Disabled) => InputEvent::ImePreedit { text: String::new(), cursor: None },
+            _ => return None,
+        })
+    }
+}
+
+/// Whether an extra mouse button is the back (false) or forward (true) side button
+/// Each platform numbers them differently
+fn side_button(button: u16) -> Option<bool> {
+    let (back, forward): (&[u16], &[u16]) = if cfg!(target_os = "windows") {
+        (&[1], &[2])
+    } else if cfg!(target_os = "macos") {
+        (&[3], &[4])
+    } else {
+        // X11 button numbers, then Wayland's BTN_SIDE/BTN_BACK and BTN_EXTRA/BTN_FORWARD
+        (&[8, 0x113, 0x116], &[9, 0x114, 0x115])
+    };
+    if back.contains(&button) {
+        Some(false)
+    } else if forward.contains(&button) {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    tick: u64,
+    ms: u64,
+    #[serde(flatten)]
+    event: InputEvent,
+}
+
+/// Appends events to a session log, flushing each line so crashes keep them
+pub struct Recorder {
+    out: BufWriter<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &str, header: &Header) -> Result<Recorder, String> {
+        let file = File::create(path).map_err(|e| format!("Cannot create {}: {}", path, e))?;
+        let mut recorder = Recorder { out: BufWriter::new(file), started: Instant::now() };
+        recorder.write_line(header);
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, tick: u64, event: &InputEvent) {
+        let ms = self.started.elapsed().as_millis() as u64;
+        self.write_line(&Entry { tick, ms, event: event.clone() });
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) {
+        let written = serde_json::to_string(value)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(self.out, "{}", line).and_then(|_| self.out.flush()).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!("[PRISM] Failed to record input: {}", e);
+        }
+    }
+}
+
+/// Events of a recorded session, handed out tick by tick
+pub struct Player {
+    pub header: Header,
+    entries: VecDeque<Entry>,
+}
+
+impl Player {
+    pub fn load(path: &str) -> Result<Player, String> {
+        let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path, e))?;
+        let mut lines = BufReader::new(file).lines();
+        let header_line = lines.next().ok_or("empty session log")?.map_err(|e| e.to_string())?;
+        let header: Header = serde_json::from_str(&header_line).map_err(|e| format!("invalid header: {}", e))?;
+        if header.prism_session != FORMAT_VERSION {
+            return Err(format!("unsupported session format {}", header.prism_session));
+        }
+        let mut entries = VecDeque::new();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Entry = serde_json::from_str(&line).map_err(|e| format!("line {}: {}", i + 2, e))?;
+            entries.push_back(entry);
+        }
+        Ok(Player { header, entries })
+    }
+
+    /// Events recorded up to and including `tick`
+    pub fn due(&mut self, tick: u64) -> Vec<InputEvent> {
+        let mut due = vec![];
+        while self.entries.front().is_some_and(|e| e.tick <= tick) {
+            due.extend(self.entries.pop_front().map(|e| e.event));
+        }
+        due
+    }
+
+    pub fn finished(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -4,11 +4,23 @@
 //! The runtime manages the event loop, state updates, and re-rendering.
 //! Extended with full statement execution and control flow.
 
-use crate::ast::{PrismApp, ActionBlock, Statement, AssignTarget, Value};
-use crate::state::StateStore;
-use crate::renderer::{Renderer, FrameBuffer};
-use crate::sandbox::Sandbox;
-use std::collections::HashMap;
+use crate::ast::{PrismApp, ActionBlock, Statement, AssignTarget, Value, PropValue, Expression};
+use crate::state::{is_builtin_function, is_hidden_key, Effect, InstanceKey, StateStore};
+use crate::renderer::{Renderer, RenderedNode, FrameBuffer, FilePicker, FocusTarget, ColumnResize, InputSpec, Combobox, HoverScope, Misspelling, MIN_COLUMN_WIDTH,
+    number_value, suggestion_highlight_key, suggestions_closed_key};
+use crate::sandbox::{Sandbox, Capabilities, ContentPolicy, OriginPolicy};
+use crate::spell::Dictionary;
+use crate::data::DataLoader;
+use crate::query::Selector;
+use crate::bus;
+use crate::emoji;
+use crate::package::Package;
+use crate::vfs::Vfs;
+use percent_encoding::percent_decode_str;
+use url::form_urlencoded;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
 /// The Prism runtime
 pub struct Runtime {
@@ -17,36 +29,590 @@ pub struct Runtime {
     pub renderer: Renderer,
     pub sandbox: Sandbox,
     pub focused_input: Option<String>,
+    /// `selected` binding of the list or table that has keyboard focus
+    pub focused_list: Option<String>,
+    /// Table column divider being dragged, with the pointer x where the drag began
+    column_drag: Option<(ColumnResize, i32)>,
     pub current_route: String,
+    /// Route change `on_before_leave` vetoed, until the host asks the user about it
+    blocked_route: Option<String>,
+    /// Item count each `on_end_reached` action last fired at, so it fires once per load
+    end_reached_fired: HashMap<String, usize>,
+    /// Origin of the document's location (see `bus::origin_of`)
+    origin: String,
+    /// Membership on the same-origin message bus, once the location is known
+    bus_id: Option<u64>,
+    /// `notify` calls not shown by the OS, waiting for the host's notification center
+    pending_notices: Vec<Notice>,
+    /// Notifications the OS refused, sent back by the threads that tried to show them
+    refused_notices: (mpsc::Sender<Notice>, mpsc::Receiver<Notice>),
+    /// When the notifications of the last `NOTIFY_WINDOW` were sent, oldest first
+    recent_notifications: VecDeque<Instant>,
+    /// Action starts and finishes not yet taken by the host
+    activity: Vec<Activity>,
+    /// When each recent action or render ended and how long it ran, oldest first
+    cpu_samples: VecDeque<(Instant, Duration)>,
+    cpu_total: Duration,
+    /// Node ids from `focus` and `scroll_to`, resolved after the next render
+    pending_focus: Option<String>,
+    pending_scroll: Option<String>,
+    scroll_request: Option<i32>,
+    /// Hover scopes the pointer was over after the last render or move
+    hovered: Vec<HoverScope>,
+    /// Box highlighted by gamepad navigation: x, top in content coordinates, width, height
+    spatial_focus: Option<(i32, i32, u32, u32)>,
+    /// Scroll offset and height of the last rendered viewport
+    viewport: (i32, i32),
+    /// Bytes charged to the sandbox for the file each `filepicker` binding holds
+    picked_files: HashMap<String, usize>,
+    /// Native file dialog open on its own thread, if any
+    file_dialog: Option<FileDialog>,
 }
 
+/// A `filepicker`'s dialog, open on a thread so the page keeps drawing
+struct FileDialog {
+    picker: FilePicker,
+    /// The picker's `on_change`, run once the chosen file is loaded
+    on_change: Option<String>,
+    component: Option<InstanceKey>,
+    chosen: mpsc::Receiver<Option<std::path::PathBuf>>,
+}
+
+/// Pointer gestures recognized by the host
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    DoubleClick,
+    LongPress,
+}
+
+/// D-pad directions for spatial navigation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Resources one page is using, for task managers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    /// Data charged to the sandbox plus the estimated size of the page's state
+    pub memory_bytes: usize,
+    pub memory_limit: usize,
+    /// Time spent running actions and rendering over the last `CPU_WINDOW`
+    pub recent_cpu: Duration,
+    pub total_cpu: Duration,
+}
+
+/// How far back `ResourceUsage::recent_cpu` looks
+pub const CPU_WINDOW: Duration = Duration::from_secs(10);
+
+/// Start or end of an action run by the page's event handlers, for hosts
+/// showing progress. Actions called from other actions aren't reported.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Activity {
+    Started { action: String },
+    Finished { action: String, elapsed: Duration },
+}
+
+/// Activity events kept for the host before the oldest are dropped
+const MAX_PENDING_ACTIVITY: usize = 64;
+
+/// A page notification for the host to show itself
+#[derive(Debug, Clone)]
+pub struct Notice {
+    pub title: String,
+    pub body: String,
+}
+
+/// Notices kept for the host before the oldest are dropped
+const MAX_PENDING_NOTICES: usize = 32;
+
+/// Notifications a page may send within `NOTIFY_WINDOW`; more are dropped
+const NOTIFY_BURST: usize = 3;
+const NOTIFY_WINDOW: Duration = Duration::from_secs(10);
+
+/// Share of the memory left under the sandbox's limit that rasterized glyphs
+/// may keep (one part in this many)
+const GLYPH_CACHE_SHARE: usize = 4;
+
+/// Gamepad highlight, and the room kept above or below it when it scrolls into view
+const SPATIAL_FOCUS_RING: u32 = 0xF9AB00;
+const SPATIAL_SCROLL_MARGIN: i32 = 16;
+
+/// Non-character keys, for hosts that don't deliver winit key codes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Backspace,
+    Tab,
+    Enter,
+    Escape,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+/// Iterations a `while` loop may run before it's stopped as runaway
+const MAX_LOOP_ITERATIONS: usize = 100_000;
+
 /// Control flow signals for statement execution
 enum ControlFlow {
     Continue,
     Break,
+    /// `continue`: skip the rest of the loop body
+    NextIteration,
     Return(Option<Value>),
 }
 
 impl Runtime {
-    pub fn new(app: PrismApp) -> Self {
+    pub fn new(mut app: PrismApp) -> Self {
+        crate::optimize::optimize(&mut app);
         let mut state = StateStore::new();
         state.init(&app.state);
+        state.set_strict(app.strict);
         state.set_computed(app.computed.clone());
+        state.set_component_state(&app.components);
+        state.set_strings(app.strings.clone());
+
+        let (capabilities, unknown) = Capabilities::from_directives(&app.capabilities);
+        for name in unknown {
+            eprintln!("[PRISM SANDBOX] Ignoring unknown capability: {}", name);
+        }
+        state.set_loader(DataLoader::new(capabilities.clone()));
+        state.set_env("dark_mode", Value::Bool(false));
+        state.set_env("battery_saver", Value::Bool(false));
+        state.set_env("locale", Value::String(system_locale()));
+        state.set_env("viewport_width", Value::Int(0));
+        state.set_route(route_value(&app, "/"));
 
         Self {
             app,
             state,
             renderer: Renderer::new(),
-            sandbox: Sandbox::new(),
+            sandbox: Sandbox::with_capabilities(capabilities),
             focused_input: None,
+            focused_list: None,
+            column_drag: None,
             current_route: "/".to_string(),
+            blocked_route: None,
+            end_reached_fired: HashMap::new(),
+            picked_files: HashMap::new(),
+            file_dialog: None,
+            origin: String::new(),
+            bus_id: None,
+            pending_notices: vec![],
+            refused_notices: mpsc::channel(),
+            recent_notifications: VecDeque::new(),
+            activity: vec![],
+            cpu_samples: VecDeque::new(),
+            cpu_total: Duration::ZERO,
+            pending_focus: None,
+            pending_scroll: None,
+            scroll_request: None,
+            hovered: vec![],
+            spatial_focus: None,
+            viewport: (0, 0),
         }
     }
 
+    /// Record where the document was loaded from (path or URL)
+    /// Relative data sources are resolved against it
+    pub fn set_location(&mut self, location: &str) {
+        if let Some(loader) = self.state.loader_mut() {
+            loader.set_base(location);
+        }
+        self.set_assets(Vfs::for_location(location));
+
+        if let Some(id) = self.bus_id.take() {
+            bus::leave(id);
+        }
+        self.origin = bus::origin_of(location);
+        self.bus_id = Some(bus::join(&self.origin));
+    }
+
+    /// Serve the document's images and data from a signed package
+    /// Call after `set_location`
+    pub fn mount_package(&mut self, package: Arc<Package>) {
+        self.set_assets(Vfs::Package(package));
+    }
+
+    /// Set the files the document's relative asset paths resolve against
+    pub fn set_assets(&mut self, assets: Vfs) {
+        if let Some(loader) = self.state.loader_mut() {
+            loader.set_assets(assets.clone());
+        }
+        self.renderer.set_assets(assets);
+        self.renderer.load_fonts(&self.app.fonts);
+    }
+
+    /// Apply the user's content policy for the document's origin
+    /// Call after `set_location`
+    pub fn set_content_policy(&mut self, policy: &ContentPolicy) {
+        let rules = OriginPolicy::new(self.origin.clone(), policy.for_origin(&self.origin));
+        if let Some(loader) = self.state.loader_mut() {
+            loader.set_policy(rules.clone());
+        }
+        self.renderer.set_policy(rules.clone());
+        self.sandbox.set_policy(rules);
+    }
+
+    /// Pick up remote data that finished loading and the file a `filepicker`'s
+    /// dialog returned; returns true (and marks the page for re-rendering) if any did
+    pub fn pump_data(&mut self) -> bool {
+        let arrived = self.state.loader_mut().is_some_and(|loader| loader.poll());
+        let picked = self.pump_file_dialog();
+        if arrived || picked {
+            self.state.invalidate();
+        }
+        arrived || picked
+    }
+
+    /// Run `on_broadcast` for each message other same-origin documents sent,
+    /// with `channel` and `data` bound; returns whether any arrived
+    pub fn pump_broadcasts(&mut self) -> bool {
+        let Some(id) = self.bus_id else {
+            return false;
+        };
+        let messages = bus::drain(id);
+        if messages.is_empty() {
+            return false;
+        }
+        if self.app.actions.contains_key("on_broadcast") {
+            for (channel, data) in messages {
+                self.state.set_local("channel", Value::String(channel));
+                self.state.set_local("data", data);
+                self.run_action_with("on_broadcast", &[]);
+            }
+        }
+        true
+    }
+
+    /// Carry out the effects the finished action queued
+    fn apply_effects(&mut self) {
+        for effect in self.state.take_effects() {
+            match effect {
+                Effect::Broadcast { channel, data } => {
+                    if let Some(id) = self.bus_id {
+                        bus::post(id, &channel, &data);
+                    }
+                }
+                Effect::Notify { title, body } => self.notify(title, body),
+                Effect::Copy(text) => self.copy(&text),
+                // Resolved against the next render, where new nodes have a position
+                Effect::Focus(id) => {
+                    self.pending_focus = Some(id);
+                    self.state.invalidate();
+                }
+                Effect::ScrollTo(id) => {
+                    self.pending_scroll = Some(id);
+                    self.state.invalidate();
+                }
+            }
+        }
+    }
+
+    /// Show a notification through the OS when the page holds the
+    /// `notifications` capability, otherwise queue it for the host. Past
+    /// `NOTIFY_BURST` in `NOTIFY_WINDOW`, notifications are dropped.
+    fn notify(&mut self, title: String, body: String) {
+        let now = Instant::now();
+        while self.recent_notifications.front().is_some_and(|sent| now - *sent >= NOTIFY_WINDOW) {
+            self.recent_notifications.pop_front();
+        }
+        if self.recent_notifications.len() >= NOTIFY_BURST {
+            eprintln!("[PRISM] Dropped notification \"{}\": more than {} in {}s", title, NOTIFY_BURST, NOTIFY_WINDOW.as_secs());
+            return;
+        }
+        self.recent_notifications.push_back(now);
+        if self.sandbox.check_notifications().is_ok() {
+            // The desktop's notification service may take a while to answer
+            let app_name = self.app.name.clone();
+            let refused = self.refused_notices.0.clone();
+            std::thread::spawn(move || {
+                let shown = notify_rust::Notification::new().appname(&app_name).summary(&title).body(&body).show();
+                if let Err(e) = shown {
+                    eprintln!("[PRISM] System notification failed: {}", e);
+                    let _ = refused.send(Notice { title, body });
+                }
+            });
+            return;
+        }
+        self.push_notice(Notice { title, body });
+    }
+
+    fn push_notice(&mut self, notice: Notice) {
+        if self.pending_notices.len() >= MAX_PENDING_NOTICES {
+            self.pending_notices.remove(0);
+        }
+        self.pending_notices.push(notice);
+    }
+
+    /// Put text on the system clipboard when the page holds the
+    /// `clipboard_write` capability
+    fn copy(&mut self, text: &str) {
+        if let Err(e) = self.sandbox.check_clipboard_write() {
+            eprintln!("[PRISM SANDBOX] Refused copy to clipboard: {}", e);
+            return;
+        }
+        if let Err(e) = crate::clipboard::copy("text/plain", text.as_bytes()) {
+            eprintln!("[PRISM] Copy to clipboard failed: {}", e);
+        }
+    }
+
+    /// Notifications for the host's own notification center
+    pub fn take_notices(&mut self) -> Vec<Notice> {
+        while let Ok(notice) = self.refused_notices.1.try_recv() {
+            self.push_notice(notice);
+        }
+        std::mem::take(&mut self.pending_notices)
+    }
+
+    /// Follow the host's light/dark theme (`env.dark_mode`)
+    pub fn set_dark_mode(&mut self, dark: bool) {
+        self.state.set_env("dark_mode", Value::Bool(dark));
+    }
+
+    /// Save power while the host does: drop drawing effects the page can do
+    /// without, and tell it with `env.battery_saver`
+    pub fn set_battery_saver(&mut self, on: bool) {
+        self.renderer.plain = on;
+        self.state.set_env("battery_saver", Value::Bool(on));
+    }
+
+    /// Check text inputs against `dictionary`, or stop checking with `None`
+    pub fn set_spelling(&mut self, dictionary: Option<Arc<Dictionary>>) {
+        self.renderer.spelling = dictionary;
+    }
+
+    /// The misspelled word at a viewport point in the last render, with up to
+    /// `max` suggested corrections
+    pub fn misspelling_at(&self, x: i32, y: i32, max: usize) -> Option<(Misspelling, Vec<String>)> {
+        let dictionary = self.renderer.spelling.as_ref()?;
+        let word = self.renderer.misspellings.iter().find(|m| {
+            x >= m.x && x < m.x + m.width as i32 && y >= m.y && y < m.y + m.height as i32
+        })?;
+        Some((word.clone(), dictionary.suggest(&word.word, max)))
+    }
+
+    /// Replace a misspelled word with `correction`, unless its input has
+    /// changed since
+    pub fn correct_spelling(&mut self, misspelling: &Misspelling, correction: &str) -> bool {
+        let value = self.state.get(&misspelling.binding).map(|v| v.as_string()).unwrap_or_default();
+        if value.get(misspelling.range.clone()) != Some(misspelling.word.as_str()) {
+            return false;
+        }
+        let mut corrected = value;
+        corrected.replace_range(misspelling.range.clone(), correction);
+        self.state.set(&misspelling.binding, Value::String(corrected));
+        true
+    }
+
+    /// Override the system locale (`env.locale`), e.g. "pt-BR"
+    pub fn set_locale(&mut self, locale: &str) {
+        self.state.set_env("locale", Value::String(locale.to_string()));
+    }
+
     /// Render the current state to a frame buffer
     pub fn render(&mut self, fb: &mut FrameBuffer, scroll_y: i32) {
-        self.renderer.render(fb, &self.app.view, &self.state, scroll_y);
+        let started = Instant::now();
+        self.state.set_env("viewport_width", Value::Int(fb.width as i64));
+        self.update_glyph_budget();
+        let view = self.app.view_for(&self.current_route);
+        self.state.index_view(view, self.app.route_pattern(view));
+        self.renderer.render(fb, view, &self.state, scroll_y);
+        self.viewport = (scroll_y, fb.height as i32);
+        // The gamepad highlight follows its box when the page lays out again
+        if let Some((x, y, width, height)) = self.spatial_focus {
+            self.spatial_focus = self.nearest_spatial_target(x + width as i32 / 2, y + height as i32 / 2);
+            if let Some((x, y, width, height)) = self.spatial_focus {
+                fb.draw_rect_outline(x - 2, y - scroll_y - 2, width + 4, height + 4, SPATIAL_FOCUS_RING, 2);
+            }
+        }
         self.state.mark_clean();
+        self.record_cpu(started.elapsed());
+        self.resolve_node_requests();
+        // The page may have moved under a pointer that stood still
+        self.update_hover();
+
+        // Disabled inputs and lists give up focus
+        let focused = match (&self.focused_input, &self.focused_list) {
+            (Some(b), _) => Some(FocusTarget::Input(b.clone())),
+            (_, Some(b)) => Some(FocusTarget::List(b.clone())),
+            _ => None,
+        };
+        if focused.is_some_and(|target| self.renderer.disabled_targets.contains(&target)) {
+            self.focus(None);
+        }
+
+        // Lists scrolled to their end ask for more items
+        for (action_name, count) in self.renderer.take_end_reached() {
+            if self.end_reached_fired.get(&action_name) == Some(&count) {
+                continue;
+            }
+            self.end_reached_fired.insert(action_name.clone(), count);
+            self.run_action_with(&action_name, &[]);
+        }
+    }
+
+    /// Carry out `focus` and `scroll_to` requests against the nodes just rendered
+    fn resolve_node_requests(&mut self) {
+        if let Some(id) = self.pending_focus.take() {
+            match self.renderer.node_ids.get(&id).map(|anchor| anchor.focus.clone()) {
+                Some(Some(target)) => self.focus(Some(target)),
+                Some(None) => eprintln!("[PRISM] focus(\"{}\"): the node can't take focus", id),
+                None => eprintln!("[PRISM] focus(\"{}\"): no visible node has this id", id),
+            }
+        }
+        if let Some(id) = self.pending_scroll.take() {
+            match self.renderer.node_ids.get(&id) {
+                Some(anchor) => self.scroll_request = Some(anchor.y),
+                None => eprintln!("[PRISM] scroll_to(\"{}\"): no visible node has this id", id),
+            }
+        }
+    }
+
+    /// Content offset a `scroll_to` asked the host to scroll to, if any
+    pub fn take_scroll_request(&mut self) -> Option<i32> {
+        self.scroll_request.take()
+    }
+
+    /// Action bound with `on_refresh` on the view's root container
+    pub fn refresh_action(&self) -> Option<String> {
+        match self.app.view_for(&self.current_route).props.get("on_refresh") {
+            Some(PropValue::Handler(name)) if self.app.actions.contains_key(name) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    /// Run the page's `on_refresh` action
+    pub fn refresh(&mut self) -> bool {
+        match self.refresh_action() {
+            Some(name) => self.run_action_with(&name, &[]),
+            None => false,
+        }
+    }
+
+    /// Run a page action by name without arguments; false if the page has none by that name
+    pub fn run_action(&mut self, name: &str) -> bool {
+        self.run_action_with(name, &[])
+    }
+
+    /// Run `on_idle` with how many whole seconds have passed since the user's
+    /// last input; hosts call it once for each such second
+    pub fn idle(&mut self, seconds: u64) -> bool {
+        self.run_action_with("on_idle", &[Value::Int(seconds as i64)])
+    }
+
+    fn run_action_with(&mut self, name: &str, args: &[Value]) -> bool {
+        if !self.app.actions.contains_key(name) {
+            return false;
+        }
+        self.call_action(name, args);
+        true
+    }
+
+    /// Run a page action by name, reporting its start and finish as [`Activity`];
+    /// returns the value it `return`ed, if any
+    fn call_action(&mut self, name: &str, args: &[Value]) -> Option<Value> {
+        let action = self.app.actions.get(name).cloned()?;
+        self.time_action(name, &action, args)
+    }
+
+    fn time_action(&mut self, name: &str, action: &ActionBlock, args: &[Value]) -> Option<Value> {
+        self.push_activity(Activity::Started { action: name.to_string() });
+        let started = Instant::now();
+        let result = self.execute_action(action, args);
+        let elapsed = started.elapsed();
+        self.record_cpu(elapsed);
+        self.push_activity(Activity::Finished { action: name.to_string(), elapsed });
+        result
+    }
+
+    /// Run the action a drawn box names: its component instance's action of
+    /// that name if the component has one, else the page's
+    fn run_box_action(&mut self, name: &str, component: Option<InstanceKey>) -> bool {
+        let previous = self.state.instance();
+        self.state.set_instance(component);
+        let action = self.component_action(name);
+        self.state.set_instance(previous);
+        let Some(action) = action else {
+            return self.run_action_with(name, &[]);
+        };
+        self.state.set_instance(component);
+        self.time_action(name, &action, &[]);
+        self.state.set_instance(previous);
+        true
+    }
+
+    /// The action `name` of the component whose instance is running, if it has one
+    fn component_action(&self, name: &str) -> Option<ActionBlock> {
+        let component = self.state.instance_component(self.state.instance()?)?;
+        self.app.components.get(&component)?.actions.get(name).cloned()
+    }
+
+    /// Whether the user may leave the current route or page for `to` (`None`
+    /// when closing): false only when `on_before_leave` returns `false`
+    pub fn may_leave(&mut self, to: Option<&str>) -> bool {
+        if !self.app.actions.contains_key("on_before_leave") {
+            return true;
+        }
+        // It may be asked from inside an action, whose locals must survive
+        let locals = self.state.take_locals();
+        self.state.set_local("to", to.map_or(Value::Null, |to| Value::String(to.to_string())));
+        let result = self.call_action("on_before_leave", &[]);
+        self.state.restore_locals(locals);
+        !matches!(result, Some(Value::Bool(false)))
+    }
+
+    fn record_cpu(&mut self, elapsed: Duration) {
+        let now = Instant::now();
+        self.cpu_total += elapsed;
+        self.cpu_samples.push_back((now, elapsed));
+        while self.cpu_samples.front().is_some_and(|(at, _)| now.duration_since(*at) > CPU_WINDOW) {
+            self.cpu_samples.pop_front();
+        }
+    }
+
+    /// Rasterize text just below the viewport until `deadline`; call when a
+    /// frame has nothing to draw. Returns true if text is left for later.
+    pub fn prerasterize(&mut self, deadline: Instant) -> bool {
+        self.renderer.prerasterize(deadline)
+    }
+
+    /// Let the glyph cache use a share of the memory the page leaves free
+    fn update_glyph_budget(&mut self) {
+        let page = self.sandbox.memory_usage() + self.state.estimated_bytes();
+        let free = self.sandbox.memory_limit().saturating_sub(page);
+        self.renderer.set_glyph_budget(free / GLYPH_CACHE_SHARE);
+    }
+
+    /// Estimated memory and the CPU time spent on the page
+    pub fn resource_usage(&self) -> ResourceUsage {
+        let now = Instant::now();
+        let recent_cpu = self.cpu_samples.iter()
+            .filter(|(at, _)| now.duration_since(*at) <= CPU_WINDOW)
+            .map(|(_, elapsed)| *elapsed)
+            .sum();
+        ResourceUsage {
+            memory_bytes: self.sandbox.memory_usage() + self.state.estimated_bytes() + self.renderer.glyph_cache_bytes(),
+            memory_limit: self.sandbox.memory_limit(),
+            recent_cpu,
+            total_cpu: self.cpu_total,
+        }
+    }
+
+    fn push_activity(&mut self, event: Activity) {
+        if self.activity.len() >= MAX_PENDING_ACTIVITY {
+            self.activity.remove(0);
+        }
+        self.activity.push(event);
+    }
+
+    /// Drain the action starts and finishes since the last call
+    pub fn take_activity(&mut self) -> Vec<Activity> {
+        std::mem::take(&mut self.activity)
     }
 
     /// Force a re-render
@@ -56,42 +622,591 @@ impl Runtime {
 
     /// Measure total content height for the current view
     pub fn content_height(&mut self, width: u32) -> u32 {
-        self.renderer.total_content_height(&self.app.view, &self.state, width)
+        self.state.set_env("viewport_width", Value::Int(width as i64));
+        let view = self.app.view_for(&self.current_route);
+        self.state.index_view(view, self.app.route_pattern(view));
+        self.renderer.total_content_height(view, &self.state, width)
     }
 
     /// Handle a click event at the given coordinates
     pub fn handle_click(&mut self, x: i32, y: i32) -> bool {
         if let Some(layout_box) = self.renderer.hit_test(x, y) {
+            // Handle file picker (runs on_change after the file is loaded)
+            let component = layout_box.component;
+            if let Some(picker) = layout_box.file_picker.clone() {
+                let on_change = layout_box.action.clone();
+                self.open_file_dialog(picker, on_change, component);
+                return true;
+            }
+
+            // Handle video play/pause
+            if let Some(src) = layout_box.media_toggle.clone() {
+                self.renderer.toggle_media(&src);
+                return true;
+            }
+
+            // Handle combobox suggestions
+            if let Some((binding, index)) = layout_box.suggestion.clone() {
+                return self.select_suggestion(&binding, index);
+            }
+
+            // Handle sortable table headers; `on_sort` receives `column` and `direction`
+            if let Some((table, column)) = layout_box.sort_column.clone() {
+                let on_sort = layout_box.action.clone();
+                let direction = self.renderer.toggle_sort(table, &column);
+                if let Some(action) = on_sort {
+                    self.state.set_local("column", Value::String(column));
+                    self.state.set_local("direction", Value::String(direction.to_string()));
+                    self.run_box_action(&action, component);
+                }
+                self.state.invalidate();
+                return true;
+            }
+
+            // Handle direct state changes (pager buttons, list row selection,
+            // rating stars), then the widget's `on_change`
+            if let Some((binding, value)) = layout_box.set_state.clone() {
+                let on_change = layout_box.action.clone();
+                if self.renderer.selectable(&binding).is_some() {
+                    self.focus(Some(FocusTarget::List(binding.clone())));
+                }
+                self.state.set(&binding, value);
+                if let Some(action) = on_change {
+                    self.run_box_action(&action, component);
+                }
+                return true;
+            }
+
             // Handle button click
-            if let Some(action_name) = &layout_box.action {
-                if let Some(action) = self.app.actions.get(action_name).cloned() {
-                    self.execute_action(&action, &[]);
+            let input_binding = layout_box.input_binding.clone();
+            if let Some(action_name) = layout_box.action.clone() {
+                if self.run_box_action(&action_name, component) {
                     return true;
                 }
             }
             
             // Handle input focus
-            if let Some(binding) = &layout_box.input_binding {
-                self.focused_input = Some(binding.clone());
-                self.state.invalidate();
+            if let Some(binding) = input_binding {
+                self.focus(Some(FocusTarget::Input(binding)));
                 return true;
             }
         } else {
             // Clicked outside any interactive element
-            self.focused_input = None;
+            self.focus(None);
         }
         false
     }
 
+    /// Run the `on_double_click` or `on_long_press` action of the innermost node
+    /// at a point, with the item variables it was drawn with
+    pub fn handle_gesture(&mut self, gesture: Gesture, x: i32, y: i32) -> bool {
+        let Some(target) = self.renderer.gesture_at(x, y).cloned() else {
+            return false;
+        };
+        let action = match gesture {
+            Gesture::DoubleClick => target.on_double_click,
+            Gesture::LongPress => target.on_long_press,
+        };
+        match action {
+            Some(action) => self.run_scoped_action(&action, &target.locals),
+            None => false,
+        }
+    }
+
+    /// Move the pointer to a point on the page, or `None` when it left
+    /// Returns true when `hovering` changed somewhere and the page needs a redraw
+    pub fn handle_hover(&mut self, point: Option<(i32, i32)>) -> bool {
+        self.renderer.hover = point;
+        self.update_hover()
+    }
+
+    /// Run `on_leave` for scopes the pointer left and `on_hover` for ones it entered
+    fn update_hover(&mut self) -> bool {
+        let now = match self.renderer.hover {
+            Some((x, y)) => self.renderer.hover_scopes_at(x, y),
+            None => vec![],
+        };
+        let previous = std::mem::replace(&mut self.hovered, now.clone());
+        let mut changed = false;
+        for scope in previous.iter().filter(|p| !now.iter().any(|n| n.same_place(p))) {
+            changed = true;
+            if let Some(ref action) = scope.on_leave {
+                self.run_scoped_action(action, &scope.locals);
+            }
+        }
+        for scope in now.iter().filter(|n| !previous.iter().any(|p| p.same_place(n))) {
+            changed = true;
+            if let Some(ref action) = scope.on_hover {
+                self.run_scoped_action(action, &scope.locals);
+            }
+        }
+        changed
+    }
+
+    /// Run an action with the item variables a node was drawn with
+    fn run_scoped_action(&mut self, action: &str, locals: &[(String, Value)]) -> bool {
+        if !self.app.actions.contains_key(action) {
+            return false;
+        }
+        for (name, value) in locals {
+            self.state.set_local(name, value.clone());
+        }
+        self.run_action_with(action, &[])
+    }
+
+    /// Whether a press at this point would start a long press
+    pub fn has_long_press(&self, x: i32, y: i32) -> bool {
+        self.renderer.gesture_at(x, y).is_some_and(|g| g.on_long_press.is_some())
+    }
+
+    /// Start dragging a table column divider if one is under the pointer
+    pub fn begin_column_resize(&mut self, x: i32, y: i32) -> bool {
+        let Some(resize) = self.renderer.hit_test(x, y).and_then(|b| b.column_resize.clone()) else {
+            return false;
+        };
+        self.column_drag = Some((resize, x));
+        true
+    }
+
+    pub fn is_resizing_column(&self) -> bool {
+        self.column_drag.is_some()
+    }
+
+    /// Whether the pointer is over a column divider (for the resize cursor)
+    pub fn over_column_divider(&self, x: i32, y: i32) -> bool {
+        self.renderer.hit_test(x, y).is_some_and(|b| b.column_resize.is_some())
+    }
+
+    /// Follow the pointer while dragging, storing the new width in the table's widths object
+    pub fn drag_column_resize(&mut self, x: i32) -> bool {
+        let Some((resize, start_x)) = &self.column_drag else {
+            return false;
+        };
+        let width = (resize.width as i64 + (x - start_x) as i64).max(MIN_COLUMN_WIDTH as i64);
+        let mut widths = match self.state.get(&resize.binding) {
+            Some(Value::Object(widths)) => widths,
+            _ => HashMap::new(),
+        };
+        widths.insert(resize.column.clone(), Value::Int(width));
+        let binding = resize.binding.clone();
+        self.state.set(&binding, Value::Object(widths));
+        true
+    }
+
+    pub fn end_column_resize(&mut self) {
+        self.column_drag = None;
+    }
+
+    /// Scroll a wide table or code block under the pointer sideways
+    pub fn scroll_table(&mut self, x: i32, y: i32, dx: i32) -> bool {
+        if self.renderer.scroll_table(x, y, dx) {
+            self.state.invalidate();
+            return true;
+        }
+        false
+    }
+
+    /// The combobox with keyboard focus, if its suggestions are showing
+    fn open_combobox(&self) -> Option<Combobox> {
+        let binding = self.focused_input.as_ref()?;
+        self.renderer.comboboxes.iter().find(|c| &c.binding == binding && c.open).cloned()
+    }
+
+    /// Up/Down in a combobox: move the highlighted suggestion
+    pub fn move_suggestion(&mut self, delta: i64) -> bool {
+        let Some(combobox) = self.open_combobox() else {
+            return false;
+        };
+        let key = suggestion_highlight_key(&combobox.binding);
+        let last = combobox.matches.len() as i64 - 1;
+        let next = match self.state.get(&key).map(|v| v.as_int()) {
+            Some(current) if (0..=last).contains(&current) => (current + delta).clamp(0, last),
+            _ => if delta < 0 { last } else { 0 },
+        };
+        self.state.set(&key, Value::Int(next));
+        true
+    }
+
+    /// Enter in a combobox: pick the highlighted suggestion
+    pub fn accept_suggestion(&mut self) -> bool {
+        let Some(combobox) = self.open_combobox() else {
+            return false;
+        };
+        match self.state.get(&suggestion_highlight_key(&combobox.binding)).map(|v| v.as_int()) {
+            Some(index) if index >= 0 => self.select_suggestion(&combobox.binding, index as usize),
+            _ => false,
+        }
+    }
+
+    /// Escape in a combobox: hide the suggestions until the user types again
+    pub fn dismiss_suggestions(&mut self) -> bool {
+        let Some(combobox) = self.open_combobox() else {
+            return false;
+        };
+        self.state.set(&suggestions_closed_key(&combobox.binding), Value::Bool(true));
+        true
+    }
+
+    /// Fill the combobox with a suggestion and run `on_select` with it bound to `item`
+    pub fn select_suggestion(&mut self, binding: &str, index: usize) -> bool {
+        let Some(combobox) = self.renderer.comboboxes.iter().find(|c| c.binding == binding).cloned() else {
+            return false;
+        };
+        let Some(item) = combobox.matches.get(index).cloned() else {
+            return false;
+        };
+        if !self.add_tag(binding, &item.as_string()) {
+            self.state.set(binding, Value::String(item.as_string()));
+        }
+        self.state.set(&suggestions_closed_key(binding), Value::Bool(true));
+        self.state.set(&suggestion_highlight_key(binding), Value::Int(-1));
+        if let Some(action) = combobox.on_select {
+            self.state.set_local("item", item.clone());
+            self.state.set_local("index", Value::Int(index as i64));
+            self.run_action_with(&action, &[item]);
+        }
+        true
+    }
+
+    /// List binding of the tags input whose draft is stored under `draft_key`
+    fn tags_binding(&self, draft_key: &str) -> Option<String> {
+        self.renderer.tag_inputs.iter().find(|(draft, _)| draft == draft_key).map(|(_, list)| list.clone())
+    }
+
+    /// Append `text` as a chip to a tags input unless it is blank or already present
+    /// Returns false when `draft_key` doesn't belong to a tags input
+    fn add_tag(&mut self, draft_key: &str, text: &str) -> bool {
+        let Some(list_binding) = self.tags_binding(draft_key) else {
+            return false;
+        };
+        let text = text.trim();
+        let mut tags = match self.state.get(&list_binding) {
+            Some(Value::List(tags)) => tags,
+            _ => vec![],
+        };
+        let tag = Value::String(text.to_string());
+        if !text.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+            self.state.set(&list_binding, Value::List(tags));
+        }
+        self.state.set(draft_key, Value::String(String::new()));
+        true
+    }
+
+    /// Enter in a tags input: turn the typed text into a chip
+    pub fn commit_tag(&mut self) -> bool {
+        let Some(draft_key) = self.focused_input.clone() else {
+            return false;
+        };
+        let draft = self.state.get(&draft_key).map(|v| v.as_string()).unwrap_or_default();
+        self.add_tag(&draft_key, &draft)
+    }
+
+    /// Typing into a combobox reopens its suggestions with nothing highlighted
+    fn reopen_suggestions(&mut self, binding: &str) {
+        if self.renderer.comboboxes.iter().any(|c| c.binding == binding) {
+            self.state.set(&suggestions_closed_key(binding), Value::Bool(false));
+            self.state.set(&suggestion_highlight_key(binding), Value::Int(-1));
+        }
+    }
+
+    /// Move keyboard focus to an input, a list, or nothing
+    pub fn focus(&mut self, target: Option<FocusTarget>) {
+        self.focused_input = None;
+        self.focused_list = None;
+        match target {
+            Some(FocusTarget::Input(binding)) => self.focused_input = Some(binding),
+            Some(FocusTarget::List(binding)) => self.focused_list = Some(binding),
+            None => {}
+        }
+        self.renderer.focused_list = self.focused_list.clone();
+        self.state.invalidate();
+    }
+
+    /// Tab/Shift+Tab: focus the next or previous input or list
+    pub fn focus_next(&mut self, backwards: bool) -> bool {
+        let targets = self.renderer.focus_targets.clone();
+        if targets.is_empty() {
+            return false;
+        }
+        let current = match (&self.focused_input, &self.focused_list) {
+            (Some(b), _) => targets.iter().position(|t| *t == FocusTarget::Input(b.clone())),
+            (_, Some(b)) => targets.iter().position(|t| *t == FocusTarget::List(b.clone())),
+            _ => None,
+        };
+        let next = match (current, backwards) {
+            (Some(i), false) => (i + 1) % targets.len(),
+            (Some(i), true) => (i + targets.len() - 1) % targets.len(),
+            (None, false) => 0,
+            (None, true) => targets.len() - 1,
+        };
+        self.focus(Some(targets[next].clone()));
+        true
+    }
+
+    /// Boxes gamepad navigation can land on, in content coordinates
+    fn spatial_targets(&self) -> Vec<(i32, i32, u32, u32)> {
+        let scroll_y = self.viewport.0;
+        self.renderer.layout_boxes.iter()
+            .filter(|b| b.action.is_some() || b.link_href.is_some() || b.input_binding.is_some() || b.file_picker.is_some()
+                || b.media_toggle.is_some() || b.set_state.is_some() || b.sort_column.is_some() || b.suggestion.is_some())
+            .map(|b| (b.x, b.y + scroll_y, b.width, b.height))
+            .collect()
+    }
+
+    fn nearest_spatial_target(&self, x: i32, y: i32) -> Option<(i32, i32, u32, u32)> {
+        let center = |&(bx, by, w, h): &(i32, i32, u32, u32)| (bx + w as i32 / 2, by + h as i32 / 2);
+        self.spatial_targets().into_iter().min_by_key(|t| {
+            let (cx, cy) = center(t);
+            (cx - x).abs() as i64 + (cy - y).abs() as i64
+        })
+    }
+
+    /// D-pad: move the highlight to the nearest clickable box in a direction,
+    /// starting from the first one in view; scrolls it into view
+    pub fn move_spatial_focus(&mut self, direction: Direction) -> bool {
+        let (scroll_y, height) = self.viewport;
+        let targets = self.spatial_targets();
+        let next = match self.spatial_focus {
+            None => targets.iter().find(|t| t.1 >= scroll_y).or(targets.first()).copied(),
+            Some(current) => {
+                let (x, y, w, h) = current;
+                let (cx, cy) = (x + w as i32 / 2, y + h as i32 / 2);
+                targets.iter()
+                    .filter_map(|&(tx, ty, tw, th)| {
+                        let (tcx, tcy) = (tx + tw as i32 / 2, ty + th as i32 / 2);
+                        // Gap from the highlight's edge to the far side of the candidate
+                        let (along, across) = match direction {
+                            Direction::Up => (y - (ty + th as i32), tcx - cx),
+                            Direction::Down => (ty - (y + h as i32), tcx - cx),
+                            Direction::Left => (x - (tx + tw as i32), tcy - cy),
+                            Direction::Right => (tx - (x + w as i32), tcy - cy),
+                        };
+                        // Boxes roughly in line win over closer ones off to the side
+                        (along >= 0).then_some((along as i64 + 2 * across.abs() as i64, (tx, ty, tw, th)))
+                    })
+                    .min_by_key(|(score, _)| *score)
+                    .map(|(_, t)| t)
+            }
+        };
+        let Some((_, y, _, h)) = next else {
+            return false;
+        };
+        self.spatial_focus = next;
+        if y < scroll_y {
+            self.scroll_request = Some(y - SPATIAL_SCROLL_MARGIN);
+        } else if y + h as i32 > scroll_y + height {
+            self.scroll_request = Some(y + h as i32 - height + SPATIAL_SCROLL_MARGIN);
+        }
+        self.state.invalidate();
+        true
+    }
+
+    /// Center of the gamepad highlight in viewport coordinates, where A clicks
+    pub fn spatial_focus_point(&self) -> Option<(i32, i32)> {
+        self.spatial_focus.map(|(x, y, w, h)| (x + w as i32 / 2, y - self.viewport.0 + h as i32 / 2))
+    }
+
+    /// Drop the gamepad highlight, e.g. once the mouse moves
+    pub fn clear_spatial_focus(&mut self) -> bool {
+        self.spatial_focus.take().is_some()
+    }
+
+    /// Move the focused list's selection by `delta` rows in display order
+    pub fn move_selection(&mut self, delta: i64) -> bool {
+        let Some(list) = self.focused_list.as_ref().and_then(|b| self.renderer.selectable(b)).cloned() else {
+            return false;
+        };
+        if list.order.is_empty() {
+            return false;
+        }
+        let last = list.order.len() as i64 - 1;
+        let current = self.state.get(&list.binding)
+            .and_then(|v| list.order.iter().position(|&i| i as i64 == v.as_int()));
+        let position = match current {
+            Some(pos) => (pos as i64).saturating_add(delta).clamp(0, last),
+            // Nothing selected yet: Down starts at the top, Up at the bottom
+            None => if delta < 0 { last } else { 0 },
+        };
+        let next = list.order[position as usize] as i64;
+        self.state.set(&list.binding, Value::Int(next));
+        if let Some((page_var, page_size)) = list.page {
+            self.state.set(&page_var, Value::Int(next / page_size as i64));
+        }
+        true
+    }
+
+    /// Enter: run the focused list's `on_activate` with the selected item bound
+    /// to its `as` name (default `item`) and its position to `index`
+    pub fn activate_selection(&mut self) -> bool {
+        let Some(list) = self.focused_list.as_ref().and_then(|b| self.renderer.selectable(b)).cloned() else {
+            return false;
+        };
+        let index = self.state.get(&list.binding).map(|v| v.as_int()).unwrap_or(-1);
+        let Some(item) = usize::try_from(index).ok().and_then(|i| list.items.get(i)).cloned() else {
+            return false;
+        };
+        match list.on_activate.filter(|a| self.app.actions.contains_key(a)) {
+            Some(action) => {
+                self.state.set_local(&list.item_name, item.clone());
+                self.state.set_local("index", Value::Int(index));
+                self.run_action_with(&action, &[item, Value::Int(index)])
+            }
+            None => false,
+        }
+    }
+
+    /// Document-space (top, height) of the focused list's selected row, if rendered
+    pub fn selected_row_extent(&self) -> Option<(i32, u32)> {
+        let list = self.focused_list.as_ref().and_then(|b| self.renderer.selectable(b))?;
+        let index = self.state.get(&list.binding)?.as_int();
+        list.rows.iter()
+            .find(|(i, _, _)| *i as i64 == index)
+            .map(|&(_, top, h)| (top, h))
+    }
+
+    /// Open the native file dialog for a `filepicker` on its own thread;
+    /// `pump_data` loads the file once one is chosen
+    fn open_file_dialog(&mut self, picker: FilePicker, on_change: Option<String>, component: Option<InstanceKey>) {
+        if let Err(e) = self.sandbox.check_files() {
+            eprintln!("[PRISM SANDBOX] {}", e);
+            return;
+        }
+        if self.file_dialog.is_some() {
+            return;
+        }
+
+        let mut dialog = rfd::FileDialog::new();
+        if !picker.accept.is_empty() {
+            let exts: Vec<&str> = picker.accept.iter().map(|e| e.as_str()).collect();
+            dialog = dialog.add_filter("Accepted files", &exts);
+        }
+        let (sender, chosen) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(dialog.pick_file());
+        });
+        self.file_dialog = Some(FileDialog { picker, on_change, component, chosen });
+    }
+
+    /// Load the file the open dialog returned, if it has, and run the picker's
+    /// `on_change`; returns true if a file was loaded
+    fn pump_file_dialog(&mut self) -> bool {
+        let Some(dialog) = &self.file_dialog else {
+            return false;
+        };
+        let path = match dialog.chosen.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => return false,
+            Ok(path) => path,
+            Err(mpsc::TryRecvError::Disconnected) => None,
+        };
+        let Some(FileDialog { picker, on_change, component, .. }) = self.file_dialog.take() else {
+            return false;
+        };
+        if !path.is_some_and(|path| self.load_picked_file(&picker, &path)) {
+            return false;
+        }
+        if let Some(action) = on_change {
+            self.run_box_action(&action, component);
+        }
+        true
+    }
+
+    /// Store a chosen file in state
+    /// The bound variable receives `{ name, content, size }`
+    fn load_picked_file(&mut self, picker: &FilePicker, path: &std::path::Path) -> bool {
+        let size = match std::fs::metadata(path) {
+            Ok(meta) => meta.len() as usize,
+            Err(e) => {
+                eprintln!("[PRISM FILES] Failed to read {}: {}", path.display(), e);
+                return false;
+            }
+        };
+        // The file this one replaces no longer counts against the limit
+        let previous = self.picked_files.get(&picker.binding).copied().unwrap_or(0);
+        self.sandbox.deallocate(previous);
+        let content = match self.sandbox.check_memory(size) {
+            Err(e) => Err(format!("[PRISM SANDBOX] {}", e)),
+            // Read no more than was charged, should the file grow meanwhile
+            Ok(()) => read_capped(path, size).map_err(|e| {
+                self.sandbox.deallocate(size);
+                format!("[PRISM FILES] Failed to read {}: {}", path.display(), e)
+            }),
+        };
+        let content = match content {
+            Ok(content) => content,
+            Err(message) => {
+                eprintln!("{}", message);
+                // The previous file stays, and so does its charge
+                let _ = self.sandbox.allocate(previous);
+                return false;
+            }
+        };
+        self.picked_files.insert(picker.binding.clone(), size);
+
+        let name = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut file = HashMap::new();
+        file.insert("name".to_string(), Value::String(name));
+        file.insert("size".to_string(), Value::Int(content.len() as i64));
+        file.insert("content".to_string(), Value::String(content));
+        self.state.set(&picker.binding, Value::Object(file));
+        true
+    }
+
     /// Handle keyboard input
+    /// Typed inputs drop characters their `type` or `mask` doesn't accept
+    /// Show an IME composition at the focused input's caret; empty ends it
+    /// `cursor` is a byte offset into `text`
+    pub fn set_preedit(&mut self, text: &str, cursor: Option<usize>) -> bool {
+        if self.focused_input.is_none() && self.renderer.preedit.is_empty() {
+            return false;
+        }
+        self.renderer.preedit = text.to_string();
+        self.renderer.preedit_cursor = cursor;
+        true
+    }
+
+    /// Type the text an IME composed into the focused input
+    pub fn commit_text(&mut self, text: &str) -> bool {
+        self.renderer.preedit.clear();
+        let mut typed = false;
+        for ch in text.chars().filter(|c| !c.is_control()) {
+            typed |= self.handle_key(ch);
+        }
+        typed
+    }
+
     pub fn handle_key(&mut self, key: char) -> bool {
-        if let Some(binding) = &self.focused_input {
-            let current = self.state.get(binding)
+        // A comma ends a tag like Enter does
+        if key == ',' && self.commit_tag() {
+            return true;
+        }
+        if let Some(binding) = self.focused_input.clone() {
+            let current = self.state.get(&binding)
                 .map(|v| v.as_string())
                 .unwrap_or_default();
-            
-            let new_value = format!("{}{}", current, key);
-            self.state.set(binding, Value::String(new_value));
+            let spec = self.renderer.input_specs.get(&binding).cloned();
+
+            let new_value = match spec.as_ref().and_then(|s| s.mask.as_deref()) {
+                Some(mask) => {
+                    let mut raw = unmask(mask, &current);
+                    raw.push(key);
+                    let formatted = apply_mask(mask, &raw);
+                    if formatted == current {
+                        return false;
+                    }
+                    formatted
+                }
+                None => {
+                    if !accepts_char(spec.as_ref().map_or("text", |s| s.kind.as_str()), &current, key) {
+                        return false;
+                    }
+                    format!("{}{}", current, key)
+                }
+            };
+            self.state.set(&binding, typed_value(spec.as_ref(), new_value));
+            self.reopen_suggestions(&binding);
             return true;
         }
         false
@@ -99,22 +1214,84 @@ impl Runtime {
 
     /// Handle backspace
     pub fn handle_backspace(&mut self) -> bool {
-        if let Some(binding) = &self.focused_input {
-            let current = self.state.get(binding)
+        if let Some(binding) = self.focused_input.clone() {
+            let current = self.state.get(&binding)
                 .map(|v| v.as_string())
                 .unwrap_or_default();
-            
+            let spec = self.renderer.input_specs.get(&binding).cloned();
+
+            // Backspace in an empty tags input removes the last chip
+            if current.is_empty() {
+                if let Some(list_binding) = self.tags_binding(&binding) {
+                    if let Some(Value::List(mut tags)) = self.state.get(&list_binding) {
+                        if tags.pop().is_some() {
+                            self.state.set(&list_binding, Value::List(tags));
+                            return true;
+                        }
+                    }
+                }
+            }
+
             if !current.is_empty() {
-                let new_value: String = current.chars().take(current.len() - 1).collect();
-                self.state.set(binding, Value::String(new_value));
+                // Masked inputs drop the last typed character along with any literals before it
+                let new_value = match spec.as_ref().and_then(|s| s.mask.as_deref()) {
+                    Some(mask) => {
+                        let mut raw = unmask(mask, &current);
+                        raw.pop();
+                        apply_mask(mask, &raw)
+                    }
+                    // An emoji with its modifiers goes in one press
+                    None => {
+                        let last = emoji::clusters(&current).last().map_or(0, str::len);
+                        current[..current.len() - last].to_string()
+                    }
+                };
+                self.state.set(&binding, typed_value(spec.as_ref(), new_value));
+                self.reopen_suggestions(&binding);
                 return true;
             }
         }
         false
     }
 
-    /// Execute an action with arguments
-    pub fn execute_action(&mut self, action: &ActionBlock, args: &[Value]) {
+    /// Handle a non-character key as the browser does: the focused input
+    /// first, then the focused list, then Tab focus traversal
+    pub fn handle_special_key(&mut self, key: Key, shift: bool) -> bool {
+        if self.focused_input.is_some() {
+            let handled = match key {
+                Key::Backspace => self.handle_backspace(),
+                Key::Up => self.move_suggestion(-1),
+                Key::Down => self.move_suggestion(1),
+                Key::Enter => self.accept_suggestion() || self.commit_tag(),
+                Key::Escape => self.dismiss_suggestions(),
+                _ => false,
+            };
+            if handled {
+                return true;
+            }
+        }
+        if self.focused_list.is_some() {
+            let handled = match key {
+                Key::Up => self.move_selection(-1),
+                Key::Down => self.move_selection(1),
+                Key::Home => self.move_selection(i64::MIN),
+                Key::End => self.move_selection(i64::MAX),
+                Key::Enter => self.activate_selection(),
+                _ => false,
+            };
+            if handled {
+                return true;
+            }
+        }
+        if key == Key::Tab && self.focus_next(shift) {
+            self.renderer.set_focus(self.focused_input.clone());
+            return true;
+        }
+        false
+    }
+
+    /// Execute an action with arguments, returning the value it `return`ed
+    pub fn execute_action(&mut self, action: &ActionBlock, args: &[Value]) -> Option<Value> {
         // Bind parameters to arguments
         for (i, param) in action.params.iter().enumerate() {
             let value = args.get(i).cloned().unwrap_or(Value::Null);
@@ -122,10 +1299,17 @@ impl Runtime {
         }
 
         // Execute statements
-        self.execute_statements(&action.statements);
+        self.state.enter_action();
+        let flow = self.execute_statements(&action.statements);
+        self.state.leave_action();
 
         // Clear locals after action completes
         self.state.clear_locals();
+        self.apply_effects();
+        match flow {
+            ControlFlow::Return(value) => value,
+            _ => None,
+        }
     }
 
     /// Execute a list of statements
@@ -150,6 +1334,10 @@ impl Runtime {
                     }
                     AssignTarget::Index { object, index } => {
                         let idx = self.state.evaluate(index);
+                        if let Some(error) = self.state.item_type_error(object, &evaluated) {
+                            eprintln!("[PRISM] Type error: {}", error);
+                            return ControlFlow::Continue;
+                        }
                         if let Some(list) = self.state.get_list_mut(object) {
                             let idx = idx.as_int() as usize;
                             if idx < list.len() {
@@ -185,14 +1373,18 @@ impl Runtime {
                     match self.execute_statements(body) {
                         ControlFlow::Break => break,
                         ControlFlow::Return(v) => return ControlFlow::Return(v),
-                        ControlFlow::Continue => {}
+                        ControlFlow::Continue | ControlFlow::NextIteration => {}
                     }
                 }
                 ControlFlow::Continue
             }
 
             Statement::While { condition, body } => {
-                loop {
+                for iteration in 0.. {
+                    if iteration == MAX_LOOP_ITERATIONS {
+                        eprintln!("[PRISM] while loop stopped after {} iterations", MAX_LOOP_ITERATIONS);
+                        break;
+                    }
                     let cond = self.state.evaluate(condition);
                     if !cond.as_bool() {
                         break;
@@ -200,7 +1392,7 @@ impl Runtime {
                     match self.execute_statements(body) {
                         ControlFlow::Break => break,
                         ControlFlow::Return(v) => return ControlFlow::Return(v),
-                        ControlFlow::Continue => {}
+                        ControlFlow::Continue | ControlFlow::NextIteration => {}
                     }
                 }
                 ControlFlow::Continue
@@ -212,14 +1404,27 @@ impl Runtime {
             }
 
             Statement::Break => ControlFlow::Break,
-            Statement::Continue => ControlFlow::Continue,
+            Statement::Continue => ControlFlow::NextIteration,
 
             Statement::Call { action, args } => {
                 let evaluated_args: Vec<Value> = args.iter()
                     .map(|a| self.state.evaluate(a))
                     .collect();
-                if let Some(action_block) = self.app.actions.get(action).cloned() {
+                // A component's actions call its own actions first; page actions
+                // run outside the instance, on the page's state
+                let instance = self.state.instance();
+                let own = self.component_action(action);
+                if let Some(action_block) = own.clone().or_else(|| self.app.actions.get(action).cloned()) {
+                    // The called action has its own locals; ours are back once it returns
+                    let locals = self.state.take_locals();
+                    if own.is_none() {
+                        self.state.set_instance(None);
+                    }
                     self.execute_action(&action_block, &evaluated_args);
+                    self.state.set_instance(instance);
+                    self.state.restore_locals(locals);
+                } else if is_builtin_function(action) {
+                    self.state.evaluate(&Expression::Call { function: action.clone(), args: args.clone() });
                 }
                 ControlFlow::Continue
             }
@@ -238,14 +1443,40 @@ impl Runtime {
 
             Statement::Navigate(expr) => {
                 let route = self.state.evaluate(expr).as_string();
-                self.current_route = route;
-                self.state.invalidate();
+                self.request_route(&route);
+                ControlFlow::Continue
+            }
+
+            Statement::Copy(text) => {
+                let text = self.state.evaluate(text).as_string();
+                self.state.queue_effect(Effect::Copy(text));
+                ControlFlow::Continue
+            }
+
+            Statement::Focus(id) | Statement::ScrollTo(id) => {
+                let id = self.state.evaluate(id).as_string();
+                if !id.is_empty() {
+                    let focus = matches!(stmt, Statement::Focus(_));
+                    self.state.queue_effect(if focus { Effect::Focus(id) } else { Effect::ScrollTo(id) });
+                }
+                ControlFlow::Continue
+            }
+
+            Statement::Notify { title, body } => {
+                let title = self.state.evaluate(title).as_string();
+                let body = body.as_ref().map(|b| self.state.evaluate(b).as_string()).unwrap_or_default();
+                self.state.queue_effect(Effect::Notify { title, body });
                 ControlFlow::Continue
             }
 
             Statement::Fetch { url, method, body, headers: _, on_success, on_error } => {
-                // Sandboxed - log but don't actually fetch for now
                 let url_val = self.state.evaluate(url).as_string();
+                if let Err(e) = self.sandbox.check_network(&url_val) {
+                    eprintln!("[PRISM SANDBOX] Refused fetch of {}: {}", url_val, e);
+                    self.run_action_with(on_error, &[Value::String(e.to_string())]);
+                    return ControlFlow::Continue;
+                }
+                // Sandboxed - log but don't actually fetch for now
                 let body_val = body.as_ref().map(|b| self.state.evaluate(b));
                 println!("[PRISM FETCH] {:?} {} body={:?}", method, url_val, body_val);
                 println!("  on_success: {}, on_error: {}", on_success, on_error);
@@ -262,6 +1493,10 @@ impl Runtime {
 
             Statement::ListPush { target, value } => {
                 let val = self.state.evaluate(value);
+                if let Some(error) = self.state.item_type_error(target, &val) {
+                    eprintln!("[PRISM] Type error: {}", error);
+                    return ControlFlow::Continue;
+                }
                 if let Some(list) = self.state.get_list_mut(target) {
                     list.push(val);
                 }
@@ -278,6 +1513,10 @@ impl Runtime {
             Statement::ListInsert { target, index, value } => {
                 let idx = self.state.evaluate(index).as_int() as usize;
                 let val = self.state.evaluate(value);
+                if let Some(error) = self.state.item_type_error(target, &val) {
+                    eprintln!("[PRISM] Type error: {}", error);
+                    return ControlFlow::Continue;
+                }
                 if let Some(list) = self.state.get_list_mut(target) {
                     if idx <= list.len() {
                         list.insert(idx, val);
@@ -316,8 +1555,203 @@ impl Runtime {
         &self.app.actions
     }
 
+    /// Snapshot of the page's state variables and route as JSON (without the
+    /// renderer's hidden keys)
+    pub fn export_state(&self) -> String {
+        let state: serde_json::Map<String, serde_json::Value> = self.state.values().iter()
+            .filter(|(k, _)| !is_hidden_key(k))
+            .map(|(k, v)| (k.clone(), v.to_json()))
+            .collect();
+        let snapshot = serde_json::json!({
+            "app": self.app.name,
+            "route": self.current_route,
+            "state": state,
+        });
+        serde_json::to_string_pretty(&snapshot).unwrap_or_default()
+    }
+
+    /// Restore a snapshot from `export_state`, returning how many variables were set
+    /// Variables this document doesn't declare are skipped, so snapshots survive edits
+    pub fn import_state(&mut self, json: &str) -> Result<usize, String> {
+        let snapshot: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let Some(fields) = snapshot.get("state").and_then(|s| s.as_object()) else {
+            return Err("snapshot has no \"state\" object".to_string());
+        };
+        let mut restored = 0;
+        for (name, value) in fields {
+            if self.app.state.fields.contains_key(name) {
+                self.state.set(name, Value::from_json(value));
+                restored += 1;
+            } else if !is_hidden_key(name) {
+                eprintln!("[PRISM] Skipping unknown state variable in snapshot: {}", name);
+            }
+        }
+        if let Some(route) = snapshot.get("route").and_then(|r| r.as_str()) {
+            self.current_route = route.to_string();
+            self.state.set_route(route_value(&self.app, route));
+        }
+        self.state.invalidate();
+        Ok(restored)
+    }
+
+    /// Nodes of the last render matching a selector, in drawing order
+    /// (see `query::Selector` for the syntax); attribute selectors need
+    /// `renderer.record_props` on
+    pub fn query(&self, selector: &str) -> Result<Vec<&RenderedNode>, String> {
+        let selector = Selector::parse(selector)?;
+        Ok(self.renderer.rendered_nodes.iter().filter(|n| selector.matches(n)).collect())
+    }
+
     /// Get current route
     pub fn route(&self) -> &str {
         &self.current_route
     }
+
+    /// Show the view of another route, from the top
+    pub fn set_route(&mut self, route: &str) {
+        self.current_route = route.to_string();
+        self.state.set_route(route_value(&self.app, route));
+        self.scroll_request = Some(0);
+        self.state.invalidate();
+    }
+
+    /// Switch routes unless `on_before_leave` vetoes it; a vetoed route waits
+    /// in `take_blocked_route` for the host to ask the user
+    pub fn request_route(&mut self, route: &str) {
+        if route != self.current_route && !self.may_leave(Some(route)) {
+            self.blocked_route = Some(route.to_string());
+            return;
+        }
+        self.set_route(route);
+    }
+
+    /// The last route change `on_before_leave` vetoed; the host may still
+    /// switch to it with `set_route` once the user agrees to discard changes
+    pub fn take_blocked_route(&mut self) -> Option<String> {
+        self.blocked_route.take()
+    }
+
+    /// Follow a link within the document: true when `href` matches one of its
+    /// routes, which is shown in place of the current one
+    pub fn follow_route(&mut self, href: &str) -> bool {
+        let path = href.split(['?', '#']).next().unwrap_or(href);
+        if !path.starts_with('/') || self.app.match_route(path).is_none() {
+            return false;
+        }
+        self.request_route(href);
+        true
+    }
+}
+
+/// The `route` object for a route such as `/item/7?tab=specs`: its `path`,
+/// the `params` its pattern captured and the decoded `query` parameters
+fn route_value(app: &PrismApp, route: &str) -> Value {
+    let (path, query) = route.split('#').next().unwrap_or(route).split_once('?').unwrap_or((route, ""));
+    let params = app.match_route(path).map(|(_, params)| params).unwrap_or_default();
+    let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+    let params = params.into_iter().map(|(name, value)| (name, Value::String(decode(&value)))).collect();
+    let query = form_urlencoded::parse(query.as_bytes()).map(|(name, value)| (name.into_owned(), Value::String(value.into_owned()))).collect();
+    let fields = [
+        ("path".to_string(), Value::String(path.to_string())),
+        ("params".to_string(), Value::Object(params)),
+        ("query".to_string(), Value::Object(query)),
+    ];
+    Value::Object(fields.into_iter().collect())
+}
+
+/// Whether `key` may be typed next into an input of the given `type`
+fn accepts_char(kind: &str, current: &str, key: char) -> bool {
+    match kind {
+        "number" => key.is_ascii_digit()
+            || (key == '-' && current.is_empty())
+            || (key == '.' && !current.contains('.')),
+        "tel" => key.is_ascii_digit() || " +-().".contains(key),
+        "email" => !key.is_whitespace(),
+        _ => true,
+    }
+}
+
+/// Number inputs hold numbers once the text parses back to itself,
+/// so partial entries like `-` or `1.` keep being editable
+fn typed_value(spec: Option<&InputSpec>, text: String) -> Value {
+    if spec.is_some_and(|s| s.kind == "number") {
+        if let Ok(n) = text.parse::<f64>() {
+            let value = number_value(n);
+            if value.as_string() == text {
+                return value;
+            }
+        }
+    }
+    Value::String(text)
+}
+
+/// User locale from the POSIX environment as a language tag ("pt_BR.UTF-8" becomes "pt-BR")
+fn system_locale() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|value| value.split(['.', '@']).next().unwrap_or("").replace('_', "-"))
+        .find(|tag| !tag.is_empty() && tag != "C" && tag != "POSIX")
+        .unwrap_or_else(|| "en-US".to_string())
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        if let Some(id) = self.bus_id {
+            bus::leave(id);
+        }
+    }
+}
+
+fn mask_slot_accepts(slot: char, c: char) -> bool {
+    match slot {
+        '#' => c.is_ascii_digit(),
+        'A' => c.is_alphabetic(),
+        '*' => c.is_alphanumeric(),
+        _ => false,
+    }
+}
+
+/// Characters typed into the mask's slots, in order
+fn unmask(mask: &str, text: &str) -> String {
+    mask.chars().zip(text.chars())
+        .filter(|(slot, _)| "#A*".contains(*slot))
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Lay typed characters into the mask's slots, adding literals as they are reached
+/// Characters that don't fit the next slot are dropped
+fn apply_mask(mask: &str, raw: &str) -> String {
+    let mut out = String::new();
+    let mut raw = raw.chars().peekable();
+    for slot in mask.chars() {
+        if raw.peek().is_none() {
+            break;
+        }
+        if "#A*".contains(slot) {
+            for c in raw.by_ref() {
+                if mask_slot_accepts(slot, c) {
+                    out.push(c);
+                    break;
+                }
+            }
+        } else {
+            out.push(slot);
+        }
+    }
+    // Drop literals that were added ahead of a character no slot accepted
+    let mut len = out.chars().count();
+    while len > 0 && mask.chars().nth(len - 1).is_some_and(|m| !"#A*".contains(m)) {
+        out.pop();
+        len -= 1;
+    }
+    out
+}
+
+/// Read up to `limit` bytes of the file at `path` as text
+fn read_capped(path: &std::path::Path, limit: usize) -> std::io::Result<String> {
+    use std::io::Read;
+    let mut content = String::new();
+    std::fs::File::open(path)?.take(limit as u64).read_to_string(&mut content)?;
+    Ok(content)
 }
@@ -6,7 +6,14 @@
 //! - No persistent storage
 //! - Memory limits
 //! - No tracking identifiers
+//!
+//! On top of what a page declares with `@capability`, the user's
+//! [`ContentPolicy`] decides per origin whether pages may fetch data, use
+//! storage, load media or follow links to other origins. Every decision goes
+//! through [`OriginPolicy::check`] and is logged.
 
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::Path;
 
 /// Memory limit per application (16MB default)
@@ -19,22 +26,102 @@ pub const MAX_FILE_SIZE_BYTES: usize = 1024 * 1024;
 pub struct Sandbox {
     memory_used: usize,
     memory_limit: usize,
+    capabilities: Capabilities,
+    /// The user's content policy for the document's origin
+    policy: OriginPolicy,
+    /// Fixed source for `session_id`, so recorded sessions replay identically
+    seed: Option<u64>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Sandbox {
     pub fn new() -> Self {
+        Self::with_capabilities(Capabilities::none())
+    }
+
+    /// Create a sandbox that grants the given capabilities
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
         Self {
             memory_used: 0,
             memory_limit: MEMORY_LIMIT_BYTES,
+            seed: None,
+            capabilities,
+            policy: OriginPolicy::default(),
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: OriginPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn policy(&self) -> &OriginPolicy {
+        &self.policy
+    }
+
+    /// Check that the user's policy lets the page follow a link; only links
+    /// to other origins are subject to `external_links`
+    pub fn check_link(&self, href: &str) -> Result<(), SandboxError> {
+        let remote = href.starts_with("http://") || href.starts_with("https://");
+        if remote && crate::bus::origin_of(href) != self.policy.origin {
+            self.policy.check(Permission::ExternalLinks, href)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the capabilities granted to the application
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Check that the application may read user-chosen files
+    pub fn check_files(&self) -> Result<(), SandboxError> {
+        if self.capabilities.files {
+            Ok(())
+        } else {
+            Err(SandboxError::FilesDisabled)
+        }
+    }
+
+    /// Check that the application may show OS notifications
+    pub fn check_notifications(&self) -> Result<(), SandboxError> {
+        if self.capabilities.notifications {
+            Ok(())
+        } else {
+            Err(SandboxError::NotificationsDisabled)
+        }
+    }
+
+    /// Check that the application may make a network request to `url`: it
+    /// needs `@capability network`, and the user's policy must allow fetching
+    pub fn check_network(&self, url: &str) -> Result<(), SandboxError> {
+        if !self.capabilities.network_same_origin {
+            return Err(SandboxError::NetworkDisabled);
+        }
+        self.policy.check(Permission::Fetch, url)
+    }
+
+    /// Check that the application may write to the system clipboard
+    pub fn check_clipboard_write(&self) -> Result<(), SandboxError> {
+        if self.capabilities.clipboard_write {
+            Ok(())
+        } else {
+            Err(SandboxError::ClipboardDisabled("clipboard_write"))
         }
     }
 
     /// Validate that a file path is safe to load
-    /// Only allows loading .prism files from the initial directory
+    /// Only allows loading .prism (or pre-compiled .prismc) files, signed .prismpkg
+    /// packages, and HTML/Markdown pages for the importer, from the initial directory
     pub fn validate_file_path(&self, path: &Path) -> Result<(), SandboxError> {
-        // Must have .prism extension
-        match path.extension() {
-            Some(ext) if ext == "prism" => {}
+        // Must be a Prism document or an importable page
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("prism" | "prismc" | "prismpkg" | "html" | "htm" | "md" | "markdown") => {}
             _ => return Err(SandboxError::InvalidFileType),
         }
 
@@ -47,6 +134,18 @@ impl Sandbox {
         Ok(())
     }
 
+    /// Validate a relative asset path (image, data, font or import) before the
+    /// document's `Vfs` resolves it: no URLs, absolute paths or traversal
+    pub fn validate_asset_path(path: &str) -> Result<(), SandboxError> {
+        if path.is_empty() || path.contains("://") || path.starts_with(['/', '\\']) || Path::new(path).is_absolute() {
+            return Err(SandboxError::InvalidAssetPath);
+        }
+        if path.split(['/', '\\']).any(|part| part == "..") {
+            return Err(SandboxError::PathTraversal);
+        }
+        Ok(())
+    }
+
     /// Check if loading content would exceed memory limits
     pub fn check_memory(&mut self, bytes: usize) -> Result<(), SandboxError> {
         if bytes > MAX_FILE_SIZE_BYTES {
@@ -85,9 +184,17 @@ impl Sandbox {
         self.memory_limit
     }
 
+    /// Derive session randomness from `seed` instead of the clock
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
     /// Generate a session-only random identifier (not persistent)
     /// This cannot be used for tracking across sessions
     pub fn session_id(&self) -> u64 {
+        if let Some(seed) = self.seed {
+            return seed ^ 0xDEADBEEF;
+        }
         // Use a simple random source - this is regenerated each session
         use std::time::{SystemTime, UNIX_EPOCH};
         let duration = SystemTime::now()
@@ -101,21 +208,32 @@ impl Sandbox {
 pub enum SandboxError {
     InvalidFileType,
     PathTraversal,
+    InvalidAssetPath,
     FileTooLarge,
     MemoryLimitExceeded,
     NetworkDisabled,
     StorageDisabled,
+    FilesDisabled,
+    NotificationsDisabled,
+    /// The clipboard capability the operation needed
+    ClipboardDisabled(&'static str),
+    BlockedByPolicy(Permission),
 }
 
 impl std::fmt::Display for SandboxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SandboxError::InvalidFileType => write!(f, "Only .prism files can be loaded"),
+            SandboxError::InvalidFileType => write!(f, "Only .prism, .prismc, HTML and Markdown files can be loaded"),
             SandboxError::PathTraversal => write!(f, "Path traversal not allowed"),
+            SandboxError::InvalidAssetPath => write!(f, "Assets must be relative paths inside the document's directory or package"),
             SandboxError::FileTooLarge => write!(f, "File exceeds maximum size limit"),
             SandboxError::MemoryLimitExceeded => write!(f, "Memory limit exceeded"),
-            SandboxError::NetworkDisabled => write!(f, "Network access is disabled"),
+            SandboxError::NetworkDisabled => write!(f, "Network access requires @capability network"),
             SandboxError::StorageDisabled => write!(f, "Persistent storage is disabled"),
+            SandboxError::FilesDisabled => write!(f, "File access requires @capability files"),
+            SandboxError::NotificationsDisabled => write!(f, "System notifications require @capability notifications"),
+            SandboxError::ClipboardDisabled(capability) => write!(f, "Clipboard access requires @capability {}", capability),
+            SandboxError::BlockedByPolicy(permission) => write!(f, "{} is blocked by the browser's content policy", permission.describe()),
         }
     }
 }
@@ -127,10 +245,14 @@ impl std::error::Error for SandboxError {}
 pub struct Capabilities {
     /// Allow same-origin network requests
     pub network_same_origin: bool,
-    /// Allow clipboard read
+    /// Allow clipboard read (reserved: documents have no way to read it yet)
     pub clipboard_read: bool,
     /// Allow clipboard write
     pub clipboard_write: bool,
+    /// Allow reading files the user picks in a native dialog
+    pub files: bool,
+    /// Allow OS-level notifications
+    pub notifications: bool,
 }
 
 impl Capabilities {
@@ -138,10 +260,135 @@ impl Capabilities {
         Self::default()
     }
 
-    /// Parse capabilities from app metadata
-    pub fn from_app_meta(_meta: &str) -> Self {
-        // For now, return no capabilities
-        // In future, could parse @capability directives
-        Self::none()
+    /// Build capabilities from `@capability` directives
+    /// Unknown names are returned so the caller can report them
+    pub fn from_directives(names: &[String]) -> (Self, Vec<String>) {
+        let mut caps = Self::none();
+        let mut unknown = vec![];
+        for name in names {
+            match name.as_str() {
+                "files" => caps.files = true,
+                "network" => caps.network_same_origin = true,
+                "clipboard_read" => caps.clipboard_read = true,
+                "clipboard_write" => caps.clipboard_write = true,
+                "notifications" => caps.notifications = true,
+                _ => unknown.push(name.clone()),
+            }
+        }
+        (caps, unknown)
+    }
+}
+
+/// Something the user's content policy can allow or refuse
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    /// Loading remote data (`load_csv` over https)
+    Fetch,
+    /// Keeping data between sessions (reserved; pages have no storage yet)
+    Storage,
+    /// Loading images and video
+    Media,
+    /// Following links to other origins
+    ExternalLinks,
+}
+
+impl Permission {
+    fn describe(&self) -> &'static str {
+        match self {
+            Permission::Fetch => "Fetching data",
+            Permission::Storage => "Storage",
+            Permission::Media => "Media",
+            Permission::ExternalLinks => "Opening links to other origins",
+        }
+    }
+}
+
+/// What the user lets pages do, whatever capabilities they declare
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    pub fetch: bool,
+    pub storage: bool,
+    pub media: bool,
+    pub external_links: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy { fetch: true, storage: true, media: true, external_links: true }
+    }
+}
+
+impl Policy {
+    pub fn allows(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::Fetch => self.fetch,
+            Permission::Storage => self.storage,
+            Permission::Media => self.media,
+            Permission::ExternalLinks => self.external_links,
+        }
+    }
+}
+
+/// Changes to the default policy for one origin; unset fields keep the default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyOverride {
+    pub fetch: Option<bool>,
+    pub storage: Option<bool>,
+    pub media: Option<bool>,
+    pub external_links: Option<bool>,
+}
+
+/// The user's content policy: a default and overrides keyed by origin
+/// (`https://example.com`, or `file:///dir` for local documents)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentPolicy {
+    pub default: Policy,
+    pub origins: BTreeMap<String, PolicyOverride>,
+}
+
+impl ContentPolicy {
+    pub fn for_origin(&self, origin: &str) -> Policy {
+        let mut policy = self.default;
+        if let Some(o) = self.origins.get(origin) {
+            policy.fetch = o.fetch.unwrap_or(policy.fetch);
+            policy.storage = o.storage.unwrap_or(policy.storage);
+            policy.media = o.media.unwrap_or(policy.media);
+            policy.external_links = o.external_links.unwrap_or(policy.external_links);
+        }
+        policy
+    }
+}
+
+/// The policy that applies to one document, shared by everything that loads
+/// on its behalf
+#[derive(Debug, Clone, Default)]
+pub struct OriginPolicy {
+    pub origin: String,
+    pub policy: Policy,
+}
+
+impl OriginPolicy {
+    pub fn new(origin: String, policy: Policy) -> Self {
+        Self { origin, policy }
+    }
+
+    /// Decide whether the document may use `permission` for `target`, logging the decision
+    pub fn check(&self, permission: Permission, target: &str) -> Result<(), SandboxError> {
+        let allowed = self.policy.allows(permission);
+        eprintln!(
+            "[PRISM POLICY] {} {:?} {} for {}",
+            if allowed { "allowed" } else { "blocked" },
+            permission,
+            target,
+            self.origin
+        );
+        if allowed {
+            Ok(())
+        } else {
+            Err(SandboxError::BlockedByPolicy(permission))
+        }
     }
 }
@@ -0,0 +1,133 @@
+//! Browser settings persisted between sessions
+//!
+//! Stored as JSON in `prism/settings.json` under the user's config directory
+//! (`$XDG_CONFIG_HOME`, `~/.config` or `%APPDATA%`). Missing or unreadable
+//! files give the defaults; unknown fields are ignored so older browsers can
+//! read newer files. Private windows load the settings but never save them.
+
+use prism::renderer::PixelFormat;
+use prism::sandbox::ContentPolicy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// An extra toolbar button, drawn between the address bar and the devtools buttons
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolbarButton {
+    /// Open `examples/home.prism`
+    Home,
+    /// Toggle reader mode: the page in a narrow, centred column
+    Reader,
+    /// Save the page area as a PNG
+    Screenshot,
+    /// Copy the page's address and show it as a QR code
+    Share,
+    /// Bookmarklet: run the named action of the current page, if it has one
+    Action { label: String, action: String },
+}
+
+/// What happens to an `https://` page whose certificate doesn't verify
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CertificatePolicy {
+    /// Show an error instead of the page
+    #[default]
+    Block,
+    /// Load it, with a warning in place of the lock
+    Warn,
+}
+
+/// When the browser saves power by drawing less
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatterySaver {
+    /// While the computer runs on battery, as far as the system says
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub compact: bool,
+    pub toolbar_bottom: bool,
+    pub toolbar: Vec<ToolbarButton>,
+    /// What pages may do, by default and per origin
+    pub policy: ContentPolicy,
+    /// Base64 ed25519 keys allowed to sign packages; when empty, each package
+    /// name is pinned to the key it was first opened with
+    pub trusted_publishers: Vec<String>,
+    /// Package name to the key it was first opened with
+    pub package_publishers: BTreeMap<String, String>,
+    pub invalid_certificates: CertificatePolicy,
+    /// Channel order the window surface expects, for backends whose colors
+    /// come out swapped with the default
+    pub pixel_format: PixelFormat,
+    /// Underline misspelled words in text inputs
+    pub spell_check: bool,
+    /// Dictionary checked against, such as `en_US` for `en_US.dic`/`en_US.aff`
+    pub spell_check_language: String,
+    /// Frames per second while something moves, at most 60; a rate that
+    /// doesn't divide 60 drops to the next one that does
+    pub frame_rate: u32,
+    pub battery_saver: BatterySaver,
+    /// Changes stay in memory (private windows)
+    #[serde(skip)]
+    pub ephemeral: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { compact: false, toolbar_bottom: false, toolbar: vec![ToolbarButton::Home, ToolbarButton::Share], policy: ContentPolicy::default(), trusted_publishers: vec![], package_publishers: BTreeMap::new(), invalid_certificates: CertificatePolicy::Block, pixel_format: PixelFormat::default(), spell_check: false, spell_check_language: "en_US".to_string(), frame_rate: 60, battery_saver: BatterySaver::Auto, ephemeral: false }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        let Some(path) = settings_path() else {
+            return Settings::default();
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                eprintln!("[PRISM] Ignoring invalid settings in {}: {}", path.display(), e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if self.ephemeral {
+            return;
+        }
+        let Some(path) = settings_path() else {
+            return;
+        };
+        let result = path.parent().map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(self).unwrap_or_default()));
+        if let Err(e) = result {
+            eprintln!("[PRISM] Failed to save settings to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// `prism/` in the user's config directory
+fn config_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let config = env_dir("XDG_CONFIG_HOME")
+        .or_else(|| env_dir("APPDATA"))
+        .or_else(|| env_dir("HOME").map(|home| home.join(".config")))?;
+    Some(config.join("prism"))
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("settings.json"))
+}
+
+/// Where the user's own spelling dictionaries go, searched before the system's
+pub fn dictionary_dir() -> Option<PathBuf> {
+    Some(config_dir()?.join("dictionaries"))
+}
@@ -0,0 +1,350 @@
+//! Spell checking for text inputs
+//!
+//! A [`Dictionary`] is read from a Hunspell-style pair of files, `xx_YY.dic`
+//! (one root word per line with affix flags) and `xx_YY.aff` (the prefix and
+//! suffix rules those flags name). Roots are expanded into every form their
+//! rules allow when the dictionary is loaded, so checking a word is a set
+//! lookup. Compounding, morphology and second-level affixes are not
+//! supported; dictionaries relying on them flag a few valid words.
+//!
+//! Suggestions are the dictionary words one edit away (a letter dropped,
+//! added, changed or two swapped), or two edits when none are.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Where installed Hunspell dictionaries are looked for, after the host's own
+/// directories
+pub const SYSTEM_DIRS: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+    "/usr/local/share/hunspell",
+    "/Library/Spelling",
+];
+
+/// Longest word suggestions are looked for, as their cost grows with length
+const MAX_SUGGEST_LEN: usize = 24;
+
+/// Letters tried in suggestions when the `.aff` file has no `TRY` line
+const DEFAULT_TRY: &str = "esianrtolcdugmphbyfvkwzxjq";
+
+/// Words known to be spelled correctly
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+    /// Letters tried when looking for suggestions, most common first
+    alphabet: Vec<char>,
+}
+
+/// How affix flags are written in the `.dic` and `.aff` files
+#[derive(Clone, Copy, PartialEq)]
+enum FlagFormat {
+    /// One char per flag
+    Char,
+    /// Two chars per flag (`FLAG long`)
+    Long,
+    /// Comma-separated numbers (`FLAG num`)
+    Num,
+}
+
+impl FlagFormat {
+    fn split(self, flags: &str) -> Vec<String> {
+        match self {
+            FlagFormat::Char => flags.chars().map(String::from).collect(),
+            FlagFormat::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars.chunks(2).map(|pair| pair.iter().collect()).collect()
+            }
+            FlagFormat::Num => flags.split(',').map(|f| f.trim().to_string()).collect(),
+        }
+    }
+}
+
+/// One char of an affix condition
+enum Condition {
+    Any,
+    Is(char),
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+impl Condition {
+    fn parse(pattern: &str) -> Vec<Condition> {
+        if pattern == "." {
+            return vec![];
+        }
+        let mut conditions = vec![];
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            conditions.push(match c {
+                '.' => Condition::Any,
+                '[' => {
+                    let set: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                    match set.strip_prefix('^') {
+                        Some(set) => Condition::NoneOf(set.chars().collect()),
+                        None => Condition::OneOf(set.chars().collect()),
+                    }
+                }
+                c => Condition::Is(c),
+            });
+        }
+        conditions
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Condition::Any => true,
+            Condition::Is(x) => *x == c,
+            Condition::OneOf(set) => set.contains(&c),
+            Condition::NoneOf(set) => !set.contains(&c),
+        }
+    }
+}
+
+/// A `PFX` or `SFX` rule
+struct Affix {
+    suffix: bool,
+    /// May combine with an affix of the other kind
+    cross: bool,
+    strip: String,
+    add: String,
+    condition: Vec<Condition>,
+}
+
+impl Affix {
+    /// The word with this affix applied, if its condition allows
+    fn apply(&self, word: &str) -> Option<String> {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() < self.condition.len() {
+            return None;
+        }
+        let tested = if self.suffix { &chars[chars.len() - self.condition.len()..] } else { &chars[..self.condition.len()] };
+        if !tested.iter().zip(&self.condition).all(|(c, condition)| condition.matches(*c)) {
+            return None;
+        }
+        if self.suffix {
+            let stem = word.strip_suffix(self.strip.as_str())?;
+            Some(format!("{}{}", stem, self.add))
+        } else {
+            let stem = word.strip_prefix(self.strip.as_str())?;
+            Some(format!("{}{}", self.add, stem))
+        }
+    }
+}
+
+impl Dictionary {
+    /// Expand the roots of a `.dic` file with the rules of its `.aff` file
+    pub fn from_hunspell(dic: &str, aff: &str) -> Dictionary {
+        let mut format = FlagFormat::Char;
+        let mut alphabet: Vec<char> = DEFAULT_TRY.chars().collect();
+        let mut need_affix = None;
+        let mut affixes: HashMap<String, Vec<Affix>> = HashMap::new();
+        // Whether each flag's rules may combine with an affix of the other kind
+        let mut cross_flags = HashMap::new();
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", "long", ..] => format = FlagFormat::Long,
+                ["FLAG", "num", ..] => format = FlagFormat::Num,
+                ["TRY", letters, ..] => alphabet = letters.chars().collect(),
+                ["NEEDAFFIX", flag, ..] => need_affix = Some(flag.to_string()),
+                ["PFX" | "SFX", flag, cross, _count] => {
+                    cross_flags.insert(flag.to_string(), *cross == "Y");
+                }
+                [kind @ ("PFX" | "SFX"), flag, strip, add, condition, ..] => {
+                    let cross = cross_flags.get(*flag).copied().unwrap_or(false);
+                    let zero = |s: &str| if s == "0" { String::new() } else { s.to_string() };
+                    // Continuation classes after '/' aren't supported
+                    let add = add.split('/').next().unwrap_or("");
+                    let affix = Affix { suffix: *kind == "SFX", cross, strip: zero(strip), add: zero(add), condition: Condition::parse(condition) };
+                    affixes.entry(flag.to_string()).or_default().push(affix);
+                }
+                _ => {}
+            }
+        }
+
+        let mut words = HashSet::new();
+        // The first line is the (approximate) number of roots
+        for line in dic.lines().skip(1) {
+            let entry = line.split(['\t', ' ']).next().unwrap_or("");
+            let (root, flags) = match entry.split_once('/') {
+                Some((root, flags)) => (root, format.split(flags)),
+                None => (entry, vec![]),
+            };
+            if root.is_empty() {
+                continue;
+            }
+            if !need_affix.as_ref().is_some_and(|flag| flags.contains(flag)) {
+                words.insert(root.to_string());
+            }
+            let rules: Vec<&Affix> = flags.iter().filter_map(|flag| affixes.get(flag)).flatten().collect();
+            for suffix in rules.iter().filter(|a| a.suffix) {
+                let Some(form) = suffix.apply(root) else {
+                    continue;
+                };
+                for prefix in rules.iter().filter(|a| !a.suffix && a.cross && suffix.cross) {
+                    if let Some(both) = prefix.apply(&form) {
+                        words.insert(both);
+                    }
+                }
+                words.insert(form);
+            }
+            for prefix in rules.iter().filter(|a| !a.suffix) {
+                if let Some(form) = prefix.apply(root) {
+                    words.insert(form);
+                }
+            }
+        }
+        Dictionary { words, alphabet }
+    }
+
+    /// Read `path` (a `.dic` file) and the `.aff` file beside it
+    pub fn open(path: &Path) -> Result<Dictionary, String> {
+        let read = |path: &Path| std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e));
+        let aff = read(&path.with_extension("aff"))?;
+        let dic = read(path)?;
+        // Older dictionaries are in a Latin encoding, named by `SET`
+        let latin = String::from_utf8_lossy(&aff)
+            .lines()
+            .any(|line| line.trim().strip_prefix("SET ").is_some_and(|set| set.trim().to_uppercase().starts_with("ISO8859")));
+        let decode = |bytes: &[u8]| {
+            if latin {
+                bytes.iter().map(|&b| b as char).collect()
+            } else {
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+        };
+        Ok(Dictionary::from_hunspell(&decode(&dic), &decode(&aff)))
+    }
+
+    /// The `.dic` file for `language` (such as `en_US`) in the first of `dirs`
+    /// that has one, then in [`SYSTEM_DIRS`]
+    pub fn find(language: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+        let file = format!("{}.dic", language);
+        dirs.iter()
+            .cloned()
+            .chain(SYSTEM_DIRS.iter().map(PathBuf::from))
+            .map(|dir| dir.join(&file))
+            .find(|path| path.is_file() && path.with_extension("aff").is_file())
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Whether `word` is spelled correctly; capitalized and all-caps forms of
+    /// lowercase words count, as do words with digits
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.replace('’', "'");
+        if word.chars().any(|c| c.is_numeric()) || self.words.contains(&word) {
+            return true;
+        }
+        let mut chars = word.chars();
+        if !chars.next().is_some_and(char::is_uppercase) {
+            return false;
+        }
+        let lower = word.to_lowercase();
+        let capitalized = chars.clone().all(|c| !c.is_uppercase());
+        let all_upper = chars.all(|c| !c.is_lowercase());
+        (capitalized || all_upper) && self.words.contains(&lower) || all_upper && self.words.contains(&capitalize(&lower))
+    }
+
+    /// Byte ranges of the misspelled words in `text`
+    pub fn misspelled(&self, text: &str) -> Vec<Range<usize>> {
+        words(text).filter(|range| !self.check(&text[range.clone()])).collect()
+    }
+
+    /// Up to `max` correctly spelled words close to `word`, in the same case
+    pub fn suggest(&self, word: &str, max: usize) -> Vec<String> {
+        let lower = word.to_lowercase();
+        if lower.chars().count() > MAX_SUGGEST_LEN {
+            return vec![];
+        }
+        let mut found: Vec<String> = vec![];
+        let keep = |candidate: String, found: &mut Vec<String>| {
+            if found.len() < max && !found.contains(&candidate) && self.check(&candidate) {
+                found.push(candidate);
+            }
+        };
+        let near = self.edits(&lower);
+        for candidate in near.iter().cloned() {
+            keep(candidate, &mut found);
+        }
+        if found.is_empty() {
+            for candidate in near.iter().flat_map(|near| self.edits(near)) {
+                keep(candidate, &mut found);
+                if found.len() == max {
+                    break;
+                }
+            }
+        }
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        let all_upper = word.chars().filter(|c| c.is_alphabetic()).all(char::is_uppercase) && word.chars().count() > 1;
+        found
+            .into_iter()
+            .map(|s| match (all_upper, capitalized) {
+                (true, _) => s.to_uppercase(),
+                (false, true) => capitalize(&s),
+                _ => s,
+            })
+            .collect()
+    }
+
+    /// Every string one edit from `word`: swaps, changes, drops, then additions
+    fn edits(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let joined = |parts: &[&[char]]| parts.iter().flat_map(|p| p.iter()).collect::<String>();
+        let mut edits = vec![];
+        for i in 1..chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(i - 1, i);
+            edits.push(swapped.into_iter().collect());
+        }
+        for i in 0..chars.len() {
+            for &c in &self.alphabet {
+                if c != chars[i] {
+                    edits.push(joined(&[&chars[..i], &[c], &chars[i + 1..]]));
+                }
+            }
+        }
+        for i in 0..chars.len() {
+            edits.push(joined(&[&chars[..i], &chars[i + 1..]]));
+        }
+        for i in 0..=chars.len() {
+            for &c in &self.alphabet {
+                edits.push(joined(&[&chars[..i], &[c], &chars[i..]]));
+            }
+        }
+        edits
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Byte ranges of the words of `text`: runs of letters, with apostrophes
+/// inside them (`don't`)
+pub fn words(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| c.is_alphabetic())?;
+        let mut end = text.len();
+        while let Some(&(i, c)) = chars.peek() {
+            let apostrophe = matches!(c, '\'' | '’') && text[i + c.len_utf8()..].chars().next().is_some_and(char::is_alphabetic);
+            if !(c.is_alphabetic() || apostrophe) {
+                end = i;
+                break;
+            }
+            chars.next();
+        }
+        Some(start..end)
+    })
+}
@@ -5,23 +5,152 @@
 //! for efficient re-rendering. Extended with list operations, computed values,
 //! and full expression evaluation.
 
-use crate::ast::{Value, StateBlock, Expression, BinaryOp, UnaryOp, InterpolationPart};
+use crate::ast::{Value, ValueType, StateBlock, ComponentDef, StringTable, Expression, BinaryOp, UnaryOp, InterpolationPart, PropValue, ViewNode};
+use crate::data::DataLoader;
+use crate::locale::NumberFormat;
+use crate::renderer::number_value;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Work a builtin or statement asks of the runtime, which evaluation can't do itself
+#[derive(Debug, Clone)]
+pub enum Effect {
+    /// `broadcast(channel, data)`
+    Broadcast { channel: String, data: Value },
+    /// `notify(title, body)` statement
+    Notify { title: String, body: String },
+    /// `focus(id)` statement
+    Focus(String),
+    /// `scroll_to(id)` statement
+    ScrollTo(String),
+    /// `copy(text)` statement
+    Copy(String),
+}
+
+/// One component instance: the key of the view node that declared it and,
+/// inside a list, the item's index, so each repeated instance keeps its own state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceKey {
+    pub node: usize,
+    pub index: Option<i64>,
+}
+
+/// One page state variable, as the task manager lists it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateUsage {
+    pub name: String,
+    pub type_name: &'static str,
+    /// Approximate bytes held, as counted by `estimated_bytes`
+    pub bytes: usize,
+    /// Length of a list or object
+    pub items: Option<usize>,
+}
+
+/// State of one component instance
+struct ComponentFrame {
+    component: String,
+    values: HashMap<String, Value>,
+    /// `inject`ed values as last drawn, readable by the instance's actions
+    injected: HashMap<String, Value>,
+}
 
 /// The reactive state store
 pub struct StateStore {
     values: HashMap<String, Value>,
     computed: HashMap<String, Expression>,
+    /// Computed values being evaluated, so one that refers back to itself reads as undefined
+    computing: RefCell<Vec<String>>,
     locals: HashMap<String, Value>,  // For loop variables, etc.
+    scope: RefCell<HashMap<String, Value>>,  // Item variables while rendering each/list
+    loader: Option<DataLoader>,
+    /// Translations for `t`, in declaration order
+    strings: Vec<StringTable>,
+    /// Read-only `env` values the host keeps current (theme, locale, viewport)
+    env: HashMap<String, Value>,
+    /// `route`: path, params and query of the route shown
+    route: Value,
+    /// Declared types of page state fields, enforced on assignment in strict mode
+    types: HashMap<String, ValueType>,
+    strict: bool,
+    /// Each component's `state` block, copied into every new instance
+    component_state: HashMap<String, StateBlock>,
+    /// Names each component `inject`s
+    component_inject: HashMap<String, Vec<String>>,
+    /// Values `provide`d by the nodes being drawn, innermost shadowing outer ones
+    provided: RefCell<HashMap<String, Value>>,
+    /// State of the component instances drawn so far
+    instances: RefCell<HashMap<InstanceKey, ComponentFrame>>,
+    /// Keys of the indexed view's nodes by address, and the view's address and name
+    node_keys: RefCell<(usize, String, HashMap<usize, usize>)>,
+    /// Instance whose action is running; its state shadows the page's
+    instance: Option<InstanceKey>,
+    /// Side effects the action requested, carried out by the runtime after it
+    effects: RefCell<Vec<Effect>>,
+    /// Actions running now; only they may queue effects
+    actions_running: usize,
     dirty: bool,
 }
 
+/// Key each node below `node` by a hash of its path
+fn index_nodes(node: &ViewNode, path: &str, keys: &mut HashMap<usize, usize>) {
+    let path = match node.props.get("id") {
+        Some(PropValue::Static(Value::String(id))) => format!("#{}", id),
+        _ => path.to_string(),
+    };
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    keys.insert(node as *const ViewNode as usize, hasher.finish() as usize);
+    for (i, child) in node.children.iter().enumerate() {
+        index_nodes(child, &format!("{}.{}", path, i), keys);
+    }
+}
+
+/// Run `f` with `bindings` inserted in `map`, restoring any shadowed values after
+fn shadowed<R>(map: &RefCell<HashMap<String, Value>>, bindings: Vec<(String, Value)>, f: impl FnOnce() -> R) -> R {
+    let saved: Vec<(String, Option<Value>)> = {
+        let mut map = map.borrow_mut();
+        bindings.into_iter().map(|(k, v)| (k.clone(), map.insert(k, v))).collect()
+    };
+    let result = f();
+    let mut map = map.borrow_mut();
+    for (key, previous) in saved {
+        match previous {
+            Some(v) => map.insert(key, v),
+            None => map.remove(&key),
+        };
+    }
+    result
+}
+
+impl Default for StateStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StateStore {
     pub fn new() -> Self {
         Self {
             values: HashMap::new(),
             computed: HashMap::new(),
+            computing: RefCell::new(vec![]),
             locals: HashMap::new(),
+            scope: RefCell::new(HashMap::new()),
+            loader: None,
+            strings: vec![],
+            env: HashMap::new(),
+            route: Value::Null,
+            types: HashMap::new(),
+            strict: false,
+            component_state: HashMap::new(),
+            component_inject: HashMap::new(),
+            provided: RefCell::new(HashMap::new()),
+            instances: RefCell::new(HashMap::new()),
+            node_keys: RefCell::new((0, String::new(), HashMap::new())),
+            instance: None,
+            effects: RefCell::new(Vec::new()),
+            actions_running: 0,
             dirty: true,
         }
     }
@@ -31,32 +160,192 @@ impl StateStore {
         for (key, value) in &block.fields {
             self.values.insert(key.clone(), value.clone());
         }
+        self.types.extend(block.types.iter().map(|(k, t)| (k.clone(), t.clone())));
         self.dirty = true;
     }
 
+    /// Refuse assignments that don't match a field's declared type (`@strict`)
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Why assigning `value` to the state field `key` is refused in strict
+    /// mode, if it is; a component instance's own state is never checked
+    pub fn type_error(&self, key: &str, value: &Value) -> Option<String> {
+        let ty = self.declared_type(key)?;
+        (!ty.accepts(value)).then(|| format!("'{}' is {}, can't assign {}", key, ty, value.type_name()))
+    }
+
+    /// Like `type_error`, for adding `item` to the list `key`
+    pub fn item_type_error(&self, key: &str, item: &Value) -> Option<String> {
+        let ty = self.declared_type(key)?.item_type()?;
+        (!ty.accepts(item)).then(|| format!("'{}' holds {}, can't add {}", key, ty, item.type_name()))
+    }
+
+    fn declared_type(&self, key: &str) -> Option<&ValueType> {
+        if !self.strict || self.instance.is_some_and(|i| self.instances.borrow().get(&i).is_some_and(|f| f.values.contains_key(key))) {
+            return None;
+        }
+        self.types.get(key)
+    }
+
     /// Set computed values
     pub fn set_computed(&mut self, computed: HashMap<String, Expression>) {
         self.computed = computed;
     }
 
-    /// Get a value from state (checks locals first, then state, then computed)
+    /// Set the `route` object expressions read
+    pub fn set_route(&mut self, route: Value) {
+        self.route = route;
+        self.dirty = true;
+    }
+
+    /// Set the `state` block and `inject` names of each component, for new instances
+    pub fn set_component_state(&mut self, components: &HashMap<String, ComponentDef>) {
+        self.component_state = components.iter().map(|(name, def)| (name.clone(), def.state.clone())).collect();
+        self.component_inject = components.iter().map(|(name, def)| (name.clone(), def.inject.clone())).collect();
+    }
+
+    /// The instance a component node draws at this point, and its state and
+    /// injected values to bind while it does; a new instance starts from the
+    /// component's `state`, and a name nothing provides reads as null
+    pub fn component_instance(&self, node: usize, component: &str) -> (InstanceKey, Vec<(String, Value)>) {
+        let index = match self.scope.borrow().get("index") {
+            Some(Value::Int(i)) => Some(*i),
+            _ => None,
+        };
+        let key = InstanceKey { node, index };
+        let mut instances = self.instances.borrow_mut();
+        let frame = instances.entry(key).or_insert_with(|| ComponentFrame {
+            component: component.to_string(),
+            values: self.component_state.get(component).map(|block| block.fields.clone()).unwrap_or_default(),
+            injected: HashMap::new(),
+        });
+        let provided = self.provided.borrow();
+        frame.injected = self.component_inject.get(component).into_iter().flatten()
+            .map(|name| (name.clone(), provided.get(name).cloned().unwrap_or(Value::Null)))
+            .collect();
+        let bindings = frame.values.iter().chain(&frame.injected).map(|(k, v)| (k.clone(), v.clone())).collect();
+        (key, bindings)
+    }
+
+    /// Run `f` with the values a `provide` block gives its descendants
+    pub fn with_provided<R>(&self, values: Vec<(String, Value)>, f: impl FnOnce() -> R) -> R {
+        shadowed(&self.provided, values, f)
+    }
+
+    /// Key the nodes of a view about to be drawn by their `id`, or else their
+    /// place in it, so state kept per node (component instances, table
+    /// filters and widths) doesn't depend on where the tree sits in memory.
+    /// `name` tells the views of different routes apart.
+    pub fn index_view(&self, view: &ViewNode, name: &str) {
+        let root = view as *const ViewNode as usize;
+        let mut index = self.node_keys.borrow_mut();
+        if index.0 == root && index.1 == name {
+            return;
+        }
+        let mut keys = HashMap::new();
+        index_nodes(view, &format!("{}|0", name), &mut keys);
+        *index = (root, name.to_string(), keys);
+    }
+
+    /// Key of a node of the indexed view (see `index_view`)
+    pub fn node_key(&self, node: &ViewNode) -> usize {
+        let address = node as *const ViewNode as usize;
+        self.node_keys.borrow().2.get(&address).copied().unwrap_or(address)
+    }
+
+    /// The component an instance was drawn by
+    pub fn instance_component(&self, key: InstanceKey) -> Option<String> {
+        self.instances.borrow().get(&key).map(|frame| frame.component.clone())
+    }
+
+    /// The instance whose action is running, if any
+    pub fn instance(&self) -> Option<InstanceKey> {
+        self.instance
+    }
+
+    /// Make an instance's state shadow the page's while its actions run
+    pub fn set_instance(&mut self, key: Option<InstanceKey>) {
+        self.instance = key;
+    }
+
+    /// The running instance's state if it has `key`, else the page's
+    fn values_for(&mut self, key: &str) -> &mut HashMap<String, Value> {
+        let frame = self.instance.and_then(|i| self.instances.get_mut().get_mut(&i)).filter(|f| f.values.contains_key(key));
+        match frame {
+            Some(frame) => &mut frame.values,
+            None => &mut self.values,
+        }
+    }
+
+    /// Set the translations `t` looks keys up in
+    pub fn set_strings(&mut self, strings: Vec<StringTable>) {
+        self.strings = strings;
+    }
+
+    /// Set the data loader used by `load_csv`
+    pub fn set_loader(&mut self, loader: DataLoader) {
+        self.loader = Some(loader);
+    }
+
+    /// Get the data loader, if any
+    pub fn loader_mut(&mut self) -> Option<&mut DataLoader> {
+        self.loader.as_mut()
+    }
+
+    /// Failed `load_csv` loads so far (see `DataLoader::failures`)
+    pub fn load_failures(&self) -> u64 {
+        self.loader.as_ref().map_or(0, DataLoader::failures)
+    }
+
+    /// Get a value from state (checks render scope and locals first, then the
+    /// running component instance's state, then state, then computed)
     pub fn get(&self, key: &str) -> Option<Value> {
+        if let Some(v) = self.scope.borrow().get(key) {
+            return Some(v.clone());
+        }
         if let Some(v) = self.locals.get(key) {
             return Some(v.clone());
         }
+        if let Some(v) = self.instance.and_then(|i| {
+            let instances = self.instances.borrow();
+            let frame = instances.get(&i)?;
+            frame.values.get(key).or_else(|| frame.injected.get(key)).cloned()
+        }) {
+            return Some(v);
+        }
+        if key == "env" {
+            return Some(Value::Object(self.env.clone()));
+        }
         if let Some(v) = self.values.get(key) {
             return Some(v.clone());
         }
+        if key == "route" {
+            return Some(self.route.clone());
+        }
         if let Some(expr) = self.computed.get(key) {
-            return Some(self.evaluate(expr));
+            if self.computing.borrow().iter().any(|name| name == key) {
+                return None;
+            }
+            self.computing.borrow_mut().push(key.to_string());
+            let value = self.evaluate(expr);
+            self.computing.borrow_mut().pop();
+            return Some(value);
         }
         None
     }
 
+    /// All state variables (not computed values or locals), including the
+    /// renderer's hidden keys
+    pub fn values(&self) -> &HashMap<String, Value> {
+        &self.values
+    }
+
     /// Get mutable reference to list
     pub fn get_list_mut(&mut self, key: &str) -> Option<&mut Vec<Value>> {
-        if let Some(Value::List(list)) = self.values.get_mut(key) {
-            self.dirty = true;
+        self.dirty = true;
+        if let Some(Value::List(list)) = self.values_for(key).get_mut(key) {
             return Some(list);
         }
         None
@@ -64,8 +353,8 @@ impl StateStore {
 
     /// Get mutable reference to object
     pub fn get_object_mut(&mut self, key: &str) -> Option<&mut HashMap<String, Value>> {
-        if let Some(Value::Object(obj)) = self.values.get_mut(key) {
-            self.dirty = true;
+        self.dirty = true;
+        if let Some(Value::Object(obj)) = self.values_for(key).get_mut(key) {
             return Some(obj);
         }
         None
@@ -73,13 +362,30 @@ impl StateStore {
 
     /// Set a value in state
     pub fn set(&mut self, key: &str, value: Value) {
-        let changed = self.values.get(key) != Some(&value);
-        self.values.insert(key.to_string(), value);
+        if key == "env" {
+            eprintln!("[PRISM] env is read-only");
+            return;
+        }
+        if let Some(error) = self.type_error(key, &value) {
+            eprintln!("[PRISM] Type error: {}", error);
+            return;
+        }
+        let values = self.values_for(key);
+        let changed = values.get(key) != Some(&value);
+        values.insert(key.to_string(), value);
         if changed {
             self.dirty = true;
         }
     }
 
+    /// Update an `env` value, invalidating the page if it changed
+    pub fn set_env(&mut self, key: &str, value: Value) {
+        if self.env.get(key) != Some(&value) {
+            self.env.insert(key.to_string(), value);
+            self.dirty = true;
+        }
+    }
+
     /// Set a local variable (for loops, etc.)
     pub fn set_local(&mut self, key: &str, value: Value) {
         self.locals.insert(key.to_string(), value);
@@ -90,6 +396,28 @@ impl StateStore {
         self.locals.clear();
     }
 
+    /// Unbind the local variables, returning them for `restore_locals`
+    pub fn take_locals(&mut self) -> HashMap<String, Value> {
+        std::mem::take(&mut self.locals)
+    }
+
+    /// Bind local variables taken with `take_locals` again
+    pub fn restore_locals(&mut self, locals: HashMap<String, Value>) {
+        self.locals = locals;
+    }
+
+    /// Item variables bound by the renderer at this point, for handlers that
+    /// run later with the same bindings
+    pub fn scope_bindings(&self) -> Vec<(String, Value)> {
+        self.scope.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    /// Run `f` with item variables bound, restoring any shadowed values after
+    /// Used by the renderer, which only has shared access to the store
+    pub fn with_scope<R>(&self, bindings: Vec<(String, Value)>, f: impl FnOnce() -> R) -> R {
+        shadowed(&self.scope, bindings, f)
+    }
+
     /// Set a nested value (object property or list index)
     pub fn set_nested(&mut self, path: &[&str], value: Value) {
         if path.is_empty() {
@@ -106,11 +434,61 @@ impl StateStore {
         self.dirty = true;
     }
 
+    /// Take the effects queued by `broadcast` and by statements such as `notify`
+    pub fn take_effects(&self) -> Vec<Effect> {
+        std::mem::take(&mut *self.effects.borrow_mut())
+    }
+
+    /// Queue an effect for the runtime, returning whether it was queued.
+    /// Expressions evaluated outside an action, such as while rendering,
+    /// queue nothing, so a view can't repeat an effect every frame.
+    pub fn queue_effect(&self, effect: Effect) -> bool {
+        if self.actions_running == 0 {
+            return false;
+        }
+        self.effects.borrow_mut().push(effect);
+        true
+    }
+
+    /// An action starts running; effects may be queued until it ends
+    pub fn enter_action(&mut self) {
+        self.actions_running += 1;
+    }
+
+    pub fn leave_action(&mut self) {
+        self.actions_running = self.actions_running.saturating_sub(1);
+    }
+
     /// Check if state has changed since last render
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
 
+    /// Approximate bytes held by the page's state variables
+    pub fn estimated_bytes(&self) -> usize {
+        self.values.iter().map(|(name, value)| name.capacity() + value.estimated_size()).sum()
+    }
+
+    /// Each of the page's state variables with its size, largest first
+    /// (the renderer's hidden keys aren't the document's variables)
+    pub fn usage(&self) -> Vec<StateUsage> {
+        let mut usage: Vec<StateUsage> = self.values.iter()
+            .filter(|(name, _)| !is_hidden_key(name))
+            .map(|(name, value)| StateUsage {
+                name: name.clone(),
+                type_name: value.type_name(),
+                bytes: name.capacity() + value.estimated_size(),
+                items: match value {
+                    Value::List(items) => Some(items.len()),
+                    Value::Object(map) => Some(map.len()),
+                    _ => None,
+                },
+            })
+            .collect();
+        usage.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+        usage
+    }
+
     /// Mark state as clean (after render)
     pub fn mark_clean(&mut self) {
         self.dirty = false;
@@ -201,7 +579,7 @@ impl StateStore {
             }
             
             Expression::Lambda { .. } => {
-                // Lambdas are evaluated when called, return as-is for now
+                // Lambdas are only applied by `|>`
                 Value::Null
             }
             
@@ -230,13 +608,37 @@ impl StateStore {
                 self.evaluate(expr)
             }
             
-            Expression::Pipe { value, transform: _ } => {
-                // Pipe passes value to transform
-                
-                // For now, treat as identity - full implementation would substitute
-                self.evaluate(value)
+            Expression::Pipe { value, transform } => {
+                let value = self.evaluate(value);
+                self.apply_transform(value, transform)
+            }
+        }
+    }
+
+    /// Right-hand side of `|>`: a name or call runs the value's method of that
+    /// name, or the builtin function when the method gives nothing, with the
+    /// value in front of the call's arguments; a lambda gets the value bound to
+    /// its first parameter
+    fn apply_transform(&self, value: Value, transform: &Expression) -> Value {
+        let (name, args) = match transform {
+            Expression::Variable(name) => (name, vec![]),
+            Expression::Call { function, args } => (function, args.iter().map(|a| self.evaluate(a)).collect()),
+            Expression::Lambda { params, body } => {
+                let mut bindings: Vec<(String, Value)> = params.iter().map(|p| (p.clone(), Value::Null)).collect();
+                if let Some(first) = bindings.first_mut() {
+                    first.1 = value;
+                }
+                return self.with_scope(bindings, || self.evaluate(body));
             }
+            _ => return Value::Null,
+        };
+        let result = self.call_method(&value, name, &args);
+        if !matches!(result, Value::Null) || !is_builtin_function(name) {
+            return result;
         }
+        let mut args = args;
+        args.insert(0, value);
+        self.call_builtin(name, &args)
     }
 
     fn apply_unary_op(&self, op: &UnaryOp, val: &Value) -> Value {
@@ -370,12 +772,52 @@ impl StateStore {
             "ceil" => args.first().map(|v| Value::Int(v.as_float().ceil() as i64)).unwrap_or(Value::Null),
             "round" => args.first().map(|v| Value::Int(v.as_float().round() as i64)).unwrap_or(Value::Null),
             "sqrt" => args.first().map(|v| Value::Float(v.as_float().sqrt())).unwrap_or(Value::Null),
-            
+            "clamp" => match args {
+                [Value::Int(x), Value::Int(lo), Value::Int(hi)] => Value::Int((*x).max(*lo).min(*hi)),
+                [x, lo, hi] => Value::Float(x.as_float().max(lo.as_float()).min(hi.as_float())),
+                _ => Value::Null,
+            },
+            "lerp" => match args {
+                [a, b, t] => {
+                    let a = a.as_float();
+                    Value::Float(a + (b.as_float() - a) * t.as_float())
+                }
+                _ => Value::Null,
+            },
+            "map_range" => match args {
+                [x, in_lo, in_hi, out_lo, out_hi] => {
+                    let (in_lo, in_hi) = (in_lo.as_float(), in_hi.as_float());
+                    let (out_lo, out_hi) = (out_lo.as_float(), out_hi.as_float());
+                    // An empty input range maps everything to the start of the output
+                    let span = in_hi - in_lo;
+                    let t = if span == 0.0 { 0.0 } else { (x.as_float() - in_lo) / span };
+                    Value::Float(out_lo + (out_hi - out_lo) * t)
+                }
+                _ => Value::Null,
+            },
+            "sin" => args.first().map(|v| Value::Float(v.as_float().sin())).unwrap_or(Value::Null),
+            "cos" => args.first().map(|v| Value::Float(v.as_float().cos())).unwrap_or(Value::Null),
+            "atan2" => match args {
+                [y, x] => Value::Float(y.as_float().atan2(x.as_float())),
+                _ => Value::Null,
+            },
+            "pi" => Value::Float(std::f64::consts::PI),
+            "distance" => match args {
+                [x1, y1, x2, y2] => Value::Float((x2.as_float() - x1.as_float()).hypot(y2.as_float() - y1.as_float())),
+                _ => Value::Null,
+            },
+
             // String
             "len" => args.first().map(|v| Value::Int(v.len() as i64)).unwrap_or(Value::Null),
             "str" => args.first().map(|v| Value::String(v.as_string())).unwrap_or(Value::Null),
-            "int" => args.first().map(|v| Value::Int(v.as_int())).unwrap_or(Value::Null),
-            "float" => args.first().map(|v| Value::Float(v.as_float())).unwrap_or(Value::Null),
+            "int" => args.first().map(|v| match v {
+                Value::String(s) => Value::Int(self.number_format().parse(s).unwrap_or(0.0) as i64),
+                v => Value::Int(v.as_int()),
+            }).unwrap_or(Value::Null),
+            "float" => args.first().map(|v| match v {
+                Value::String(s) => Value::Float(self.number_format().parse(s).unwrap_or(0.0)),
+                v => Value::Float(v.as_float()),
+            }).unwrap_or(Value::Null),
             "bool" => args.first().map(|v| Value::Bool(v.as_bool())).unwrap_or(Value::Null),
             
             // Type checking
@@ -408,6 +850,48 @@ impl StateStore {
                 _ => Value::List(vec![]),
             }).unwrap_or(Value::List(vec![])),
             
+            // Data loading (capability-gated by the loader)
+            "load_csv" => {
+                let source = args.first().map(|v| v.as_string()).unwrap_or_default();
+                let delimiter = args.get(1).and_then(|v| v.as_string().chars().next());
+                match &self.loader {
+                    Some(loader) if !source.is_empty() => loader.load_csv(&source, delimiter),
+                    _ => Value::Null,
+                }
+            }
+
+            // Localization
+            "format_number" => {
+                let Some(n) = args.first() else {
+                    return Value::Null;
+                };
+                let decimals = match args.get(1) {
+                    Some(d) => Some(d.as_int().clamp(0, 20) as usize),
+                    None if matches!(n, Value::Int(_)) => Some(0),
+                    None => None,
+                };
+                Value::String(self.number_format().format(n.as_float(), decimals))
+            }
+            "parse_number" => match args.first() {
+                Some(Value::String(s)) => self.number_format().parse(s).map(number_value).unwrap_or(Value::Null),
+                Some(v @ (Value::Int(_) | Value::Float(_))) => v.clone(),
+                _ => Value::Null,
+            },
+            "t" => {
+                let key = args.first().map(|v| v.as_string()).unwrap_or_default();
+                Value::String(self.translate(&key, args.get(1)))
+            }
+
+            // Effects, carried out by the runtime once the action finishes
+            "broadcast" => {
+                let channel = args.first().map(|v| v.as_string()).unwrap_or_default();
+                if channel.is_empty() {
+                    return Value::Bool(false);
+                }
+                let data = args.get(1).cloned().unwrap_or(Value::Null);
+                Value::Bool(self.queue_effect(Effect::Broadcast { channel, data }))
+            }
+
             // JSON
             "json_encode" => args.first().map(|v| Value::String(Self::to_json(v))).unwrap_or(Value::Null),
             
@@ -415,6 +899,77 @@ impl StateStore {
         }
     }
 
+    /// Separators for `env.locale`
+    fn number_format(&self) -> NumberFormat {
+        NumberFormat::for_locale(&self.env.get("locale").map(|v| v.as_string()).unwrap_or_default())
+    }
+
+    /// `t(key, arg)`: the key's text in the best table for `env.locale`, with
+    /// `{name}` filled from `arg` (an object, or a number bound as `count`) or
+    /// state; a number or `count` field picks the plural form
+    fn translate(&self, key: &str, arg: Option<&Value>) -> String {
+        let locale = self.env.get("locale").map(|v| v.as_string()).unwrap_or_default();
+        let Some((table, entry)) = self.lookup_string(&locale, key) else {
+            return key.to_string();
+        };
+        let count = match arg {
+            Some(Value::Int(_) | Value::Float(_)) => arg.cloned(),
+            Some(Value::Object(fields)) => fields.get("count").cloned(),
+            _ => None,
+        };
+        let template = match entry {
+            Value::Object(forms) => {
+                let n = count.as_ref().map(|c| c.as_float()).unwrap_or(0.0);
+                let category = plural_category(&table.locale, n);
+                let exact_zero = (n == 0.0).then(|| forms.get("zero")).flatten();
+                exact_zero
+                    .or_else(|| forms.get(category))
+                    .or_else(|| forms.get("other"))
+                    .map(|v| v.as_string())
+                    .unwrap_or_else(|| key.to_string())
+            }
+            other => other.as_string(),
+        };
+
+        let mut out = String::new();
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let name = rest[start + 1..start + len].trim();
+            let value = match (arg, name) {
+                (Some(Value::Object(fields)), _) if fields.contains_key(name) => fields.get(name).cloned(),
+                (_, "count") if count.is_some() => count.clone(),
+                _ => self.get(name),
+            };
+            match value {
+                Some(v) => out.push_str(&v.as_string()),
+                None => out.push_str(&rest[start..=start + len]),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// The entry for `key` from the exact locale, its language, English or
+    /// the first table that has it
+    fn lookup_string(&self, locale: &str, key: &str) -> Option<(&StringTable, &Value)> {
+        let language = locale.split('-').next().unwrap_or(locale);
+        let matches = |table: &StringTable, want: &str| table.locale.eq_ignore_ascii_case(want);
+        let candidates = [
+            self.strings.iter().find(|t| matches(t, locale)),
+            self.strings.iter().find(|t| matches(t, language)),
+            self.strings.iter().find(|t| t.locale.split('-').next().is_some_and(|l| l.eq_ignore_ascii_case(language))),
+            self.strings.iter().find(|t| matches(t, "en")),
+        ];
+        candidates.into_iter().flatten()
+            .chain(self.strings.iter())
+            .find_map(|table| table.entries.get(key).map(|entry| (table, entry)))
+    }
+
     /// Call a method on a value
     fn call_method(&self, obj: &Value, method: &str, args: &[Value]) -> Value {
         match (obj, method) {
@@ -635,3 +1190,64 @@ impl StateStore {
         }
     }
 }
+
+/// CLDR plural category of `n` for a locale's language, for the common rule families
+fn plural_category(locale: &str, n: f64) -> &'static str {
+    let language = locale.split('-').next().unwrap_or("").to_ascii_lowercase();
+    if n.fract() != 0.0 {
+        return "other";
+    }
+    let i = n.abs() as u64;
+    let (m10, m100) = (i % 10, i % 100);
+    match language.as_str() {
+        "ja" | "zh" | "ko" | "vi" | "th" | "id" | "ms" | "tr" => "other",
+        "fr" | "pt" if i <= 1 => "one",
+        "ru" | "uk" | "be" => {
+            if m10 == 1 && m100 != 11 {
+                "one"
+            } else if (2..=4).contains(&m10) && !(12..=14).contains(&m100) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "pl" => {
+            if i == 1 {
+                "one"
+            } else if (2..=4).contains(&m10) && !(12..=14).contains(&m100) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "cs" | "sk" => match i {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        "ar" => match (i, m100) {
+            (0, _) => "zero",
+            (1, _) => "one",
+            (2, _) => "two",
+            (_, 3..=10) => "few",
+            (_, 11..=99) => "many",
+            _ => "other",
+        },
+        _ if i == 1 => "one",
+        _ => "other",
+    }
+}
+
+/// Functions `StateStore::evaluate` knows how to call
+pub fn is_builtin_function(name: &str) -> bool {
+    matches!(name, "abs" | "min" | "max" | "floor" | "ceil" | "round" | "sqrt" | "clamp" | "lerp" | "map_range" |
+        "sin" | "cos" | "atan2" | "pi" | "distance" | "len" | "str" | "int" | "float" | "bool" | "type" | "is_null" |
+        "is_list" | "is_object" | "list" | "range" | "keys" | "values" | "load_csv" | "json_encode" | "broadcast" |
+        "t" | "format_number" | "parse_number")
+}
+
+/// Keys the renderer keeps in state for widgets (drafts, filters, column
+/// widths) start with `#`, which can't start an identifier
+pub fn is_hidden_key(name: &str) -> bool {
+    name.starts_with('#')
+}
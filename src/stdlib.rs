@@ -0,0 +1,14 @@
+//! Standard component library
+//!
+//! Modules are plain Prism sources defining components, pulled in with
+//! `@import std/<name>`. A document can restyle any of them by declaring a
+//! component with the same name, which takes precedence over the library one.
+
+/// Source of a standard module such as `std/nav`
+pub fn module_source(path: &str) -> Option<&'static str> {
+    match path {
+        "std/nav" => Some(include_str!("std/nav.prism")),
+        "std/clipboard" => Some(include_str!("std/clipboard.prism")),
+        _ => None,
+    }
+}
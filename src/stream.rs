@@ -0,0 +1,120 @@
+//! Documents shown while they are still arriving
+//!
+//! A [`DocumentStream`] reads a `.prism` source on a background thread (a slow
+//! connection, or a file too large to parse in one frame) and hands its host
+//! previews: the document parsed as far as its last complete top-level view
+//! child. Previews are taken of prefixes that double in size, so parsing the
+//! start again each time costs at most as much as parsing the whole document
+//! once more.
+
+use crate::ast::PrismApp;
+use crate::parser;
+use crate::vfs::Vfs;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Sources at least this long, or of unknown length, are worth streaming;
+/// shorter ones are parsed whole as soon as they arrive
+pub const STREAM_MIN_BYTES: u64 = 1024 * 1024;
+
+/// Prefix parsed for the first preview
+const FIRST_PREVIEW_BYTES: usize = 64 * 1024;
+
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+/// A document being read, and how much of it has been shown
+pub struct DocumentStream {
+    chunks: Receiver<std::io::Result<Vec<u8>>>,
+    body: Vec<u8>,
+    /// Length announced by the source, if any
+    total: Option<u64>,
+    /// Where the document's imports are read from
+    assets: Vfs,
+    finished: bool,
+    /// The body has to reach this length before the next preview
+    next_preview: usize,
+    /// A preview has been tried, so a finished body is parsed whole
+    previewed: bool,
+    /// Top-level view children in the last preview handed out
+    shown: usize,
+}
+
+/// What a stream has for its host after taking in what arrived
+pub enum StreamUpdate {
+    /// Nothing new to show yet
+    Pending,
+    /// More of the page than the last preview showed
+    Preview(Box<PrismApp>),
+    /// The whole source has arrived, to be parsed as usual; the stream is done
+    Complete(String),
+    /// Reading the source failed
+    Failed(String),
+}
+
+impl DocumentStream {
+    /// Start reading `source`, whose length is `total` if known
+    pub fn spawn(mut source: impl Read + Send + 'static, total: Option<u64>, assets: Vfs) -> Self {
+        let (sender, chunks) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buf = vec![0; READ_CHUNK_BYTES];
+            loop {
+                let read = source.read(&mut buf).map(|n| buf[..n].to_vec());
+                // An empty chunk marks the end, and an error ends the stream too
+                let last = !matches!(&read, Ok(chunk) if !chunk.is_empty());
+                if sender.send(read).is_err() || last {
+                    break;
+                }
+            }
+        });
+        Self {
+            chunks,
+            body: vec![],
+            total,
+            assets,
+            finished: false,
+            next_preview: FIRST_PREVIEW_BYTES,
+            previewed: false,
+            shown: 0,
+        }
+    }
+
+    /// Share of the source received so far, when its length is known
+    pub fn progress(&self) -> Option<f32> {
+        self.total.filter(|&total| total > 0).map(|total| (self.body.len() as f64 / total as f64).min(1.0) as f32)
+    }
+
+    /// Take in what has arrived and say what to show, parsing at most once
+    pub fn poll(&mut self) -> StreamUpdate {
+        loop {
+            match self.chunks.try_recv() {
+                Ok(Ok(chunk)) if chunk.is_empty() => self.finished = true,
+                Ok(Ok(chunk)) => self.body.extend_from_slice(&chunk),
+                Ok(Err(e)) => return StreamUpdate::Failed(e.to_string()),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+
+        // Once the top of a finished document is up, the rest is parsed in one go
+        if self.body.len() >= self.next_preview && !(self.finished && self.previewed) {
+            let prefix = String::from_utf8_lossy(&self.body[..self.next_preview]).into_owned();
+            self.next_preview *= 2;
+            self.previewed = true;
+            return match parser::parse_partial(&prefix, &self.assets) {
+                Some(app) if app.view.children.len() > self.shown => {
+                    self.shown = app.view.children.len();
+                    StreamUpdate::Preview(Box::new(app))
+                }
+                _ => StreamUpdate::Pending,
+            };
+        }
+        if self.finished {
+            let body = std::mem::take(&mut self.body);
+            return StreamUpdate::Complete(String::from_utf8_lossy(&body).into_owned());
+        }
+        StreamUpdate::Pending
+    }
+}
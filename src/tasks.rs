@@ -0,0 +1,263 @@
+//! `prism://tasks`: the task manager page
+//!
+//! The page the task manager was opened from keeps running in the background
+//! and is listed with its estimated memory and recent CPU time. The task
+//! manager itself is an ordinary Prism page; the browser refreshes its state
+//! every second and reads `end_requested` to end the background page.
+//!
+//! Below the figures, the page's state is broken down by type, with its
+//! largest variables. Lists that have grown at every refresh for a while and
+//! variables the page never declared (set by an action, so they live until
+//! the page ends) are flagged, as the usual ways a page runs out of memory.
+
+use prism::ast::{PrismApp, Value};
+use prism::parser;
+use prism::runtime::{Runtime, CPU_WINDOW};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+pub const TASKS_URL: &str = "prism://tasks";
+
+/// How often the figures on the page are refreshed
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+const SOURCE: &str = r#"@app "Task Manager"
+@version 1
+
+state {
+  has_task: false
+  title: ""
+  location: ""
+  memory: ""
+  cpu: ""
+  state_summary: ""
+  state_types: []
+  state_rows: []
+  end_requested: false
+}
+
+view {
+  column {
+    padding: 24
+    gap: 16
+
+    text "Task Manager" {
+      size: 24
+      color: #333333
+    }
+
+    text "The page you came from keeps running while this one is open. Go back to return to it." {
+      size: 14
+      color: #666666
+    }
+
+    text "No page is running in the background." {
+      visible: has_task == false
+      size: 14
+      color: #999999
+    }
+
+    column {
+      visible: has_task == true
+      gap: 6
+
+      text "{title}" {
+        size: 16
+        color: #333333
+      }
+
+      text "{location}" {
+        size: 12
+        color: #999999
+      }
+
+      text "Memory: {memory}" {
+        size: 14
+        color: #333333
+      }
+
+      text "CPU: {cpu}" {
+        size: 14
+        color: #333333
+      }
+
+      text "State" {
+        size: 16
+        color: #333333
+      }
+
+      text "{state_summary}" {
+        size: 12
+        color: #999999
+      }
+
+      each {
+        items: state_types
+        as: group
+        row {
+          gap: 8
+          align: center
+          text "{group.type}" {
+            width: 56
+            size: 13
+            color: #333333
+          }
+          progress {
+            value: group.share
+            flex: 1
+          }
+          text "{group.count} · {group.size}" {
+            width: 160
+            size: 13
+            color: #666666
+          }
+        }
+      }
+
+      table {
+        data: state_rows
+        columns: "name,type,size,note"
+      }
+
+      button "End task" {
+        on_click: end_task
+      }
+    }
+  }
+}
+
+actions {
+  end_task {
+    end_requested: true
+  }
+}
+"#;
+
+pub fn page() -> PrismApp {
+    parser::parse(SOURCE).expect("task manager page parses")
+}
+
+/// Refreshes in a row a list must grow at before it is flagged
+const GROWING_REFRESHES: u32 = 5;
+/// Most variables listed in the state table
+const MAX_STATE_ROWS: usize = 20;
+
+/// Lengths of the background page's lists at the last refresh, and at how
+/// many refreshes in a row each has grown
+#[derive(Default)]
+pub struct StateWatch {
+    lists: HashMap<String, (usize, u32)>,
+}
+
+impl StateWatch {
+    /// Note the lists' lengths now; returns the names of those that keep growing
+    fn observe<'a>(&mut self, lists: impl Iterator<Item = (&'a str, usize)>) -> HashSet<String> {
+        let previous = std::mem::take(&mut self.lists);
+        for (name, len) in lists {
+            let streak = match previous.get(name) {
+                Some(&(last, streak)) if len > last => streak + 1,
+                Some(&(last, streak)) if len == last => streak,
+                _ => 0,
+            };
+            self.lists.insert(name.to_string(), (len, streak));
+        }
+        self.lists.iter().filter(|(_, &(_, streak))| streak >= GROWING_REFRESHES).map(|(name, _)| name.clone()).collect()
+    }
+}
+
+/// Show the background page's figures on the task manager, or that there is none
+pub fn update(tasks: &mut Runtime, task: Option<(&str, &Runtime)>, watch: &mut StateWatch) {
+    let state = &mut tasks.state;
+    match task {
+        Some((location, page)) => {
+            let usage = page.resource_usage();
+            state.set("has_task", Value::Bool(true));
+            state.set("title", Value::String(page.title().to_string()));
+            state.set("location", Value::String(location.to_string()));
+            state.set("memory", Value::String(format!("{} of {}", format_bytes(usage.memory_bytes), format_bytes(usage.memory_limit))));
+            state.set("cpu", Value::String(format!(
+                "{} ms in the last {} s, {} ms in total",
+                usage.recent_cpu.as_millis(),
+                CPU_WINDOW.as_secs(),
+                usage.total_cpu.as_millis()
+            )));
+            update_state(tasks, page, watch);
+        }
+        None => state.set("has_task", Value::Bool(false)),
+    }
+}
+
+/// Fill the state breakdown with the page's variables
+fn update_state(tasks: &mut Runtime, page: &Runtime, watch: &mut StateWatch) {
+    let usage = page.state.usage();
+    let growing = watch.observe(usage.iter().filter(|u| u.type_name == "list").filter_map(|u| Some((u.name.as_str(), u.items?))));
+    let total: usize = usage.iter().map(|u| u.bytes).sum();
+
+    let mut types: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for u in &usage {
+        let (count, bytes) = types.entry(u.type_name).or_default();
+        *count += 1;
+        *bytes += u.bytes;
+    }
+    let mut types: Vec<_> = types.into_iter().collect();
+    types.sort_by_key(|&(_, (_, bytes))| std::cmp::Reverse(bytes));
+    let types = types.into_iter().map(|(name, (count, bytes))| object(&[
+        ("type", Value::String(name.to_string())),
+        ("count", Value::String(plural(count, "variable"))),
+        ("size", Value::String(format_bytes(bytes))),
+        ("share", Value::Int((bytes * 100).checked_div(total).unwrap_or(0) as i64)),
+    ]));
+
+    let rows = usage.iter().take(MAX_STATE_ROWS).map(|u| {
+        let mut notes = vec![];
+        if growing.contains(&u.name) {
+            notes.push("keeps growing");
+        }
+        if !page.app.state.fields.contains_key(&u.name) {
+            notes.push("not declared");
+        }
+        let kind = match u.items {
+            Some(n) => format!("{} of {}", u.type_name, n),
+            None => u.type_name.to_string(),
+        };
+        object(&[
+            ("name", Value::String(u.name.clone())),
+            ("type", Value::String(kind)),
+            ("size", Value::String(format_bytes(u.bytes))),
+            ("note", Value::String(notes.join(", "))),
+        ])
+    });
+
+    let state = &mut tasks.state;
+    state.set("state_summary", Value::String(format!("{} in {}", format_bytes(total), plural(usage.len(), "variable"))));
+    state.set("state_types", Value::List(types.collect()));
+    state.set("state_rows", Value::List(rows.collect()));
+}
+
+fn object(fields: &[(&str, Value)]) -> Value {
+    Value::Object(fields.iter().map(|(k, v)| (k.to_string(), v.clone())).collect())
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    match n {
+        1 => format!("1 {}", noun),
+        n => format!("{} {}s", n, noun),
+    }
+}
+
+/// Whether the user clicked "End task" since the last call
+pub fn take_end_request(tasks: &mut Runtime) -> bool {
+    let requested = tasks.state.get("end_requested").is_some_and(|v| v.as_bool());
+    if requested {
+        tasks.state.set("end_requested", Value::Bool(false));
+    }
+    requested
+}
+
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
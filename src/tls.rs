@@ -0,0 +1,193 @@
+//! TLS details for the address bar's security indicator
+//!
+//! `https://` pages are fetched through a client whose certificate verifier
+//! records what the handshake established: the certificate the server
+//! presented, whether it verifies against the bundled web roots and the
+//! negotiated protocol. The settings decide whether a page whose certificate
+//! doesn't verify is blocked or loaded with a warning.
+
+use rustls::client::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, DigitallySignedStruct, OwnedTrustAnchor, RootCertStore, ServerName, SignatureScheme};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// What the handshake with a page's server established
+#[derive(Debug, Clone, Default)]
+pub struct Connection {
+    pub host: String,
+    /// Such as "TLS 1.3"
+    pub protocol: String,
+    pub certificate: Option<CertificateInfo>,
+    /// Why the certificate doesn't verify, if it doesn't
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CertificateInfo {
+    /// Common name (or organisation) the certificate was issued to
+    pub subject: String,
+    /// Organisation (or common name) that issued it
+    pub issuer: String,
+    /// Last day it is valid, as `YYYY-MM-DD`
+    pub expires: String,
+}
+
+/// Checks certificates against the web roots for the connection the page is
+/// actually fetched over, remembering what the server presented. A
+/// certificate that doesn't verify is let through only when `accept_invalid`
+/// is set, so a failed check still shows what the server presented.
+pub struct Recorder {
+    inner: WebPkiVerifier,
+    accept_invalid: bool,
+    seen: Mutex<Option<Connection>>,
+}
+
+impl Recorder {
+    /// What the last handshake established, if one got as far as the certificate
+    pub fn take(&self) -> Option<Connection> {
+        self.seen.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    fn set_protocol(&self, protocol: &str) {
+        if let Some(connection) = self.seen.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            connection.protocol = protocol.to_string();
+        }
+    }
+}
+
+impl ServerCertVerifier for Recorder {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let result = self.inner.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now);
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_string(),
+            ServerName::IpAddress(ip) => ip.to_string(),
+            _ => String::new(),
+        };
+        let error = result.as_ref().err().map(|e| e.to_string());
+        *self.seen.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(Connection { host, protocol: String::new(), certificate: parse_certificate(&end_entity.0), error });
+        match result {
+            Err(_) if self.accept_invalid => Ok(ServerCertVerified::assertion()),
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.set_protocol("TLS 1.2");
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.set_protocol("TLS 1.3");
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Client settings for fetching pages, and the recorder their handshakes report to
+pub fn client_config(accept_invalid: bool) -> (ClientConfig, Arc<Recorder>) {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+    }));
+    let recorder = Arc::new(Recorder { inner: WebPkiVerifier::new(roots, None), accept_invalid, seen: Mutex::new(None) });
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(recorder.clone())
+        .with_no_client_auth();
+    (config, recorder)
+}
+
+/// One DER element: its tag, its contents and whatever follows it
+fn element(der: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = der.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7F) as usize;
+        if count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count].iter().fold(0usize, |len, &b| (len << 8) | b as usize);
+        (len, &rest[count..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// Subject, issuer and expiry of an X.509 certificate
+fn parse_certificate(der: &[u8]) -> Option<CertificateInfo> {
+    let (_, certificate, _) = element(der)?;
+    let (_, tbs, _) = element(certificate)?;
+    let mut fields = tbs;
+    // Optional explicit version, then serial number and signature algorithm
+    if fields.first() == Some(&0xA0) {
+        fields = element(fields)?.2;
+    }
+    let (_, _, fields) = element(fields)?;
+    let (_, _, fields) = element(fields)?;
+    let (_, issuer, fields) = element(fields)?;
+    let (_, validity, fields) = element(fields)?;
+    let (_, subject, _) = element(fields)?;
+    let (_, _, validity) = element(validity)?;
+    let (time_tag, not_after, _) = element(validity)?;
+    Some(CertificateInfo {
+        subject: name_attribute(subject, COMMON_NAME).or_else(|| name_attribute(subject, ORGANIZATION)).unwrap_or_default(),
+        issuer: name_attribute(issuer, ORGANIZATION).or_else(|| name_attribute(issuer, COMMON_NAME)).unwrap_or_default(),
+        expires: date(time_tag, not_after)?,
+    })
+}
+
+/// Object identifiers 2.5.4.3 and 2.5.4.10
+const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const ORGANIZATION: &[u8] = &[0x55, 0x04, 0x0A];
+
+/// An attribute of a distinguished name (a sequence of sets of OID/value pairs)
+fn name_attribute(name: &[u8], oid: &[u8]) -> Option<String> {
+    let mut sets = name;
+    while let Some((_, set, rest)) = element(sets) {
+        let (_, pair, _) = element(set)?;
+        let (_, id, value) = element(pair)?;
+        let (_, value, _) = element(value)?;
+        if id == oid {
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        sets = rest;
+    }
+    None
+}
+
+/// `YYYY-MM-DD` from a UTCTime (`YYMMDD…`) or GeneralizedTime (`YYYYMMDD…`)
+fn date(tag: u8, time: &[u8]) -> Option<String> {
+    let time = std::str::from_utf8(time).ok()?;
+    let (year, rest) = match tag {
+        0x17 => {
+            let yy: u32 = time.get(..2)?.parse().ok()?;
+            (if yy < 50 { 2000 + yy } else { 1900 + yy }, time.get(2..)?)
+        }
+        0x18 => (time.get(..4)?.parse().ok()?, time.get(4..)?),
+        _ => return None,
+    };
+    Some(format!("{}-{}-{}", year, rest.get(..2)?, rest.get(2..4)?))
+}
@@ -0,0 +1,90 @@
+//! Virtual file system for document assets
+//!
+//! Relative asset paths in a document (image and video `src`, data sources,
+//! fonts, imported files) are resolved through its `Vfs`: the document's
+//! directory on the host, or the bundled files of a signed package. Every
+//! path is checked by the sandbox first, so assets can't escape the
+//! document's directory or package, and package assets never fall back to
+//! the host file system.
+
+use crate::package::Package;
+use crate::sandbox::Sandbox;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub enum Vfs {
+    /// No local assets (remote documents)
+    #[default]
+    Empty,
+    /// Files under the document's directory
+    Directory(PathBuf),
+    /// Files bundled in a signed package
+    Package(Arc<Package>),
+}
+
+impl Vfs {
+    /// The assets of a document loaded from `location` (a file path or URL)
+    pub fn for_location(location: &str) -> Vfs {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            return Vfs::Empty;
+        }
+        match Path::new(location).parent() {
+            Some(dir) => Vfs::Directory(dir.to_path_buf()),
+            None => Vfs::Empty,
+        }
+    }
+
+    pub fn is_package(&self) -> bool {
+        matches!(self, Vfs::Package(_))
+    }
+
+    /// Read an asset of at most `max_bytes`
+    pub fn read(&self, path: &str, max_bytes: usize) -> Result<Cow<'_, [u8]>, String> {
+        Sandbox::validate_asset_path(path).map_err(|e| e.to_string())?;
+        let bytes = match self {
+            Vfs::Empty => return Err("remote documents cannot load local files".to_string()),
+            Vfs::Directory(dir) => {
+                let full = contained(dir, path)?;
+                let meta = std::fs::metadata(&full).map_err(|e| e.to_string())?;
+                if meta.len() as usize > max_bytes {
+                    return Err("file exceeds maximum size limit".to_string());
+                }
+                Cow::Owned(std::fs::read(&full).map_err(|e| e.to_string())?)
+            }
+            Vfs::Package(package) => Cow::Borrowed(package.file(path).ok_or("not found in the package")?),
+        };
+        if bytes.len() > max_bytes {
+            return Err("file exceeds maximum size limit".to_string());
+        }
+        Ok(bytes)
+    }
+
+    /// Read a UTF-8 asset of at most `max_bytes`
+    pub fn read_to_string(&self, path: &str, max_bytes: usize) -> Result<String, String> {
+        self.read(path, max_bytes).map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Host path of an asset, for decoders that stream from disk
+    /// Package assets have none
+    pub fn host_path(&self, path: &str) -> Result<PathBuf, String> {
+        Sandbox::validate_asset_path(path).map_err(|e| e.to_string())?;
+        match self {
+            Vfs::Directory(dir) => contained(dir, path),
+            Vfs::Package(_) => Err("cannot be streamed from a package".to_string()),
+            Vfs::Empty => Err("remote documents cannot load local files".to_string()),
+        }
+    }
+}
+
+/// `path` under `dir` with symlinks resolved, as long as it stays inside `dir`
+fn contained(dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let root = dir.canonicalize().map_err(|e| e.to_string())?;
+    let full = root.join(path).canonicalize().map_err(|e| e.to_string())?;
+    if !full.starts_with(&root) {
+        return Err("path escapes the document's directory".to_string());
+    }
+    Ok(full)
+}
@@ -0,0 +1,323 @@
+//! Video playback for the `video` node
+//!
+//! Decodes H.264 video from MP4 files into RGB frames. Decoding is behind
+//! the `video` cargo feature (openh264 + mp4); without it, players fail to
+//! open and the renderer falls back to a placeholder.
+
+use std::path::Path;
+use std::time::Instant;
+
+/// Largest video file that will be loaded (16MB)
+pub const MAX_VIDEO_FILE_BYTES: usize = 16 * 1024 * 1024;
+
+/// A decoded frame in the framebuffer's 0RGB format
+pub struct VideoFrame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+/// Playback state for a single video source
+pub struct VideoPlayer {
+    decoder: backend::Decoder,
+    frame: Option<VideoFrame>,
+    playing: bool,
+    looping: bool,
+    position_ms: u64,
+    last_update: Option<Instant>,
+}
+
+impl VideoPlayer {
+    /// Open a video file and decode its first frame
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let meta = std::fs::metadata(path).map_err(|e| e.to_string())?;
+        if meta.len() as usize > MAX_VIDEO_FILE_BYTES {
+            return Err("Video exceeds maximum size limit".to_string());
+        }
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let mut decoder = backend::Decoder::new(bytes)?;
+        let frame = decoder.decode_until(0);
+        Ok(Self {
+            decoder,
+            frame,
+            playing: false,
+            looping: false,
+            position_ms: 0,
+            last_update: None,
+        })
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn play(&mut self) {
+        if !self.playing {
+            if self.position_ms >= self.duration_ms() {
+                self.rewind();
+            }
+            self.playing = true;
+            self.last_update = Some(Instant::now());
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+        self.last_update = None;
+    }
+
+    pub fn toggle(&mut self) {
+        if self.playing {
+            self.pause();
+        } else {
+            self.play();
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Advance playback by wall-clock time; returns true if the frame changed
+    pub fn advance(&mut self) -> bool {
+        if !self.playing {
+            return false;
+        }
+        let now = Instant::now();
+        let elapsed = self.last_update.map(|t| now.duration_since(t).as_millis() as u64).unwrap_or(0);
+        self.last_update = Some(now);
+        self.position_ms += elapsed;
+
+        let duration = self.duration_ms();
+        if self.position_ms >= duration {
+            if self.looping && duration > 0 {
+                self.rewind();
+            } else {
+                self.position_ms = duration;
+                self.playing = false;
+            }
+        }
+
+        match self.decoder.decode_until(self.position_ms) {
+            Some(frame) => {
+                self.frame = Some(frame);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn rewind(&mut self) {
+        self.position_ms = 0;
+        self.decoder.rewind();
+    }
+
+    pub fn frame(&self) -> Option<&VideoFrame> {
+        self.frame.as_ref()
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        self.decoder.dimensions()
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.decoder.duration_ms()
+    }
+
+    /// Playback progress from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        let duration = self.duration_ms();
+        if duration == 0 {
+            0.0
+        } else {
+            (self.position_ms as f32 / duration as f32).clamp(0.0, 1.0)
+        }
+    }
+}
+
+#[cfg(feature = "video")]
+mod backend {
+    use super::VideoFrame;
+    use openh264::decoder::Decoder as H264Decoder;
+    use openh264::formats::YUVSource;
+    use std::io::Cursor;
+
+    /// MP4 demuxer + H.264 decoder
+    pub struct Decoder {
+        reader: mp4::Mp4Reader<Cursor<Vec<u8>>>,
+        h264: H264Decoder,
+        track_id: u32,
+        timescale: u64,
+        sample_count: u32,
+        next_sample: u32,
+        /// A sample already read that starts after the position it was read for
+        ahead: Option<mp4::Mp4Sample>,
+        /// Whether the stream's first packet (carrying SPS/PPS) was decoded
+        started: bool,
+        length_size: usize,
+        sps: Vec<u8>,
+        pps: Vec<u8>,
+        width: usize,
+        height: usize,
+        duration_ms: u64,
+    }
+
+    impl Decoder {
+        pub fn new(bytes: Vec<u8>) -> Result<Self, String> {
+            let size = bytes.len() as u64;
+            let reader = mp4::Mp4Reader::read_header(Cursor::new(bytes), size).map_err(|e| e.to_string())?;
+            let track = reader
+                .tracks()
+                .values()
+                .find(|t| matches!(t.media_type(), Ok(mp4::MediaType::H264)))
+                .ok_or_else(|| "No H.264 video track found".to_string())?;
+
+            let avc1 = track.trak.mdia.minf.stbl.stsd.avc1.as_ref()
+                .ok_or_else(|| "Missing AVC configuration".to_string())?;
+            let length_size = (avc1.avcc.length_size_minus_one & 0x3) as usize + 1;
+            let sps = track.sequence_parameter_set().map_err(|e| e.to_string())?.to_vec();
+            let pps = track.picture_parameter_set().map_err(|e| e.to_string())?.to_vec();
+
+            let track_id = track.track_id();
+            let timescale = track.timescale().max(1) as u64;
+            let sample_count = track.sample_count();
+            let width = track.width() as usize;
+            let height = track.height() as usize;
+            let duration_ms = track.duration().as_millis() as u64;
+
+            Ok(Self {
+                reader,
+                h264: H264Decoder::new().map_err(|e| e.to_string())?,
+                track_id,
+                timescale,
+                sample_count,
+                next_sample: 1,
+                ahead: None,
+                started: false,
+                length_size,
+                sps,
+                pps,
+                width,
+                height,
+                duration_ms,
+            })
+        }
+
+        pub fn dimensions(&self) -> (usize, usize) {
+            (self.width, self.height)
+        }
+
+        pub fn duration_ms(&self) -> u64 {
+            self.duration_ms
+        }
+
+        pub fn rewind(&mut self) {
+            if let Ok(h264) = H264Decoder::new() {
+                self.h264 = h264;
+            }
+            self.next_sample = 1;
+            self.ahead = None;
+            self.started = false;
+        }
+
+        /// Decode every sample starting at or before `position_ms`
+        /// Returns the newest decoded frame, if any; only it is converted to RGB
+        pub fn decode_until(&mut self, position_ms: u64) -> Option<VideoFrame> {
+            let mut latest = None;
+            let mut packet = self.packet_until(position_ms);
+            while let Some(current) = packet {
+                packet = self.packet_until(position_ms);
+                if let Ok(Some(yuv)) = self.h264.decode(&current) {
+                    if packet.is_none() {
+                        let (w, h) = yuv.dimensions();
+                        let mut rgb = vec![0u8; w * h * 3];
+                        yuv.write_rgb8(&mut rgb);
+                        let pixels = rgb
+                            .chunks_exact(3)
+                            .map(|p| ((p[0] as u32) << 16) | ((p[1] as u32) << 8) | p[2] as u32)
+                            .collect();
+                        latest = Some(VideoFrame { width: w, height: h, pixels });
+                    }
+                }
+            }
+            latest
+        }
+
+        /// The next sample starting at or before `position_ms` as an Annex B
+        /// packet; a later one is kept for the next call
+        fn packet_until(&mut self, position_ms: u64) -> Option<Vec<u8>> {
+            let sample = loop {
+                if let Some(sample) = self.ahead.take() {
+                    break sample;
+                }
+                if self.next_sample > self.sample_count {
+                    return None;
+                }
+                let read = self.reader.read_sample(self.track_id, self.next_sample);
+                self.next_sample += 1;
+                if let Ok(Some(sample)) = read {
+                    break sample;
+                }
+            };
+            if sample.start_time * 1000 / self.timescale > position_ms {
+                self.ahead = Some(sample);
+                return None;
+            }
+            let packet = self.to_annex_b(&sample.bytes);
+            self.started = true;
+            Some(packet)
+        }
+
+        /// Convert length-prefixed NAL units to Annex B start codes,
+        /// prefixing the stream's first packet with SPS/PPS
+        fn to_annex_b(&self, sample: &[u8]) -> Vec<u8> {
+            let mut out = Vec::with_capacity(sample.len() + 16);
+            if !self.started {
+                for ps in [&self.sps, &self.pps] {
+                    out.extend_from_slice(&[0, 0, 0, 1]);
+                    out.extend_from_slice(ps);
+                }
+            }
+            let mut rest = sample;
+            while rest.len() > self.length_size {
+                let len = rest[..self.length_size]
+                    .iter()
+                    .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+                rest = &rest[self.length_size..];
+                let len = len.min(rest.len());
+                out.extend_from_slice(&[0, 0, 0, 1]);
+                out.extend_from_slice(&rest[..len]);
+                rest = &rest[len..];
+            }
+            out
+        }
+    }
+}
+
+#[cfg(not(feature = "video"))]
+mod backend {
+    use super::VideoFrame;
+
+    /// Stand-in used when the `video` feature is disabled
+    pub struct Decoder;
+
+    impl Decoder {
+        pub fn new(_bytes: Vec<u8>) -> Result<Self, String> {
+            Err("Video playback requires the `video` feature".to_string())
+        }
+
+        pub fn dimensions(&self) -> (usize, usize) {
+            (0, 0)
+        }
+
+        pub fn duration_ms(&self) -> u64 {
+            0
+        }
+
+        pub fn rewind(&mut self) {}
+
+        pub fn decode_until(&mut self, _position_ms: u64) -> Option<VideoFrame> {
+            None
+        }
+    }
+}